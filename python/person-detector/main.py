@@ -14,52 +14,71 @@ from ultralytics import YOLO
 from tqdm import tqdm
 
 
+# 每个片段最多保留的检测框数量（超出时均匀抽样）
+MAX_BOXES_PER_SEGMENT = 100
+
+
+def sample_boxes(frames, fps):
+    """均匀抽样片段内的检测框，坐标为按画面宽高归一化的 [x1, y1, x2, y2]"""
+    if len(frames) > MAX_BOXES_PER_SEGMENT:
+        step = len(frames) / MAX_BOXES_PER_SEGMENT
+        frames = [frames[int(i * step)] for i in range(MAX_BOXES_PER_SEGMENT)]
+    return [
+        {"time": round(frame_idx / fps, 3), "box": box, "confidence": round(conf, 4)}
+        for frame_idx, conf, box in frames
+    ]
+
+
 def merge_segments(detections, fps, frame_interval, max_gap_duration, min_segment_duration):
     """将检测到人物的帧合并为连续时间段"""
     if not detections:
         return []
 
-    # detections: list of (frame_index, confidence)
+    # detections: list of (frame_index, confidence, box)
     detections.sort(key=lambda x: x[0])
 
     segments = []
-    seg_start_frame = detections[0][0]
-    seg_end_frame = detections[0][0]
-    seg_max_conf = detections[0][1]
-
     max_gap_frames = max_gap_duration * fps
 
-    for frame_idx, conf in detections[1:]:
-        gap = frame_idx - seg_end_frame
+    def flush(frames):
+        start_t = frames[0][0] / fps
+        end_t = (frames[-1][0] + frame_interval) / fps
+        if end_t - start_t >= min_segment_duration:
+            segments.append({
+                "start_time": round(start_t, 3),
+                "end_time": round(end_t, 3),
+                "confidence": round(max(conf for _, conf, _ in frames), 4),
+                "boxes": sample_boxes(frames, fps),
+            })
+
+    seg_frames = [detections[0]]
+    for detection in detections[1:]:
+        gap = detection[0] - seg_frames[-1][0]
         if gap <= max_gap_frames:
-            seg_end_frame = frame_idx
-            seg_max_conf = max(seg_max_conf, conf)
+            seg_frames.append(detection)
         else:
-            start_t = seg_start_frame / fps
-            end_t = (seg_end_frame + frame_interval) / fps
-            if end_t - start_t >= min_segment_duration:
-                segments.append({
-                    "start_time": round(start_t, 3),
-                    "end_time": round(end_t, 3),
-                    "confidence": round(seg_max_conf, 4),
-                })
-            seg_start_frame = frame_idx
-            seg_end_frame = frame_idx
-            seg_max_conf = conf
+            flush(seg_frames)
+            seg_frames = [detection]
 
     # 最后一段
-    start_t = seg_start_frame / fps
-    end_t = (seg_end_frame + frame_interval) / fps
-    if end_t - start_t >= min_segment_duration:
-        segments.append({
-            "start_time": round(start_t, 3),
-            "end_time": round(end_t, 3),
-            "confidence": round(seg_max_conf, 4),
-        })
+    flush(seg_frames)
 
     return segments
 
 
+def write_result(path: str, result: dict):
+    """写入 JSON 结果（先写临时文件再替换，避免读取到写了一半的文件）"""
+    os.makedirs(os.path.dirname(os.path.abspath(path)), exist_ok=True)
+    temp_path = path + ".tmp"
+    with open(temp_path, "w", encoding="utf-8") as f:
+        json.dump(result, f, ensure_ascii=False, indent=2)
+    os.replace(temp_path, path)
+
+
+# 每处理多少帧写入一次中间结果
+PARTIAL_WRITE_INTERVAL = 50
+
+
 def detect_persons(
     video_path: str,
     model_path: str,
@@ -69,8 +88,13 @@ def detect_persons(
     device: str = "auto",
     max_gap_duration: float = 2.0,
     min_segment_duration: float = 1.0,
+    partial_json: str = None,
 ):
-    """主检测函数"""
+    """主检测函数
+
+    指定 partial_json 时定期写入已处理部分的中间结果（partial=true），
+    进程被中途终止后调用方仍可读取已检测的片段。
+    """
     # 确定设备
     if device == "auto":
         import torch
@@ -103,7 +127,18 @@ def detect_persons(
 
     pbar = tqdm(total=len(frames_to_process), desc="Detecting", file=sys.stderr, ncols=80)
 
-    for frame_idx in frames_to_process:
+    for processed, frame_idx in enumerate(frames_to_process):
+        if partial_json and processed > 0 and processed % PARTIAL_WRITE_INTERVAL == 0:
+            write_result(partial_json, {
+                "segments": merge_segments(
+                    list(detections), fps, frame_interval, max_gap_duration, min_segment_duration
+                ),
+                "total_frames": total_frames,
+                "processed_frames": processed,
+                "detection_frames": detection_frame_count,
+                "partial": True,
+            })
+
         cap.set(cv2.CAP_PROP_POS_FRAMES, frame_idx)
         ret, frame = cap.read()
         if not ret:
@@ -116,10 +151,13 @@ def detect_persons(
             device=dev, verbose=False,
         )
 
-        # 检查是否检测到人物
+        # 检查是否检测到人物，记录置信度最高的检测框
         if len(results) > 0 and len(results[0].boxes) > 0:
-            max_conf = float(results[0].boxes.conf.max())
-            detections.append((frame_idx, max_conf))
+            boxes = results[0].boxes
+            best = int(boxes.conf.argmax())
+            max_conf = float(boxes.conf[best])
+            box = [round(float(v), 4) for v in boxes.xyxyn[best].tolist()]
+            detections.append((frame_idx, max_conf, box))
             detection_frame_count += 1
 
         pbar.update(1)
@@ -140,9 +178,9 @@ def detect_persons(
     }
 
     # 写入 JSON
-    os.makedirs(os.path.dirname(os.path.abspath(output_json)), exist_ok=True)
-    with open(output_json, "w", encoding="utf-8") as f:
-        json.dump(result, f, ensure_ascii=False, indent=2)
+    write_result(output_json, result)
+    if partial_json and os.path.exists(partial_json):
+        os.remove(partial_json)
 
     print(f"Detection complete: {len(segments)} segments found", file=sys.stderr)
 
@@ -157,6 +195,7 @@ def main():
     parser.add_argument("--device", choices=["auto", "cpu", "gpu"], default="auto")
     parser.add_argument("--max_gap_duration", type=float, default=2.0)
     parser.add_argument("--min_segment_duration", type=float, default=1.0)
+    parser.add_argument("--partial_json", default=None, help="Partial result JSON path (written periodically)")
     args = parser.parse_args()
 
     detect_persons(
@@ -168,6 +207,7 @@ def main():
         device=args.device,
         max_gap_duration=args.max_gap_duration,
         min_segment_duration=args.min_segment_duration,
+        partial_json=args.partial_json,
     )
 
 
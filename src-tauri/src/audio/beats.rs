@@ -0,0 +1,181 @@
+// 节拍检测模块
+//
+// 基于能量的起音（onset）检测 + 自相关估计速度，返回节拍时间戳（秒），
+// 供前端把手动调整的片段边界吸附到最近的节拍。
+//
+// 精度限制：
+// - 仅用宽带能量变化检测起音，鼓点清晰的音乐效果较好，
+//   弦乐/人声等软起音或大量混响的音乐可能漏检或偏移
+// - 假设整首曲目速度恒定，变速、自由速度或中途变拍的曲目节拍会逐渐偏离
+// - 速度搜索范围为 60-200 BPM，超出范围时可能得到半速/倍速结果
+// - 帧跳长约 23ms，节拍时间精度约为 ±1 帧
+
+use crate::error::AppResult;
+use crate::video::ffmpeg;
+use tracing::info;
+
+/// 分析采样率（节拍检测不需要高频细节）
+const BEAT_SAMPLE_RATE: u32 = 11025;
+/// 能量帧长（采样数）
+const FRAME_SIZE: usize = 512;
+/// 帧跳长（采样数，约 23ms）
+const HOP_SIZE: usize = 256;
+/// 速度搜索范围（BPM）
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+/// 速度先验中心（BPM）
+const PRIOR_BPM: f64 = 120.0;
+
+/// 检测音频文件的节拍时间戳（秒，升序）
+pub fn detect_beats(audio_path: &str) -> AppResult<Vec<f64>> {
+    let samples = ffmpeg::decode_mono_pcm(audio_path, BEAT_SAMPLE_RATE)?;
+    let beats = estimate_beats(&samples, BEAT_SAMPLE_RATE);
+    info!("[BEATS] 检测完成: {} 个节拍, path={}", beats.len(), audio_path);
+    Ok(beats)
+}
+
+/// 由 PCM 采样估计节拍时间戳
+///
+/// 1. 分帧计算对数能量，取正向能量差作为起音强度包络
+/// 2. 在 60-200 BPM 对应的延迟范围内对包络做自相关，取最大值作为节拍周期
+/// 3. 对每个起始相位沿周期网格吸附到局部起音峰值，取起音强度之和最大的节拍序列
+pub fn estimate_beats(samples: &[i16], sample_rate: u32) -> Vec<f64> {
+    let envelope = onset_envelope(samples);
+    if envelope.len() < 4 {
+        return Vec::new();
+    }
+
+    let frames_per_sec = sample_rate as f64 / HOP_SIZE as f64;
+    let min_lag = ((60.0 / MAX_BPM) * frames_per_sec).floor().max(1.0) as usize;
+    let max_lag = ((60.0 / MIN_BPM) * frames_per_sec).ceil() as usize;
+    let max_lag = max_lag.min(envelope.len() / 2);
+    if min_lag >= max_lag {
+        return Vec::new();
+    }
+
+    // 自相关求节拍周期（帧）；乘以以 120 BPM 为中心的对数高斯权重，抑制半速/倍速误判
+    let smoothed = smooth_envelope(&envelope);
+    let mut best_lag = 0;
+    let mut best_score = 0.0;
+    for lag in min_lag..=max_lag {
+        let acf: f64 = smoothed[lag..]
+            .iter()
+            .zip(&smoothed[..smoothed.len() - lag])
+            .map(|(a, b)| a * b)
+            .sum::<f64>()
+            / (smoothed.len() - lag) as f64;
+        let bpm = 60.0 * frames_per_sec / lag as f64;
+        let score = acf * (-0.5 * (bpm / PRIOR_BPM).log2().powi(2)).exp();
+        if score > best_score {
+            best_score = score;
+            best_lag = lag;
+        }
+    }
+    if best_lag == 0 {
+        return Vec::new();
+    }
+
+    // 对每个起始相位做一次吸附跟踪，取网格上起音强度之和最大者
+    let tolerance = (best_lag / 10).max(1);
+    let (frames, _) = (0..best_lag)
+        .map(|phase| track_beats(&envelope, phase, best_lag, tolerance))
+        .fold((Vec::new(), -1.0), |best, candidate| {
+            if candidate.1 > best.1 { candidate } else { best }
+        });
+    frames
+        .into_iter()
+        .map(|frame| (frame * HOP_SIZE) as f64 / sample_rate as f64)
+        .collect()
+}
+
+/// 从 start 帧开始按周期推进，每个网格点在 ±tolerance 帧内吸附到起音峰值
+///
+/// 从吸附后的位置继续推进，避免整数周期误差累积；返回 (节拍帧序列, 起音强度之和)。
+fn track_beats(envelope: &[f64], start: usize, lag: usize, tolerance: usize) -> (Vec<usize>, f64) {
+    let mut frames = Vec::new();
+    let mut total = 0.0;
+    let mut pos = start;
+    while pos < envelope.len() {
+        let lo = pos.saturating_sub(tolerance);
+        let hi = (pos + tolerance).min(envelope.len() - 1);
+        let frame = (lo..=hi).fold(pos, |best, i| if envelope[i] > envelope[best] { i } else { best });
+        total += envelope[frame];
+        frames.push(frame);
+        pos = frame + lag;
+    }
+    (frames, total)
+}
+
+/// 计算起音强度包络（相邻帧对数能量的正向差值）
+fn onset_envelope(samples: &[i16]) -> Vec<f64> {
+    if samples.len() < FRAME_SIZE {
+        return Vec::new();
+    }
+    let energies: Vec<f64> = (0..=(samples.len() - FRAME_SIZE) / HOP_SIZE)
+        .map(|i| {
+            let frame = &samples[i * HOP_SIZE..i * HOP_SIZE + FRAME_SIZE];
+            let energy: f64 = frame.iter().map(|&s| {
+                let v = s as f64 / i16::MAX as f64;
+                v * v
+            }).sum::<f64>() / FRAME_SIZE as f64;
+            (energy + 1e-10).ln()
+        })
+        .collect();
+
+    let mut envelope = Vec::with_capacity(energies.len());
+    envelope.push(0.0);
+    for w in energies.windows(2) {
+        envelope.push((w[1] - w[0]).max(0.0));
+    }
+    envelope
+}
+
+/// 对包络做 3 帧滑动平均，使相邻帧上的起音在自相关中也能对齐
+fn smooth_envelope(envelope: &[f64]) -> Vec<f64> {
+    (0..envelope.len())
+        .map(|i| {
+            let lo = i.saturating_sub(1);
+            let hi = (i + 1).min(envelope.len() - 1);
+            envelope[lo..=hi].iter().sum::<f64>() / (hi - lo + 1) as f64
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 生成固定间隔的短促噪声脉冲（点击音轨）
+    fn click_track(sample_rate: u32, bpm: f64, seconds: f64) -> Vec<i16> {
+        let total = (sample_rate as f64 * seconds) as usize;
+        let interval = (sample_rate as f64 * 60.0 / bpm) as usize;
+        let click_len = sample_rate as usize / 50;
+        let mut samples = vec![0i16; total];
+        let mut start = interval / 2;
+        while start < total {
+            for (i, s) in samples[start..(start + click_len).min(total)].iter_mut().enumerate() {
+                *s = if i % 2 == 0 { 20000 } else { -20000 };
+            }
+            start += interval;
+        }
+        samples
+    }
+
+    #[test]
+    fn test_estimate_beats_click_track() {
+        let beats = estimate_beats(&click_track(BEAT_SAMPLE_RATE, 120.0, 10.0), BEAT_SAMPLE_RATE);
+        assert!(beats.len() >= 18 && beats.len() <= 21, "节拍数: {}", beats.len());
+        // 相邻节拍间隔接近 0.5s（120 BPM）
+        for w in beats.windows(2) {
+            assert!((w[1] - w[0] - 0.5).abs() < 0.05, "节拍间隔: {}", w[1] - w[0]);
+        }
+        // 首个节拍对齐到第一个点击（0.25s）
+        assert!((beats[0] - 0.25).abs() < 0.05, "首个节拍: {}", beats[0]);
+    }
+
+    #[test]
+    fn test_estimate_beats_too_short() {
+        assert!(estimate_beats(&[], BEAT_SAMPLE_RATE).is_empty());
+        assert!(estimate_beats(&[0; 100], BEAT_SAMPLE_RATE).is_empty());
+    }
+}
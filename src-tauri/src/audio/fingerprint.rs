@@ -4,18 +4,52 @@
 // 用于音乐匹配流程中的滑动窗口片段与音乐库指纹比对。
 
 use crate::error::{AppError, AppResult};
-use crate::utils::{resolve_tool_path, hidden_command};
+use crate::utils::{create_operation_temp_dir, generate_id, resolve_tool_path, hidden_command};
+use crate::video::ffmpeg::{self, AudioFormat};
 use sha2::{Sha256, Digest};
 use tracing::{debug, error};
 
+/// 当前指纹参数标识（采样率、声道数与分析时长策略），随音乐记录保存
+///
+/// 参数变化后旧指纹与新提取的查询窗口不可比，需要 reextract_fingerprints 重新提取。
+/// `_full` 表示音乐库指纹覆盖整首歌曲；此前按 fpcalc 默认只分析前 120 秒的记录会被判定为过期。
+pub fn current_fingerprint_version() -> String {
+    let format = AudioFormat::fingerprint();
+    format!("{}hz_{}ch_full", format.sample_rate, format.channels)
+}
+
+/// 按指纹匹配参数转码后提取指纹
+///
+/// 先用 ffmpeg 转为 `AudioFormat::fingerprint()`（默认单声道 22.05kHz）再调用 fpcalc，
+/// 保证音乐库指纹与匹配查询窗口的音频参数一致。转码文件写入操作临时目录，而非系统临时目录。
+pub fn extract_fingerprint_normalized(audio_path: &str) -> AppResult<(Vec<u8>, f64)> {
+    let temp_dir = create_operation_temp_dir(&format!("{}_fingerprint_", generate_id()))?;
+    let temp_path = temp_dir.path().join("audio.wav").to_string_lossy().to_string();
+    ffmpeg::transcode_audio(audio_path, &temp_path, AudioFormat::fingerprint())?;
+    extract_fingerprint_from_file(&temp_path, None)
+}
+
+/// fpcalc `-length` 参数值（整秒，向上取整）
+///
+/// None 表示分析整个文件（0 = 不限制；fpcalc 默认只分析前 120 秒）。
+fn fpcalc_length_arg(max_length_seconds: Option<f64>) -> String {
+    match max_length_seconds.filter(|s| s.is_finite() && *s > 0.0) {
+        Some(seconds) => (seconds.ceil() as u64).max(1).to_string(),
+        None => "0".to_string(),
+    }
+}
+
 /// 从音频文件提取指纹
 ///
 /// 调用 fpcalc 以 raw JSON 模式提取 Chromaprint 指纹，返回 (指纹字节数据, 音频时长)。
-pub fn extract_fingerprint_from_file(audio_path: &str) -> AppResult<(Vec<u8>, f64)> {
-    debug!("[FINGERPRINT] 提取指纹: {}", audio_path);
+/// max_length_seconds 限制分析的音频时长（对应 fpcalc `-length`）：滑动窗口匹配只需窗口长度，
+/// 音乐库曲目传 None 覆盖整首歌曲。
+pub fn extract_fingerprint_from_file(audio_path: &str, max_length_seconds: Option<f64>) -> AppResult<(Vec<u8>, f64)> {
+    let length = fpcalc_length_arg(max_length_seconds);
+    debug!("[FINGERPRINT] 提取指纹: {}, length={}", audio_path, length);
     let fpcalc_path = resolve_tool_path("fpcalc");
     let output = hidden_command(&fpcalc_path)
-        .args(["-raw", "-json", audio_path])
+        .args(["-raw", "-json", "-length", &length, audio_path])
         .output()
         .map_err(|e| AppError::DependencyMissing(format!("fpcalc 执行失败: {}", e)))?;
 
@@ -91,6 +125,140 @@ pub fn compare_fingerprints(fp1: &[u8], fp2: &[u8]) -> f64 {
     matching_bits as f64 / total_bits as f64
 }
 
+/// 将指纹字节还原为 Chromaprint 原始 u32 数组
+pub fn decode_fingerprint(fp: &[u8]) -> Vec<u32> {
+    fp.chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+/// 偏移对齐比较结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlignedMatch {
+    /// 重叠部分的相似度 (0.0-1.0)
+    pub similarity: f64,
+    /// query[0] 对齐到的 reference 下标（可为负，表示 query 开头超出 reference）
+    pub offset: isize,
+    /// 重叠部分占 query 长度的比例 (0.0-1.0)
+    pub overlap_ratio: f64,
+}
+
+/// 偏移投票使用的指纹项高位（右移位数），高 20 位相同即视为命中，容忍低位噪声
+const ALIGN_VOTE_SHIFT: u32 = 12;
+/// 同一取值在 query 中出现超过该次数时不参与投票（静音等重复项会产生大量无效偏移）
+const ALIGN_VOTE_MAX_REPEATS: usize = 8;
+/// 取得票最多的前 N 个偏移作为候选
+const ALIGN_VOTE_CANDIDATES: usize = 8;
+/// 粗扫描的偏移步长与 query 抽样间隔
+const ALIGN_COARSE_STEP: usize = 4;
+/// 取粗扫描相似度最高的前 N 个偏移作为候选
+const ALIGN_COARSE_CANDIDATES: usize = 4;
+
+/// 偏移对齐比较两个指纹
+///
+/// 将 query 在 reference 上滑动，只统计两者重叠部分的汉明相似度，取最高者。
+/// 重叠长度小于 min_overlap_ratio * query 长度的偏移不参与比较（至少重叠 1 项），
+/// 因此片段只部分覆盖歌曲首尾时不会因少量重叠而得到虚高的分数。
+///
+/// 先粗后细，避免逐偏移全量比较的 O(q·r) 开销：
+/// 1. 高位相同的指纹项按偏移投票（近似互相关峰值），O(q + r)；
+/// 2. 按步长抽样偏移、隔项抽样 query 粗扫描，覆盖噪声较大、没有高位命中的情况；
+/// 3. 在两类候选偏移的 ±步长范围内逐项全量比较，取最高相似度。
+pub fn compare_fingerprints_aligned(query: &[u32], reference: &[u32], min_overlap_ratio: f64) -> Option<AlignedMatch> {
+    use std::collections::{HashMap, HashSet};
+
+    if query.is_empty() || reference.is_empty() {
+        return None;
+    }
+
+    let q_len = query.len() as isize;
+    let r_len = reference.len() as isize;
+    let min_overlap = ((q_len as f64 * min_overlap_ratio.clamp(0.0, 1.0)).ceil() as isize).max(1);
+    if min_overlap > q_len.min(r_len) {
+        return None;
+    }
+    let (min_offset, max_offset) = (min_overlap - q_len, r_len - min_overlap);
+
+    // 1. 偏移投票
+    let mut positions: HashMap<u32, Vec<isize>> = HashMap::new();
+    for (i, v) in query.iter().enumerate() {
+        positions.entry(v >> ALIGN_VOTE_SHIFT).or_default().push(i as isize);
+    }
+    let mut votes: HashMap<isize, u32> = HashMap::new();
+    for (j, v) in reference.iter().enumerate() {
+        let Some(hits) = positions.get(&(v >> ALIGN_VOTE_SHIFT)) else { continue };
+        if hits.len() > ALIGN_VOTE_MAX_REPEATS {
+            continue;
+        }
+        for &i in hits {
+            let offset = j as isize - i;
+            if (min_offset..=max_offset).contains(&offset) {
+                *votes.entry(offset).or_default() += 1;
+            }
+        }
+    }
+    let mut voted: Vec<(isize, u32)> = votes.into_iter().collect();
+    voted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let mut candidates: Vec<isize> = voted.into_iter().take(ALIGN_VOTE_CANDIDATES).map(|(offset, _)| offset).collect();
+
+    // 2. 粗扫描
+    let mut coarse: Vec<(f64, isize)> = (min_offset..=max_offset)
+        .step_by(ALIGN_COARSE_STEP)
+        .filter_map(|offset| {
+            overlap_similarity(query, reference, offset, min_overlap, ALIGN_COARSE_STEP).map(|(sim, _)| (sim, offset))
+        })
+        .collect();
+    coarse.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal).then(a.1.cmp(&b.1)));
+    candidates.extend(coarse.into_iter().take(ALIGN_COARSE_CANDIDATES).map(|(_, offset)| offset));
+
+    // 3. 候选邻域全量比较
+    let radius = ALIGN_COARSE_STEP as isize;
+    let mut checked: HashSet<isize> = HashSet::new();
+    let mut best: Option<AlignedMatch> = None;
+    for candidate in candidates {
+        for offset in (candidate - radius).max(min_offset)..=(candidate + radius).min(max_offset) {
+            if !checked.insert(offset) {
+                continue;
+            }
+            let Some((similarity, overlap)) = overlap_similarity(query, reference, offset, min_overlap, 1) else { continue };
+            let is_better = match best {
+                Some(b) => similarity > b.similarity || (similarity == b.similarity && offset < b.offset),
+                None => true,
+            };
+            if is_better {
+                best = Some(AlignedMatch {
+                    similarity,
+                    offset,
+                    overlap_ratio: overlap as f64 / q_len as f64,
+                });
+            }
+        }
+    }
+
+    best
+}
+
+/// 计算 query 对齐到 reference[offset] 时重叠部分的汉明相似度，返回 (相似度, 重叠长度)
+///
+/// stride > 1 时隔项抽样（粗扫描用）。重叠不足 min_overlap 时返回 None。
+fn overlap_similarity(query: &[u32], reference: &[u32], offset: isize, min_overlap: isize, stride: usize) -> Option<(f64, isize)> {
+    let q_start = (-offset).max(0);
+    let r_start = offset.max(0);
+    let overlap = (query.len() as isize - q_start).min(reference.len() as isize - r_start);
+    if overlap < min_overlap {
+        return None;
+    }
+
+    let q = &query[q_start as usize..(q_start + overlap) as usize];
+    let r = &reference[r_start as usize..(r_start + overlap) as usize];
+    let (diff_bits, compared) = q
+        .iter()
+        .zip(r)
+        .step_by(stride.max(1))
+        .fold((0u64, 0u64), |(diff, n), (a, b)| (diff + (a ^ b).count_ones() as u64, n + 1));
+    Some((1.0 - diff_bits as f64 / (compared as f64 * 32.0), overlap))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,10 +271,68 @@ mod tests {
         assert_eq!(hash.len(), 64); // SHA256 produces 64 hex characters
     }
 
+    #[test]
+    fn test_fpcalc_length_arg() {
+        assert_eq!(fpcalc_length_arg(None), "0");
+        assert_eq!(fpcalc_length_arg(Some(20.0)), "20");
+        assert_eq!(fpcalc_length_arg(Some(21.3)), "22");
+        assert_eq!(fpcalc_length_arg(Some(0.2)), "1");
+        assert_eq!(fpcalc_length_arg(Some(0.0)), "0");
+        assert_eq!(fpcalc_length_arg(Some(f64::NAN)), "0");
+    }
+
     #[test]
     fn test_compare_identical() {
         let fp = vec![0u8, 0, 0, 1, 0, 0, 0, 2];
         let similarity = compare_fingerprints(&fp, &fp);
         assert!((similarity - 1.0).abs() < 0.001);
     }
+
+    #[test]
+    fn test_aligned_finds_offset() {
+        let reference: Vec<u32> = (0..200u32).map(|i| i.wrapping_mul(2654435761)).collect();
+        let query = reference[50..80].to_vec();
+        let m = compare_fingerprints_aligned(&query, &reference, 0.5).unwrap();
+        assert_eq!(m.offset, 50);
+        assert!((m.similarity - 1.0).abs() < 1e-9);
+        assert!((m.overlap_ratio - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_aligned_min_overlap_ratio() {
+        let reference: Vec<u32> = (0..100u32).map(|i| i.wrapping_mul(2654435761)).collect();
+        // query 前 10 项与 reference 结尾重合，其余 30 项超出 reference
+        let mut query = reference[90..100].to_vec();
+        query.extend((0..30u32).map(|i| !i.wrapping_mul(40503)));
+
+        let permissive = compare_fingerprints_aligned(&query, &reference, 0.2).unwrap();
+        assert_eq!(permissive.offset, 90);
+        assert!((permissive.overlap_ratio - 0.25).abs() < 1e-9);
+
+        // 要求至少一半重叠时，该对齐位置被拒绝
+        let strict = compare_fingerprints_aligned(&query, &reference, 0.5).unwrap();
+        assert!(strict.overlap_ratio >= 0.5);
+        assert!(strict.similarity < permissive.similarity);
+    }
+
+    #[test]
+    fn test_aligned_matches_brute_force() {
+        // 长 reference 中带噪声的片段：一半项高位被翻转（不参与投票），其余项低位有噪声
+        let reference: Vec<u32> = (0..3000u32).map(|i| i.wrapping_mul(2654435761) ^ (i >> 3)).collect();
+        let query: Vec<u32> = reference[1234..1354]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| if i % 2 == 0 { v ^ 0x8000_0000 } else { v ^ 0b101 })
+            .collect();
+        let m = compare_fingerprints_aligned(&query, &reference, 0.1).unwrap();
+        assert_eq!(m.offset, 1234);
+
+        // 与逐偏移全量比较的结果一致
+        let min_overlap = (query.len() as f64 * 0.1).ceil() as isize;
+        let brute = ((min_overlap - query.len() as isize)..=(reference.len() as isize - min_overlap))
+            .filter_map(|offset| overlap_similarity(&query, &reference, offset, min_overlap, 1).map(|(sim, _)| (sim, offset)))
+            .fold((f64::MIN, 0), |best, cur| if cur.0 > best.0 { cur } else { best });
+        assert_eq!(brute.1, m.offset);
+        assert!((brute.0 - m.similarity).abs() < 1e-12);
+    }
 }
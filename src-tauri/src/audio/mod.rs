@@ -3,6 +3,8 @@
 // 子模块：
 // - separator: 人声/伴奏分离（调用 audio-separator，基于 MDX-Net ONNX 模型）
 // - fingerprint: 音频指纹提取与相似度比较（调用 fpcalc，基于 Chromaprint）
+// - beats: 节拍检测（能量起音 + 自相关估计速度，用于剪辑点吸附节拍）
 
+pub mod beats;
 pub mod fingerprint;
 pub mod separator;
@@ -8,9 +8,9 @@
 // 2. Resources: exe_dir/resources/audio-separator/audio-separator.exe
 // 3. 系统 PATH: audio-separator
 
-use crate::config::{SeparationConfig, AccelerationMode, GpuType};
+use crate::config::{self, SeparationConfig, AccelerationMode, GpuType};
 use crate::error::{AppError, AppResult};
-use crate::utils::{SeparationResult, hidden_command};
+use crate::utils::{SeparationResult, StallWatchdog, hidden_command, is_gpu_oom_output};
 use crate::models;
 use crate::video::ffmpeg::get_audio_duration;
 use std::process::Stdio;
@@ -222,6 +222,10 @@ pub fn separate_vocals(
     if !use_gpu {
         cmd.env("CUDA_VISIBLE_DEVICES", "-1");
         info!("[SEPARATOR] 已设置 CUDA_VISIBLE_DEVICES=\"-1\" 禁用 GPU");
+    } else if let Some(index) = config::get_config().gpu_device_index {
+        // 多 GPU 机器上指定使用的显卡
+        cmd.env("CUDA_VISIBLE_DEVICES", index.to_string());
+        info!("[SEPARATOR] 已设置 CUDA_VISIBLE_DEVICES=\"{}\" 指定 GPU", index);
     }
 
     let child = cmd.spawn()
@@ -232,7 +236,7 @@ pub fn separate_vocals(
     info!("[SEPARATOR] 进程已启动, project_id={}", project_id);
 
     // 注册子进程句柄，支持即时取消（直接 kill）
-    let child_handle = crate::commands::video::register_child_process(project_id, child);
+    let child_handle = crate::commands::video::register_child_process(project_id, "separate_vocals", child);
 
     // 取出 stderr 后释放锁，让 kill_child_processes 可以随时获取锁来终止进程
     let stderr = {
@@ -278,6 +282,8 @@ pub fn separate_vocals(
     let mut reader = BufReader::new(stderr);
     let mut error_output = String::new();
     let mut line_buffer = String::new();
+    // 有新输出即视为有进展，长时间无输出判定为卡死
+    let mut watchdog = StallWatchdog::new(config::process_timeout());
 
     loop {
         // 检查取消标志
@@ -293,6 +299,21 @@ pub fn separate_vocals(
             return Err(AppError::Cancelled);
         }
 
+        // 暂停（进程被挂起）不算卡死
+        if crate::commands::video::is_paused(project_id) {
+            watchdog.touch();
+        }
+        if watchdog.is_stalled() {
+            error!("[SEPARATOR] audio-separator 超过 {} 秒无进展，终止进程: project_id={}", watchdog.timeout_secs(), project_id);
+            if let Ok(mut guard) = child_handle.lock() {
+                if let Some(ref mut child) = *guard {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+            }
+            return Err(AppError::Timeout(format!("audio-separator 超过 {} 秒无进展", watchdog.timeout_secs())));
+        }
+
         // 检查进程是否结束（需要短暂获取锁）
         let try_wait_result = {
             let mut guard = child_handle.lock().unwrap();
@@ -338,6 +359,7 @@ pub fn separate_vocals(
                     Ok(_) => {
                         let line = line_buffer.trim_end();
                         if !line.is_empty() {
+                            watchdog.touch();
                             debug!("[SEPARATOR] stderr: {}", line);
                             if !error_output.is_empty() {
                                 error_output.push('\n');
@@ -390,6 +412,10 @@ pub fn separate_vocals(
     if !status.success() {
         error!("[SEPARATOR] 处理失败，退出码: {:?}", status.code());
         error!("[SEPARATOR] 错误输出: {}", error_output);
+        if use_gpu && is_gpu_oom_output(&error_output) {
+            let tail: String = error_output.chars().rev().take(500).collect::<Vec<_>>().into_iter().rev().collect();
+            return Err(AppError::GpuOutOfMemory(format!("audio-separator: {}", tail)));
+        }
         let error_msg = if error_output.is_empty() {
             "audio-separator 处理失败（无详细错误信息）".to_string()
         } else {
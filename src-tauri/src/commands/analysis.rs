@@ -0,0 +1,358 @@
+// 媒体分析命令模块
+//
+// 与音乐匹配/人物检测互补的内容分析命令：
+// - detect_silence: 静音区间检测（可用于自动拆分长片段或裁剪首尾）
+// - detect_scene_changes: 场景切换检测（无可匹配音乐时的自动剪辑点）
+// - detect_black_frames: 黑屏/静止画面区间（建议裁剪片头片尾或排除出匹配范围）
+// - get_keyframes: 关键帧时间戳（按项目缓存到 temp/{project_id}_keyframes.json）
+// - generate_spectrogram: 频谱图（检查人声分离效果、定位音乐区间）
+// - detect_beats: 节拍时间戳（前端将手动调整的片段边界吸附到最近节拍）
+//
+// 取消机制复用 commands/video.rs 的 CANCEL_FLAGS / CHILD_PROCESSES，
+// 前端通过 cancel_processing(project_id) 即可取消。
+
+use crate::error::{AppError, AppResult};
+use crate::audio::beats;
+use crate::video::ffmpeg;
+use crate::commands::video::{CancelFlagGuard, reset_cancel_flag};
+use crate::utils::{AppState, media_input_exists};
+use tauri::{State, Window};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// 默认静音阈值（dB）
+pub(crate) const DEFAULT_SILENCE_THRESHOLD_DB: f64 = -30.0;
+/// 默认最短静音时长（秒）
+pub(crate) const DEFAULT_SILENCE_MIN_DURATION: f64 = 0.5;
+/// 默认场景变化阈值
+const DEFAULT_SCENE_THRESHOLD: f64 = 0.4;
+/// 频谱图默认尺寸
+const DEFAULT_SPECTROGRAM_WIDTH: u32 = 1280;
+const DEFAULT_SPECTROGRAM_HEIGHT: u32 = 512;
+/// 频谱图最大边长（showspectrumpic 的尺寸上限）
+const MAX_SPECTROGRAM_SIZE: u32 = 8192;
+
+/// 检测静音区间
+///
+/// 返回 `[(start, end), ...]`，单位秒，按时间排序。
+/// 事件: `silence-progress` — 检测进度
+#[tauri::command]
+pub async fn detect_silence(
+    window: Window,
+    path: String,
+    threshold_db: Option<f64>,
+    min_duration: Option<f64>,
+    project_id: Option<String>,
+) -> AppResult<Vec<(f64, f64)>> {
+    info!("[ANALYSIS] === 开始静音检测 === path={}", path);
+
+    if !media_input_exists(&path) {
+        return Err(AppError::NotFound(format!("文件不存在: {}", path)));
+    }
+
+    let threshold_db = threshold_db.unwrap_or(DEFAULT_SILENCE_THRESHOLD_DB);
+    let min_duration = min_duration.unwrap_or(DEFAULT_SILENCE_MIN_DURATION);
+    if !threshold_db.is_finite() || threshold_db > 0.0 {
+        return Err(AppError::InvalidArgument(format!("静音阈值必须为非正数 dB 值: {}", threshold_db)));
+    }
+    if !min_duration.is_finite() || min_duration <= 0.0 {
+        return Err(AppError::InvalidArgument(format!("最短静音时长必须大于 0: {}", min_duration)));
+    }
+
+    let cancel_flag_id = project_id.clone().unwrap_or_else(|| "default".to_string());
+    let _guard = CancelFlagGuard::new(cancel_flag_id.clone());
+    let cancel_flag = reset_cancel_flag(&cancel_flag_id);
+
+    let _ = window.emit("silence-progress", serde_json::json!({
+        "progress": 0.0,
+        "message": "开始静音检测...",
+        "project_id": project_id
+    }));
+
+    let project_id_clone = project_id.clone();
+    let window_clone = window.clone();
+    let intervals = ffmpeg::detect_silence(
+        &path,
+        threshold_db,
+        min_duration,
+        Some(Box::new(move |progress| {
+            let _ = window_clone.emit("silence-progress", serde_json::json!({
+                "progress": progress,
+                "message": format!("检测中: {:.1}%", progress * 100.0),
+                "project_id": project_id_clone
+            }));
+        })),
+        cancel_flag,
+        &cancel_flag_id,
+    )?;
+
+    info!("[ANALYSIS] 静音检测完成: {} 个静音区间", intervals.len());
+    Ok(intervals)
+}
+
+/// 检测场景切换点
+///
+/// 返回候选剪辑点时间列表（秒），前端可据此生成片段。
+/// 事件: `scene-progress` — 检测进度
+#[tauri::command]
+pub async fn detect_scene_changes(
+    window: Window,
+    path: String,
+    threshold: Option<f64>,
+    project_id: Option<String>,
+) -> AppResult<Vec<f64>> {
+    info!("[ANALYSIS] === 开始场景切换检测 === path={}", path);
+
+    if !media_input_exists(&path) {
+        return Err(AppError::NotFound(format!("视频文件不存在: {}", path)));
+    }
+
+    let threshold = threshold.unwrap_or(DEFAULT_SCENE_THRESHOLD);
+    if !threshold.is_finite() || threshold <= 0.0 || threshold >= 1.0 {
+        return Err(AppError::InvalidArgument(format!("场景变化阈值必须在 (0, 1) 之间: {}", threshold)));
+    }
+
+    let cancel_flag_id = project_id.clone().unwrap_or_else(|| "default".to_string());
+    let _guard = CancelFlagGuard::new(cancel_flag_id.clone());
+    let cancel_flag = reset_cancel_flag(&cancel_flag_id);
+
+    let _ = window.emit("scene-progress", serde_json::json!({
+        "progress": 0.0,
+        "message": "开始场景切换检测...",
+        "project_id": project_id
+    }));
+
+    let project_id_clone = project_id.clone();
+    let window_clone = window.clone();
+    let cut_points = ffmpeg::detect_scene_changes(
+        &path,
+        threshold,
+        Some(Box::new(move |progress| {
+            let _ = window_clone.emit("scene-progress", serde_json::json!({
+                "progress": progress,
+                "message": format!("检测中: {:.1}%", progress * 100.0),
+                "project_id": project_id_clone
+            }));
+        })),
+        cancel_flag,
+        &cancel_flag_id,
+    )?;
+
+    info!("[ANALYSIS] 场景切换检测完成: {} 个切换点", cut_points.len());
+    Ok(cut_points)
+}
+
+/// 检测黑屏与静止画面区间
+///
+/// 返回 `[(start, end), ...]`，单位秒，按时间排序，黑屏与静止画面重叠的区间已合并。
+/// 事件: `black-frames-progress` — 检测进度
+#[tauri::command]
+pub async fn detect_black_frames(
+    window: Window,
+    video_path: String,
+    project_id: Option<String>,
+) -> AppResult<Vec<(f64, f64)>> {
+    info!("[ANALYSIS] === 开始黑屏/静止画面检测 === path={}", video_path);
+
+    if !media_input_exists(&video_path) {
+        return Err(AppError::NotFound(format!("视频文件不存在: {}", video_path)));
+    }
+
+    let cancel_flag_id = project_id.clone().unwrap_or_else(|| "default".to_string());
+    let _guard = CancelFlagGuard::new(cancel_flag_id.clone());
+    let cancel_flag = reset_cancel_flag(&cancel_flag_id);
+
+    let _ = window.emit("black-frames-progress", serde_json::json!({
+        "progress": 0.0,
+        "message": "开始黑屏/静止画面检测...",
+        "project_id": project_id
+    }));
+
+    let project_id_clone = project_id.clone();
+    let window_clone = window.clone();
+    let intervals = ffmpeg::detect_black_frames(
+        &video_path,
+        Some(Box::new(move |progress| {
+            let _ = window_clone.emit("black-frames-progress", serde_json::json!({
+                "progress": progress,
+                "message": format!("检测中: {:.1}%", progress * 100.0),
+                "project_id": project_id_clone
+            }));
+        })),
+        cancel_flag,
+        &cancel_flag_id,
+    )?;
+
+    info!("[ANALYSIS] 黑屏/静止画面检测完成: {} 个区间", intervals.len());
+    Ok(intervals)
+}
+
+/// 获取关键帧时间戳（带项目级磁盘缓存）
+///
+/// 缓存文件比源视频新时直接读取，否则重新探测并写回缓存。
+/// 缓存读写失败不影响结果，只记录警告。
+pub(crate) fn load_keyframes_cached(
+    app_dir: &Path,
+    project_id: Option<&str>,
+    video_path: &str,
+) -> AppResult<Vec<f64>> {
+    let cache_file = project_id.map(|id| app_dir.join("temp").join(format!("{}_keyframes.json", id)));
+
+    if let Some(ref cache_file) = cache_file {
+        let cache_mtime = cache_file.metadata().and_then(|m| m.modified()).ok();
+        let video_mtime = Path::new(video_path).metadata().and_then(|m| m.modified()).ok();
+        if let (Some(c), Some(v)) = (cache_mtime, video_mtime) {
+            if c >= v {
+                match std::fs::read_to_string(cache_file)
+                    .map_err(AppError::from)
+                    .and_then(|content| serde_json::from_str::<Vec<f64>>(&content).map_err(AppError::from))
+                {
+                    Ok(keyframes) => {
+                        info!("[ANALYSIS] 关键帧缓存命中: {} ({} 个)", cache_file.display(), keyframes.len());
+                        return Ok(keyframes);
+                    }
+                    Err(e) => warn!("[ANALYSIS] 读取关键帧缓存失败，重新探测: {}", e),
+                }
+            }
+        }
+    }
+
+    let keyframes = ffmpeg::get_keyframes(video_path)?;
+
+    if let Some(ref cache_file) = cache_file {
+        let write_result = serde_json::to_string(&keyframes)
+            .map_err(AppError::from)
+            .and_then(|content| std::fs::write(cache_file, content).map_err(AppError::from));
+        if let Err(e) = write_result {
+            warn!("[ANALYSIS] 写入关键帧缓存失败: {}", e);
+        }
+    }
+
+    Ok(keyframes)
+}
+
+/// 获取视频关键帧时间戳
+///
+/// 传入 project_id 时按项目缓存探测结果，长视频重复调用无需再次探测。
+#[tauri::command]
+pub async fn get_keyframes(
+    video_path: String,
+    project_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<f64>> {
+    if !media_input_exists(&video_path) {
+        return Err(AppError::NotFound(format!("视频文件不存在: {}", video_path)));
+    }
+
+    load_keyframes_cached(&state.app_dir, project_id.as_deref(), &video_path)
+}
+
+/// 频谱图缓存键：源文件 + 修改时间 + 尺寸，源文件变化后自动失效
+fn spectrogram_cache_key(path: &str, width: u32, height: u32) -> String {
+    use sha2::{Digest, Sha256};
+    let mtime = Path::new(path).metadata().and_then(|m| m.modified()).ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}|{}|{}x{}", path, mtime, width, height));
+    hex::encode(&hasher.finalize()[..8])
+}
+
+/// 生成音频/视频文件的频谱图（PNG），返回图片路径
+///
+/// 未指定 output_png 时输出到 previews/spectrograms/{project_id}/，按 (文件, 修改时间, 尺寸) 缓存。
+/// 事件: `spectrogram-progress`；可通过 cancel_processing("spectrogram_{project_id}") 取消，
+/// 独立的取消标志不会影响同一项目正在进行的导出等任务。
+#[tauri::command]
+pub async fn generate_spectrogram(
+    window: Window,
+    path: String,
+    output_png: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    project_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<String> {
+    info!("[ANALYSIS] === 生成频谱图 === path={}", path);
+
+    if !media_input_exists(&path) {
+        return Err(AppError::NotFound(format!("文件不存在: {}", path)));
+    }
+
+    let width = width.unwrap_or(DEFAULT_SPECTROGRAM_WIDTH);
+    let height = height.unwrap_or(DEFAULT_SPECTROGRAM_HEIGHT);
+    if width == 0 || height == 0 || width > MAX_SPECTROGRAM_SIZE || height > MAX_SPECTROGRAM_SIZE {
+        return Err(AppError::InvalidArgument(format!(
+            "频谱图尺寸必须在 1-{} 之间: {}x{}", MAX_SPECTROGRAM_SIZE, width, height
+        )));
+    }
+
+    let output_path = match output_png {
+        Some(output) => output,
+        None => {
+            let cache_dir = state.app_dir
+                .join("previews")
+                .join("spectrograms")
+                .join(project_id.as_deref().unwrap_or("default"));
+            let cached = cache_dir.join(format!("{}.png", spectrogram_cache_key(&path, width, height)));
+            if cached.exists() {
+                info!("[ANALYSIS] 频谱图缓存命中: {}", cached.display());
+                return Ok(cached.to_string_lossy().to_string());
+            }
+            cached.to_string_lossy().to_string()
+        }
+    };
+
+    let cancel_flag_id = format!("spectrogram_{}", project_id.as_deref().unwrap_or("default"));
+    let _guard = CancelFlagGuard::new(cancel_flag_id.clone());
+    let cancel_flag = reset_cancel_flag(&cancel_flag_id);
+
+    let _ = window.emit("spectrogram-progress", serde_json::json!({
+        "progress": 0.0,
+        "message": "开始生成频谱图...",
+        "project_id": project_id
+    }));
+
+    let project_id_clone = project_id.clone();
+    let window_clone = window.clone();
+    let source_path = path.clone();
+    let output_clone = output_path.clone();
+    tokio::task::spawn_blocking(move || {
+        ffmpeg::generate_spectrogram(
+            &source_path,
+            &output_clone,
+            width,
+            height,
+            Some(Box::new(move |progress| {
+                let _ = window_clone.emit("spectrogram-progress", serde_json::json!({
+                    "progress": progress,
+                    "message": format!("生成中: {:.1}%", progress * 100.0),
+                    "project_id": project_id_clone
+                }));
+            })),
+            cancel_flag,
+            &cancel_flag_id,
+        )
+    })
+    .await
+    .map_err(|e| AppError::Video(format!("频谱图生成任务失败: {}", e)))??;
+
+    Ok(output_path)
+}
+
+/// 检测音频节拍，返回节拍时间戳（秒，升序）
+///
+/// 基于能量起音检测并假设速度恒定，软起音或变速曲目精度有限，详见 audio/beats.rs。
+#[tauri::command]
+pub async fn detect_beats(audio_path: String) -> AppResult<Vec<f64>> {
+    info!("[ANALYSIS] === 节拍检测 === path={}", audio_path);
+
+    if !media_input_exists(&audio_path) {
+        return Err(AppError::NotFound(format!("文件不存在: {}", audio_path)));
+    }
+
+    tokio::task::spawn_blocking(move || beats::detect_beats(&audio_path))
+        .await
+        .map_err(|e| AppError::Video(format!("节拍检测任务失败: {}", e)))?
+}
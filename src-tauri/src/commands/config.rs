@@ -1,15 +1,21 @@
 // 配置命令
 
 use crate::config::{self, AppConfig, WindowState};
-use crate::error::AppResult;
-use crate::utils::AccelerationOptions;
-use crate::commands::system::get_gpu_info;
-use crate::commands::video::detect_gpu_capabilities;
+use crate::error::{AppError, AppResult};
+use crate::utils::{AccelerationOptions, is_operation_temp_name};
+use crate::commands::system::{get_gpu_info, nvidia_gpu_count};
+use crate::commands::video::{detect_gpu_capabilities, has_active_operations, is_project_busy};
 use crate::database;
 use serde::{Deserialize, Serialize};
 use tauri::{State, Window};
 use crate::utils::AppState;
 use std::fs;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+/// 指纹提取采样率允许范围 (Hz)
+const FINGERPRINT_SAMPLE_RATE_MIN: u32 = 11025;
+const FINGERPRINT_SAMPLE_RATE_MAX: u32 = 48000;
 
 /// 获取配置
 #[tauri::command]
@@ -48,6 +54,69 @@ pub async fn update_config(window: Window, new_config: AppConfig) -> AppResult<(
         config::get_config().window_state
     };
 
+    // 校验 GPU 序号在检测到的 GPU 数量范围内（仅在修改时校验，避免换机后无法保存其他配置）
+    if let Some(index) = new_config.gpu_device_index.filter(|i| Some(*i) != config::get_config().gpu_device_index) {
+        let gpu_count = nvidia_gpu_count();
+        if index >= gpu_count {
+            return Err(AppError::InvalidArgument(format!(
+                "GPU 序号 {} 超出范围（检测到 {} 个 NVIDIA GPU）",
+                index, gpu_count
+            )));
+        }
+    }
+
+    // 低于 Chromaprint 内部处理采样率会丢失频段信息，高于 48kHz 无意义
+    let fp_rate = new_config.matching.fingerprint_sample_rate;
+    if !(FINGERPRINT_SAMPLE_RATE_MIN..=FINGERPRINT_SAMPLE_RATE_MAX).contains(&fp_rate) {
+        return Err(AppError::InvalidArgument(format!(
+            "指纹采样率必须在 {}-{} Hz 之间: {}",
+            FINGERPRINT_SAMPLE_RATE_MIN, FINGERPRINT_SAMPLE_RATE_MAX, fp_rate
+        )));
+    }
+
+    if !(0.0..=1.0).contains(&new_config.thumbnail_time_ratio) {
+        return Err(AppError::InvalidArgument(format!(
+            "缩略图截取位置必须在 0-1 之间: {}",
+            new_config.thumbnail_time_ratio
+        )));
+    }
+
+    if !(new_config.min_clip_seconds.is_finite() && new_config.min_clip_seconds >= 0.0) {
+        return Err(AppError::InvalidArgument(format!(
+            "剪辑最短时长必须为非负数: {}",
+            new_config.min_clip_seconds
+        )));
+    }
+
+    if new_config.log_retention_days < 1 {
+        return Err(AppError::InvalidArgument(format!(
+            "日志保留天数至少为 1 天: {}",
+            new_config.log_retention_days
+        )));
+    }
+
+    if let Some(dir) = new_config.cache_dir.as_deref().filter(|d| !d.trim().is_empty()) {
+        if !Path::new(dir).is_absolute() {
+            return Err(AppError::InvalidArgument(format!("缓存目录必须为绝对路径: {}", dir)));
+        }
+    }
+
+    let matching = &new_config.matching;
+    if !(matching.min_window_size > 0.0 && matching.max_window_size >= matching.min_window_size) {
+        return Err(AppError::InvalidArgument(format!(
+            "自适应窗口范围无效: {}-{} 秒",
+            matching.min_window_size, matching.max_window_size
+        )));
+    }
+
+    // 指纹参数变化后音乐库旧指纹不可比，前端通过 get_fingerprint_status 提示重新提取
+    let current_matching = config::get_config().matching;
+    if current_matching.fingerprint_sample_rate != new_config.matching.fingerprint_sample_rate
+        || current_matching.fingerprint_mono != new_config.matching.fingerprint_mono
+    {
+        warn!("[CONFIG] 指纹参数已变更，需重新提取音乐库指纹，完成前匹配结果可能不可靠");
+    }
+
     let mut config_to_save = new_config;
     config_to_save.window_state = current_window_state;
 
@@ -120,20 +189,175 @@ pub async fn get_storage_info(state: State<'_, AppState>) -> AppResult<StorageIn
     })
 }
 
+/// 库与项目统计信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsInfo {
+    pub music_count: u64,
+    /// 音乐库总时长（秒）
+    pub music_total_duration: f64,
+    pub project_count: u64,
+    pub segment_count: u64,
+    pub temp_size: u64,
+    pub previews_size: u64,
+    pub thumbnails_size: u64,
+    pub cache_total_size: u64,
+}
+
+/// 获取统计信息（用于概览面板）
+#[tauri::command]
+pub async fn get_stats(state: State<'_, AppState>) -> AppResult<StatsInfo> {
+    let (music_count, music_total_duration, project_count, segment_count) = database::get_aggregate_stats()?;
+
+    let temp_size = calculate_dir_size(&state.app_dir.join("temp"));
+    let previews_size = calculate_dir_size(&state.app_dir.join("previews"));
+    let thumbnails_size = calculate_dir_size(&state.app_dir.join("thumbnails"));
+
+    Ok(StatsInfo {
+        music_count,
+        music_total_duration,
+        project_count,
+        segment_count,
+        temp_size,
+        previews_size,
+        thumbnails_size,
+        cache_total_size: temp_size + previews_size + thumbnails_size,
+    })
+}
+
 /// 清理缓存（临时文件）
+///
+/// 与自动清理相同，所属项目有进行中的操作时跳过对应缓存项，共享缓存只在没有任何进行中操作时删除。
+/// 配置了独立的 cache_dir 时一并清理其 musiccut_temp 子目录中残留的操作临时目录
+/// （只删除 {uuid}_match_* / {uuid}_concat_* 等，不碰其他文件）。返回实际释放的字节数。
 #[tauri::command]
 pub async fn clear_cache(state: State<'_, AppState>) -> AppResult<u64> {
-    let temp_dir = state.app_dir.join("temp");
-    let cleared_size = calculate_dir_size(&temp_dir);
+    let app_temp = state.app_dir.join("temp");
+    let mut roots = vec![app_temp.clone()];
+    let cache_dir = config::app_temp_dir();
+    if !roots.contains(&cache_dir) {
+        roots.push(cache_dir);
+    }
 
-    if temp_dir.exists() {
-        fs::remove_dir_all(&temp_dir)?;
-        fs::create_dir_all(&temp_dir)?;
+    let mut cleared_size = 0;
+    for root in roots {
+        let only_operation_dirs = root != app_temp;
+        let Ok(entries) = fs::read_dir(&root) else { continue };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if only_operation_dirs && !is_operation_temp_name(&name) {
+                continue;
+            }
+            if is_cache_entry_busy(&name) {
+                debug!("[CACHE] 缓存项使用中，跳过清理: {}", name);
+                continue;
+            }
+            let size = if path.is_dir() {
+                calculate_dir_size(&path)
+            } else {
+                entry.metadata().map(|m| m.len()).unwrap_or(0)
+            };
+            let result = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+            match result {
+                Ok(()) => cleared_size += size,
+                Err(e) => warn!("[CACHE] 清理缓存项失败: {}, 错误: {}", path.display(), e),
+            }
+        }
     }
 
+    info!("[CACHE] 手动清理缓存，释放 {} 字节", cleared_size);
     Ok(cleared_size)
 }
 
+/// 缓存项是否正在使用：名称以项目 UUID 开头时看该项目是否有进行中的操作，否则看是否有任何进行中的操作
+fn is_cache_entry_busy(name: &str) -> bool {
+    let owner = name.split('_').next().unwrap_or_default();
+    if uuid::Uuid::parse_str(owner).is_ok() {
+        is_project_busy(owner)
+    } else {
+        has_active_operations()
+    }
+}
+
+/// 缓存自动清理结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachePurgeReport {
+    /// 被删除的缓存项（temp 下的文件/目录名）
+    pub purged: Vec<String>,
+    pub freed_bytes: u64,
+    /// 清理后的缓存大小
+    pub remaining_bytes: u64,
+}
+
+/// temp 缓存超过 max_cache_bytes 时按修改时间从旧到新删除缓存项
+///
+/// 缓存项为 temp 下的直接子项（{project_id}_audio.wav、{project_id}_separated 等），
+/// 所属项目有进行中的操作时跳过；segment_cache 等共享缓存只在没有任何进行中操作时删除。
+/// max_cache_bytes 为 0 时不清理。启动时与导出完成后调用。
+pub(crate) fn enforce_cache_limit(app_dir: &Path) -> CachePurgeReport {
+    let max_bytes = config::get_config().max_cache_bytes;
+    let mut report = CachePurgeReport::default();
+    let Ok(entries) = fs::read_dir(app_dir.join("temp")) else { return report };
+
+    let mut items: Vec<(std::path::PathBuf, String, u64, std::time::SystemTime)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| {
+            let path = e.path();
+            let name = e.file_name().to_string_lossy().to_string();
+            let size = if path.is_dir() {
+                calculate_dir_size(&path)
+            } else {
+                e.metadata().map(|m| m.len()).unwrap_or(0)
+            };
+            let mtime = e.metadata().and_then(|m| m.modified()).unwrap_or(std::time::UNIX_EPOCH);
+            (path, name, size, mtime)
+        })
+        .collect();
+
+    let mut total: u64 = items.iter().map(|(_, _, size, _)| size).sum();
+    report.remaining_bytes = total;
+    if max_bytes == 0 || total <= max_bytes {
+        return report;
+    }
+
+    // 最久未修改的排在前面
+    items.sort_by_key(|(_, _, _, mtime)| *mtime);
+    for (path, name, size, _) in items {
+        if total <= max_bytes {
+            break;
+        }
+        if is_cache_entry_busy(&name) {
+            debug!("[CACHE] 缓存项使用中，跳过清理: {}", name);
+            continue;
+        }
+        let result = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+        match result {
+            Ok(()) => {
+                total -= size;
+                report.freed_bytes += size;
+                report.purged.push(name);
+            }
+            Err(e) => warn!("[CACHE] 清理缓存项失败: {}, 错误: {}", path.display(), e),
+        }
+    }
+    report.remaining_bytes = total;
+
+    info!(
+        "[CACHE] 缓存超出上限 {} 字节，已清理 {} 项，释放 {} 字节，剩余 {} 字节",
+        max_bytes, report.purged.len(), report.freed_bytes, total
+    );
+    report
+}
+
+/// 按 max_cache_bytes 立即执行一次缓存自动清理，返回清理结果
+#[tauri::command]
+pub async fn auto_clean_cache(state: State<'_, AppState>) -> AppResult<CachePurgeReport> {
+    let app_dir = state.app_dir.clone();
+    tokio::task::spawn_blocking(move || enforce_cache_limit(&app_dir))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(format!("缓存清理任务失败: {}", e))))
+}
+
 /// 重置数据库（清空所有数据）
 #[tauri::command]
 pub async fn reset_database() -> AppResult<()> {
@@ -141,6 +365,31 @@ pub async fn reset_database() -> AppResult<()> {
     database::clear_all_data()
 }
 
+/// 数据库优化结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseOptimizeResult {
+    pub size_before: u64,
+    pub size_after: u64,
+    pub reclaimed: u64,
+}
+
+/// 优化数据库（PRAGMA optimize + VACUUM），返回优化前后的文件大小
+///
+/// 导入、匹配、导出等操作进行中时拒绝执行，避免长时间锁库。
+#[tauri::command]
+pub async fn optimize_database(state: State<'_, AppState>) -> AppResult<DatabaseOptimizeResult> {
+    if database::has_heavy_operation() || has_active_operations() {
+        return Err(AppError::InvalidArgument("有正在进行的任务，请完成后再优化数据库".to_string()));
+    }
+
+    let (size_before, size_after) = database::optimize_database(&state.db_path)?;
+    Ok(DatabaseOptimizeResult {
+        size_before,
+        size_after,
+        reclaimed: size_before.saturating_sub(size_after),
+    })
+}
+
 /// 重置配置为默认值
 #[tauri::command]
 pub async fn reset_config() -> AppResult<()> {
@@ -149,7 +398,7 @@ pub async fn reset_config() -> AppResult<()> {
 }
 
 /// 计算目录大小
-fn calculate_dir_size(path: &std::path::Path) -> u64 {
+pub(crate) fn calculate_dir_size(path: &std::path::Path) -> u64 {
     if !path.exists() {
         return 0;
     }
@@ -8,18 +8,27 @@
 use crate::config::{self, AccelerationMode};
 use crate::database;
 use crate::error::{AppError, AppResult};
-use crate::utils::{Segment, SegmentStatus, SegmentType, generate_id};
+use crate::utils::{Segment, SegmentStatus, SegmentType, generate_id, media_input_exists};
 use crate::detection::detector;
-use crate::commands::video::{CancelFlagGuard, get_cancel_flag, reset_cancel_flag, kill_child_processes};
+use crate::logging;
+use crate::commands::video::{CancelFlagGuard, OperationMetrics, get_cancel_flag, reset_cancel_flag, kill_child_processes};
 use tauri::Window;
-use std::sync::atomic::Ordering;
-use tracing::info;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{error, info, warn};
 
 /// 独立的人物检测 GPU 信号量：与人声分离的 GPU_SEMAPHORE 完全隔离，
 /// 允许检测和分离任务各自独立排队，互不阻塞
 static DETECTION_GPU_SEMAPHORE: once_cell::sync::Lazy<tokio::sync::Semaphore> =
     once_cell::sync::Lazy::new(|| tokio::sync::Semaphore::new(1));
 
+/// 人物检测 GPU 是否被占用
+pub(crate) fn detection_gpu_busy() -> bool {
+    DETECTION_GPU_SEMAPHORE.available_permits() == 0
+}
+
 /// 执行人物检测
 ///
 /// 流程：获取 GPU 许可 → 调用 person-detector → 清除旧片段 → 写入新片段 → 发送完成事件
@@ -27,7 +36,10 @@ static DETECTION_GPU_SEMAPHORE: once_cell::sync::Lazy<tokio::sync::Semaphore> =
 /// 事件：
 /// - `detection-queued`   — GPU 繁忙时通知前端排队
 /// - `detection-progress`  — 检测进度 (progress: 0.0-1.0, message, project_id)
-/// - `detection-complete`  — 检测完成统计 (segments_count, total_frames, ...)
+/// - `detection-complete`  — 检测完成统计 (segments_count, total_frames, ..., partial)
+/// - `operation-metrics`   — 耗时等性能指标
+///
+/// 中途取消时若 person-detector 已写出中间结果，保存已检测的开头部分并以 partial=true 返回。
 #[tauri::command]
 pub async fn detect_persons(
     window: Window,
@@ -36,6 +48,7 @@ pub async fn detect_persons(
     output_dir: String,
     acceleration: Option<String>,
 ) -> AppResult<Vec<Segment>> {
+    let started = std::time::Instant::now();
     info!("[DETECTION] === 开始人物检测 === project_id={}", project_id);
     info!("[DETECTION] 视频路径: {}", video_path);
     info!("[DETECTION] 输出目录: {}", output_dir);
@@ -43,14 +56,102 @@ pub async fn detect_persons(
     // 使用 det_ 前缀的取消标志，与人声分离完全隔离
     let cancel_flag_id = format!("det_{}", project_id);
     info!("[DETECTION] 取消标志ID: {}", cancel_flag_id);
-    let _guard = CancelFlagGuard::new(cancel_flag_id.clone());
+    let _guard = CancelFlagGuard::with_project_lock(cancel_flag_id.clone(), &project_id);
     let cancel_flag = reset_cancel_flag(&cancel_flag_id);
 
-    // 独立的 GPU 信号量排队
-    let _permit = match DETECTION_GPU_SEMAPHORE.try_acquire() {
+    let _permit = acquire_detection_permit(&window, &project_id, &cancel_flag).await?;
+
+    let accel_mode = parse_acceleration(acceleration.as_deref());
+
+    // 通知前端开始检测
+    let _ = window.emit("detection-progress", serde_json::json!({
+        "progress": 0.0,
+        "message": "开始人物检测...",
+        "project_id": project_id
+    }));
+
+    let make_progress_callback = || -> detector::ProgressCallback {
+        let window_clone = window.clone();
+        let project_id_clone = project_id.clone();
+        Box::new(move |progress, message| {
+            let _ = window_clone.emit("detection-progress", serde_json::json!({
+                "progress": progress,
+                "message": message,
+                "project_id": project_id_clone
+            }));
+        })
+    };
+    let op_span = logging::operation_span(&project_id, "detection");
+    let _op_enter = op_span.enter();
+    let mut used_gpu = accel_mode != AccelerationMode::Cpu;
+    let (segments, result) = match detect_and_store(
+        &project_id,
+        &video_path,
+        &output_dir,
+        &accel_mode,
+        make_progress_callback(),
+        cancel_flag.clone(),
+    ) {
+        // GPU 显存不足时自动以 CPU 模式重试一次
+        Err(AppError::GpuOutOfMemory(msg)) if accel_mode != AccelerationMode::Cpu => {
+            warn!("[DETECTION] GPU 显存不足，改用 CPU 重试: {}", msg);
+            let _ = window.emit("gpu-fallback", serde_json::json!({
+                "project_id": project_id,
+                "operation": "detection",
+                "message": "GPU 显存不足，已自动改用 CPU 进行人物检测"
+            }));
+            used_gpu = false;
+            detect_and_store(
+                &project_id,
+                &video_path,
+                &output_dir,
+                &AccelerationMode::Cpu,
+                make_progress_callback(),
+                cancel_flag,
+            )?
+        }
+        other => other?,
+    };
+
+    // 通知前端检测完成
+    let _ = window.emit("detection-complete", serde_json::json!({
+        "project_id": project_id,
+        "segments_count": segments.len(),
+        "total_frames": result.total_frames,
+        "processed_frames": result.processed_frames,
+        "detection_frames": result.detection_frames,
+        "partial": result.partial,
+    }));
+
+    OperationMetrics {
+        input_duration: crate::video::ffmpeg::get_audio_duration(&video_path).ok(),
+        segment_count: Some(segments.len()),
+        gpu: used_gpu,
+        ..OperationMetrics::new("detection", Some(project_id.clone()), started, &video_path)
+    }
+    .emit(&window);
+
+    Ok(segments)
+}
+
+/// 解析加速选项（默认 GPU）
+fn parse_acceleration(acceleration: Option<&str>) -> AccelerationMode {
+    match acceleration {
+        Some("cpu") => AccelerationMode::Cpu,
+        Some("gpu") | Some("auto") | Some("hybrid") | _ => AccelerationMode::Gpu,
+    }
+}
+
+/// 获取检测 GPU 许可，繁忙时发送 `detection-queued` 并排队（排队期间响应取消）
+async fn acquire_detection_permit(
+    window: &Window,
+    project_id: &str,
+    cancel_flag: &AtomicBool,
+) -> AppResult<tokio::sync::SemaphorePermit<'static>> {
+    match DETECTION_GPU_SEMAPHORE.try_acquire() {
         Ok(permit) => {
             info!("[DETECTION] 直接获取检测 GPU 许可: project_id={}", project_id);
-            permit
+            Ok(permit)
         }
         Err(_) => {
             info!("[DETECTION] 检测 GPU 繁忙，排队等待: project_id={}", project_id);
@@ -65,7 +166,7 @@ pub async fn detect_persons(
                         match result {
                             Ok(permit) => {
                                 info!("[DETECTION] 排队结束，获取许可: project_id={}", project_id);
-                                break permit;
+                                return Ok(permit);
                             }
                             Err(_) => {
                                 return Err(AppError::Detection("检测 GPU 信号量异常关闭".to_string()));
@@ -81,55 +182,47 @@ pub async fn detect_persons(
                 }
             }
         }
-    };
+    }
+}
 
+/// 调用 person-detector 并用检测结果替换项目片段（调用方需已持有检测 GPU 许可）
+fn detect_and_store(
+    project_id: &str,
+    video_path: &str,
+    output_dir: &str,
+    accel_mode: &AccelerationMode,
+    progress_callback: detector::ProgressCallback,
+    cancel_flag: Arc<AtomicBool>,
+) -> AppResult<(Vec<Segment>, detector::DetectionResult)> {
     let app_config = config::get_config();
     let det_config = &app_config.detection;
 
-    let accel_mode = match acceleration.as_deref() {
-        Some("cpu") => AccelerationMode::Cpu,
-        Some("gpu") | Some("auto") | Some("hybrid") | _ => AccelerationMode::Gpu,
-    };
     info!("[DETECTION] 加速模式: {:?}", accel_mode);
     info!("[DETECTION] 检测配置: 置信度={}, 抽帧间隔={}, 最小片段={}s, 最大间隔={}s",
         det_config.confidence_threshold, det_config.frame_interval,
         det_config.min_segment_duration, det_config.max_gap_duration);
 
-    // 通知前端开始检测
-    let _ = window.emit("detection-progress", serde_json::json!({
-        "progress": 0.0,
-        "message": "开始人物检测...",
-        "project_id": project_id
-    }));
-
     // 调用 person-detector 核心检测逻辑
-    let window_clone = window.clone();
-    let project_id_clone = project_id.clone();
     let result = detector::detect_persons(
-        &video_path,
-        &output_dir,
+        video_path,
+        output_dir,
         det_config,
-        &accel_mode,
-        Some(Box::new(move |progress, message| {
-            let _ = window_clone.emit("detection-progress", serde_json::json!({
-                "progress": progress,
-                "message": message,
-                "project_id": project_id_clone
-            }));
-        })),
+        accel_mode,
+        Some(progress_callback),
         cancel_flag,
-        &project_id,
+        project_id,
     )?;
 
     // 清除该项目的所有旧片段（音乐匹配 + 人物检测），每次任务输出全新结果
     info!("[DETECTION] 清除所有旧片段: project_id={}", project_id);
-    database::delete_segments_by_project(&project_id)?;
+    database::delete_segments_by_project(project_id)?;
+    database::delete_detection_boxes_by_project(project_id)?;
 
     // 将检测结果转换为 Segment 并批量写入数据库
     let segments: Vec<Segment> = result.segments.iter().map(|s| {
         Segment {
             id: generate_id(),
-            project_id: project_id.clone(),
+            project_id: project_id.to_string(),
             music_id: None,
             music_title: None,
             start_time: s.start_time,
@@ -137,24 +230,209 @@ pub async fn detect_persons(
             confidence: s.confidence,
             status: SegmentStatus::Detected,
             segment_type: SegmentType::Person,
+            gain_db: None,
         }
     }).collect();
 
     info!("[DETECTION] 写入 {} 个 person 片段到数据库", segments.len());
     database::batch_insert_detection_segments(&segments, "person")?;
+    let boxes: Vec<(String, Vec<detector::DetectionBox>)> = segments
+        .iter()
+        .zip(&result.segments)
+        .map(|(segment, person)| (segment.id.clone(), person.boxes.clone()))
+        .collect();
+    database::batch_insert_detection_boxes(project_id, &boxes)?;
 
-    // 通知前端检测完成
-    let _ = window.emit("detection-complete", serde_json::json!({
-        "project_id": project_id,
-        "segments_count": segments.len(),
-        "total_frames": result.total_frames,
-        "processed_frames": result.processed_frames,
-        "detection_frames": result.detection_frames,
+    info!("[DETECTION] === 人物检测完成 === project_id={}, 片段数={}, 总帧数={}, 处理帧数={}, 检测帧数={}, 部分结果={}",
+        project_id, segments.len(), result.total_frames, result.processed_frames, result.detection_frames, result.partial);
+    Ok((segments, result))
+}
+
+/// 获取项目人物片段的检测框
+///
+/// 返回 segment_id -> 检测框列表（按时间排序，坐标归一化到 0-1），
+/// 旧版 person-detector 生成的片段没有检测框，不出现在结果中。
+#[tauri::command]
+pub async fn get_detection_boxes(project_id: String) -> AppResult<HashMap<String, Vec<detector::DetectionBox>>> {
+    database::get_detection_boxes(&project_id)
+}
+
+/// 批量检测的取消标识，cancel_processing("det_batch") 可取消整个批次
+pub(crate) const BATCH_DETECTION_FLAG_ID: &str = "det_batch";
+
+/// 批量人物检测
+///
+/// 按顺序逐个项目检测（经 DETECTION_GPU_SEMAPHORE 串行），结果写入各项目片段。
+/// 每个项目使用自己的 det_ 取消标志，可单独取消；源视频缺失或检测失败的项目
+/// 记录错误后继续处理下一个，不中断整个批次。
+/// 检测输出写入 output_dir/{project_id}_detection。
+///
+/// 事件: `batch-detection-progress` / `batch-detection-complete`
+#[tauri::command]
+pub async fn batch_detect_persons(
+    window: Window,
+    project_ids: Vec<String>,
+    output_dir: String,
+    acceleration: Option<String>,
+) -> AppResult<serde_json::Value> {
+    let total = project_ids.len();
+    let accel_mode = parse_acceleration(acceleration.as_deref());
+    info!("[BATCH_DETECTION] === 开始批量人物检测 === 项目数={}, 输出目录={}, 加速模式={:?}", total, output_dir, accel_mode);
+
+    let _batch_guard = CancelFlagGuard::new(BATCH_DETECTION_FLAG_ID.to_string());
+    let batch_cancel = reset_cancel_flag(BATCH_DETECTION_FLAG_ID);
+
+    // 批次取消时，将取消信号转发给正在检测的项目
+    let batch_done = Arc::new(AtomicBool::new(false));
+    let watcher = {
+        let batch_cancel = batch_cancel.clone();
+        let batch_done = batch_done.clone();
+        let ids = project_ids.clone();
+        std::thread::spawn(move || {
+            while !batch_done.load(Ordering::SeqCst) {
+                if batch_cancel.load(Ordering::SeqCst) {
+                    for id in &ids {
+                        let flag_id = format!("det_{}", id);
+                        get_cancel_flag(&flag_id).store(true, Ordering::SeqCst);
+                        kill_child_processes(&flag_id);
+                    }
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+        })
+    };
+
+    let mut detected = 0;
+    let mut skipped = 0;
+    let mut total_segments = 0;
+    let mut errors: Vec<String> = Vec::new();
+
+    for (index, project_id) in project_ids.iter().enumerate() {
+        if batch_cancel.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let cancel_flag_id = format!("det_{}", project_id);
+        let _guard = CancelFlagGuard::with_project_lock(cancel_flag_id.clone(), project_id);
+        let cancel_flag = reset_cancel_flag(&cancel_flag_id);
+
+        let result = async {
+            let project = match database::get_project_by_id(project_id) {
+                Ok(Some(p)) => p,
+                Ok(None) => return Err(format!("{}: 项目不存在", project_id)),
+                Err(e) => return Err(format!("{}: 读取项目失败 - {}", project_id, e)),
+            };
+
+            if !media_input_exists(&project.source_video_path) {
+                info!("[BATCH_DETECTION] 跳过源视频缺失的项目: {} ({})", project.name, project.source_video_path);
+                return Ok(None);
+            }
+
+            let _permit = acquire_detection_permit(&window, project_id, &cancel_flag)
+                .await
+                .map_err(|e| format!("{}: {}", project.name, e))?;
+
+            let project_output_dir = Path::new(&output_dir).join(format!("{}_detection", project_id));
+            let make_progress_callback = || -> detector::ProgressCallback {
+                let window_clone = window.clone();
+                let pid = project_id.clone();
+                Box::new(move |progress, message| {
+                    let overall = (index as f32 + progress) / total as f32;
+                    let _ = window_clone.emit("batch-detection-progress", serde_json::json!({
+                        "project_id": pid,
+                        "progress": progress,
+                        "overall_progress": overall.min(1.0),
+                        "current": index,
+                        "total": total,
+                        "message": message
+                    }));
+                })
+            };
+            let project_output_dir = project_output_dir.to_string_lossy();
+            let detected = match detect_and_store(
+                project_id,
+                &project.source_video_path,
+                &project_output_dir,
+                &accel_mode,
+                make_progress_callback(),
+                cancel_flag.clone(),
+            ) {
+                // GPU 显存不足时自动以 CPU 模式重试一次
+                Err(AppError::GpuOutOfMemory(msg)) if accel_mode != AccelerationMode::Cpu => {
+                    warn!("[BATCH_DETECTION] GPU 显存不足，改用 CPU 重试: project_id={}, {}", project_id, msg);
+                    let _ = window.emit("gpu-fallback", serde_json::json!({
+                        "project_id": project_id,
+                        "operation": "detection",
+                        "message": "GPU 显存不足，已自动改用 CPU 进行人物检测"
+                    }));
+                    detect_and_store(
+                        project_id,
+                        &project.source_video_path,
+                        &project_output_dir,
+                        &AccelerationMode::Cpu,
+                        make_progress_callback(),
+                        cancel_flag.clone(),
+                    )
+                }
+                other => other,
+            };
+            let (segments, _) = detected.map_err(|e| format!("{}: {}", project.name, e))?;
+
+            Ok::<_, String>(Some(segments.len()))
+        }
+        .await;
+
+        let done = index + 1;
+        let _ = window.emit("batch-detection-progress", serde_json::json!({
+            "project_id": project_id,
+            "progress": 1.0,
+            "overall_progress": done as f32 / total.max(1) as f32,
+            "current": done,
+            "total": total,
+            "message": match &result {
+                Ok(Some(count)) => format!("检测完成: {} 个片段", count),
+                Ok(None) => "跳过(源视频不存在)".to_string(),
+                Err(e) => format!("检测失败: {}", e),
+            }
+        }));
+
+        match result {
+            Ok(Some(count)) => {
+                detected += 1;
+                total_segments += count;
+            }
+            Ok(None) => skipped += 1,
+            Err(e) => {
+                error!("[BATCH_DETECTION] 项目检测失败: project_id={}, {}", project_id, e);
+                errors.push(e);
+            }
+        }
+    }
+
+    batch_done.store(true, Ordering::SeqCst);
+    let _ = watcher.join();
+
+    let _ = window.emit("batch-detection-complete", serde_json::json!({
+        "detected": detected,
+        "skipped": skipped,
+        "errors": errors.len(),
+        "error_messages": errors,
+        "total": total
     }));
 
-    info!("[DETECTION] === 人物检测完成 === project_id={}, 片段数={}, 总帧数={}, 处理帧数={}, 检测帧数={}",
-        project_id, segments.len(), result.total_frames, result.processed_frames, result.detection_frames);
-    Ok(segments)
+    info!("[BATCH_DETECTION] 批量检测完成: 成功 {}, 跳过 {}, 失败 {}, 总计 {}", detected, skipped, errors.len(), total);
+
+    if batch_cancel.load(Ordering::SeqCst) {
+        return Err(AppError::Cancelled);
+    }
+
+    Ok(serde_json::json!({
+        "detected_count": detected,
+        "skipped_count": skipped,
+        "segments_count": total_segments,
+        "error_messages": errors
+    }))
 }
 
 /// 取消人物检测
@@ -1,30 +1,68 @@
 // 指纹命令
 
+use crate::config;
 use crate::database;
-use crate::error::AppResult;
+use crate::error::{AppError, AppResult};
 use crate::utils::MatchResult;
 use crate::audio::fingerprint;
+use crate::commands::system::{require_dependencies, Dependency};
 use tauri::Window;
 use rayon::prelude::*;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 
 /// 提取指纹
 #[tauri::command]
 pub async fn extract_fingerprint(audio_path: String) -> AppResult<String> {
-    let (fingerprint_data, _duration) = fingerprint::extract_fingerprint_from_file(&audio_path)?;
+    require_dependencies(&[Dependency::Ffmpeg, Dependency::Fpcalc]).await?;
+    let (fingerprint_data, _duration) = fingerprint::extract_fingerprint_normalized(&audio_path)?;
     let hash = fingerprint::compute_fingerprint_hash(&fingerprint_data);
     Ok(hash)
 }
 
+/// 原始指纹数据（供外部工具使用）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RawFingerprint {
+    /// Chromaprint 原始指纹（小端 i32 数组）的 base64 编码
+    pub fingerprint: String,
+    /// 音频时长（秒）
+    pub duration: f64,
+}
+
+/// 获取音乐库中已存储的原始指纹
+#[tauri::command]
+pub async fn get_fingerprint(music_id: String) -> AppResult<RawFingerprint> {
+    let (fingerprint_data, duration) = database::get_fingerprint_by_id(&music_id)?
+        .ok_or_else(|| AppError::NotFound(format!("音乐不存在: {}", music_id)))?;
+    Ok(RawFingerprint {
+        fingerprint: BASE64.encode(fingerprint_data),
+        duration,
+    })
+}
+
+/// 提取原始指纹（不写入音乐库）
+///
+/// 与音乐库导入使用相同的音频参数，结果可直接与 get_fingerprint 比较。
+#[tauri::command]
+pub async fn extract_fingerprint_raw(path: String) -> AppResult<RawFingerprint> {
+    require_dependencies(&[Dependency::Ffmpeg, Dependency::Fpcalc]).await?;
+    let (fingerprint_data, duration) = fingerprint::extract_fingerprint_normalized(&path)?;
+    Ok(RawFingerprint {
+        fingerprint: BASE64.encode(fingerprint_data),
+        duration,
+    })
+}
+
 /// 匹配指纹
 #[tauri::command]
 pub async fn match_fingerprint(
     audio_path: String,
     min_confidence: Option<f64>,
 ) -> AppResult<Vec<MatchResult>> {
+    require_dependencies(&[Dependency::Ffmpeg, Dependency::Fpcalc]).await?;
     let min_conf = min_confidence.unwrap_or(0.6);
 
     // 提取待匹配音频的指纹
-    let (query_fingerprint, _duration) = fingerprint::extract_fingerprint_from_file(&audio_path)?;
+    let (query_fingerprint, _duration) = fingerprint::extract_fingerprint_normalized(&audio_path)?;
 
     // 获取所有音乐指纹
     let library = database::get_all_fingerprints()?;
@@ -33,11 +71,16 @@ pub async fn match_fingerprint(
         return Ok(Vec::new());
     }
 
-    // 并行匹配
+    let min_overlap_ratio = config::get_config().matching.min_overlap_ratio as f64;
+    let query = fingerprint::decode_fingerprint(&query_fingerprint);
+
+    // 并行匹配（偏移对齐）
     let results: Vec<MatchResult> = library
         .par_iter()
         .filter_map(|(music_id, music_title, music_fingerprint)| {
-            let confidence = fingerprint::compare_fingerprints(&query_fingerprint, music_fingerprint);
+            let reference = fingerprint::decode_fingerprint(music_fingerprint);
+            let confidence = fingerprint::compare_fingerprints_aligned(&query, &reference, min_overlap_ratio)?
+                .similarity;
 
             if confidence >= min_conf {
                 Some(MatchResult {
@@ -70,13 +113,14 @@ pub async fn batch_extract_fingerprints(
     window: Window,
     paths: Vec<String>,
 ) -> AppResult<Vec<String>> {
+    require_dependencies(&[Dependency::Ffmpeg, Dependency::Fpcalc]).await?;
     let total = paths.len();
 
     let results: Vec<AppResult<String>> = paths
         .par_iter()
         .enumerate()
         .map(|(index, path)| {
-            let result = fingerprint::extract_fingerprint_from_file(path)
+            let result = fingerprint::extract_fingerprint_normalized(path)
                 .map(|(data, _)| fingerprint::compute_fingerprint_hash(&data));
 
             let _ = window.emit("fingerprint-progress", serde_json::json!({
@@ -1,16 +1,18 @@
 // 音乐库命令
 
+use crate::config::{self, FileExtensionsConfig};
 use crate::database;
 use crate::error::{AppError, AppResult};
-use crate::utils::{MusicInfo, generate_id};
+use crate::utils::{MusicInfo, MusicUsage, generate_id, lock_or_recover};
 use crate::audio::fingerprint;
+use crate::commands::system::{require_dependencies, Dependency};
 use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use walkdir::WalkDir;
 use tauri::Window;
 use chrono::Local;
-
-/// 支持的音频格式
-const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "wav", "m4a", "aac", "ogg", "wma"];
+use rayon::prelude::*;
 
 /// 导入音乐文件夹
 #[tauri::command]
@@ -18,23 +20,19 @@ pub async fn import_music_folder(
     window: Window,
     path: String,
 ) -> AppResult<Vec<MusicInfo>> {
+    require_dependencies(&[Dependency::Ffmpeg, Dependency::Fpcalc]).await?;
+    let _db_guard = database::begin_heavy_operation();
     let folder_path = Path::new(&path);
     if !folder_path.exists() {
         return Err(AppError::NotFound(format!("文件夹不存在: {}", path)));
     }
 
-    // 收集所有音频文件
+    // 收集所有音频文件（扩展名列表可在配置中修改）
+    let audio_extensions = config::get_config().file_extensions.audio;
     let audio_files: Vec<String> = WalkDir::new(folder_path)
         .into_iter()
         .filter_map(|e| e.ok())
-        .filter(|e| {
-            if let Some(ext) = e.path().extension() {
-                let ext_lower = ext.to_string_lossy().to_lowercase();
-                AUDIO_EXTENSIONS.contains(&ext_lower.as_str())
-            } else {
-                false
-            }
-        })
+        .filter(|e| FileExtensionsConfig::matches(&audio_extensions, e.path()))
         .map(|e| e.path().to_string_lossy().to_string())
         .collect();
 
@@ -50,12 +48,11 @@ pub async fn import_music_folder(
         "message": format!("发现 {} 个音频文件", total)
     }));
 
-    // 顺序处理音频文件（避免并行访问数据库导致死锁）
-    let mut imported = Vec::new();
+    // 先顺序过滤已存在的文件（只读查询，开销很小）
     let mut skipped = 0;
     let mut errors = Vec::new();
-
-    for (index, file_path) in audio_files.iter().enumerate() {
+    let mut pending = Vec::new();
+    for file_path in audio_files.iter() {
         let file_name = Path::new(file_path).file_name().unwrap_or_default().to_string_lossy();
 
         // 检查是否已存在
@@ -63,42 +60,79 @@ pub async fn import_music_folder(
             Ok(true) => {
                 skipped += 1;
                 let _ = window.emit("import-progress", serde_json::json!({
-                    "current": index + 1,
+                    "current": skipped + errors.len(),
                     "total": total,
                     "message": format!("跳过(已存在): {}", file_name)
                 }));
-                continue;
             }
+            Ok(false) => pending.push(file_path.clone()),
             Err(e) => {
                 tracing::warn!("检查文件是否存在失败 {}: {}", file_path, e);
                 errors.push(format!("{}: {}", file_name, e));
-                continue;
             }
-            _ => {}
         }
+    }
 
-        // 发送进度
-        let _ = window.emit("import-progress", serde_json::json!({
-            "current": index + 1,
-            "total": total,
-            "message": format!("处理中: {}", file_name)
-        }));
+    // 并行提取指纹（耗时主要在 fpcalc），数据库写入经由互斥锁串行执行，避免并发写库
+    let writer = Mutex::new(ImportWriter {
+        duplicate_checker: NearDuplicateChecker::load()?,
+        imported: Vec::new(),
+        duplicates: Vec::new(),
+        errors,
+        skipped,
+    });
+    let completed = AtomicUsize::new(total - pending.len());
 
-        match process_audio_file(file_path) {
-            Ok(music) => imported.push(music),
-            Err(e) => {
-                tracing::warn!("导入音乐失败 {}: {}", file_path, e);
-                errors.push(format!("{}: {}", file_name, e));
+    let num_threads = num_cpus::get().saturating_sub(2).max(1);
+    tracing::info!("[LIBRARY] 并行导入: {} 个文件, 线程数={}", pending.len(), num_threads);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .map_err(|e| AppError::Config(format!("创建线程池失败: {}", e)))?;
+
+    pool.install(|| {
+        pending.par_iter().for_each(|file_path| {
+            let file_name = Path::new(file_path).file_name().unwrap_or_default().to_string_lossy();
+            let extracted = extract_audio_file(file_path);
+
+            let mut writer = lock_or_recover(&writer, "音乐导入写入");
+            let result = extracted.and_then(|audio| {
+                store_audio_file(file_path, audio, writer.duplicate_checker.as_mut())
+            });
+            match result {
+                Ok(ImportOutcome::Imported(music)) => writer.imported.push(music),
+                Ok(ImportOutcome::NearDuplicate(duplicate)) => {
+                    writer.skipped += 1;
+                    writer.duplicates.push(duplicate);
+                }
+                Err(e) => {
+                    tracing::warn!("导入音乐失败 {}: {}", file_path, e);
+                    writer.errors.push(format!("{}: {}", file_name, e));
+                }
             }
-        }
-    }
+            drop(writer);
+
+            // 发送进度（按完成顺序计数）
+            let current = completed.fetch_add(1, Ordering::SeqCst) + 1;
+            let _ = window.emit("import-progress", serde_json::json!({
+                "current": current,
+                "total": total,
+                "message": format!("处理中: {}", file_name)
+            }));
+        });
+    });
+
+    let ImportWriter { imported, duplicates, errors, skipped, .. } = writer
+        .into_inner()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
 
     // 发送完成事件
     let _ = window.emit("import-complete", serde_json::json!({
         "imported": imported.len(),
         "skipped": skipped,
         "errors": errors.len(),
-        "error_messages": errors
+        "error_messages": errors,
+        "duplicates": duplicates
     }));
 
     Ok(imported)
@@ -110,13 +144,17 @@ pub async fn import_music_files(
     window: Window,
     paths: Vec<String>,
 ) -> AppResult<Vec<MusicInfo>> {
+    let _db_guard = database::begin_heavy_operation();
     let total = paths.len();
     if total == 0 {
         return Ok(Vec::new());
     }
+    require_dependencies(&[Dependency::Ffmpeg, Dependency::Fpcalc]).await?;
 
     let mut imported = Vec::new();
     let mut skipped = 0;
+    let mut duplicates = Vec::new();
+    let mut duplicate_checker = NearDuplicateChecker::load()?;
 
     for (index, file_path) in paths.iter().enumerate() {
         let file_name = Path::new(file_path).file_name().unwrap_or_default().to_string_lossy();
@@ -145,8 +183,12 @@ pub async fn import_music_files(
             "message": format!("处理中: {}", file_name)
         }));
 
-        match process_audio_file(file_path) {
-            Ok(music) => imported.push(music),
+        match process_audio_file(file_path, duplicate_checker.as_mut()) {
+            Ok(ImportOutcome::Imported(music)) => imported.push(music),
+            Ok(ImportOutcome::NearDuplicate(duplicate)) => {
+                skipped += 1;
+                duplicates.push(duplicate);
+            }
             Err(e) => {
                 tracing::warn!("导入音乐失败 {}: {}", file_path, e);
             }
@@ -157,17 +199,121 @@ pub async fn import_music_files(
     let _ = window.emit("import-complete", serde_json::json!({
         "imported": imported.len(),
         "skipped": skipped,
-        "total": total
+        "total": total,
+        "duplicates": duplicates
     }));
 
     Ok(imported)
 }
 
+/// 疑似重复的导入文件
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NearDuplicate {
+    /// 未导入的文件路径
+    pub file_path: String,
+    /// 音乐库中相似的歌曲
+    pub music_id: String,
+    pub music_title: String,
+    /// 指纹相似度 (0.0 - 1.0)
+    pub similarity: f64,
+}
+
+/// 单个文件的导入结果
+enum ImportOutcome {
+    Imported(MusicInfo),
+    /// 与音乐库已有歌曲指纹相似度超过阈值，未导入
+    NearDuplicate(NearDuplicate),
+}
+
+/// 并行导入时由互斥锁保护的写入状态（数据库写入与结果汇总）
+struct ImportWriter {
+    duplicate_checker: Option<NearDuplicateChecker>,
+    imported: Vec<MusicInfo>,
+    duplicates: Vec<NearDuplicate>,
+    errors: Vec<String>,
+    skipped: usize,
+}
+
+/// 导入时的近似重复检查（配置 near_duplicate_check 开启时使用）
+///
+/// 导入开始时加载一次全库指纹，新导入的歌曲追加进来，同批次内的重复也能识别。
+struct NearDuplicateChecker {
+    library: Vec<(String, String, Vec<u8>)>,
+    threshold: f64,
+}
+
+impl NearDuplicateChecker {
+    /// 配置未开启时返回 None
+    fn load() -> AppResult<Option<Self>> {
+        let config = config::get_config();
+        if !config.near_duplicate_check {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            library: database::get_all_fingerprints()?,
+            threshold: config.near_duplicate_threshold as f64,
+        }))
+    }
+
+    /// 查找相似度最高且超过阈值的已有歌曲，返回 (ID, 标题, 相似度)
+    fn find(&self, fingerprint_data: &[u8]) -> Option<(String, String, f64)> {
+        self.library
+            .par_iter()
+            .map(|(id, title, fp)| (id, title, fingerprint::compare_fingerprints(fingerprint_data, fp)))
+            .filter(|(_, _, similarity)| *similarity >= self.threshold)
+            .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(id, title, similarity)| (id.clone(), title.clone(), similarity))
+    }
+}
+
+/// 单个文件的指纹提取结果（可并行计算，不访问数据库）
+struct ExtractedAudio {
+    fingerprint_data: Vec<u8>,
+    fingerprint_hash: String,
+    fingerprint_version: String,
+    duration: f64,
+}
+
 /// 处理单个音频文件
-fn process_audio_file(file_path: &str) -> AppResult<MusicInfo> {
-    // 提取指纹
-    let (fingerprint_data, duration) = fingerprint::extract_fingerprint_from_file(file_path)?;
+fn process_audio_file(
+    file_path: &str,
+    duplicate_checker: Option<&mut NearDuplicateChecker>,
+) -> AppResult<ImportOutcome> {
+    let audio = extract_audio_file(file_path)?;
+    store_audio_file(file_path, audio, duplicate_checker)
+}
+
+/// 提取单个音频文件的指纹
+fn extract_audio_file(file_path: &str) -> AppResult<ExtractedAudio> {
+    let fingerprint_version = fingerprint::current_fingerprint_version();
+    let (fingerprint_data, duration) = fingerprint::extract_fingerprint_normalized(file_path)?;
     let fingerprint_hash = fingerprint::compute_fingerprint_hash(&fingerprint_data);
+    Ok(ExtractedAudio { fingerprint_data, fingerprint_hash, fingerprint_version, duration })
+}
+
+/// 近似重复检查并写入数据库（调用方需保证串行执行）
+fn store_audio_file(
+    file_path: &str,
+    audio: ExtractedAudio,
+    duplicate_checker: Option<&mut NearDuplicateChecker>,
+) -> AppResult<ImportOutcome> {
+    let ExtractedAudio { fingerprint_data, fingerprint_hash, fingerprint_version, duration } = audio;
+
+    // 近似重复检查（不同码率/格式的同一首歌）
+    if let Some(ref checker) = duplicate_checker {
+        if let Some((music_id, music_title, similarity)) = checker.find(&fingerprint_data) {
+            tracing::warn!(
+                "疑似重复音乐，跳过导入: {} (与 {} 相似度 {:.2})",
+                file_path, music_title, similarity
+            );
+            return Ok(ImportOutcome::NearDuplicate(NearDuplicate {
+                file_path: file_path.to_string(),
+                music_id,
+                music_title,
+                similarity,
+            }));
+        }
+    }
 
     // 获取文件名作为标题
     let path = Path::new(file_path);
@@ -188,15 +334,113 @@ fn process_audio_file(file_path: &str) -> AppResult<MusicInfo> {
     };
 
     // 保存到数据库
-    database::insert_music(&music, &fingerprint_data)?;
+    database::insert_music(&music, &fingerprint_data, &fingerprint_version)?;
+
+    if let Some(checker) = duplicate_checker {
+        checker.library.push((music.id.clone(), music.title.clone(), fingerprint_data));
+    }
+
+    Ok(ImportOutcome::Imported(music))
+}
+
+/// 音乐库指纹参数状态
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FingerprintStatus {
+    /// 当前配置对应的指纹参数标识
+    pub current_version: String,
+    /// 指纹参数与当前配置不一致、需要重新提取的音乐数
+    pub outdated_count: u64,
+}
 
-    Ok(music)
+/// 获取音乐库指纹参数状态（outdated_count > 0 时匹配结果可能不可靠）
+#[tauri::command]
+pub async fn get_fingerprint_status() -> AppResult<FingerprintStatus> {
+    let current_version = fingerprint::current_fingerprint_version();
+    let outdated_count = database::count_outdated_fingerprints(&current_version)?;
+    Ok(FingerprintStatus { current_version, outdated_count })
+}
+
+/// 按当前指纹参数重新提取音乐库中参数不一致的指纹
+///
+/// 事件: `reextract-progress` / `reextract-complete`。源文件缺失或提取失败的记录保持原样，
+/// 计入 error_messages。返回成功更新的数量。
+#[tauri::command]
+pub async fn reextract_fingerprints(window: Window) -> AppResult<usize> {
+    require_dependencies(&[Dependency::Ffmpeg, Dependency::Fpcalc]).await?;
+    let _db_guard = database::begin_heavy_operation();
+
+    let version = fingerprint::current_fingerprint_version();
+    let outdated = database::get_music_with_outdated_fingerprint(&version)?;
+    let total = outdated.len();
+    tracing::info!("[LIBRARY] 重新提取指纹: 当前参数 {}, 需更新 {} 首", version, total);
+
+    let mut updated = 0;
+    let mut errors = Vec::new();
+    for (index, (id, title, file_path)) in outdated.iter().enumerate() {
+        let _ = window.emit("reextract-progress", serde_json::json!({
+            "current": index + 1,
+            "total": total,
+            "message": format!("重新提取指纹: {}", title)
+        }));
+
+        if !Path::new(file_path).exists() {
+            errors.push(format!("{}: 源文件不存在", title));
+            continue;
+        }
+        let result = fingerprint::extract_fingerprint_normalized(file_path).and_then(|(data, duration)| {
+            let hash = fingerprint::compute_fingerprint_hash(&data);
+            database::update_music_fingerprint(id, &data, &hash, duration, &version)
+        });
+        match result {
+            Ok(()) => updated += 1,
+            Err(e) => {
+                tracing::warn!("重新提取指纹失败 {}: {}", file_path, e);
+                errors.push(format!("{}: {}", title, e));
+            }
+        }
+    }
+
+    let _ = window.emit("reextract-complete", serde_json::json!({
+        "updated": updated,
+        "errors": errors.len(),
+        "error_messages": errors
+    }));
+    tracing::info!("[LIBRARY] 重新提取指纹完成: 成功 {}, 失败 {}", updated, errors.len());
+
+    Ok(updated)
 }
 
 /// 获取音乐库
+///
+/// check_files 默认为 true；传 false 时跳过文件存在性检查以加快加载（适用于网络盘）
+#[tauri::command]
+pub async fn get_music_library(check_files: Option<bool>) -> AppResult<Vec<MusicInfo>> {
+    database::get_all_music(check_files.unwrap_or(true))
+}
+
+/// 单页最大条数
+const MAX_PAGE_SIZE: u32 = 1000;
+
+/// 分页获取音乐库
+///
+/// sort 可选值: title_asc（默认）/ title_desc / created_asc / created_desc / duration_asc / duration_desc
 #[tauri::command]
-pub async fn get_music_library() -> AppResult<Vec<MusicInfo>> {
-    database::get_all_music()
+pub async fn get_music_library_page(
+    offset: u32,
+    limit: u32,
+    sort: Option<String>,
+    check_files: Option<bool>,
+) -> AppResult<Vec<MusicInfo>> {
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(AppError::InvalidArgument(format!("分页大小必须在 1 - {} 之间: {}", MAX_PAGE_SIZE, limit)));
+    }
+    database::get_music_page(offset, limit, sort.as_deref().unwrap_or("title_asc"), check_files.unwrap_or(true))
+}
+
+/// 获取音乐库总数
+#[tauri::command]
+pub async fn count_music() -> AppResult<u64> {
+    database::count_music()
 }
 
 /// 删除音乐
@@ -211,13 +455,56 @@ pub async fn delete_all_music() -> AppResult<()> {
     database::clear_all_music()
 }
 
+/// 批量删除音乐（单个事务），返回实际删除的数量
+///
+/// 引用这些音乐的片段保留，music_id 置空。
+#[tauri::command]
+pub async fn delete_music_bulk(ids: Vec<String>) -> AppResult<usize> {
+    database::delete_music_bulk(&ids)
+}
+
+/// 按条件批量删除音乐的筛选条件，多个条件同时满足才删除
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct MusicDeleteFilter {
+    /// 只删除源文件已不存在的音乐
+    #[serde(default)]
+    pub missing_file: bool,
+    /// 只删除指定专辑的音乐（不区分大小写）
+    pub album: Option<String>,
+}
+
+/// 按条件批量删除音乐，返回删除的数量
+///
+/// 至少需要一个条件，避免误删整个音乐库（清空请使用 delete_all_music）。
+#[tauri::command]
+pub async fn delete_music_where(filter: MusicDeleteFilter) -> AppResult<usize> {
+    let album = filter.album.as_deref().map(str::trim).filter(|a| !a.is_empty());
+    if !filter.missing_file && album.is_none() {
+        return Err(AppError::InvalidArgument("请至少指定一个删除条件".to_string()));
+    }
+
+    let ids: Vec<String> = database::get_all_music(filter.missing_file)?
+        .into_iter()
+        .filter(|m| !filter.missing_file || !m.file_exists)
+        .filter(|m| match album {
+            Some(album) => m.album.as_deref().is_some_and(|a| a.eq_ignore_ascii_case(album)),
+            None => true,
+        })
+        .map(|m| m.id)
+        .collect();
+
+    tracing::info!("[LIBRARY] 按条件删除音乐: missing_file={}, album={:?}, 匹配 {} 首", filter.missing_file, album, ids.len());
+    database::delete_music_bulk(&ids)
+}
+
 /// 搜索音乐
 #[tauri::command]
-pub async fn search_music(query: String) -> AppResult<Vec<MusicInfo>> {
+pub async fn search_music(query: String, check_files: Option<bool>) -> AppResult<Vec<MusicInfo>> {
+    let check_files = check_files.unwrap_or(true);
     if query.is_empty() {
-        return database::get_all_music();
+        return database::get_all_music(check_files);
     }
-    database::search_music(&query)
+    database::search_music(&query, check_files)
 }
 
 /// 获取音乐信息
@@ -225,3 +512,18 @@ pub async fn search_music(query: String) -> AppResult<Vec<MusicInfo>> {
 pub async fn get_music_info(id: String) -> AppResult<Option<MusicInfo>> {
     database::get_music_by_id(&id)
 }
+
+/// 获取音乐使用情况（引用片段数、项目数、最近使用的项目）
+#[tauri::command]
+pub async fn get_music_usage(id: String) -> AppResult<MusicUsage> {
+    if database::get_music_by_id(&id)?.is_none() {
+        return Err(AppError::NotFound(format!("音乐不存在: {}", id)));
+    }
+    database::get_music_usage(&id)
+}
+
+/// 获取未被任何项目片段引用的音乐（便于清理音乐库）
+#[tauri::command]
+pub async fn find_unused_music(check_files: Option<bool>) -> AppResult<Vec<MusicInfo>> {
+    database::find_unused_music(check_files.unwrap_or(true))
+}
@@ -8,3 +8,4 @@ pub mod video;
 pub mod project;
 pub mod models;
 pub mod detection;
+pub mod analysis;
@@ -1,10 +1,16 @@
 // 模型管理命令
 
+use crate::config;
+use crate::commands::config::calculate_dir_size;
 use crate::models::{self, ModelInfo, ModelStatus};
 use crate::error::{AppResult, AppError};
-use crate::utils::hidden_command;
+use crate::commands::video::{CancelFlagGuard, get_cancel_flag, kill_child_processes, register_child_process, reset_cancel_flag};
+use crate::utils::{hidden_command, lock_or_recover};
+use std::collections::HashSet;
 use std::process::Stdio;
 use std::io::{BufRead, BufReader, Read as _};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use tauri::Manager;
 use serde::Serialize;
 use tracing::{info, error, debug};
@@ -17,6 +23,116 @@ pub struct ModelDownloadProgress {
     pub message: String,
     pub completed: bool,
     pub error: Option<String>,
+    /// 是否因用户取消而结束
+    pub cancelled: bool,
+}
+
+/// 模型下载的取消标志 ID（复用 CANCEL_FLAGS / CHILD_PROCESSES）
+pub(crate) fn model_download_flag_id(model_id: &str) -> String {
+    format!("model_{}", model_id)
+}
+
+/// 下载进度转发回调
+pub(crate) type DownloadProgressForward = Arc<dyn Fn(&ModelDownloadProgress) + Send + Sync>;
+
+/// 下载进度上报：发送 model-download-progress 事件，可同时转发给调用方（如分离前自动下载）
+#[derive(Clone)]
+pub(crate) struct DownloadReporter {
+    app_handle: tauri::AppHandle,
+    forward: Option<DownloadProgressForward>,
+}
+
+impl DownloadReporter {
+    pub(crate) fn new(app_handle: tauri::AppHandle) -> Self {
+        Self { app_handle, forward: None }
+    }
+
+    pub(crate) fn with_forward(app_handle: tauri::AppHandle, forward: DownloadProgressForward) -> Self {
+        Self { app_handle, forward: Some(forward) }
+    }
+
+    fn emit(&self, progress: ModelDownloadProgress) -> tauri::Result<()> {
+        if let Some(ref forward) = self.forward {
+            forward(&progress);
+        }
+        self.app_handle.emit_all("model-download-progress", progress)
+    }
+}
+
+/// 模型下载管理：按 model_id 跟踪进行中的下载，限制同时下载数
+#[derive(Default)]
+struct DownloadManager {
+    /// 已提交（排队或下载中）的模型 ID
+    in_flight: HashSet<String>,
+    /// 正在下载的数量
+    running: u32,
+}
+
+static DOWNLOADS: once_cell::sync::Lazy<Mutex<DownloadManager>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(DownloadManager::default()));
+
+/// 下载排队时检查空位的间隔
+const DOWNLOAD_QUEUE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// RAII 守卫：下载结束（成功/失败）时释放 model_id 与下载名额
+struct DownloadSlot {
+    model_id: String,
+    running: bool,
+}
+
+impl DownloadSlot {
+    /// 登记下载请求；同一模型已在排队或下载中时返回 None，避免两个下载写同一个临时文件
+    fn register(model_id: &str) -> Option<Self> {
+        let mut downloads = lock_or_recover(&DOWNLOADS, "模型下载");
+        if !downloads.in_flight.insert(model_id.to_string()) {
+            return None;
+        }
+        Some(Self { model_id: model_id.to_string(), running: false })
+    }
+
+    /// 等待下载名额（同时下载数由 max_concurrent_downloads 配置）
+    ///
+    /// 排队期间被取消时返回 Cancelled。
+    async fn acquire(&mut self, reporter: &DownloadReporter, cancel_flag: &AtomicBool) -> AppResult<()> {
+        let mut notified = false;
+        loop {
+            if cancel_flag.load(Ordering::SeqCst) {
+                return Err(AppError::Cancelled);
+            }
+            {
+                let limit = config::get_config().max_concurrent_downloads.max(1);
+                let mut downloads = lock_or_recover(&DOWNLOADS, "模型下载");
+                if downloads.running < limit {
+                    downloads.running += 1;
+                    self.running = true;
+                    return Ok(());
+                }
+            }
+            if !notified {
+                notified = true;
+                info!("模型下载排队等待: {}", self.model_id);
+                let _ = reporter.emit(ModelDownloadProgress {
+                    model_id: self.model_id.clone(),
+                    progress: 0.0,
+                    message: "排队等待下载...".to_string(),
+                    completed: false,
+                    error: None,
+                    cancelled: false,
+                });
+            }
+            tokio::time::sleep(DOWNLOAD_QUEUE_POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl Drop for DownloadSlot {
+    fn drop(&mut self) {
+        let mut downloads = lock_or_recover(&DOWNLOADS, "模型下载");
+        downloads.in_flight.remove(&self.model_id);
+        if self.running {
+            downloads.running = downloads.running.saturating_sub(1);
+        }
+    }
 }
 
 /// 获取所有可用模型列表（包括分离模型和检测模型）
@@ -57,30 +173,182 @@ pub async fn get_model_info(model_id: String) -> AppResult<Option<ModelInfo>> {
     Ok(models::get_detection_model_by_id(&model_id))
 }
 
+/// 单个模型目录的磁盘占用
+#[derive(Clone, Serialize)]
+pub struct ModelStorageEntry {
+    /// 模型 ID（孤立目录为目录名）
+    pub model_id: String,
+    /// 显示名称，孤立目录为 None
+    pub name: Option<String>,
+    /// 模型类别: "separation" / "detection"
+    pub kind: String,
+    pub path: String,
+    /// 目录大小（字节）
+    pub size: u64,
+    /// 目录不对应任何已知模型（旧版本残留等）
+    pub orphaned: bool,
+}
+
+/// 模型磁盘占用汇总
+#[derive(Clone, Serialize)]
+pub struct ModelStorage {
+    pub models: Vec<ModelStorageEntry>,
+    pub total_size: u64,
+}
+
+/// 统计模型根目录下各子目录的大小，按 known 匹配模型，其余标记为孤立目录
+fn collect_model_storage(root: &std::path::Path, kind: &str, known: &[ModelInfo]) -> Vec<ModelStorageEntry> {
+    let Ok(entries) = std::fs::read_dir(root) else { return Vec::new() };
+    let mut result: Vec<ModelStorageEntry> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            let model = known.iter().find(|m| m.id == dir_name);
+            ModelStorageEntry {
+                name: model.map(|m| m.name.clone()),
+                orphaned: model.is_none(),
+                model_id: dir_name,
+                kind: kind.to_string(),
+                size: calculate_dir_size(&entry.path()),
+                path: entry.path().to_string_lossy().to_string(),
+            }
+        })
+        .collect();
+    result.sort_by(|a, b| a.model_id.cmp(&b.model_id));
+    result
+}
+
+/// 获取各模型目录的磁盘占用（含不对应已知模型的孤立目录）
+#[tauri::command]
+pub async fn get_model_storage() -> AppResult<ModelStorage> {
+    tokio::task::spawn_blocking(|| {
+        let mut models = collect_model_storage(
+            &models::get_models_cache_dir(),
+            "separation",
+            &models::get_available_models(),
+        );
+        models.extend(collect_model_storage(
+            &models::get_detection_models_cache_dir(),
+            "detection",
+            &models::get_detection_models(),
+        ));
+        let total_size = models.iter().map(|m| m.size).sum();
+        info!("模型磁盘占用: {} 个目录, 共 {} 字节", models.len(), total_size);
+        ModelStorage { models, total_size }
+    })
+    .await
+    .map_err(|e| AppError::Io(std::io::Error::other(format!("统计模型占用失败: {}", e))))
+}
+
 /// 下载模型
 /// 分离模型通过 audio-separator 触发下载，检测模型通过 HTTP 直接下载
+/// 同一模型重复请求时等待进行中的下载结束并返回其结果；超过同时下载数时排队等待
 #[tauri::command]
 pub async fn download_model(
     app_handle: tauri::AppHandle,
     model_id: String,
 ) -> AppResult<()> {
+    run_model_download(DownloadReporter::new(app_handle), model_id).await
+}
+
+/// 执行模型下载（download_model 与分离前自动下载共用）
+pub(crate) async fn run_model_download(reporter: DownloadReporter, model_id: String) -> AppResult<()> {
+    let detection_model = models::get_detection_model_by_id(&model_id);
+    if detection_model.is_none() && models::get_model_by_id(&model_id).is_none() {
+        return Err(AppError::NotFound(format!("模型不存在: {}", model_id)));
+    }
+
+    let Some(mut slot) = DownloadSlot::register(&model_id) else {
+        return wait_for_in_flight_download(&reporter, &model_id).await;
+    };
+    let flag_id = model_download_flag_id(&model_id);
+    let _guard = CancelFlagGuard::new(flag_id.clone());
+    let cancel_flag = reset_cancel_flag(&flag_id);
+
+    if let Err(e) = slot.acquire(&reporter, &cancel_flag).await {
+        emit_download_cancelled(&reporter, &model_id);
+        return Err(e);
+    }
+
     // 先查检测模型
-    if let Some(model) = models::get_detection_model_by_id(&model_id) {
-        return download_detection_model(app_handle, model_id, model).await;
+    if let Some(model) = detection_model {
+        return download_detection_model(reporter, model_id, model, cancel_flag).await;
     }
 
     // 否则按分离模型处理
     let model = models::get_model_by_id(&model_id)
         .ok_or_else(|| AppError::NotFound(format!("模型不存在: {}", model_id)))?;
 
-    download_separation_model(app_handle, model_id, model).await
+    download_separation_model(reporter, model_id, model, cancel_flag).await
+}
+
+/// 等待同一模型进行中的下载结束，按模型文件是否就绪返回其结果
+///
+/// 不登记新的下载，也不重置取消标志，取消该模型下载会同时结束等待。
+async fn wait_for_in_flight_download(reporter: &DownloadReporter, model_id: &str) -> AppResult<()> {
+    info!("模型已在下载中，等待其完成: {}", model_id);
+    let _ = reporter.emit(ModelDownloadProgress {
+        model_id: model_id.to_string(),
+        progress: 0.0,
+        message: "等待进行中的下载...".to_string(),
+        completed: false,
+        error: None,
+        cancelled: false,
+    });
+    while lock_or_recover(&DOWNLOADS, "模型下载").in_flight.contains(model_id) {
+        tokio::time::sleep(DOWNLOAD_QUEUE_POLL_INTERVAL).await;
+    }
+
+    let downloaded = match models::get_detection_model_by_id(model_id) {
+        Some(model) => models::check_detection_model_downloaded(&model).downloaded,
+        None => models::get_model_by_id(model_id)
+            .map(|model| models::check_model_downloaded(&model).downloaded)
+            .unwrap_or(false),
+    };
+    if downloaded {
+        info!("进行中的模型下载已完成: {}", model_id);
+        Ok(())
+    } else {
+        Err(AppError::NotFound(format!("模型下载未完成（已取消或失败）: {}", model_id)))
+    }
+}
+
+/// 取消模型下载（排队中或下载中均可取消）
+///
+/// 检测模型在下一次读取数据时中止并删除 .pt.tmp；分离模型直接终止 audio-separator 进程。
+#[tauri::command]
+pub async fn cancel_model_download(model_id: String) -> AppResult<()> {
+    if !lock_or_recover(&DOWNLOADS, "模型下载").in_flight.contains(&model_id) {
+        return Err(AppError::NotFound(format!("模型未在下载中: {}", model_id)));
+    }
+
+    info!("取消模型下载: {}", model_id);
+    let flag_id = model_download_flag_id(&model_id);
+    get_cancel_flag(&flag_id).store(true, Ordering::SeqCst);
+    kill_child_processes(&flag_id);
+    Ok(())
+}
+
+/// 发送下载已取消事件
+fn emit_download_cancelled(reporter: &DownloadReporter, model_id: &str) {
+    info!("模型下载已取消: {}", model_id);
+    let _ = reporter.emit(ModelDownloadProgress {
+        model_id: model_id.to_string(),
+        progress: 0.0,
+        message: "下载已取消".to_string(),
+        completed: true,
+        error: None,
+        cancelled: true,
+    });
 }
 
 /// 下载检测模型（YOLO）- 通过 HTTP 直接下载 .pt 文件
 async fn download_detection_model(
-    app_handle: tauri::AppHandle,
+    reporter: DownloadReporter,
     model_id: String,
     model: ModelInfo,
+    cancel_flag: Arc<AtomicBool>,
 ) -> AppResult<()> {
     let model_dir = models::ensure_detection_model_dir(&model)?;
     let model_path = model_dir.join(&model.filename);
@@ -88,12 +356,13 @@ async fn download_detection_model(
     info!("开始下载检测模型: {} ({})", model.name, model.filename);
     info!("目标路径: {}", model_path.display());
 
-    let _ = app_handle.emit_all("model-download-progress", ModelDownloadProgress {
+    let _ = reporter.emit(ModelDownloadProgress {
         model_id: model_id.clone(),
         progress: 0.0,
         message: "准备下载检测模型...".to_string(),
         completed: false,
         error: None,
+        cancelled: false,
     });
 
     // YOLO 模型下载 URL (GitHub releases)
@@ -103,17 +372,18 @@ async fn download_detection_model(
     );
     info!("下载地址: {}", download_url);
 
-    let _ = app_handle.emit_all("model-download-progress", ModelDownloadProgress {
+    let _ = reporter.emit(ModelDownloadProgress {
         model_id: model_id.clone(),
         progress: 0.1,
         message: "正在下载检测模型...".to_string(),
         completed: false,
         error: None,
+        cancelled: false,
     });
 
     // 在阻塞线程中执行 HTTP 下载
     let model_id_clone = model_id.clone();
-    let app_handle_clone = app_handle.clone();
+    let reporter_clone = reporter.clone();
     let result = tokio::task::spawn_blocking(move || {
         let response = ureq::get(&download_url)
             .call()
@@ -122,9 +392,11 @@ async fn download_detection_model(
         let content_length = response.header("Content-Length")
             .and_then(|s| s.parse::<u64>().ok());
 
-        // 写入临时文件，完成后重命名
-        let temp_path = model_path.with_extension("pt.tmp");
-        let mut file = std::fs::File::create(&temp_path)
+        // 写入同目录下的唯一临时文件，完成后重命名；中途失败或取消时临时文件随 drop 删除
+        let mut file = tempfile::Builder::new()
+            .prefix(&format!("{}_", model_id_clone))
+            .suffix(".pt.tmp")
+            .tempfile_in(&model_dir)
             .map_err(|e| AppError::Detection(format!("创建文件失败: {}", e)))?;
 
         let mut reader = response.into_reader();
@@ -133,6 +405,10 @@ async fn download_detection_model(
         let mut last_progress: f32 = 0.1;
 
         loop {
+            if cancel_flag.load(Ordering::SeqCst) {
+                return Err(AppError::Cancelled);
+            }
+
             let n = reader.read(&mut buf)
                 .map_err(|e| AppError::Detection(format!("读取数据失败: {}", e)))?;
             if n == 0 { break; }
@@ -147,22 +423,21 @@ async fn download_detection_model(
                 // 每 5% 更新一次进度
                 if progress - last_progress >= 0.05 {
                     last_progress = progress;
-                    let _ = app_handle_clone.emit_all("model-download-progress", ModelDownloadProgress {
+                    let _ = reporter_clone.emit(ModelDownloadProgress {
                         model_id: model_id_clone.clone(),
                         progress,
                         message: format!("下载中... {:.0}%", progress * 100.0),
                         completed: false,
                         error: None,
+                        cancelled: false,
                     });
                 }
             }
         }
 
-        drop(file);
-
         // 重命名临时文件
-        std::fs::rename(&temp_path, &model_path)
-            .map_err(|e| AppError::Detection(format!("重命名文件失败: {}", e)))?;
+        file.persist(&model_path)
+            .map_err(|e| AppError::Detection(format!("重命名文件失败: {}", e.error)))?;
 
         info!("检测模型下载完成: {}", model_path.display());
         Ok::<(), AppError>(())
@@ -170,23 +445,29 @@ async fn download_detection_model(
 
     match result {
         Ok(()) => {
-            let _ = app_handle.emit_all("model-download-progress", ModelDownloadProgress {
+            let _ = reporter.emit(ModelDownloadProgress {
                 model_id: model_id.clone(),
                 progress: 1.0,
                 message: "下载完成".to_string(),
                 completed: true,
                 error: None,
+                cancelled: false,
             });
             Ok(())
         }
+        Err(AppError::Cancelled) => {
+            emit_download_cancelled(&reporter, &model_id);
+            Err(AppError::Cancelled)
+        }
         Err(e) => {
             error!("检测模型下载失败: {}", e);
-            let _ = app_handle.emit_all("model-download-progress", ModelDownloadProgress {
+            let _ = reporter.emit(ModelDownloadProgress {
                 model_id: model_id.clone(),
                 progress: 0.0,
                 message: "下载失败".to_string(),
                 completed: true,
                 error: Some(format!("{}", e)),
+                cancelled: false,
             });
             Err(e)
         }
@@ -195,9 +476,10 @@ async fn download_detection_model(
 
 /// 下载分离模型 - 通过 audio-separator 触发下载
 async fn download_separation_model(
-    app_handle: tauri::AppHandle,
+    reporter: DownloadReporter,
     model_id: String,
     model: ModelInfo,
+    cancel_flag: Arc<AtomicBool>,
 ) -> AppResult<()> {
 
     let model_dir = models::ensure_model_dir(&model)?;
@@ -210,24 +492,27 @@ async fn download_separation_model(
     );
 
     // 发送开始事件
-    let _ = app_handle.emit_all("model-download-progress", ModelDownloadProgress {
+    let _ = reporter.emit(ModelDownloadProgress {
         model_id: model_id.clone(),
         progress: 0.0,
         message: "准备下载模型...".to_string(),
         completed: false,
         error: None,
+        cancelled: false,
     });
 
-    // 创建临时目录和静音音频文件
-    let temp_dir = std::env::temp_dir().join("musiccut_model_download");
-    std::fs::create_dir_all(&temp_dir)?;
+    // 创建临时目录和静音音频文件（每次下载使用唯一目录，重试或并发下载互不覆盖；
+    // TempDir 在离开作用域时删除，成功与失败路径都会清理）
+    let temp_dir = tempfile::Builder::new()
+        .prefix(&format!("musiccut_model_download_{}_", model_id))
+        .tempdir()?;
 
-    let temp_audio = temp_dir.join("silence.wav");
-    let temp_output = temp_dir.join("output");
+    let temp_audio = temp_dir.path().join("silence.wav");
+    let temp_output = temp_dir.path().join("output");
     std::fs::create_dir_all(&temp_output)?;
     info!(
         "临时目录: {}, 临时音频: {}, 临时输出: {}",
-        temp_dir.to_string_lossy(),
+        temp_dir.path().to_string_lossy(),
         temp_audio.to_string_lossy(),
         temp_output.to_string_lossy()
     );
@@ -247,12 +532,13 @@ async fn download_separation_model(
 
     if ffmpeg_result.is_err() || !ffmpeg_result.unwrap().success() {
         error!("创建临时音频文件失败");
-        let _ = app_handle.emit_all("model-download-progress", ModelDownloadProgress {
+        let _ = reporter.emit(ModelDownloadProgress {
             model_id: model_id.clone(),
             progress: 0.0,
             message: "创建临时文件失败".to_string(),
             completed: true,
             error: Some("无法创建临时音频文件，请确保 ffmpeg 已安装".to_string()),
+            cancelled: false,
         });
         return Err(AppError::FFmpeg("创建临时音频文件失败".to_string()));
     }
@@ -273,12 +559,13 @@ async fn download_separation_model(
     info!("执行命令: audio-separator {}", args.join(" "));
     info!("模型下载源: 由 audio-separator 内部决定，若其输出包含 URL 将记录到日志");
 
-    let _ = app_handle.emit_all("model-download-progress", ModelDownloadProgress {
+    let _ = reporter.emit(ModelDownloadProgress {
         model_id: model_id.clone(),
         progress: 0.1,
         message: "正在下载模型...".to_string(),
         completed: false,
         error: None,
+        cancelled: false,
     });
 
     // 构建命令
@@ -296,7 +583,11 @@ async fn download_separation_model(
     let stderr = child.stderr.take().unwrap();
     let reader = BufReader::new(stderr);
 
-    let app_handle_clone = app_handle.clone();
+    // 注册子进程，取消时直接 kill，stderr 随之关闭结束读取循环
+    let flag_id = model_download_flag_id(&model_id);
+    let child_handle = register_child_process(&flag_id, "model_download", child);
+
+    let reporter_clone = reporter.clone();
     let model_id_clone = model_id.clone();
 
     for line in reader.lines() {
@@ -309,12 +600,13 @@ async fn download_separation_model(
 
             if line.contains("Downloading") || line.contains("downloading") {
                 info!("下载中: {}", line);
-                let _ = app_handle_clone.emit_all("model-download-progress", ModelDownloadProgress {
+                let _ = reporter_clone.emit(ModelDownloadProgress {
                     model_id: model_id_clone.clone(),
                     progress: 0.3,
                     message: "正在下载模型文件...".to_string(),
                     completed: false,
                     error: None,
+                    cancelled: false,
                 });
             }
 
@@ -328,12 +620,13 @@ async fn download_separation_model(
                         info!("下载进度: {:.0}% {}", percent, line);
                         // 下载占 10%-90%，处理占 90%-100%
                         let progress = 0.1 + (percent / 100.0) * 0.8;
-                        let _ = app_handle_clone.emit_all("model-download-progress", ModelDownloadProgress {
+                        let _ = reporter_clone.emit(ModelDownloadProgress {
                             model_id: model_id_clone.clone(),
                             progress,
                             message: format!("处理中... {:.0}%", percent),
                             completed: false,
                             error: None,
+                            cancelled: false,
                         });
                     }
                 }
@@ -341,10 +634,22 @@ async fn download_separation_model(
         }
     }
 
-    let status = child.wait()?;
+    let status = {
+        let mut guard = child_handle.lock()
+            .map_err(|_| AppError::VocalSeparation("audio-separator 进程锁异常".to_string()))?;
+        match guard.as_mut() {
+            Some(child) => child.wait()?,
+            None => return Err(AppError::VocalSeparation("audio-separator 进程句柄丢失".to_string())),
+        }
+    };
 
     // 清理临时文件
-    let _ = std::fs::remove_dir_all(&temp_dir);
+    drop(temp_dir);
+
+    if cancel_flag.load(Ordering::SeqCst) {
+        emit_download_cancelled(&reporter, &model_id);
+        return Err(AppError::Cancelled);
+    }
 
     if status.success() {
         let status = models::check_model_downloaded(&model);
@@ -354,22 +659,24 @@ async fn download_separation_model(
             status.downloaded,
             status.local_path
         );
-        let _ = app_handle.emit_all("model-download-progress", ModelDownloadProgress {
+        let _ = reporter.emit(ModelDownloadProgress {
             model_id: model_id.clone(),
             progress: 1.0,
             message: "下载完成".to_string(),
             completed: true,
             error: None,
+            cancelled: false,
         });
         Ok(())
     } else {
         error!("模型下载失败，退出码: {:?}", status.code());
-        let _ = app_handle.emit_all("model-download-progress", ModelDownloadProgress {
+        let _ = reporter.emit(ModelDownloadProgress {
             model_id: model_id.clone(),
             progress: 0.0,
             message: "下载失败".to_string(),
             completed: true,
             error: Some("模型下载失败，请检查网络连接".to_string()),
+            cancelled: false,
         });
         Err(AppError::VocalSeparation("模型下载失败".to_string()))
     }
@@ -1,18 +1,24 @@
 // 项目命令
 
+use crate::config;
 use crate::database;
 use crate::error::{AppError, AppResult};
-use crate::utils::{AppState, Project, Segment, SegmentStatus, SegmentType, generate_id};
+use crate::utils::{AppState, Project, Segment, SegmentStatus, SegmentType, generate_id, is_url, lock_or_recover, media_input_exists};
 use crate::video::ffmpeg;
 use chrono::Local;
 use tauri::{State, Window};
-use tracing::{info, error};
+use tracing::{debug, info, error, warn};
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 /// 创建项目
 #[tauri::command]
 pub async fn create_project(
+    window: Window,
     video_path: String,
+    state: State<'_, AppState>,
 ) -> AppResult<Project> {
     // 检查项目是否已存在
     if database::project_exists_by_path(&video_path)? {
@@ -31,26 +37,190 @@ pub async fn create_project(
 
     let now = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
-    let project = Project {
+    let mut project = Project {
         id: generate_id(),
         name,
         source_video_path: video_path,
         preview_video_path: None,
+        thumbnail_path: None,
         video_info,
         segments: Vec::new(),
         created_at: now.clone(),
         updated_at: now,
         file_exists: true,
+        match_config: None,
     };
 
     database::insert_project(&project)?;
+    if let Some(hash) = quick_content_hash(&project.source_video_path) {
+        database::set_project_content_hash(&project.id, &hash)?;
+    }
+    let thumbnail_time = config::thumbnail_time(project.video_info.duration);
+    if let Err(e) = generate_project_thumbnail(&state.app_dir, &mut project, thumbnail_time) {
+        error!("生成缩略图失败: {}, 项目: {}", e, project.name);
+    }
+
+    spawn_auto_preview(&window, &state.app_dir, &project);
 
     Ok(project)
 }
 
+/// 在指定时间截取项目缩略图并记录路径
+///
+/// 先截取到临时文件再重命名覆盖目标，失败时保留原缩略图；
+/// 成功后才删除旧路径的缩略图，且只删除应用 thumbnails 目录下的文件。
+fn generate_project_thumbnail(app_dir: &Path, project: &mut Project, time: f64) -> AppResult<String> {
+    let thumb_dir = app_dir.join("thumbnails");
+    let thumb_path = thumb_dir.join(format!("{}.jpg", project.id)).to_string_lossy().to_string();
+    let tmp_path = thumb_dir.join(format!("{}.tmp.jpg", project.id));
+    if let Err(e) = ffmpeg::extract_thumbnail(&project.source_video_path, &tmp_path.to_string_lossy(), time) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    if let Err(e) = std::fs::rename(&tmp_path, &thumb_path) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+    database::set_project_thumbnail_path(&project.id, &thumb_path)?;
+
+    if let Some(old) = project.thumbnail_path.replace(thumb_path.clone()) {
+        let old_path = Path::new(&old);
+        if old != thumb_path && old_path.starts_with(&thumb_dir) && old_path.exists() {
+            if let Err(e) = std::fs::remove_file(old_path) {
+                warn!("删除旧缩略图失败: {}, 路径: {}", e, old);
+            }
+        }
+    }
+    Ok(thumb_path)
+}
+
+/// 重新生成项目缩略图
+///
+/// 在 time（秒）处重新截取并更新记录的缩略图路径，用于替换片头黑屏等不具代表性的画面。
+#[tauri::command]
+pub async fn set_project_thumbnail(
+    project_id: String,
+    time: f64,
+    state: State<'_, AppState>,
+) -> AppResult<String> {
+    let mut project = database::get_project_by_id(&project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", project_id)))?;
+
+    let duration = project.video_info.duration;
+    if !time.is_finite() || time < 0.0 || (duration > 0.0 && time >= duration) {
+        return Err(AppError::InvalidArgument(format!(
+            "缩略图时间超出视频范围: {:.2}s（视频时长 {:.2}s）",
+            time, duration
+        )));
+    }
+    if !project.file_exists {
+        return Err(AppError::NotFound(format!("视频文件不存在: {}", project.source_video_path)));
+    }
+
+    info!("重新生成缩略图: project_id={}, time={:.2}s", project_id, time);
+    generate_project_thumbnail(&state.app_dir, &mut project, time)
+}
+
+/// 快速内容哈希读取的头/尾字节数
+const CONTENT_HASH_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// 计算视频快速内容哈希：文件大小 + 开头 1MB + 结尾 1MB 的 SHA-256
+///
+/// 不读取整个文件，用于识别不同路径下的同一视频。网络输入返回 None。
+fn quick_content_hash(video_path: &str) -> Option<String> {
+    use sha2::{Digest, Sha256};
+    use std::io::{Read, Seek, SeekFrom};
+
+    if is_url(video_path) {
+        return None;
+    }
+    let hash = (|| -> std::io::Result<String> {
+        let mut file = std::fs::File::open(video_path)?;
+        let size = file.metadata()?.len();
+        let mut hasher = Sha256::new();
+        hasher.update(size.to_le_bytes());
+
+        let mut buffer = Vec::with_capacity(CONTENT_HASH_CHUNK_SIZE as usize);
+        (&mut file).take(CONTENT_HASH_CHUNK_SIZE).read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+
+        if size > CONTENT_HASH_CHUNK_SIZE {
+            buffer.clear();
+            file.seek(SeekFrom::Start(size.saturating_sub(CONTENT_HASH_CHUNK_SIZE).max(CONTENT_HASH_CHUNK_SIZE)))?;
+            file.read_to_end(&mut buffer)?;
+            hasher.update(&buffer);
+        }
+        Ok(hex::encode(hasher.finalize()))
+    })();
+
+    match hash {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            warn!("计算视频内容哈希失败: {}, {}", video_path, e);
+            None
+        }
+    }
+}
+
+/// 为迁移前创建、尚未记录内容哈希的项目补算哈希，使重复检测覆盖已有项目
+///
+/// 源视频不存在或为网络输入的项目跳过（下次检测时重试）。返回补算的项目数。
+fn backfill_content_hashes() -> AppResult<usize> {
+    let mut filled = 0;
+    for (project_id, video_path) in database::get_projects_missing_content_hash()? {
+        if is_url(&video_path) || !Path::new(&video_path).exists() {
+            continue;
+        }
+        if let Some(hash) = quick_content_hash(&video_path) {
+            database::set_project_content_hash(&project_id, &hash)?;
+            filled += 1;
+        }
+    }
+    if filled > 0 {
+        info!("补算已有项目内容哈希: {} 个", filled);
+    }
+    Ok(filled)
+}
+
+/// 自动预览生成并发限制（批量创建时逐个转码，避免同时启动大量 ffmpeg）
+static AUTO_PREVIEW_SEMAPHORE: once_cell::sync::Lazy<tokio::sync::Semaphore> =
+    once_cell::sync::Lazy::new(|| tokio::sync::Semaphore::new(1));
+
+/// 按配置在后台为新项目生成预览视频
+///
+/// 仅在 auto_generate_preview 开启且源格式需要转码时执行，完成后写回 preview_video_path。
+/// 进度通过 preview-progress / preview-complete 事件通知，可用 cancel_preview_generation 取消。
+fn spawn_auto_preview(window: &Window, app_dir: &Path, project: &Project) {
+    if !config::get_config().auto_generate_preview {
+        return;
+    }
+    if !is_url(&project.source_video_path) && !ffmpeg::needs_preview_transcode(&project.video_info) {
+        return;
+    }
+
+    let window = window.clone();
+    let project_id = project.id.clone();
+    let source_path = project.source_video_path.clone();
+    let output_path = app_dir.join("previews").join(format!("{}.mp4", project.id)).to_string_lossy().to_string();
+    info!("[PREVIEW] 自动生成预览视频: project_id={}, 源视频={}", project_id, source_path);
+
+    tauri::async_runtime::spawn(async move {
+        let _permit = AUTO_PREVIEW_SEMAPHORE.acquire().await;
+        match super::video::generate_preview_video(window, source_path, output_path, Some(project_id.clone()), None).await {
+            Ok(preview_path) => {
+                if let Err(e) = update_project_preview(project_id.clone(), preview_path).await {
+                    error!("[PREVIEW] 保存预览视频路径失败: project_id={}, {}", project_id, e);
+                }
+            }
+            Err(e) => error!("[PREVIEW] 自动生成预览视频失败: project_id={}, {}", project_id, e),
+        }
+    });
+}
+
 /// 保存项目
 #[tauri::command]
 pub async fn save_project(project: Project) -> AppResult<()> {
+    super::video::ensure_project_not_processing(&project.id)?;
     let mut updated_project = project;
     updated_project.updated_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
@@ -61,6 +231,7 @@ pub async fn save_project(project: Project) -> AppResult<()> {
     for segment in &updated_project.segments {
         database::insert_segment(segment)?;
     }
+    database::delete_orphan_detection_boxes(&updated_project.id)?;
 
     Ok(())
 }
@@ -72,10 +243,105 @@ pub async fn load_project(id: String) -> AppResult<Project> {
         .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", id)))
 }
 
+/// 项目导出文件的格式版本，所有项目导出命令共用
+///
+/// 导出格式（Project / Segment 字段）发生不兼容变化时递增，并在 migrate_project_export 中补充迁移。
+pub(crate) const PROJECT_SCHEMA_VERSION: u32 = 1;
+
+/// 项目导出文件
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct ProjectExport {
+    schema_version: u32,
+    /// 导出时的应用版本（仅供排查问题）
+    app_version: String,
+    exported_at: String,
+    project: Project,
+}
+
+/// 将旧版本的导出内容逐级迁移到当前格式版本
+fn migrate_project_export(value: serde_json::Value, from_version: u32) -> AppResult<serde_json::Value> {
+    match from_version {
+        PROJECT_SCHEMA_VERSION => Ok(value),
+        v => Err(AppError::InvalidArgument(format!(
+            "不支持的项目文件格式版本: {}（当前版本 {}）", v, PROJECT_SCHEMA_VERSION
+        ))),
+    }
+}
+
+/// 导出项目（项目信息与全部片段）为 JSON 文件
+///
+/// 文件带有 schema_version，导入时据此拒绝或迁移其他版本导出的文件。
+#[tauri::command]
+pub async fn export_project_file(project_id: String, dest: String) -> AppResult<String> {
+    let project = database::get_project_by_id(&project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", project_id)))?;
+    let export = ProjectExport {
+        schema_version: PROJECT_SCHEMA_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        project,
+    };
+    std::fs::write(&dest, serde_json::to_string_pretty(&export)?)?;
+    info!("[PROJECT_EXPORT] 导出项目: {} -> {}（格式版本 {}）", project_id, dest, PROJECT_SCHEMA_VERSION);
+    Ok(dest)
+}
+
+/// 从 export_project_file 导出的 JSON 文件导入项目
+///
+/// 先检查 schema_version：缺失或高于当前版本时拒绝并给出明确提示，低于当前版本时先迁移。
+/// 导入的项目与片段使用新 ID；同一源视频已有项目时拒绝导入。
+#[tauri::command]
+pub async fn import_project_file(path: String) -> AppResult<Project> {
+    let content = std::fs::read_to_string(&path)?;
+    let value: serde_json::Value = serde_json::from_str(&content)?;
+
+    let version = value.get("schema_version")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| AppError::InvalidArgument("缺少 schema_version，不是有效的项目导出文件".to_string()))? as u32;
+    if version > PROJECT_SCHEMA_VERSION {
+        return Err(AppError::InvalidArgument(format!(
+            "项目文件由更新版本的应用导出（格式版本 {}，当前支持 {}），请升级后再导入",
+            version, PROJECT_SCHEMA_VERSION
+        )));
+    }
+    if version < PROJECT_SCHEMA_VERSION {
+        info!("[PROJECT_IMPORT] 迁移项目文件格式: {} -> {}", version, PROJECT_SCHEMA_VERSION);
+    }
+    let export: ProjectExport = serde_json::from_value(migrate_project_export(value, version)?)?;
+
+    let mut project = export.project;
+    if database::project_exists_by_path(&project.source_video_path)? {
+        return Err(AppError::InvalidArgument(format!("该视频已创建过项目: {}", project.source_video_path)));
+    }
+
+    project.id = generate_id();
+    for segment in &mut project.segments {
+        segment.id = generate_id();
+        segment.project_id = project.id.clone();
+    }
+    // 预览与缩略图属于导出方本地文件，不随项目导入
+    project.preview_video_path = None;
+    project.thumbnail_path = None;
+    project.updated_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    project.file_exists = media_input_exists(&project.source_video_path);
+
+    database::insert_project(&project)?;
+    database::batch_insert_segments(&project.segments)?;
+
+    info!(
+        "[PROJECT_IMPORT] 导入项目: {} ({}), {} 个片段, 源文件存在: {}",
+        project.name, project.id, project.segments.len(), project.file_exists
+    );
+    Ok(project)
+}
+
 /// 获取所有项目
+///
+/// check_files 默认为 true；传 false 时跳过源视频存在性检查以加快加载（适用于网络盘）
+/// sort: recent（最近修改，默认）/ created（最近创建）/ name（名称）
 #[tauri::command]
-pub async fn get_projects() -> AppResult<Vec<Project>> {
-    database::get_all_projects()
+pub async fn get_projects(check_files: Option<bool>, sort: Option<String>) -> AppResult<Vec<Project>> {
+    database::get_all_projects(check_files.unwrap_or(true), sort.as_deref().unwrap_or("recent"))
 }
 
 /// 清理项目关联的文件（缩略图、预览视频、音频处理文件）
@@ -96,6 +362,30 @@ fn cleanup_project_files(id: &str, app_dir: &std::path::Path) {
         }
     }
 
+    // 删除片段预览目录
+    let segment_preview_dir = app_dir.join("previews").join("segments").join(id);
+    if segment_preview_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&segment_preview_dir) {
+            info!("删除片段预览目录失败: {:?}, 错误: {}", segment_preview_dir, e);
+        }
+    }
+
+    // 删除频谱图缓存目录
+    let spectrogram_dir = app_dir.join("previews").join("spectrograms").join(id);
+    if spectrogram_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&spectrogram_dir) {
+            info!("删除频谱图缓存目录失败: {:?}, 错误: {}", spectrogram_dir, e);
+        }
+    }
+
+    // 删除片段波形缓存目录
+    let waveform_dir = app_dir.join("previews").join("waveforms").join(id);
+    if waveform_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&waveform_dir) {
+            info!("删除波形缓存目录失败: {:?}, 错误: {}", waveform_dir, e);
+        }
+    }
+
     // 删除音频处理文件
     let temp_dir = app_dir.join("temp");
 
@@ -106,6 +396,14 @@ fn cleanup_project_files(id: &str, app_dir: &std::path::Path) {
         }
     }
 
+    // 删除关键帧缓存
+    let keyframes_path = temp_dir.join(format!("{}_keyframes.json", id));
+    if keyframes_path.exists() {
+        if let Err(e) = std::fs::remove_file(&keyframes_path) {
+            info!("删除关键帧缓存失败: {:?}, 错误: {}", keyframes_path, e);
+        }
+    }
+
     // 删除人声分离目录（包含人声和伴奏文件）
     let separated_dir = temp_dir.join(format!("{}_separated", id));
     if separated_dir.exists() {
@@ -114,6 +412,14 @@ fn cleanup_project_files(id: &str, app_dir: &std::path::Path) {
         }
     }
 
+    // 删除可恢复导出的工作目录
+    let export_dir = temp_dir.join(format!("{}_export", id));
+    if export_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&export_dir) {
+            info!("删除导出工作目录失败: {:?}, 错误: {}", export_dir, e);
+        }
+    }
+
     // 删除人物检测输出目录（包含检测结果 JSON 等文件）
     let detection_dir = temp_dir.join(format!("{}_detection", id));
     if detection_dir.exists() {
@@ -126,8 +432,11 @@ fn cleanup_project_files(id: &str, app_dir: &std::path::Path) {
 /// 删除项目
 #[tauri::command]
 pub async fn delete_project(id: String, state: State<'_, AppState>) -> AppResult<()> {
+    super::video::ensure_project_not_processing(&id)?;
+
     // 清理取消标志，避免内存泄漏
     super::video::remove_cancel_flag(&id);
+    stop_autosave(&id);
 
     // 清理关联文件
     cleanup_project_files(&id, &state.app_dir);
@@ -140,10 +449,14 @@ pub async fn delete_project(id: String, state: State<'_, AppState>) -> AppResult
 #[tauri::command]
 pub async fn delete_all_projects(state: State<'_, AppState>) -> AppResult<()> {
     // 获取所有项目 ID，用于清理文件
-    let projects = database::get_all_projects()?;
+    let projects = database::get_all_projects(false, "recent")?;
+    for project in &projects {
+        super::video::ensure_project_not_processing(&project.id)?;
+    }
 
     for project in &projects {
         super::video::remove_cancel_flag(&project.id);
+        stop_autosave(&project.id);
         cleanup_project_files(&project.id, &state.app_dir);
     }
 
@@ -153,16 +466,123 @@ pub async fn delete_all_projects(state: State<'_, AppState>) -> AppResult<()> {
     database::clear_all_projects()
 }
 
+/// 校验并修正片段时间范围
+/// 1. 拒绝非有限值与反向区间（end <= start）
+/// 2. 修正到 [0, duration] 范围内（duration 未知时只修正下界）
+/// 3. 拒绝修正后为空的片段（完全超出视频时长）
+fn validate_segments(segments: Vec<Segment>, duration: f64) -> AppResult<Vec<Segment>> {
+    let mut validated = Vec::with_capacity(segments.len());
+
+    for (i, mut segment) in segments.into_iter().enumerate() {
+        if !segment.start_time.is_finite() || !segment.end_time.is_finite() {
+            return Err(AppError::InvalidArgument(format!(
+                "片段[{}] 时间无效: id={}, start={}, end={}",
+                i, segment.id, segment.start_time, segment.end_time
+            )));
+        }
+        if segment.end_time <= segment.start_time {
+            return Err(AppError::InvalidArgument(format!(
+                "片段[{}] 结束时间必须大于开始时间: id={}, {:.3}s - {:.3}s",
+                i, segment.id, segment.start_time, segment.end_time
+            )));
+        }
+        if segment.gain_db.is_some_and(|g| !g.is_finite()) {
+            return Err(AppError::InvalidArgument(format!(
+                "片段[{}] 音量增益无效: id={}, gain_db={:?}",
+                i, segment.id, segment.gain_db
+            )));
+        }
+
+        let start = segment.start_time.max(0.0);
+        let end = if duration > 0.0 { segment.end_time.min(duration) } else { segment.end_time };
+        if start >= end {
+            return Err(AppError::InvalidArgument(format!(
+                "片段[{}] 超出视频时长 {:.3}s: id={}, {:.3}s - {:.3}s",
+                i, duration, segment.id, segment.start_time, segment.end_time
+            )));
+        }
+
+        if start != segment.start_time || end != segment.end_time {
+            info!(
+                "[UPDATE_SEGMENTS] 修正片段[{}] 时间范围: {:.3}s - {:.3}s -> {:.3}s - {:.3}s",
+                i, segment.start_time, segment.end_time, start, end
+            );
+            segment.start_time = start;
+            segment.end_time = end;
+        }
+        validated.push(segment);
+    }
+
+    Ok(validated)
+}
+
+/// 合并重叠片段
+/// 仅合并类型、状态、音乐均相同的片段，合并后保留第一个片段的 ID，置信度取最大值
+fn merge_segment_overlaps(segments: Vec<Segment>) -> Vec<Segment> {
+    let mut groups: std::collections::BTreeMap<(String, SegmentStatus, Option<String>), Vec<Segment>> =
+        std::collections::BTreeMap::new();
+    for segment in segments {
+        // 按完整状态分组：候选片段（Provisional）不能与已确认片段合并，否则合并结果会沿用其中一个的状态
+        let key = (
+            segment.segment_type.as_str().to_string(),
+            segment.status.clone(),
+            segment.music_id.clone(),
+        );
+        groups.entry(key).or_default().push(segment);
+    }
+
+    let mut merged = Vec::new();
+    for (_, mut group) in groups {
+        group.sort_by(|a, b| a.start_time.partial_cmp(&b.start_time).unwrap_or(std::cmp::Ordering::Equal));
+        let ranges: Vec<(f64, f64)> = group.iter().map(|s| (s.start_time, s.end_time)).collect();
+
+        for (start, end) in ffmpeg::merge_overlapping_segments(&ranges) {
+            let members: Vec<&Segment> = group
+                .iter()
+                .filter(|s| s.start_time >= start && s.end_time <= end)
+                .collect();
+            if let Some(first) = members.first() {
+                let mut segment = (*first).clone();
+                segment.start_time = start;
+                segment.end_time = end;
+                segment.confidence = members.iter().map(|s| s.confidence).fold(0.0, f64::max);
+                merged.push(segment);
+            }
+        }
+    }
+
+    merged.sort_by(|a, b| a.start_time.partial_cmp(&b.start_time).unwrap_or(std::cmp::Ordering::Equal));
+    merged
+}
+
 /// 更新片段
+///
+/// 写入前校验片段时间范围（修正到视频时长内，拒绝反向区间）。
+/// merge_overlaps 为 true 时合并同类型、同音乐的重叠片段。
 #[tauri::command]
 pub async fn update_segments(
     project_id: String,
     segments: Vec<Segment>,
+    merge_overlaps: Option<bool>,
 ) -> AppResult<()> {
+    super::video::ensure_project_not_processing(&project_id)?;
+
     // 验证项目存在
-    database::get_project_by_id(&project_id)?
+    let project = database::get_project_by_id(&project_id)?
         .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", project_id)))?;
 
+    let segments = validate_segments(segments, project.video_info.duration)?;
+    let segments = if merge_overlaps.unwrap_or(false) {
+        let before = segments.len();
+        let merged = merge_segment_overlaps(segments);
+        if merged.len() < before {
+            info!("[UPDATE_SEGMENTS] 合并重叠片段: {} -> {} 个", before, merged.len());
+        }
+        merged
+    } else {
+        segments
+    };
+
     // 记录前端传入的片段详情
     let detected = segments.iter().filter(|s| s.status == SegmentStatus::Detected).count();
     let removed = segments.iter().filter(|s| s.status == SegmentStatus::Removed).count();
@@ -177,9 +597,16 @@ pub async fn update_segments(
         );
     }
 
-    // 删除旧片段并插入新片段
-    database::delete_segments_by_project(&project_id)?;
-    database::batch_update_segments(&segments)?;
+    // 手动保存优先：推进保存代数并丢弃尚未写入的自动保存快照，持锁写入，
+    // 已取出但未写入的旧快照会因代数过期而跳过，不会覆盖本次写入
+    {
+        let mut saves = lock_or_recover(&SEGMENT_SAVES, "片段保存");
+        *saves.entry(project_id.clone()).or_default() += 1;
+        discard_pending_autosave(&project_id);
+
+        // 删除旧片段并插入新片段（同一事务）
+        database::replace_project_segments(&project_id, &segments)?;
+    }
 
     // 验证写入后的数据库状态
     let db_segments = database::get_segments_by_project(&project_id)?;
@@ -198,6 +625,437 @@ pub async fn update_segments(
     Ok(())
 }
 
+/// 项目的自动保存状态
+#[derive(Default)]
+struct AutosaveEntry {
+    /// 前端推送、尚未写入的最新片段快照及推送时的手动保存代数
+    pending: Option<(u64, Vec<Segment>)>,
+    /// 定时保存任务的停止标志，None 表示未开启定时保存
+    stop: Option<Arc<AtomicBool>>,
+}
+
+static AUTOSAVE: once_cell::sync::Lazy<Mutex<HashMap<String, AutosaveEntry>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 按项目记录手动保存（update_segments）的代数，片段写入时持有该锁以串行化手动保存与自动保存
+static SEGMENT_SAVES: once_cell::sync::Lazy<Mutex<HashMap<String, u64>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 自动保存最小间隔（秒）
+const AUTOSAVE_MIN_INTERVAL_SECS: u64 = 5;
+
+/// 项目当前的手动保存代数
+fn manual_save_generation(project_id: &str) -> u64 {
+    lock_or_recover(&SEGMENT_SAVES, "片段保存").get(project_id).copied().unwrap_or(0)
+}
+
+/// 写入自动保存的片段（校验时间范围，不记录逐片段日志、不更新项目修改时间）
+///
+/// generation 为快照推送时的手动保存代数；之后发生过手动保存时跳过写入（返回 0），手动保存优先。
+fn write_autosave(project_id: &str, segments: Vec<Segment>, generation: u64) -> AppResult<usize> {
+    super::video::ensure_project_not_processing(project_id)?;
+    let project = database::get_project_by_id(project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", project_id)))?;
+    let segments = validate_segments(segments, project.video_info.duration)?;
+
+    let saves = lock_or_recover(&SEGMENT_SAVES, "片段保存");
+    if saves.get(project_id).copied().unwrap_or(0) != generation {
+        debug!("[AUTOSAVE] 快照早于最近一次手动保存，跳过写入: project_id={}", project_id);
+        return Ok(0);
+    }
+    database::replace_project_segments(project_id, &segments)?;
+    Ok(segments.len())
+}
+
+/// 丢弃项目尚未写入的自动保存快照
+fn discard_pending_autosave(project_id: &str) {
+    if let Some(entry) = lock_or_recover(&AUTOSAVE, "自动保存").get_mut(project_id) {
+        entry.pending = None;
+    }
+}
+
+/// 停止项目的定时自动保存并丢弃快照（删除项目时调用）
+fn stop_autosave(project_id: &str) {
+    if let Some(entry) = lock_or_recover(&AUTOSAVE, "自动保存").remove(project_id) {
+        if let Some(stop) = entry.stop {
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+    lock_or_recover(&SEGMENT_SAVES, "片段保存").remove(project_id);
+}
+
+/// 设置项目的定时自动保存
+///
+/// interval_secs 为 None 或 0 时关闭，并立即写入剩余的快照；否则每隔 interval_secs 秒
+/// （最小 5 秒）写入 autosave_segments 推送的最新片段，没有新快照时不写数据库。
+#[tauri::command]
+pub async fn set_autosave(project_id: String, interval_secs: Option<u64>) -> AppResult<()> {
+    let interval = interval_secs.filter(|s| *s > 0).map(|s| s.max(AUTOSAVE_MIN_INTERVAL_SECS));
+
+    let mut autosave = lock_or_recover(&AUTOSAVE, "自动保存");
+    let Some(interval) = interval else {
+        let entry = autosave.remove(&project_id);
+        drop(autosave);
+        if let Some(entry) = entry {
+            if let Some(stop) = entry.stop {
+                stop.store(true, Ordering::SeqCst);
+            }
+            if let Some((generation, segments)) = entry.pending {
+                write_autosave(&project_id, segments, generation)?;
+            }
+        }
+        info!("[AUTOSAVE] 已关闭自动保存: project_id={}", project_id);
+        return Ok(());
+    };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let entry = autosave.entry(project_id.clone()).or_default();
+    if let Some(old) = entry.stop.replace(stop.clone()) {
+        old.store(true, Ordering::SeqCst);
+    }
+    drop(autosave);
+    info!("[AUTOSAVE] 开启自动保存: project_id={}, 间隔 {} 秒", project_id, interval);
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            // 项目处理中时保留快照，待处理结束后再写入
+            if super::video::is_project_processing(&project_id) {
+                continue;
+            }
+            let pending = lock_or_recover(&AUTOSAVE, "自动保存")
+                .get_mut(&project_id)
+                .and_then(|entry| entry.pending.take());
+            let Some((generation, segments)) = pending else { continue };
+            match write_autosave(&project_id, segments, generation) {
+                Ok(count) => debug!("[AUTOSAVE] 已自动保存: project_id={}, {} 个片段", project_id, count),
+                Err(AppError::NotFound(_)) => {
+                    warn!("[AUTOSAVE] 项目已不存在，停止自动保存: project_id={}", project_id);
+                    stop_autosave(&project_id);
+                    break;
+                }
+                Err(e) => warn!("[AUTOSAVE] 自动保存失败: project_id={}, {}", project_id, e),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// 推送片段快照用于自动保存
+///
+/// 已开启定时保存时只暂存快照，由定时任务写入；否则立即写入。
+/// 与 update_segments 相比不合并重叠、不记录逐片段日志，适合编辑过程中频繁调用。
+#[tauri::command]
+pub async fn autosave_segments(project_id: String, segments: Vec<Segment>) -> AppResult<()> {
+    let generation = manual_save_generation(&project_id);
+    {
+        let mut autosave = lock_or_recover(&AUTOSAVE, "自动保存");
+        if let Some(entry) = autosave.get_mut(&project_id).filter(|e| e.stop.is_some()) {
+            entry.pending = Some((generation, segments));
+            return Ok(());
+        }
+    }
+    write_autosave(&project_id, segments, generation).map(|_| ())
+}
+
+/// 获取项目的指定类型片段（音乐匹配或人物检测），按开始时间排序
+#[tauri::command]
+pub async fn get_segments_by_type(
+    project_id: String,
+    segment_type: SegmentType,
+) -> AppResult<Vec<Segment>> {
+    database::get_segments_by_type(&project_id, segment_type.as_str())
+}
+
+/// 静音拆分后保留的最短片段时长（秒）
+const MIN_SPLIT_PIECE_DURATION: f64 = 0.1;
+
+/// 关键帧吸附默认容差（秒）
+const DEFAULT_KEYFRAME_SNAP_TOLERANCE: f64 = 1.0;
+
+/// 将项目片段边界吸附到最近的关键帧并保存
+///
+/// 超出容差范围的边界保持不变；吸附后 start >= end 的片段保留原时间。
+/// 返回调整后的片段，供前端刷新显示。
+#[tauri::command]
+pub async fn snap_segments_to_keyframes(
+    project_id: String,
+    tolerance: Option<f64>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<Segment>> {
+    let mut project = database::get_project_by_id(&project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", project_id)))?;
+
+    let tolerance = tolerance.unwrap_or(DEFAULT_KEYFRAME_SNAP_TOLERANCE);
+    if !tolerance.is_finite() || tolerance < 0.0 {
+        return Err(AppError::InvalidArgument(format!("吸附容差不能为负数: {}", tolerance)));
+    }
+
+    let keyframes = super::analysis::load_keyframes_cached(
+        &state.app_dir,
+        Some(&project_id),
+        &project.source_video_path,
+    )?;
+    if keyframes.is_empty() {
+        return Err(AppError::Video("未探测到关键帧".to_string()));
+    }
+
+    let mut segments = project.segments.clone();
+    let mut snapped_count = 0;
+    for segment in segments.iter_mut() {
+        let start = ffmpeg::snap_to_keyframe(segment.start_time, &keyframes, tolerance);
+        let end = ffmpeg::snap_to_keyframe(segment.end_time, &keyframes, tolerance);
+        if start >= end {
+            info!("[SNAP] 片段 {} 吸附后无效 ({:.3}s - {:.3}s)，保留原时间", segment.id, start, end);
+            continue;
+        }
+        if start != segment.start_time || end != segment.end_time {
+            info!(
+                "[SNAP] 片段 {}: {:.3}s - {:.3}s -> {:.3}s - {:.3}s",
+                segment.id, segment.start_time, segment.end_time, start, end
+            );
+            segment.start_time = start;
+            segment.end_time = end;
+            snapped_count += 1;
+        }
+    }
+
+    database::batch_update_segments(&segments)?;
+
+    project.updated_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    database::update_project(&project)?;
+
+    info!("[SNAP] project_id={}, 共 {} 个片段, 调整 {} 个", project_id, segments.len(), snapped_count);
+    Ok(segments)
+}
+
+/// 合并相邻的同源片段
+///
+/// 按时间顺序遍历同类型的未移除片段，music_id 和状态相同且间隙不超过 max_gap 的相邻片段合并为一个，
+/// 合并后保留前一个片段的 ID，置信度取最大值。已移除的片段保持不变；
+/// 待确认（Provisional）片段只与待确认片段合并，不会被并入已确认片段而被提升。
+fn merge_adjacent(segments: Vec<Segment>, max_gap: f64) -> Vec<Segment> {
+    let (mut active, removed): (Vec<Segment>, Vec<Segment>) = segments
+        .into_iter()
+        .partition(|s| s.status != SegmentStatus::Removed);
+    active.sort_by(|a, b| {
+        a.segment_type.as_str().cmp(b.segment_type.as_str())
+            .then(a.start_time.partial_cmp(&b.start_time).unwrap_or(std::cmp::Ordering::Equal))
+    });
+
+    let mut merged: Vec<Segment> = Vec::with_capacity(active.len() + removed.len());
+    for segment in active {
+        if let Some(last) = merged.last_mut() {
+            if last.segment_type == segment.segment_type
+                && last.status == segment.status
+                && last.music_id == segment.music_id
+                && segment.start_time - last.end_time <= max_gap
+            {
+                info!(
+                    "[MERGE_ADJACENT] 合并片段 {} ({:.2}s - {:.2}s) 到 {} ({:.2}s - {:.2}s)",
+                    segment.id, segment.start_time, segment.end_time, last.id, last.start_time, last.end_time
+                );
+                last.end_time = last.end_time.max(segment.end_time);
+                last.confidence = last.confidence.max(segment.confidence);
+                continue;
+            }
+        }
+        merged.push(segment);
+    }
+
+    merged.extend(removed);
+    merged.sort_by(|a, b| a.start_time.partial_cmp(&b.start_time).unwrap_or(std::cmp::Ordering::Equal));
+    merged
+}
+
+/// 合并相邻的同一歌曲片段（手动编辑后整理用）
+///
+/// max_gap: 允许合并的最大间隙（秒），默认使用匹配配置中的 max_gap_duration。
+/// 返回合并后的片段，供前端刷新显示。
+#[tauri::command]
+pub async fn merge_adjacent_segments(
+    project_id: String,
+    max_gap: Option<f64>,
+) -> AppResult<Vec<Segment>> {
+    let mut project = database::get_project_by_id(&project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", project_id)))?;
+
+    let max_gap = max_gap.unwrap_or_else(|| config::get_config().matching.max_gap_duration as f64);
+    if !max_gap.is_finite() || max_gap < 0.0 {
+        return Err(AppError::InvalidArgument(format!("最大间隙不能为负数: {}", max_gap)));
+    }
+
+    let before = project.segments.len();
+    let segments = merge_adjacent(std::mem::take(&mut project.segments), max_gap);
+
+    if segments.len() < before {
+        database::delete_segments_by_project(&project_id)?;
+        database::batch_update_segments(&segments)?;
+        database::delete_orphan_detection_boxes(&project_id)?;
+
+        project.updated_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        database::update_project(&project)?;
+    }
+
+    info!("[MERGE_ADJACENT] project_id={}, 最大间隙 {:.2}s, 片段 {} -> {} 个", project_id, max_gap, before, segments.len());
+    Ok(segments)
+}
+
+/// 判断两个片段是否完全重叠（同类型且起止时间相同）
+fn is_exact_overlap(a: &Segment, b: &Segment) -> bool {
+    const EPSILON: f64 = 1e-6;
+    a.segment_type == b.segment_type
+        && (a.start_time - b.start_time).abs() < EPSILON
+        && (a.end_time - b.end_time).abs() < EPSILON
+}
+
+/// 将另一个项目的片段合并到目标项目
+///
+/// 两个项目必须引用同一源视频（例如一个做音乐匹配、另一个做人物检测）。
+/// 源项目的片段以新 ID 复制到目标项目，保留 segment_type；与目标项目已有片段完全重叠的跳过。
+/// 源项目保持不变。返回目标项目的全部片段，供前端刷新显示。
+#[tauri::command]
+pub async fn merge_project_segments(target_id: String, source_id: String) -> AppResult<Vec<Segment>> {
+    if target_id == source_id {
+        return Err(AppError::InvalidArgument("不能将项目与自身合并".to_string()));
+    }
+    super::video::ensure_project_not_processing(&target_id)?;
+
+    let mut target = database::get_project_by_id(&target_id)?
+        .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", target_id)))?;
+    let source = database::get_project_by_id(&source_id)?
+        .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", source_id)))?;
+
+    if target.source_video_path != source.source_video_path {
+        return Err(AppError::InvalidArgument(format!(
+            "两个项目的源视频不同，无法合并: {} / {}",
+            target.source_video_path, source.source_video_path
+        )));
+    }
+
+    let mut added = Vec::new();
+    let mut skipped = 0;
+    for segment in &source.segments {
+        if target.segments.iter().chain(added.iter()).any(|s| is_exact_overlap(s, segment)) {
+            skipped += 1;
+            continue;
+        }
+        added.push(Segment {
+            id: generate_id(),
+            project_id: target_id.clone(),
+            ..segment.clone()
+        });
+    }
+
+    if !added.is_empty() {
+        database::batch_insert_segments(&added)?;
+        target.updated_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        database::update_project(&target)?;
+    }
+
+    info!(
+        "[MERGE_PROJECT] {} -> {}: 复制 {} 个片段, 跳过重复 {} 个",
+        source_id, target_id, added.len(), skipped
+    );
+    database::get_segments_by_project(&target_id)
+}
+
+/// 在静音处拆分片段
+///
+/// 仅对该片段的时间范围执行静音检测，静音部分被剔除，其余部分成为新片段。
+/// 第一段保留原片段 ID，其余使用新 ID；短于 MIN_SPLIT_PIECE_DURATION 的碎片被丢弃。
+/// 返回项目的全部片段，供前端刷新显示。可通过 cancel_processing(project_id) 取消。
+#[tauri::command]
+pub async fn split_segment_at_silence(
+    project_id: String,
+    segment_id: String,
+    threshold_db: Option<f64>,
+    min_silence: Option<f64>,
+) -> AppResult<Vec<Segment>> {
+    let mut project = database::get_project_by_id(&project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", project_id)))?;
+    let index = project.segments.iter().position(|s| s.id == segment_id)
+        .ok_or_else(|| AppError::NotFound(format!("片段不存在: {}", segment_id)))?;
+
+    let threshold_db = threshold_db.unwrap_or(super::analysis::DEFAULT_SILENCE_THRESHOLD_DB);
+    let min_silence = min_silence.unwrap_or(super::analysis::DEFAULT_SILENCE_MIN_DURATION);
+    if !threshold_db.is_finite() || threshold_db > 0.0 {
+        return Err(AppError::InvalidArgument(format!("静音阈值必须为非正数 dB 值: {}", threshold_db)));
+    }
+    if !min_silence.is_finite() || min_silence <= 0.0 {
+        return Err(AppError::InvalidArgument(format!("最短静音时长必须大于 0: {}", min_silence)));
+    }
+
+    let segment = project.segments[index].clone();
+    info!(
+        "[SPLIT_SILENCE] 拆分片段 {} ({:.2}s - {:.2}s), threshold={}dB, min_silence={}s",
+        segment.id, segment.start_time, segment.end_time, threshold_db, min_silence
+    );
+
+    let _guard = super::video::CancelFlagGuard::new(project_id.clone());
+    let cancel_flag = super::video::reset_cancel_flag(&project_id);
+    let silences = ffmpeg::detect_silence_in_range(
+        &project.source_video_path,
+        Some((segment.start_time, segment.end_time)),
+        threshold_db,
+        min_silence,
+        None,
+        cancel_flag,
+        &project_id,
+    )?;
+
+    // 静音区间之间的部分作为新片段
+    let mut pieces: Vec<(f64, f64)> = Vec::new();
+    let mut cursor = segment.start_time;
+    for (silence_start, silence_end) in silences {
+        let silence_start = silence_start.clamp(segment.start_time, segment.end_time);
+        let silence_end = silence_end.clamp(segment.start_time, segment.end_time);
+        if silence_start - cursor >= MIN_SPLIT_PIECE_DURATION {
+            pieces.push((cursor, silence_start));
+        }
+        cursor = cursor.max(silence_end);
+    }
+    if segment.end_time - cursor >= MIN_SPLIT_PIECE_DURATION {
+        pieces.push((cursor, segment.end_time));
+    }
+
+    if pieces.is_empty() {
+        return Err(AppError::InvalidArgument("片段内几乎全部为静音，无法拆分".to_string()));
+    }
+    if pieces.len() == 1 && pieces[0] == (segment.start_time, segment.end_time) {
+        info!("[SPLIT_SILENCE] 片段内未检测到静音，保持不变");
+        return Ok(project.segments);
+    }
+
+    let new_segments: Vec<Segment> = pieces
+        .iter()
+        .enumerate()
+        .map(|(i, &(start, end))| Segment {
+            id: if i == 0 { segment.id.clone() } else { generate_id() },
+            start_time: start,
+            end_time: end,
+            ..segment.clone()
+        })
+        .collect();
+    info!("[SPLIT_SILENCE] 片段 {} 拆分为 {} 段", segment.id, new_segments.len());
+
+    project.segments.splice(index..=index, new_segments);
+    project.segments.sort_by(|a, b| a.start_time.partial_cmp(&b.start_time).unwrap_or(std::cmp::Ordering::Equal));
+
+    database::delete_segments_by_project(&project_id)?;
+    database::batch_update_segments(&project.segments)?;
+    database::delete_orphan_detection_boxes(&project_id)?;
+
+    project.updated_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    database::update_project(&project)?;
+
+    Ok(project.segments)
+}
+
 /// 更新项目预览视频路径
 #[tauri::command]
 pub async fn update_project_preview(
@@ -219,7 +1077,7 @@ pub async fn update_project_preview(
 /// 扫描文件夹中的视频文件
 #[tauri::command]
 pub async fn scan_video_files(folder_path: String) -> AppResult<Vec<String>> {
-    let video_extensions = ["mp4", "mkv", "avi", "mov", "wmv", "flv", "webm"];
+    let video_extensions = config::get_config().file_extensions.video;
     let mut video_files = Vec::new();
 
     let path = Path::new(&folder_path);
@@ -230,12 +1088,8 @@ pub async fn scan_video_files(folder_path: String) -> AppResult<Vec<String>> {
     for entry in std::fs::read_dir(path)? {
         let entry = entry?;
         let file_path = entry.path();
-        if file_path.is_file() {
-            if let Some(ext) = file_path.extension() {
-                if video_extensions.contains(&ext.to_str().unwrap_or("").to_lowercase().as_str()) {
-                    video_files.push(file_path.to_string_lossy().to_string());
-                }
-            }
+        if file_path.is_file() && config::FileExtensionsConfig::matches(&video_extensions, &file_path) {
+            video_files.push(file_path.to_string_lossy().to_string());
         }
     }
 
@@ -246,19 +1100,31 @@ pub async fn scan_video_files(folder_path: String) -> AppResult<Vec<String>> {
 }
 
 /// 批量创建项目
+///
+/// skip_duplicates: 为 true 时按快速内容哈希跳过与已有项目内容相同的视频（路径不同也能识别），
+/// 匹配结果在 batch-create-complete 事件的 duplicates 字段中返回
 #[tauri::command]
 pub async fn batch_create_projects(
     window: Window,
     video_paths: Vec<String>,
+    skip_duplicates: Option<bool>,
     state: State<'_, AppState>,
 ) -> AppResult<Vec<Project>> {
     let total = video_paths.len();
+    let skip_duplicates = skip_duplicates.unwrap_or(false);
     let mut projects = Vec::new();
     let mut skipped = 0;
     let mut errors: Vec<String> = Vec::new();
+    let mut duplicates: Vec<serde_json::Value> = Vec::new();
 
     info!("开始批量创建 {} 个项目", total);
 
+    if skip_duplicates {
+        if let Err(e) = backfill_content_hashes() {
+            warn!("补算已有项目内容哈希失败: {}，重复检测可能遗漏旧项目", e);
+        }
+    }
+
     for (index, video_path) in video_paths.iter().enumerate() {
         // 获取文件名用于进度显示
         let file_name = Path::new(&video_path)
@@ -288,6 +1154,32 @@ pub async fn batch_create_projects(
             _ => {}
         }
 
+        // 检查内容是否与已有项目重复（同批次内先创建的项目也会被识别）
+        let content_hash = quick_content_hash(video_path);
+        if skip_duplicates {
+            if let Some(ref hash) = content_hash {
+                match database::find_project_by_content_hash(hash) {
+                    Ok(Some((existing_id, existing_name))) => {
+                        skipped += 1;
+                        info!("跳过重复视频: {} (与项目 {} 内容相同)", video_path, existing_name);
+                        let _ = window.emit("batch-create-progress", serde_json::json!({
+                            "current": index + 1,
+                            "total": total,
+                            "message": format!("跳过(与 {} 重复): {}", existing_name, file_name)
+                        }));
+                        duplicates.push(serde_json::json!({
+                            "video_path": video_path,
+                            "project_id": existing_id,
+                            "project_name": existing_name
+                        }));
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("查询重复视频失败: {}, 继续创建: {}", e, video_path),
+                }
+            }
+        }
+
         // 发送进度事件
         let _ = window.emit("batch-create-progress", serde_json::json!({
             "current": index + 1,
@@ -314,16 +1206,18 @@ pub async fn batch_create_projects(
         };
 
         let now = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let project = Project {
+        let mut project = Project {
             id: generate_id(),
             name,
             source_video_path: video_path.clone(),
             preview_video_path: None,
+            thumbnail_path: None,
             video_info,
             segments: Vec::new(),
             created_at: now.clone(),
             updated_at: now,
             file_exists: true,
+            match_config: None,
         };
 
         // 插入数据库
@@ -333,14 +1227,20 @@ pub async fn batch_create_projects(
             error!("插入项目失败: {}, 跳过: {}", e, video_path);
             continue;
         }
+        if let Some(ref hash) = content_hash {
+            if let Err(e) = database::set_project_content_hash(&project.id, hash) {
+                warn!("保存视频内容哈希失败: {}, 项目: {}", e, project.name);
+            }
+        }
 
-        // 生成缩略图
-        let thumb_path = state.app_dir.join("thumbnails").join(format!("{}.jpg", project.id));
-        if let Err(e) = ffmpeg::extract_thumbnail(&video_path, thumb_path.to_str().unwrap(), 0.0) {
+        // 生成缩略图（失败不影响项目创建）
+        let thumbnail_time = config::thumbnail_time(project.video_info.duration);
+        if let Err(e) = generate_project_thumbnail(&state.app_dir, &mut project, thumbnail_time) {
             error!("生成缩略图失败: {}, 项目: {}", e, project.name);
-            // 缩略图失败不影响项目创建
         }
 
+        spawn_auto_preview(&window, &state.app_dir, &project);
+
         projects.push(project);
     }
 
@@ -349,9 +1249,44 @@ pub async fn batch_create_projects(
         "skipped": skipped,
         "errors": errors.len(),
         "error_messages": errors,
+        "duplicates": duplicates,
         "total": total
     }));
 
     info!("批量创建完成: 创建 {}, 跳过 {}, 失败 {}, 总计 {}", projects.len(), skipped, errors.len(), total);
     Ok(projects)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_segment(id: &str, start: f64, end: f64, status: SegmentStatus) -> Segment {
+        Segment {
+            id: id.to_string(),
+            project_id: "p1".to_string(),
+            music_id: Some("m1".to_string()),
+            music_title: None,
+            start_time: start,
+            end_time: end,
+            confidence: 0.5,
+            status,
+            segment_type: SegmentType::Music,
+            gain_db: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_segment_overlaps_keeps_status_apart() {
+        let merged = merge_segment_overlaps(vec![
+            test_segment("a", 0.0, 10.0, SegmentStatus::Provisional),
+            test_segment("b", 5.0, 15.0, SegmentStatus::Detected),
+            test_segment("c", 12.0, 20.0, SegmentStatus::Detected),
+        ]);
+        assert_eq!(merged.len(), 2);
+        let provisional = merged.iter().find(|s| s.status == SegmentStatus::Provisional).unwrap();
+        assert_eq!((provisional.id.as_str(), provisional.start_time, provisional.end_time), ("a", 0.0, 10.0));
+        let detected = merged.iter().find(|s| s.status == SegmentStatus::Detected).unwrap();
+        assert_eq!((detected.id.as_str(), detected.start_time, detected.end_time), ("b", 5.0, 20.0));
+    }
+}
@@ -1,7 +1,76 @@
 // 系统命令
 
-use crate::error::AppResult;
-use crate::utils::{SystemInfo, GpuInfo, DependencyCheck, resolve_tool_path, hidden_command};
+use crate::config::{self, AccelerationMode};
+use crate::error::{AppError, AppResult};
+use crate::audio::{fingerprint, separator};
+use crate::commands::video::{CancelFlagGuard, detect_gpu_capabilities, reset_cancel_flag};
+use crate::utils::{AppState, SystemInfo, GpuInfo, DependencyCheck, generate_id, resolve_tool_path, hidden_command};
+use crate::video::ffmpeg::{self, FfmpegCapabilities};
+use serde::Serialize;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::State;
+use tracing::{info, warn};
+
+/// 命令依赖的外部工具
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Dependency {
+    Ffmpeg,
+    Fpcalc,
+}
+
+/// 本次会话中已确认可用的工具（只缓存成功结果，安装后无需重启即可重试）
+static FFMPEG_VERIFIED: AtomicBool = AtomicBool::new(false);
+static FPCALC_VERIFIED: AtomicBool = AtomicBool::new(false);
+
+impl Dependency {
+    fn name(self) -> &'static str {
+        match self {
+            Dependency::Ffmpeg => "FFmpeg",
+            Dependency::Fpcalc => "Chromaprint (fpcalc)",
+        }
+    }
+
+    fn install_url(self) -> &'static str {
+        match self {
+            Dependency::Ffmpeg => "https://ffmpeg.org/download.html",
+            Dependency::Fpcalc => "https://acoustid.org/chromaprint",
+        }
+    }
+
+    fn verified(self) -> &'static AtomicBool {
+        match self {
+            Dependency::Ffmpeg => &FFMPEG_VERIFIED,
+            Dependency::Fpcalc => &FPCALC_VERIFIED,
+        }
+    }
+}
+
+/// 依赖门禁：每个工具在会话内首次使用前确认可执行
+///
+/// 工具缺失时返回带安装地址的 DependencyMissing，避免在深层调用中只得到
+/// 原始的进程启动错误。
+pub(crate) async fn require_dependencies(deps: &[Dependency]) -> AppResult<()> {
+    for &dep in deps {
+        if dep.verified().load(Ordering::Relaxed) {
+            continue;
+        }
+        let available = match dep {
+            Dependency::Ffmpeg => check_ffmpeg_dependency().await.available,
+            Dependency::Fpcalc => check_fpcalc().await,
+        };
+        if !available {
+            warn!("依赖缺失: {}", dep.name());
+            return Err(AppError::DependencyMissing(format!(
+                "未找到 {}，请从 {} 下载安装并添加到 PATH",
+                dep.name(),
+                dep.install_url()
+            )));
+        }
+        dep.verified().store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
 
 /// 获取系统信息
 #[tauri::command]
@@ -59,6 +128,277 @@ pub async fn check_dependencies() -> AppResult<Vec<DependencyCheck>> {
     Ok(checks)
 }
 
+/// 获取当前 ffmpeg 支持的容器格式与解码器
+///
+/// 首次调用时执行 `ffmpeg -formats` / `-decoders` 并缓存，前端可据此提示源视频编码无法解码。
+#[tauri::command]
+pub async fn get_ffmpeg_capabilities() -> AppResult<FfmpegCapabilities> {
+    require_dependencies(&[Dependency::Ffmpeg]).await?;
+    tokio::task::spawn_blocking(ffmpeg::get_capabilities)
+        .await
+        .map_err(|e| AppError::FFmpeg(format!("能力探测任务失败: {}", e)))?
+}
+
+/// 自检生成的测试素材时长（秒），需足够 fpcalc 生成指纹
+const SELF_TEST_MEDIA_DURATION: f64 = 10.0;
+/// 自检人声分离使用的片段时长（秒）
+const SELF_TEST_SEPARATION_DURATION: f64 = 3.0;
+
+/// 自检单个阶段的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestStage {
+    /// 阶段: generate / extract_audio / fingerprint / separation
+    pub name: String,
+    pub passed: bool,
+    /// 前置阶段失败或条件不满足（如模型未下载）时跳过
+    pub skipped: bool,
+    pub duration_ms: u64,
+    pub message: String,
+}
+
+/// 自检报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub stages: Vec<SelfTestStage>,
+    /// 所有未跳过的阶段均通过
+    pub passed: bool,
+}
+
+/// 执行一个自检阶段并记录耗时，返回是否通过
+fn run_self_test_stage(stages: &mut Vec<SelfTestStage>, name: &str, stage: impl FnOnce() -> AppResult<String>) -> bool {
+    let started = std::time::Instant::now();
+    let result = stage();
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let (passed, message) = match result {
+        Ok(message) => (true, message),
+        Err(e) => (false, e.to_string()),
+    };
+    info!("[SELF_TEST] {}: {} ({} ms) {}", name, if passed { "通过" } else { "失败" }, duration_ms, message);
+    stages.push(SelfTestStage { name: name.to_string(), passed, skipped: false, duration_ms, message });
+    passed
+}
+
+/// 记录被跳过的自检阶段
+fn skip_self_test_stage(stages: &mut Vec<SelfTestStage>, name: &str, reason: &str) {
+    info!("[SELF_TEST] {}: 跳过 ({})", name, reason);
+    stages.push(SelfTestStage {
+        name: name.to_string(),
+        passed: false,
+        skipped: true,
+        duration_ms: 0,
+        message: reason.to_string(),
+    });
+}
+
+/// 生成自检用的测试视频（testsrc 画面 + sine 音频）
+fn generate_self_test_video(output_path: &str) -> AppResult<String> {
+    let duration = SELF_TEST_MEDIA_DURATION.to_string();
+    let ffmpeg_path = resolve_tool_path("ffmpeg");
+    let output = hidden_command(&ffmpeg_path)
+        .args([
+            "-f", "lavfi",
+            "-i", &format!("testsrc=duration={}:size=320x240:rate=25", duration),
+            "-f", "lavfi",
+            "-i", &format!("sine=frequency=440:duration={}", duration),
+            "-pix_fmt", "yuv420p",
+            "-shortest",
+            "-y",
+            output_path,
+        ])
+        .output()
+        .map_err(|e| AppError::FFmpeg(format!("ffmpeg 执行失败: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::FFmpeg(format!("生成测试视频失败: {}", stderr.trim())));
+    }
+    Ok(format!("已生成 {} 秒测试视频", SELF_TEST_MEDIA_DURATION))
+}
+
+/// 依次执行自检阶段（阻塞），素材写入 work_dir
+fn run_self_test_stages(work_dir: &std::path::Path) -> Vec<SelfTestStage> {
+    let mut stages = Vec::new();
+    let video_path = work_dir.join("self_test.mp4").to_string_lossy().to_string();
+    let audio_path = work_dir.join("self_test.wav").to_string_lossy().to_string();
+    let clip_path = work_dir.join("self_test_clip.wav").to_string_lossy().to_string();
+    let separation_dir = work_dir.join("separation").to_string_lossy().to_string();
+
+    if !run_self_test_stage(&mut stages, "generate", || generate_self_test_video(&video_path)) {
+        for name in ["extract_audio", "fingerprint", "separation"] {
+            skip_self_test_stage(&mut stages, name, "测试视频生成失败");
+        }
+        return stages;
+    }
+
+    if !run_self_test_stage(&mut stages, "extract_audio", || {
+        ffmpeg::extract_audio_track(&video_path, &audio_path, None, ffmpeg::AudioFormat::fingerprint(), None)?;
+        Ok("音频提取成功".to_string())
+    }) {
+        for name in ["fingerprint", "separation"] {
+            skip_self_test_stage(&mut stages, name, "音频提取失败");
+        }
+        return stages;
+    }
+
+    run_self_test_stage(&mut stages, "fingerprint", || {
+        let (fingerprint, duration) = fingerprint::extract_fingerprint_from_file(&audio_path, None)?;
+        if fingerprint.is_empty() {
+            return Err(AppError::Fingerprint("fpcalc 未返回指纹".to_string()));
+        }
+        Ok(format!("指纹 {} 字节，时长 {:.1} 秒", fingerprint.len(), duration))
+    });
+
+    let config = config::get_config();
+    let model_downloaded = crate::models::get_model_by_id(&config.separation.selected_model_id)
+        .is_some_and(|m| crate::models::check_model_downloaded(&m).downloaded);
+    if !model_downloaded {
+        skip_self_test_stage(&mut stages, "separation", "所选分离模型未下载");
+        return stages;
+    }
+    run_self_test_stage(&mut stages, "separation", || {
+        ffmpeg::extract_audio_segment(&audio_path, &clip_path, 0.0, SELF_TEST_SEPARATION_DURATION, ffmpeg::AudioFormat::EXPORT)?;
+        let flag_id = "self_test";
+        let _guard = CancelFlagGuard::new(flag_id.to_string());
+        let cancel_flag = reset_cancel_flag(flag_id);
+        let result = separator::separate_vocals(
+            &clip_path,
+            &separation_dir,
+            &config.separation,
+            &config.detected_gpu,
+            &AccelerationMode::Cpu,
+            &detect_gpu_capabilities(),
+            None,
+            cancel_flag,
+            flag_id,
+        )?;
+        if !std::path::Path::new(&result.vocals_path).exists() {
+            return Err(AppError::VocalSeparation("未找到分离输出文件".to_string()));
+        }
+        Ok(format!("分离成功（CPU，{} 秒片段）", SELF_TEST_SEPARATION_DURATION))
+    });
+
+    stages
+}
+
+/// 端到端自检
+///
+/// 用 ffmpeg 生成测试视频（testsrc + sine），依次执行：音频提取 → 指纹提取 → 短片段人声分离，
+/// 返回各阶段通过/失败及耗时。所选分离模型未下载时跳过分离阶段。临时文件在结束后删除。
+#[tauri::command]
+pub async fn run_self_test(state: State<'_, AppState>) -> AppResult<SelfTestReport> {
+    info!("[SELF_TEST] === 开始自检 ===");
+    let work_dir = state.app_dir.join("temp").join(format!("self_test_{}", generate_id()));
+    std::fs::create_dir_all(&work_dir)?;
+
+    let stage_dir = work_dir.clone();
+    let result = tokio::task::spawn_blocking(move || run_self_test_stages(&stage_dir)).await;
+
+    if let Err(e) = std::fs::remove_dir_all(&work_dir) {
+        warn!("[SELF_TEST] 清理临时目录失败: {} ({})", work_dir.display(), e);
+    }
+
+    let stages = result.map_err(|e| AppError::Io(std::io::Error::other(format!("自检任务失败: {}", e))))?;
+    let passed = stages.iter().all(|s| s.passed || s.skipped);
+    info!("[SELF_TEST] === 自检完成: {} ===", if passed { "全部通过" } else { "存在失败阶段" });
+    Ok(SelfTestReport { stages, passed })
+}
+
+/// 在系统文件管理器中显示文件（或打开目录）
+///
+/// Windows 使用 `explorer /select,` 选中文件，macOS 使用 `open -R`，
+/// Linux 的 xdg-open 不支持选中文件，打开所在目录。
+#[tauri::command]
+pub async fn open_in_file_manager(path: String) -> AppResult<()> {
+    let target = std::path::Path::new(&path);
+    if !target.exists() {
+        return Err(AppError::NotFound(format!("路径不存在: {}", path)));
+    }
+
+    #[cfg(target_os = "windows")]
+    let result = if target.is_dir() {
+        hidden_command("explorer").arg(&path).spawn()
+    } else {
+        hidden_command("explorer").arg(format!("/select,{}", path)).spawn()
+    };
+
+    #[cfg(target_os = "macos")]
+    let result = if target.is_dir() {
+        hidden_command("open").arg(&path).spawn()
+    } else {
+        hidden_command("open").args(["-R", &path]).spawn()
+    };
+
+    #[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
+    let result = {
+        let dir = if target.is_dir() {
+            target.to_path_buf()
+        } else {
+            target.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| target.to_path_buf())
+        };
+        hidden_command("xdg-open").arg(dir).spawn()
+    };
+
+    // explorer 即使成功也会返回非零退出码，只检查能否启动
+    result?;
+    Ok(())
+}
+
+/// 导出诊断信息压缩包（用于问题反馈）
+///
+/// 包含：
+/// - logs/musiccut.log*（滚动日志）
+/// - config.json（当前配置，不含密钥）
+/// - system_info.json（应用版本、系统信息、依赖检查结果）
+///
+/// 不包含数据库、音乐库和项目文件。返回生成的压缩包路径。
+#[tauri::command]
+pub async fn export_diagnostics(dest: String, state: State<'_, AppState>) -> AppResult<String> {
+    info!("[DIAGNOSTICS] 导出诊断信息: {}", dest);
+
+    let system_info = get_system_info().await?;
+    let dependencies = check_dependencies().await?;
+    let diagnostics = serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "system_info": system_info,
+        "dependencies": dependencies,
+    });
+    let config_json = serde_json::to_string_pretty(&config::get_config())?;
+
+    let zip_err = |e: zip::result::ZipError| AppError::Io(std::io::Error::other(e));
+    let file = std::fs::File::create(&dest)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    // 滚动日志（按文件名排序，保留期外的已在启动时清理）
+    let log_dir = state.app_dir.join("logs");
+    let mut log_files: Vec<std::path::PathBuf> = std::fs::read_dir(&log_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("musiccut.log")))
+                .collect()
+        })
+        .unwrap_or_default();
+    log_files.sort();
+    for path in &log_files {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        zip.start_file(format!("logs/{}", name), options).map_err(zip_err)?;
+        zip.write_all(&std::fs::read(path)?)?;
+    }
+
+    zip.start_file("config.json", options).map_err(zip_err)?;
+    zip.write_all(config_json.as_bytes())?;
+
+    zip.start_file("system_info.json", options).map_err(zip_err)?;
+    zip.write_all(serde_json::to_string_pretty(&diagnostics)?.as_bytes())?;
+
+    zip.finish().map_err(zip_err)?;
+
+    info!("[DIAGNOSTICS] 诊断信息已导出: {} 个日志文件 -> {}", log_files.len(), dest);
+    Ok(dest)
+}
+
 /// 检测 GPU
 async fn detect_gpu() -> AppResult<GpuInfo> {
     // 导入 GPU 能力检测
@@ -126,6 +466,22 @@ async fn detect_gpu() -> AppResult<GpuInfo> {
     })
 }
 
+/// 获取 NVIDIA GPU 数量（nvidia-smi 不可用时返回 0）
+pub(crate) fn nvidia_gpu_count() -> u32 {
+    hidden_command("nvidia-smi")
+        .args(["--query-gpu=index", "--format=csv,noheader"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .count() as u32
+        })
+        .unwrap_or(0)
+}
+
 /// 获取 CUDA 版本
 async fn get_cuda_version() -> Option<String> {
     if let Ok(output) = hidden_command("nvcc").args(["--version"]).output() {
@@ -210,6 +566,46 @@ async fn check_python() -> bool {
         .unwrap_or(false)
 }
 
+/// ffmpeg 最低支持版本：更早的版本 -force_key_frames 表达式处理有误，部分构建缺少 loudnorm
+const MIN_FFMPEG_VERSION: (u32, u32, u32) = (4, 3, 0);
+const MIN_FFMPEG_VERSION_NOTE: &str = "-force_key_frames 与 loudnorm 等功能可能异常";
+/// fpcalc 最低支持版本：1.4 起支持 -raw / -json 输出
+const MIN_FPCALC_VERSION: (u32, u32, u32) = (1, 4, 0);
+const MIN_FPCALC_VERSION_NOTE: &str = "原始指纹输出可能不受支持";
+
+/// 从 `-version` 输出中解析版本号
+///
+/// 支持 "ffmpeg version 6.1.1-full_build..."、"ffmpeg version n4.4.2"、"fpcalc version 1.5.1"。
+/// git 快照构建（如 "N-112345-g..."、"2023-10-01-git-..."）无法判断版本，返回 None。
+fn parse_tool_version(version_line: &str) -> Option<(u32, u32, u32)> {
+    let token = version_line.split("version").nth(1)?.split_whitespace().next()?;
+    let token = token.strip_prefix('n').unwrap_or(token);
+    let numeric: String = token.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+    let mut parts = numeric.split('.').filter(|p| !p.is_empty()).map(|p| p.parse::<u32>().ok());
+    let major = parts.next()??;
+    // 日期格式的快照版本（如 2023-10-01）不是语义化版本
+    if major >= 1000 {
+        return None;
+    }
+    let minor = parts.next().flatten().unwrap_or(0);
+    let patch = parts.next().flatten().unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// 版本低于最低支持版本时返回警告信息
+fn version_warning(tool: &str, version_line: &str, min: (u32, u32, u32), note: &str) -> Option<String> {
+    let version = parse_tool_version(version_line)?;
+    if version >= min {
+        return None;
+    }
+    let warning = format!(
+        "{} 版本 {}.{}.{} 低于最低支持版本 {}.{}.{}，{}，建议升级",
+        tool, version.0, version.1, version.2, min.0, min.1, min.2, note
+    );
+    warn!("[DEPENDENCY] {}", warning);
+    Some(warning)
+}
+
 /// 检查 FFmpeg 依赖
 async fn check_ffmpeg_dependency() -> DependencyCheck {
     let ffmpeg_path = resolve_tool_path("ffmpeg");
@@ -217,12 +613,14 @@ async fn check_ffmpeg_dependency() -> DependencyCheck {
         if output.status.success() {
             let stdout = String::from_utf8_lossy(&output.stdout);
             let version = stdout.lines().next().map(|s| s.to_string());
+            let warning = version.as_deref().and_then(|v| version_warning("FFmpeg", v, MIN_FFMPEG_VERSION, MIN_FFMPEG_VERSION_NOTE));
             return DependencyCheck {
                 name: "FFmpeg".to_string(),
                 available: true,
                 version,
                 path: Some(ffmpeg_path),
                 message: "FFmpeg 已安装".to_string(),
+                warning,
             };
         }
     }
@@ -232,6 +630,7 @@ async fn check_ffmpeg_dependency() -> DependencyCheck {
         version: None,
         path: None,
         message: "FFmpeg 未安装，请安装 FFmpeg 并添加到 PATH".to_string(),
+        warning: None,
     }
 }
 
@@ -248,6 +647,7 @@ async fn check_ffprobe_dependency() -> DependencyCheck {
                 version,
                 path: Some(ffprobe_path),
                 message: "FFprobe 已安装".to_string(),
+                warning: None,
             };
         }
     }
@@ -257,6 +657,7 @@ async fn check_ffprobe_dependency() -> DependencyCheck {
         version: None,
         path: None,
         message: "FFprobe 未安装，请安装 FFmpeg 并添加到 PATH".to_string(),
+        warning: None,
     }
 }
 
@@ -267,12 +668,14 @@ async fn check_fpcalc_dependency() -> DependencyCheck {
         if output.status.success() {
             let stdout = String::from_utf8_lossy(&output.stdout);
             let version = Some(stdout.trim().to_string());
+            let warning = version.as_deref().and_then(|v| version_warning("Chromaprint", v, MIN_FPCALC_VERSION, MIN_FPCALC_VERSION_NOTE));
             return DependencyCheck {
                 name: "Chromaprint (fpcalc)".to_string(),
                 available: true,
                 version,
                 path: Some(fpcalc_path),
                 message: "Chromaprint 已安装".to_string(),
+                warning,
             };
         }
     }
@@ -282,6 +685,7 @@ async fn check_fpcalc_dependency() -> DependencyCheck {
         version: None,
         path: None,
         message: "Chromaprint 未安装，请从 https://acoustid.org/chromaprint 下载".to_string(),
+        warning: None,
     }
 }
 
@@ -297,6 +701,7 @@ async fn check_python_dependency() -> DependencyCheck {
                 version,
                 path: Some("python".to_string()),
                 message: "Python 已安装".to_string(),
+                warning: None,
             };
         }
     }
@@ -306,6 +711,7 @@ async fn check_python_dependency() -> DependencyCheck {
         version: None,
         path: None,
         message: "Python 未安装，请安装 Python 3.10+".to_string(),
+        warning: None,
     }
 }
 
@@ -406,6 +812,7 @@ async fn check_cuda_dependency() -> DependencyCheck {
             version: Some(version),
             path: None,
             message: "CUDA 已安装，GPU 加速可用".to_string(),
+            warning: None,
         };
     }
     DependencyCheck {
@@ -414,5 +821,6 @@ async fn check_cuda_dependency() -> DependencyCheck {
         version: None,
         path: None,
         message: "CUDA 未安装，GPU 加速不可用".to_string(),
+        warning: None,
     }
 }
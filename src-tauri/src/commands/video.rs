@@ -5,35 +5,44 @@
 // - separate_vocals: 人声/伴奏分离（GPU 信号量排队）
 // - match_video_segments: 滑动窗口音频指纹匹配
 // - cut_video / export_video: 视频剪辑与导出
+// - batch_export_projects: 多项目批量导出（有限并行）
 // - detect_persons: 人物检测（独立模块 commands/detection.rs）
 //
 // 并发控制：
 // - GPU_SEMAPHORE: 人声分离 GPU 信号量（同时只允许一个分离任务）
 // - CANCEL_FLAGS: 按项目 ID 管理的取消标志
-// - CHILD_PROCESSES: 按项目 ID 管理的子进程句柄（支持即时 kill）
+// - CHILD_PROCESSES: 按项目 ID 管理的子进程句柄及元数据（支持即时 kill、单独终止）
+// - PAUSED_OPERATIONS: 已暂停的项目 ID（子进程被挂起，恢复后继续）
 
-use crate::config::{self, AccelerationMode};
+use crate::config::{self, AccelerationMode, ConfidenceAggregation};
 use crate::database;
 use crate::error::{AppError, AppResult};
-use crate::utils::{VideoInfo, Segment, SegmentStatus, SegmentType, SeparationResult, CutParams, generate_id, hidden_command, lock_or_recover};
+use crate::utils::{VideoInfo, Segment, SegmentStatus, SegmentType, SeparationResult, CutParams, AudioSource, MatchAudioSource, ExportSelection, GapFill, OutputContainer, SegmentOrder, create_operation_temp_dir, generate_id, hidden_command, lock_or_recover, is_url, media_input_exists, suspend_process, resume_process};
 use crate::video::ffmpeg;
+use crate::logging;
+use crate::commands::system::{require_dependencies, Dependency};
+use crate::commands::models::{DownloadProgressForward, DownloadReporter, ModelDownloadProgress, model_download_flag_id, run_model_download};
 use crate::audio::{separator, fingerprint};
 use crate::audio::separator::{GpuCapabilities, find_separation_outputs};
-use tauri::{Window, State};
+use tauri::{Manager, Window, State};
 use crate::utils::AppState;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::process::Child;
-use tracing::{info, error};
+use tracing::{info, error, warn};
 use rayon::prelude::*;
 
 // 按项目 ID 管理的取消标志，支持多个并发操作互不干扰
 lazy_static::lazy_static! {
     static ref CANCEL_FLAGS: Mutex<HashMap<String, Arc<AtomicBool>>> = Mutex::new(HashMap::new());
     // 按项目 ID 管理的子进程句柄，支持即时取消（直接 kill 进程）
-    static ref CHILD_PROCESSES: Mutex<HashMap<String, Vec<Arc<Mutex<Option<Child>>>>>> = Mutex::new(HashMap::new());
+    static ref CHILD_PROCESSES: Mutex<HashMap<String, Vec<ChildProcessEntry>>> = Mutex::new(HashMap::new());
+    // 已暂停的项目 ID，暂停期间新启动的子进程也会立即挂起
+    static ref PAUSED_OPERATIONS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    // 处理中的项目 ID -> 进行中的任务数（匹配/分离/导出/检测），期间拒绝编辑和删除
+    static ref PROCESSING_PROJECTS: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
 }
 
 // GPU 能力缓存（整个应用生命周期只检测一次）
@@ -46,11 +55,21 @@ static GPU_SEMAPHORE: once_cell::sync::Lazy<tokio::sync::Semaphore> =
 /// RAII 守卫：作用域结束时自动清理取消标志，防止内存泄漏
 pub(crate) struct CancelFlagGuard {
     project_id: String,
+    /// 任务期间锁定的项目（禁止编辑/删除），守卫释放时解锁
+    locked_project: Option<String>,
 }
 
 impl CancelFlagGuard {
     pub(crate) fn new(project_id: String) -> Self {
-        Self { project_id }
+        Self { project_id, locked_project: None }
+    }
+
+    /// 创建守卫并锁定项目，任务结束前 update_segments / save_project / delete_project 会被拒绝
+    pub(crate) fn with_project_lock(flag_id: String, project_id: &str) -> Self {
+        *lock_or_recover(&PROCESSING_PROJECTS, "项目锁")
+            .entry(project_id.to_string())
+            .or_insert(0) += 1;
+        Self { project_id: flag_id, locked_project: Some(project_id.to_string()) }
     }
 }
 
@@ -58,7 +77,38 @@ impl Drop for CancelFlagGuard {
     fn drop(&mut self) {
         remove_cancel_flag(&self.project_id);
         clear_child_processes(&self.project_id);
+        lock_or_recover(&PAUSED_OPERATIONS, "暂停状态").remove(&self.project_id);
+        if let Some(ref project_id) = self.locked_project {
+            let mut processing = lock_or_recover(&PROCESSING_PROJECTS, "项目锁");
+            if let Some(count) = processing.get_mut(project_id) {
+                *count -= 1;
+                if *count == 0 {
+                    processing.remove(project_id);
+                }
+            }
+        }
+    }
+}
+
+/// 项目是否有进行中的处理任务
+pub(crate) fn is_project_processing(project_id: &str) -> bool {
+    lock_or_recover(&PROCESSING_PROJECTS, "项目锁").contains_key(project_id)
+}
+
+/// 项目是否有进行中的操作（处理锁，或任意以项目 ID 结尾的取消标志：分离、预览、检测等）
+pub(crate) fn is_project_busy(project_id: &str) -> bool {
+    if is_project_processing(project_id) {
+        return true;
+    }
+    lock_or_recover(&CANCEL_FLAGS, "取消标志").keys().any(|id| id.ends_with(project_id))
+}
+
+/// 项目处理中时返回错误，供编辑/删除命令调用
+pub(crate) fn ensure_project_not_processing(project_id: &str) -> AppResult<()> {
+    if is_project_processing(project_id) {
+        return Err(AppError::InvalidArgument(format!("项目正在处理中: {}", project_id)));
     }
+    Ok(())
 }
 
 /// 获取或创建项目的取消标志
@@ -86,13 +136,41 @@ pub fn remove_cancel_flag(project_id: &str) {
     flags.remove(project_id);
 }
 
+/// 是否有进行中的可取消操作（匹配、导出、分离、检测等）
+pub(crate) fn has_active_operations() -> bool {
+    !lock_or_recover(&CANCEL_FLAGS, "取消标志").is_empty()
+}
+
+/// 已注册的子进程句柄及元数据
+struct ChildProcessEntry {
+    handle: Arc<Mutex<Option<Child>>>,
+    pid: u32,
+    /// 所属操作（如 encode_segment、separate_vocals）
+    op: &'static str,
+    started: std::time::Instant,
+}
+
 /// 注册子进程到项目（用于即时取消）
-pub fn register_child_process(project_id: &str, child: Child) -> Arc<Mutex<Option<Child>>> {
+///
+/// op 标识启动该进程的操作，供 list_child_processes 排查卡住的进程。
+/// 项目处于暂停状态时立即挂起新进程，避免暂停后启动的下一个片段继续占用 CPU。
+pub fn register_child_process(project_id: &str, op: &'static str, child: Child) -> Arc<Mutex<Option<Child>>> {
+    if is_paused(project_id) {
+        if let Err(e) = suspend_process(&child) {
+            warn!("[PROCESS] 挂起新启动的子进程失败: project_id={}, {}", project_id, e);
+        }
+    }
+    let pid = child.id();
     let handle = Arc::new(Mutex::new(Some(child)));
     let mut processes = lock_or_recover(&CHILD_PROCESSES, "子进程");
     processes.entry(project_id.to_string())
         .or_insert_with(Vec::new)
-        .push(handle.clone());
+        .push(ChildProcessEntry {
+            handle: handle.clone(),
+            pid,
+            op,
+            started: std::time::Instant::now(),
+        });
     handle
 }
 
@@ -105,11 +183,11 @@ pub fn clear_child_processes(project_id: &str) {
 /// kill 项目的所有子进程
 pub(crate) fn kill_child_processes(project_id: &str) {
     let processes = lock_or_recover(&CHILD_PROCESSES, "子进程");
-    if let Some(handles) = processes.get(project_id) {
-        for handle in handles {
-            if let Ok(mut guard) = handle.lock() {
+    if let Some(entries) = processes.get(project_id) {
+        for entry in entries {
+            if let Ok(mut guard) = entry.handle.lock() {
                 if let Some(ref mut child) = *guard {
-                    info!("[PROCESS] 正在终止子进程: project_id={}", project_id);
+                    info!("[PROCESS] 正在终止子进程: project_id={}, op={}, pid={}", project_id, entry.op, entry.pid);
                     let _ = child.kill();
                 }
             }
@@ -117,15 +195,252 @@ pub(crate) fn kill_child_processes(project_id: &str) {
     }
 }
 
+/// 项目当前是否处于暂停状态
+///
+/// 轮询子进程的循环据此区分"暂停"与"卡死"：暂停期间不应触发停滞超时。
+pub(crate) fn is_paused(project_id: &str) -> bool {
+    lock_or_recover(&PAUSED_OPERATIONS, "暂停状态").contains(project_id)
+}
+
+/// 挂起或恢复项目的所有子进程，返回成功处理的进程数
+fn set_child_processes_suspended(project_id: &str, suspend: bool) -> usize {
+    let processes = lock_or_recover(&CHILD_PROCESSES, "子进程");
+    let mut count = 0;
+    if let Some(entries) = processes.get(project_id) {
+        for entry in entries {
+            if let Ok(guard) = entry.handle.lock() {
+                if let Some(ref child) = *guard {
+                    let result = if suspend { suspend_process(child) } else { resume_process(child) };
+                    match result {
+                        Ok(()) => count += 1,
+                        Err(e) => warn!("[PROCESS] {}子进程失败: project_id={}, {}", if suspend { "挂起" } else { "恢复" }, project_id, e),
+                    }
+                }
+            }
+        }
+    }
+    count
+}
+
+/// 子进程信息
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChildProcessInfo {
+    pub pid: u32,
+    /// 所属操作（如 encode_segment、separate_vocals）
+    pub op: String,
+    /// 已运行时长（秒）
+    pub elapsed_secs: f64,
+}
+
+/// 列出项目（或取消标志 ID）下仍在运行的子进程
+///
+/// 用于排查卡住的导出：配合 kill_child_process 只终止单个进程，不取消整个任务。
+#[tauri::command]
+pub async fn list_child_processes(project_id: String) -> AppResult<Vec<ChildProcessInfo>> {
+    let processes = lock_or_recover(&CHILD_PROCESSES, "子进程");
+    let Some(entries) = processes.get(&project_id) else {
+        return Ok(Vec::new());
+    };
+
+    let mut result = Vec::new();
+    for entry in entries {
+        let running = match entry.handle.lock() {
+            Ok(mut guard) => match *guard {
+                Some(ref mut child) => matches!(child.try_wait(), Ok(None)),
+                None => false,
+            },
+            Err(_) => false,
+        };
+        if running {
+            result.push(ChildProcessInfo {
+                pid: entry.pid,
+                op: entry.op.to_string(),
+                elapsed_secs: entry.started.elapsed().as_secs_f64(),
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// 终止项目下的单个子进程
+///
+/// 只结束指定 pid 的进程，其所属操作会以失败返回（可恢复导出的已完成片段保留），
+/// 项目的其他进程和取消标志不受影响。
+#[tauri::command]
+pub async fn kill_child_process(project_id: String, pid: u32) -> AppResult<()> {
+    let processes = lock_or_recover(&CHILD_PROCESSES, "子进程");
+    let entry = processes
+        .get(&project_id)
+        .and_then(|entries| entries.iter().find(|e| e.pid == pid))
+        .ok_or_else(|| AppError::NotFound(format!("子进程不存在: project_id={}, pid={}", project_id, pid)))?;
+
+    let mut guard = lock_or_recover(&entry.handle, "子进程句柄");
+    let alive = guard.as_mut().map(|c| matches!(c.try_wait(), Ok(None))).unwrap_or(false);
+    match guard.as_mut() {
+        Some(child) if alive => {
+            warn!("[PROCESS] 手动终止子进程: project_id={}, op={}, pid={}", project_id, entry.op, pid);
+            child.kill()?;
+            Ok(())
+        }
+        _ => Err(AppError::NotFound(format!("子进程已退出: project_id={}, pid={}", project_id, pid))),
+    }
+}
+
+/// 进行中的任务
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ActiveJob {
+    /// 项目 ID（已去除 det_ / preview_ 前缀；模型下载为模型 ID），批量任务为 None
+    pub project_id: Option<String>,
+    /// 所属流程: processing（提取/分离/匹配/剪辑/导出）、detection、preview、batch_export、batch_detection、model_download
+    pub pipeline: String,
+    /// 原始取消标志 ID，可传给对应的取消命令
+    pub flag_id: String,
+    /// 是否已请求取消（取消中但尚未退出）
+    pub cancelling: bool,
+    /// 是否已暂停
+    pub paused: bool,
+    /// 存活的子进程数
+    pub child_processes: usize,
+}
+
+/// 当前任务状态
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ActiveJobs {
+    pub jobs: Vec<ActiveJob>,
+    /// 人声分离 GPU 是否被占用
+    pub separation_gpu_busy: bool,
+    /// 人物检测 GPU 是否被占用
+    pub detection_gpu_busy: bool,
+}
+
+/// 按取消标志 ID 的前缀区分所属流程
+fn classify_flag_id(flag_id: &str) -> (Option<String>, &'static str) {
+    if flag_id == BATCH_EXPORT_FLAG_ID {
+        (None, "batch_export")
+    } else if flag_id == super::detection::BATCH_DETECTION_FLAG_ID {
+        (None, "batch_detection")
+    } else if let Some(id) = flag_id.strip_prefix("det_") {
+        (Some(id.to_string()), "detection")
+    } else if let Some(id) = flag_id.strip_prefix("preview_").or_else(|| flag_id.strip_prefix("segment_preview_")) {
+        (Some(id.to_string()), "preview")
+    } else if let Some(id) = flag_id.strip_prefix("model_") {
+        (Some(id.to_string()), "model_download")
+    } else {
+        (Some(flag_id.to_string()), "processing")
+    }
+}
+
+/// 获取进行中的任务
+///
+/// 由 CANCEL_FLAGS / CHILD_PROCESSES 的键推导，用于"正在处理"面板和排查卡住的任务。
+#[tauri::command]
+pub async fn get_active_jobs() -> AppResult<ActiveJobs> {
+    let flags: HashMap<String, bool> = lock_or_recover(&CANCEL_FLAGS, "取消标志")
+        .iter()
+        .map(|(id, flag)| (id.clone(), flag.load(Ordering::SeqCst)))
+        .collect();
+    let children: HashMap<String, usize> = lock_or_recover(&CHILD_PROCESSES, "子进程")
+        .iter()
+        .map(|(id, entries)| {
+            let alive = entries
+                .iter()
+                .filter(|e| e.handle.lock().map(|g| g.is_some()).unwrap_or(false))
+                .count();
+            (id.clone(), alive)
+        })
+        .collect();
+
+    let mut flag_ids: Vec<&String> = flags.keys().chain(children.keys()).collect();
+    flag_ids.sort();
+    flag_ids.dedup();
+
+    let jobs = flag_ids
+        .into_iter()
+        .map(|flag_id| {
+            let (project_id, pipeline) = classify_flag_id(flag_id);
+            ActiveJob {
+                project_id,
+                pipeline: pipeline.to_string(),
+                flag_id: flag_id.clone(),
+                cancelling: flags.get(flag_id).copied().unwrap_or(false),
+                paused: is_paused(flag_id),
+                child_processes: children.get(flag_id).copied().unwrap_or(0),
+            }
+        })
+        .collect();
+
+    Ok(ActiveJobs {
+        jobs,
+        separation_gpu_busy: GPU_SEMAPHORE.available_permits() == 0,
+        detection_gpu_busy: super::detection::detection_gpu_busy(),
+    })
+}
+
+/// 探测心跳间隔
+const PROBE_HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// 在阻塞线程中执行 ffprobe 探测，期间定期发送心跳事件
+///
+/// 网络文件探测可能耗时数秒，心跳让前端知道应用仍在工作。
+/// 超过 process_timeout 仍未返回则放弃等待（探测线程在后台自行结束）。
+pub(crate) async fn probe_with_heartbeat<T, F>(
+    window: &Window,
+    event: &str,
+    project_id: Option<&str>,
+    message: &str,
+    probe: F,
+) -> AppResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> AppResult<T> + Send + 'static,
+{
+    let started = std::time::Instant::now();
+    let timeout = config::process_timeout();
+    let mut task = tokio::task::spawn_blocking(probe);
+
+    loop {
+        tokio::select! {
+            result = &mut task => {
+                return result.map_err(|e| AppError::FFmpeg(format!("探测任务异常退出: {}", e)))?;
+            }
+            _ = tokio::time::sleep(PROBE_HEARTBEAT_INTERVAL) => {
+                let elapsed = started.elapsed();
+                if timeout.is_some_and(|t| elapsed > t) {
+                    warn!("[PROBE] 探测超时（{:.0}s）: {}", elapsed.as_secs_f64(), message);
+                    return Err(AppError::Timeout(format!("{}超过 {} 秒未完成", message, elapsed.as_secs())));
+                }
+                let _ = window.emit(event, serde_json::json!({
+                    "elapsed": elapsed.as_secs_f64(),
+                    "message": format!("{}（已等待 {} 秒）", message, elapsed.as_secs()),
+                    "project_id": project_id
+                }));
+            }
+        }
+    }
+}
+
 /// 分析视频
+///
+/// 事件: `analyze-progress` — 探测耗时较长时每秒发送一次心跳
 #[tauri::command]
-pub async fn analyze_video(path: String) -> AppResult<VideoInfo> {
-    let video_path = Path::new(&path);
-    if !video_path.exists() {
+pub async fn analyze_video(
+    window: Window,
+    path: String,
+    project_id: Option<String>,
+) -> AppResult<VideoInfo> {
+    // 网络 URL（http/rtmp 等）交给 ffprobe 直接读取
+    if !media_input_exists(&path) {
         return Err(AppError::NotFound(format!("视频文件不存在: {}", path)));
     }
 
-    ffmpeg::get_video_info(&path)
+    probe_with_heartbeat(
+        &window,
+        "analyze-progress",
+        project_id.as_deref(),
+        "正在分析视频",
+        move || ffmpeg::get_video_info(&path),
+    )
+    .await
 }
 
 /// 缓存状态
@@ -147,13 +462,23 @@ pub async fn check_cache_status(
     model_id: String,
     state: State<'_, AppState>,
 ) -> AppResult<CacheStatus> {
+    Ok(inspect_cache_status(&state.app_dir, &project_id, &video_path, &model_id))
+}
+
+/// 检查项目的音频提取与人声分离缓存（check_cache_status 的实现，供导出等流程复用）
+pub(crate) fn inspect_cache_status(
+    app_dir: &Path,
+    project_id: &str,
+    video_path: &str,
+    model_id: &str,
+) -> CacheStatus {
     info!("[CACHE] === 检查缓存状态 === project_id={}, model_id={}", project_id, model_id);
 
-    let temp_dir = state.app_dir.join("temp");
+    let temp_dir = app_dir.join("temp");
 
     // 1. 检查音频提取缓存
     let audio_file = temp_dir.join(format!("{}_audio.wav", project_id));
-    let video_file = Path::new(&video_path);
+    let video_file = Path::new(video_path);
 
     let audio_valid = if !audio_file.exists() {
         info!("[CACHE] 音频缓存未命中: 文件不存在 {}", audio_file.display());
@@ -161,6 +486,9 @@ pub async fn check_cache_status(
     } else if !audio_file.metadata().map(|m| m.len() > 0).unwrap_or(false) {
         info!("[CACHE] 音频缓存未命中: 文件为空 {}", audio_file.display());
         false
+    } else if is_url(video_path) {
+        // 网络输入无法比较修改时间，音频文件存在即视为有效
+        true
     } else if !video_file.exists() {
         info!("[CACHE] 音频缓存未命中: 源视频不存在 {}", video_path);
         false
@@ -192,7 +520,7 @@ pub async fn check_cache_status(
         let audio_filename = format!("{}_audio", project_id);
         let sep_config = config::get_config();
 
-        if let Some(model) = crate::models::get_model_by_id(&model_id) {
+        if let Some(model) = crate::models::get_model_by_id(model_id) {
             let output = find_separation_outputs(
                 &separated_dir.to_string_lossy(),
                 &audio_filename,
@@ -229,18 +557,52 @@ pub async fn check_cache_status(
 
     info!("[CACHE] 分离缓存有效: {}", separation_valid);
 
-    Ok(CacheStatus {
+    CacheStatus {
         audio_valid,
         audio_path: if audio_valid { Some(audio_file.to_string_lossy().to_string()) } else { None },
         separation_valid,
         vocals_path: vocals_path_result,
         accompaniment_path: accompaniment_path_result,
-    })
+    }
+}
+
+/// 根据导出音轨来源解析要替换的分离音频文件
+///
+/// Original 返回 None；Vocals/Instrumental 要求当前模型的分离缓存有效，否则报错。
+fn resolve_audio_stem(
+    app_dir: &Path,
+    project_id: &str,
+    video_path: &str,
+    audio_source: AudioSource,
+) -> AppResult<Option<String>> {
+    if audio_source == AudioSource::Original {
+        return Ok(None);
+    }
+
+    let model_id = config::get_config().separation.selected_model_id;
+    let status = inspect_cache_status(app_dir, project_id, video_path, &model_id);
+    let stem = match audio_source {
+        AudioSource::Vocals => status.vocals_path,
+        AudioSource::Instrumental => status.accompaniment_path,
+        AudioSource::Original => None,
+    };
+
+    match stem {
+        Some(path) if status.separation_valid && Path::new(&path).exists() => {
+            info!("[EXPORT] 使用分离音频替换音轨: {:?} -> {}", audio_source, path);
+            Ok(Some(path))
+        }
+        _ => Err(AppError::InvalidArgument(format!(
+            "人声分离缓存无效，无法使用{}作为导出音轨，请先执行人声分离",
+            if audio_source == AudioSource::Vocals { "人声" } else { "伴奏" }
+        ))),
+    }
 }
 
 /// 提取音频
 ///
 /// 从视频文件中提取音频轨道，输出为 WAV 格式。
+/// audio_track 指定音轨序号（从 0 开始），超出探测到的音轨数量时返回错误。
 /// 事件: `extract-progress` — 提取进度
 #[tauri::command]
 pub async fn extract_audio(
@@ -248,16 +610,18 @@ pub async fn extract_audio(
     video_path: String,
     output_path: String,
     project_id: Option<String>,
+    audio_track: Option<usize>,
 ) -> AppResult<String> {
     info!("[EXTRACT] === 开始提取音频 ===");
     info!("[EXTRACT] 视频路径: {}", video_path);
     info!("[EXTRACT] 输出路径: {}", output_path);
 
     // 检查视频文件是否存在
-    if !Path::new(&video_path).exists() {
+    if !media_input_exists(&video_path) {
         error!("[EXTRACT] 视频文件不存在: {}", video_path);
         return Err(AppError::NotFound(format!("视频文件不存在: {}", video_path)));
     }
+    require_dependencies(&[Dependency::Ffmpeg]).await?;
 
     let project_id_clone = project_id.clone();
     let _ = window.emit("extract-progress", serde_json::json!({
@@ -269,6 +633,8 @@ pub async fn extract_audio(
     ffmpeg::extract_audio_track(
         &video_path,
         &output_path,
+        audio_track,
+        ffmpeg::AudioFormat::EXPORT,
         Some(Box::new(move |progress| {
             let _ = window.emit("extract-progress", serde_json::json!({
                 "progress": progress,
@@ -282,10 +648,117 @@ pub async fn extract_audio(
     Ok(output_path)
 }
 
+/// 自动下载模型时轮询项目取消标志的间隔
+const AUTO_DOWNLOAD_CANCEL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// 所选分离模型未下载且开启 auto_download_model 时，先下载模型
+///
+/// 下载进度通过 `separation-progress` 事件发送（`download_progress` 字段）；
+/// 项目被取消时同时取消下载并返回 Cancelled。
+async fn ensure_separation_model(
+    window: &Window,
+    separation: &config::SeparationConfig,
+    project_id: &Option<String>,
+    cancel_flag: &Arc<AtomicBool>,
+) -> AppResult<()> {
+    let Some(model) = crate::models::get_model_by_id(&separation.selected_model_id) else { return Ok(()) };
+    if !separation.auto_download_model || crate::models::check_model_downloaded(&model).downloaded {
+        return Ok(());
+    }
+
+    info!("[SEPARATION] 模型未下载，自动下载: {} ({})", model.name, model.id);
+    let forward_window = window.clone();
+    let forward_project_id = project_id.clone();
+    let forward: DownloadProgressForward = Arc::new(move |p: &ModelDownloadProgress| {
+        let _ = forward_window.emit("separation-progress", serde_json::json!({
+            "progress": 0.0,
+            "message": format!("下载模型: {}", p.message),
+            "download_progress": p.progress,
+            "project_id": forward_project_id
+        }));
+    });
+
+    // 项目取消时转为取消模型下载（下载开始时会重置其标志，因此持续设置直到下载结束）
+    let flag_id = model_download_flag_id(&model.id);
+    let done = Arc::new(AtomicBool::new(false));
+    let watcher = {
+        let done = done.clone();
+        let cancel_flag = cancel_flag.clone();
+        let flag_id = flag_id.clone();
+        tauri::async_runtime::spawn(async move {
+            while !done.load(Ordering::SeqCst) {
+                if cancel_flag.load(Ordering::SeqCst) {
+                    get_cancel_flag(&flag_id).store(true, Ordering::SeqCst);
+                    kill_child_processes(&flag_id);
+                }
+                tokio::time::sleep(AUTO_DOWNLOAD_CANCEL_POLL_INTERVAL).await;
+            }
+        })
+    };
+
+    let reporter = DownloadReporter::with_forward(window.app_handle(), forward);
+    let result = run_model_download(reporter, model.id.clone()).await;
+    done.store(true, Ordering::SeqCst);
+    let _ = watcher.await;
+
+    if cancel_flag.load(Ordering::SeqCst) {
+        // 监视任务可能在下载结束后重新创建了标志
+        remove_cancel_flag(&flag_id);
+        info!("[SEPARATION] 自动下载模型时被取消: {}", model.id);
+        return Err(AppError::Cancelled);
+    }
+    result?;
+    info!("[SEPARATION] 模型自动下载完成: {}", model.id);
+    Ok(())
+}
+
+/// 操作完成后的性能指标（`operation-metrics` 事件），用于耗时统计与性能问题排查
+#[derive(Debug, Clone, serde::Serialize)]
+pub(crate) struct OperationMetrics {
+    pub project_id: Option<String>,
+    /// 操作类型：match / separation / export / detection
+    pub operation: &'static str,
+    /// 总耗时（毫秒，含排队等待）
+    pub duration_ms: u64,
+    /// 输入文件大小（字节）
+    pub input_size: Option<u64>,
+    /// 输入媒体时长（秒）
+    pub input_duration: Option<f64>,
+    /// 产生/导出的片段数
+    pub segment_count: Option<usize>,
+    /// 是否使用了 GPU 或硬件编码
+    pub gpu: bool,
+}
+
+impl OperationMetrics {
+    /// 以 started 为起点计算耗时，输入大小从 input_path 读取
+    pub(crate) fn new(operation: &'static str, project_id: Option<String>, started: std::time::Instant, input_path: &str) -> Self {
+        Self {
+            project_id,
+            operation,
+            duration_ms: started.elapsed().as_millis() as u64,
+            input_size: std::fs::metadata(input_path).map(|m| m.len()).ok(),
+            input_duration: None,
+            segment_count: None,
+            gpu: false,
+        }
+    }
+
+    /// 发送 `operation-metrics` 事件并记录日志
+    pub(crate) fn emit(self, window: &Window) {
+        info!(
+            "[METRICS] {} 完成: project_id={:?}, 耗时 {:.2}s, 输入 {:?} 字节 / {:?} 秒, 片段 {:?}, GPU={}",
+            self.operation, self.project_id, self.duration_ms as f64 / 1000.0,
+            self.input_size, self.input_duration, self.segment_count, self.gpu
+        );
+        let _ = window.emit("operation-metrics", self);
+    }
+}
+
 /// 人声分离
 ///
-/// 流程：获取 GPU 许可 → 调用 audio-separator → 返回人声/伴奏路径
-/// 事件: `separation-queued` / `separation-progress` / `separation-complete`
+/// 流程：（可选）自动下载模型 → 获取 GPU 许可 → 调用 audio-separator → 返回人声/伴奏路径
+/// 事件: `separation-queued` / `separation-progress` / `separation-complete` / `operation-metrics`
 #[tauri::command]
 pub async fn separate_vocals(
     window: Window,
@@ -294,6 +767,7 @@ pub async fn separate_vocals(
     acceleration: Option<String>,
     project_id: Option<String>,
 ) -> AppResult<SeparationResult> {
+    let started = std::time::Instant::now();
     info!("[SEPARATION] === 开始人声分离 ===");
     info!("[SEPARATION] 音频路径: {}", audio_path);
     info!("[SEPARATION] 输出目录: {}", output_dir);
@@ -301,9 +775,14 @@ pub async fn separate_vocals(
 
     // 获取项目取消标志，如果没有 project_id 则使用默认标识
     let cancel_flag_id = project_id.clone().unwrap_or_else(|| "default".to_string());
-    let _guard = CancelFlagGuard::new(cancel_flag_id.clone());
+    let _guard = match project_id {
+        Some(ref id) => CancelFlagGuard::with_project_lock(cancel_flag_id.clone(), id),
+        None => CancelFlagGuard::new(cancel_flag_id.clone()),
+    };
     let cancel_flag = reset_cancel_flag(&cancel_flag_id);
 
+    ensure_separation_model(&window, &config::get_config().separation, &project_id, &cancel_flag).await?;
+
     // GPU 信号量排队：同一时间只允许一个分离任务运行，避免 GPU OOM
     let _permit = match GPU_SEMAPHORE.try_acquire() {
         Ok(permit) => {
@@ -361,25 +840,54 @@ pub async fn separate_vocals(
         "project_id": project_id
     }));
 
-    let window_clone = window.clone();
-    let project_id_clone = project_id.clone();
-    let result = separator::separate_vocals(
+    let make_progress_callback = || -> separator::ProgressCallback {
+        let window_clone = window.clone();
+        let project_id_clone = project_id.clone();
+        Box::new(move |progress, message| {
+            let _ = window_clone.emit("separation-progress", serde_json::json!({
+                "progress": progress,
+                "message": message,
+                "project_id": project_id_clone
+            }));
+        })
+    };
+    let op_span = logging::operation_span(&cancel_flag_id, "separation");
+    let _op_enter = op_span.enter();
+    let mut used_gpu = accel_mode == AccelerationMode::Gpu && gpu_caps.onnx_gpu_available;
+    let result = match separator::separate_vocals(
         &audio_path,
         &output_dir,
         &config.separation,
         &config.detected_gpu,
         &accel_mode,
         &gpu_caps,
-        Some(Box::new(move |progress, message| {
-            let _ = window_clone.emit("separation-progress", serde_json::json!({
-                "progress": progress,
-                "message": message,
-                "project_id": project_id_clone
-            }));
-        })),
-        cancel_flag,
+        Some(make_progress_callback()),
+        cancel_flag.clone(),
         &cancel_flag_id,
-    )?;
+    ) {
+        // GPU 显存不足时自动以 CPU 模式重试一次
+        Err(AppError::GpuOutOfMemory(msg)) if accel_mode != AccelerationMode::Cpu => {
+            warn!("[SEPARATION] GPU 显存不足，改用 CPU 重试: {}", msg);
+            let _ = window.emit("gpu-fallback", serde_json::json!({
+                "project_id": project_id,
+                "operation": "separation",
+                "message": "GPU 显存不足，已自动改用 CPU 进行人声分离"
+            }));
+            used_gpu = false;
+            separator::separate_vocals(
+                &audio_path,
+                &output_dir,
+                &config.separation,
+                &config.detected_gpu,
+                &AccelerationMode::Cpu,
+                &gpu_caps,
+                Some(make_progress_callback()),
+                cancel_flag,
+                &cancel_flag_id,
+            )?
+        }
+        other => other?,
+    };
 
     let _ = window.emit("separation-complete", serde_json::json!({
         "vocals_path": result.vocals_path,
@@ -387,96 +895,477 @@ pub async fn separate_vocals(
         "project_id": project_id
     }));
 
+    OperationMetrics {
+        input_duration: ffmpeg::get_audio_duration(&audio_path).ok(),
+        gpu: used_gpu,
+        ..OperationMetrics::new("separation", project_id.clone(), started, &audio_path)
+    }
+    .emit(&window);
+
     Ok(result)
 }
 
-/// 匹配视频片段
-///
-/// 滑动窗口提取伴奏音频指纹，与音乐库指纹比对，合并连续匹配窗口为片段。
-/// 事件: `matching-progress` / `matching-complete`
-#[tauri::command]
-pub async fn match_video_segments(
-    window: Window,
-    accompaniment_path: String,
-    project_id: String,
-    min_confidence: Option<f64>,
-    music_ids: Option<Vec<String>>,
-) -> AppResult<Vec<Segment>> {
-    let _guard = CancelFlagGuard::new(project_id.clone());
-    let cancel_flag = reset_cancel_flag(&project_id);
+/// 末尾剩余未覆盖部分超过该比例的步长时，追加一个锚定在结尾的窗口
+const FINAL_WINDOW_TAIL_RATIO: f64 = 0.25;
 
-    let config = config::get_config();
-    let min_conf = min_confidence.unwrap_or(config.matching.min_confidence as f64);
-    let window_size = config.matching.window_size as f64;
-    let hop_size = config.matching.hop_size as f64;
-    let min_duration = config.matching.min_segment_duration as f64;
-    let max_gap_duration = config.matching.max_gap_duration as f64;
+/// 自适应窗口：不超过该时长的视频使用 min_window_size
+const ADAPTIVE_SHORT_DURATION: f64 = 120.0;
+/// 自适应窗口：不短于该时长的视频使用 max_window_size
+const ADAPTIVE_LONG_DURATION: f64 = 3600.0;
 
-    // 验证参数，防止除零错误
-    if hop_size <= 0.0 {
-        return Err(AppError::Config("滑动步长必须大于0".to_string()));
+/// 按视频时长计算自适应窗口大小与步长 (window_size, hop_size)
+///
+/// 缩放系数按对数时长线性插值：
+/// `t = clamp(ln(D / SHORT) / ln(LONG / SHORT), 0, 1)`，
+/// `window = min_window_size + t * (max_window_size - min_window_size)`。
+/// 步长保持配置中的 hop_size / window_size 比例，长视频窗口变大的同时步长也随之变大，
+/// 控制窗口总数；短视频用小窗口捕捉短暂的音乐片段。
+fn adaptive_window_params(total_duration: f64, matching: &config::MatchConfig) -> (f64, f64) {
+    let min_window = matching.min_window_size as f64;
+    let max_window = matching.max_window_size as f64;
+    let t = ((total_duration.max(1.0) / ADAPTIVE_SHORT_DURATION).ln()
+        / (ADAPTIVE_LONG_DURATION / ADAPTIVE_SHORT_DURATION).ln())
+        .clamp(0.0, 1.0);
+    let window_size = min_window + t * (max_window - min_window);
+    let hop_ratio = matching.hop_size as f64 / matching.window_size as f64;
+    (window_size, window_size * hop_ratio)
+}
+
+/// 生成匹配窗口起始时间 (window_index, start_time)
+///
+/// 常规窗口按 hop_size 滑动且必须完整落在音频内；若最后一个常规窗口之后
+/// 剩余的尾部超过 FINAL_WINDOW_TAIL_RATIO * hop_size，追加一个起点为
+/// total_duration - window_size 的窗口，避免视频结尾处的音乐漏检。
+fn compute_window_times(total_duration: f64, window_size: f64, hop_size: f64) -> Vec<(usize, f64)> {
+    if total_duration < window_size || hop_size <= 0.0 {
+        return Vec::new();
     }
-    if window_size <= 0.0 {
-        return Err(AppError::Config("窗口大小必须大于0".to_string()));
+
+    let total_windows = ((total_duration - window_size) / hop_size).ceil() as usize + 1;
+    let mut window_times: Vec<(usize, f64)> = (0..total_windows)
+        .map(|i| (i, i as f64 * hop_size))
+        .filter(|(_, t)| *t + window_size <= total_duration)
+        .collect();
+
+    let last_end = window_times.last().map(|(_, t)| t + window_size).unwrap_or(0.0);
+    if total_duration - last_end > hop_size * FINAL_WINDOW_TAIL_RATIO {
+        window_times.push((window_times.len(), total_duration - window_size));
     }
 
-    let _ = window.emit("matching-progress", serde_json::json!({
-        "progress": 0.0,
-        "message": "开始匹配音频片段...",
-        "project_id": project_id
-    }));
+    window_times
+}
 
-    // 获取音频时长
-    let total_duration = ffmpeg::get_audio_duration(&accompaniment_path)?;
+/// 生成限定在 [range_start, range_end) 内的匹配窗口，起始时间为原音频中的绝对时间
+fn compute_window_times_in_range(range_start: f64, range_end: f64, window_size: f64, hop_size: f64) -> Vec<(usize, f64)> {
+    compute_window_times(range_end - range_start, window_size, hop_size)
+        .into_iter()
+        .map(|(index, time)| (index, time + range_start))
+        .collect()
+}
 
-    // 边界检查：视频时长必须大于窗口大小才能进行匹配
-    if total_duration < window_size {
-        let msg = format!(
-            "视频时长 ({:.1}s) 小于最小匹配时长 ({:.1}s)，无法进行识别",
-            total_duration, window_size
-        );
-        info!("[MATCHING] {}", msg);
-        return Err(AppError::InvalidArgument(msg));
+/// 多曲模式：按 music_id 分组后各自合并，同一时间可存在多首歌曲的并行片段
+///
+/// 结果按片段开始时间排序。
+#[allow(clippy::too_many_arguments)]
+fn merge_window_matches_per_music(
+    sorted_results: Vec<(usize, String, String, f64)>,
+    window_times: &[(usize, f64)],
+    window_size: f64,
+    max_gap_duration: f64,
+    min_duration: f64,
+    total_duration: f64,
+    project_id: &str,
+    aggregation: ConfidenceAggregation,
+) -> Vec<Segment> {
+    let mut groups: HashMap<String, Vec<(usize, String, String, f64)>> = HashMap::new();
+    for result in sorted_results {
+        groups.entry(result.1.clone()).or_default().push(result);
     }
 
-    // 获取音乐库指纹（支持自定义音乐列表）
-    let library = match &music_ids {
-        Some(ids) if !ids.is_empty() => {
-        info!("[MATCHING] 使用自定义音乐库: {} 首音乐, ID 列表: {:?}", ids.len(), ids);
-            database::get_fingerprints_by_ids(ids)?
+    let mut segments: Vec<Segment> = groups
+        .into_values()
+        .flat_map(|group| {
+            merge_window_matches(group, window_times, window_size, max_gap_duration, min_duration, total_duration, project_id, aggregation)
+        })
+        .collect();
+    segments.sort_by(|a, b| a.start_time.partial_cmp(&b.start_time).unwrap_or(std::cmp::Ordering::Equal));
+    segments
+}
+
+/// 按配置聚合片段内各窗口的置信度
+///
+/// windows: 片段内匹配窗口的 (开始时间, 置信度)，按时间排序。
+/// 覆盖加权：每个窗口按新覆盖的时长（扣除与前一窗口的重叠）加权后除以片段时长，
+/// 片段内未被匹配窗口覆盖的间隙计为 0，只有一个强窗口的长片段得分会明显偏低。
+fn aggregate_confidence(
+    windows: &[(f64, f64)],
+    window_size: f64,
+    start: f64,
+    end: f64,
+    mode: ConfidenceAggregation,
+) -> f64 {
+    let max = windows.iter().map(|(_, conf)| *conf).fold(0.0, f64::max);
+    match mode {
+        ConfidenceAggregation::Max => max,
+        ConfidenceAggregation::Mean => {
+            if windows.is_empty() {
+                return 0.0;
+            }
+            windows.iter().map(|(_, conf)| conf).sum::<f64>() / windows.len() as f64
         }
-        _ => {
-            info!("[MATCHING] 使用全部音乐库");
-            database::get_all_fingerprints()?
+        ConfidenceAggregation::CoverageWeighted => {
+            let duration = end - start;
+            if duration <= 0.0 {
+                return max;
+            }
+            let mut covered_until = start;
+            let mut weighted = 0.0;
+            for (time, conf) in windows {
+                let window_end = (time + window_size).min(end);
+                let covered = window_end - time.max(covered_until);
+                if covered > 0.0 {
+                    weighted += conf * covered;
+                    covered_until = window_end;
+                }
+            }
+            weighted / duration
         }
-    };
-    info!("[MATCHING] 音乐库加载完成: 共 {} 首音乐", library.len());
-    if library.is_empty() {
-        return Err(AppError::NotFound("音乐库为空，请先导入音乐".to_string()));
     }
+}
 
-    // 清除该项目的所有旧片段（音乐匹配 + 人物检测），每次任务输出全新结果
-    database::delete_segments_by_project(&project_id)?;
+/// 合并中的片段：(music_id, title, start_time, [(window_start, confidence)], last_window_index)
+type PendingMatch = (String, String, f64, Vec<(f64, f64)>, usize);
 
-    let temp_dir = tempfile::tempdir()?;
-    let total_windows = ((total_duration - window_size) / hop_size).ceil() as usize + 1;
+/// 将按窗口索引排序的匹配结果合并为片段
+///
+/// results: (window_index, music_id, music_title, confidence)，window_index 对应 window_times 下标。
+/// 同一歌曲的相邻窗口间隙不超过 max_gap_duration 时合并，短于 min_duration 的片段被丢弃。
+/// 片段置信度按 aggregation 聚合各窗口置信度。
+#[allow(clippy::too_many_arguments)]
+fn merge_window_matches(
+    sorted_results: Vec<(usize, String, String, f64)>,
+    window_times: &[(usize, f64)],
+    window_size: f64,
+    max_gap_duration: f64,
+    min_duration: f64,
+    total_duration: f64,
+    project_id: &str,
+    aggregation: ConfidenceAggregation,
+) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut current_match: Option<PendingMatch> = None;
 
-    // 生成所有窗口时间点
-    let window_times: Vec<(usize, f64)> = (0..total_windows)
-        .map(|i| (i, i as f64 * hop_size))
-        .filter(|(_, t)| *t + window_size <= total_duration)
-        .collect();
+    // 结束当前片段，时长达到 min_duration 时生成 Segment
+    let mut finish = |music_id: String, music_title: String, start: f64, windows: Vec<(f64, f64)>, last_idx: usize| {
+        let end_time = window_times[last_idx].1 + window_size;
+        if end_time - start >= min_duration {
+            let end_time = end_time.min(total_duration);
+            segments.push(Segment {
+                id: generate_id(),
+                project_id: project_id.to_string(),
+                music_id: Some(music_id),
+                music_title: Some(music_title),
+                start_time: start,
+                end_time,
+                confidence: aggregate_confidence(&windows, window_size, start, end_time, aggregation),
+                status: SegmentStatus::Detected,
+                segment_type: SegmentType::Music,
+                gain_db: None,
+            });
+        }
+    };
 
-    let actual_windows = window_times.len();
+    for (window_index, music_id, music_title, confidence) in sorted_results {
+        let current_time = window_times[window_index].1;
 
-    // 进度计数器
-    let processed_count = Arc::new(AtomicUsize::new(0));
-    let window_for_progress = window.clone();
-    let project_id_for_progress = project_id.clone();
+        match current_match.take() {
+            None => {
+                // 开始新的匹配片段
+                current_match = Some((music_id, music_title, current_time, vec![(current_time, confidence)], window_index));
+            }
+            Some((curr_id, curr_title, start, mut windows, last_idx)) if curr_id == music_id => {
+                // 检查时间连续性：计算与上一个匹配窗口的实际间隙
+                // 间隙 = 当前窗口开始时间 - 上一个窗口结束时间
+                let last_end_time = window_times[last_idx].1 + window_size;
+                let gap = current_time - last_end_time;
 
-    // 并行处理每个窗口（限制线程数，预留 CPU 给 tokio 和 UI 响应）
+                if gap <= max_gap_duration {
+                    // 间隙在允许范围内（包括重叠的情况，gap <= 0），继续合并当前片段
+                    windows.push((current_time, confidence));
+                    current_match = Some((curr_id, curr_title, start, windows, window_index));
+                } else {
+                    // 间隙过大，结束当前片段，开始新片段
+                    finish(curr_id, curr_title, start, windows, last_idx);
+                    current_match = Some((music_id, music_title, current_time, vec![(current_time, confidence)], window_index));
+                }
+            }
+            Some((curr_id, curr_title, start, windows, last_idx)) => {
+                // 不同歌曲，结束当前匹配片段，开始新的匹配
+                finish(curr_id, curr_title, start, windows, last_idx);
+                current_match = Some((music_id, music_title, current_time, vec![(current_time, confidence)], window_index));
+            }
+        }
+    }
+
+    // 处理最后一个匹配片段
+    if let Some((music_id, music_title, start, windows, last_idx)) = current_match {
+        finish(music_id, music_title, start, windows, last_idx);
+    }
+
+    segments
+}
+
+/// 窗口指纹分析时长余量（秒），fpcalc 只需分析窗口长度
+const WINDOW_FINGERPRINT_MARGIN: f64 = 1.0;
+
+/// 按置信度阈值筛选单个窗口的匹配结果（按置信度降序，首个为最佳匹配）
+///
+/// 最佳匹配低于 min_conf 时丢弃整个窗口，次级匹配需达到 secondary_conf（多曲模式的次级阈值）；
+/// 审核模式下最佳候选不受阈值限制（合并后再标记为待确认），次级匹配仍需达到次级阈值。
+fn filter_window_matches(
+    matches: Vec<(usize, String, String, f64)>,
+    min_conf: f64,
+    secondary_conf: f64,
+    review_mode: bool,
+) -> Vec<(usize, String, String, f64)> {
+    match matches.first() {
+        Some((_, _, _, best)) if review_mode || *best >= min_conf => {}
+        _ => return Vec::new(),
+    }
+    matches
+        .into_iter()
+        .enumerate()
+        .filter(|(rank, (_, _, _, conf))| *rank == 0 || *conf >= secondary_conf)
+        .map(|(_, m)| m)
+        .collect()
+}
+
+/// 两个路径是否指向同一文件（规范化后比较，兼容相对路径、符号链接与大小写/分隔符差异）
+///
+/// 任一路径无法规范化（如文件不存在）时回退为直接比较。
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// 审核模式：将置信度低于阈值的片段标记为待确认
+fn mark_provisional_segments(segments: &mut [Segment], min_confidence: f64) {
+    for segment in segments.iter_mut().filter(|s| s.confidence < min_confidence) {
+        segment.status = SegmentStatus::Provisional;
+    }
+}
+
+/// 匹配视频片段
+///
+/// 滑动窗口提取伴奏音频指纹，与音乐库指纹比对，合并连续匹配窗口为片段。
+/// start_time / end_time 限定只分析音频中的一段，片段时间仍为原音频中的绝对时间。
+/// multi_match 为 true 时保留每个窗口中达到 secondary_confidence 的所有歌曲，
+/// 按歌曲分别合并，串烧/交叉淡入淡出处可产生时间重叠的多个片段。
+/// music_album 只匹配该专辑下的音乐（不区分大小写），与 music_ids 同时指定时取交集。
+/// review_mode 为 true 时保留每个窗口的最佳候选（不受 min_confidence 限制），
+/// 合并后置信度低于 min_confidence 的片段标记为 Provisional，供前端逐个确认或拒绝。
+/// audio_source 指定 accompaniment_path 的来源，省略时使用配置 matching.audio_source（默认要求人声分离后的伴奏）；
+/// 为 Mixed 时可直接传入提取的原始音轨以跳过人声分离，accompaniment_path 为空时使用项目已提取的音轨
+/// （准确率取舍见 MatchAudioSource）。实际使用的来源记录在项目匹配参数中。
+/// window_size / hop_size / max_gap_duration / min_segment_duration 仅覆盖本次调用的匹配参数，
+/// 省略时使用全局配置；显式指定窗口或步长时不再应用自适应窗口。
+/// 事件: `matching-progress` / `matching-complete` / `operation-metrics`
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn match_video_segments(
+    window: Window,
+    accompaniment_path: String,
+    project_id: String,
+    min_confidence: Option<f64>,
+    music_ids: Option<Vec<String>>,
+    verbose: Option<bool>,
+    adaptive_window: Option<bool>,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+    multi_match: Option<bool>,
+    music_album: Option<String>,
+    review_mode: Option<bool>,
+    audio_source: Option<MatchAudioSource>,
+    window_size: Option<f64>,
+    hop_size: Option<f64>,
+    max_gap_duration: Option<f64>,
+    min_segment_duration: Option<f64>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<Segment>> {
+    let started = std::time::Instant::now();
+    require_dependencies(&[Dependency::Ffmpeg, Dependency::Fpcalc]).await?;
+
+    let config = config::get_config();
+
+    // 未指定时使用配置的音频来源（默认伴奏）
+    let audio_source = audio_source.unwrap_or(config.matching.audio_source);
+    let extracted_audio = state.app_dir.join("temp").join(format!("{}_audio.wav", project_id));
+    let accompaniment_path = match audio_source {
+        // 伴奏：传入的是项目提取的原始音轨时提示先执行人声分离或显式选择混合音轨
+        MatchAudioSource::Accompaniment if is_same_file(Path::new(&accompaniment_path), &extracted_audio) => {
+            return Err(AppError::InvalidArgument(
+                "匹配音频为未分离的原始音轨，请先执行人声分离，或将音频来源设为混合音轨".to_string()
+            ));
+        }
+        // 混合音轨：未传入路径时直接使用项目已提取的原始音轨，无需人声分离
+        MatchAudioSource::Mixed if accompaniment_path.trim().is_empty() => {
+            if !extracted_audio.exists() {
+                return Err(AppError::NotFound(format!(
+                    "项目音轨尚未提取，请先提取音频: {}",
+                    extracted_audio.display()
+                )));
+            }
+            extracted_audio.to_string_lossy().to_string()
+        }
+        _ => accompaniment_path,
+    };
+    info!("[MATCHING] 音频来源: {:?}, path={}", audio_source, accompaniment_path);
+    let _guard = CancelFlagGuard::with_project_lock(project_id.clone(), &project_id);
+    let cancel_flag = reset_cancel_flag(&project_id);
+
+    let min_conf = min_confidence.unwrap_or(config.matching.min_confidence as f64);
+    let window_overridden = window_size.is_some() || hop_size.is_some();
+    let mut window_size = window_size.unwrap_or(config.matching.window_size as f64);
+    let mut hop_size = hop_size.unwrap_or(config.matching.hop_size as f64);
+    let min_duration = min_segment_duration.unwrap_or(config.matching.min_segment_duration as f64);
+    let max_gap_duration = max_gap_duration.unwrap_or(config.matching.max_gap_duration as f64);
+    let min_overlap_ratio = config.matching.min_overlap_ratio as f64;
+    let multi_match = multi_match.unwrap_or(false);
+    let secondary_conf = config.matching.secondary_confidence as f64;
+
+    // 验证参数，防止除零错误
+    if hop_size <= 0.0 || !hop_size.is_finite() {
+        return Err(AppError::Config("滑动步长必须大于0".to_string()));
+    }
+    if window_size <= 0.0 || !window_size.is_finite() {
+        return Err(AppError::Config("窗口大小必须大于0".to_string()));
+    }
+    if max_gap_duration < 0.0 || !max_gap_duration.is_finite() {
+        return Err(AppError::Config("最大合并间隙不能为负数".to_string()));
+    }
+    if min_duration < 0.0 || !min_duration.is_finite() {
+        return Err(AppError::Config("最短片段时长不能为负数".to_string()));
+    }
+    if !(min_overlap_ratio > 0.0 && min_overlap_ratio <= 1.0) {
+        return Err(AppError::Config("最小重叠比例必须在 (0, 1] 之间".to_string()));
+    }
+
+    let _ = window.emit("matching-progress", serde_json::json!({
+        "progress": 0.0,
+        "message": "开始匹配音频片段...",
+        "project_id": project_id
+    }));
+
+    // 音乐库中有按旧指纹参数提取的记录时提醒用户，与查询窗口参数不一致会导致匹配不可靠
+    let outdated_fingerprints = database::count_outdated_fingerprints(&fingerprint::current_fingerprint_version())?;
+    if outdated_fingerprints > 0 {
+        warn!("[MATCH] 音乐库中有 {} 首歌曲的指纹参数与当前配置不一致，匹配结果可能不可靠", outdated_fingerprints);
+        let _ = window.emit("fingerprint-outdated", serde_json::json!({
+            "project_id": project_id,
+            "outdated_count": outdated_fingerprints,
+            "message": format!("{} 首歌曲的指纹参数已过时，重新提取指纹前匹配结果可能不可靠", outdated_fingerprints)
+        }));
+    }
+
+    // 获取音频时长
+    let total_duration = ffmpeg::get_audio_duration(&accompaniment_path)?;
+
+    // 匹配范围：默认整段音频，指定时只分析该范围内的窗口
+    let range_start = start_time.unwrap_or(0.0);
+    let range_end = end_time.unwrap_or(total_duration);
+    if !(range_start >= 0.0 && range_start < range_end && range_end <= total_duration) {
+        return Err(AppError::InvalidArgument(format!(
+            "匹配范围无效: {:.1}s - {:.1}s（音频时长 {:.1}s）",
+            range_start, range_end, total_duration
+        )));
+    }
+    let range_duration = range_end - range_start;
+
+    // 自适应模式：按时长缩放窗口与步长（默认沿用固定配置）
+    let adaptive_window = adaptive_window.unwrap_or(config.matching.adaptive_window) && !window_overridden;
+    if adaptive_window {
+        (window_size, hop_size) = adaptive_window_params(range_duration, &config.matching);
+        info!(
+            "[MATCHING] 自适应窗口: 时长={:.1}s, 窗口={:.1}s, 步长={:.1}s",
+            range_duration, window_size, hop_size
+        );
+    }
+
+    // 边界检查：视频时长（或匹配范围）必须大于窗口大小才能进行匹配
+    if range_duration < window_size {
+        let msg = format!(
+            "{} ({:.1}s) 小于最小匹配时长 ({:.1}s)，无法进行识别",
+            if range_duration < total_duration { "匹配范围" } else { "视频时长" },
+            range_duration, window_size
+        );
+        info!("[MATCHING] {}", msg);
+        return Err(AppError::InvalidArgument(msg));
+    }
+
+    // 获取音乐库指纹（支持自定义音乐列表与专辑筛选）
+    let music_album = music_album.as_deref().map(str::trim).filter(|a| !a.is_empty());
+    let library = match (&music_ids, music_album) {
+        (Some(ids), album) if !ids.is_empty() => {
+        info!("[MATCHING] 使用自定义音乐库: {} 首音乐, ID 列表: {:?}", ids.len(), ids);
+            let mut library = database::get_fingerprints_by_ids(ids)?;
+            if let Some(album) = album {
+                let album_ids: std::collections::HashSet<String> = database::get_fingerprints_by_album(album)?
+                    .into_iter()
+                    .map(|(id, _, _)| id)
+                    .collect();
+                library.retain(|(id, _, _)| album_ids.contains(id));
+                info!("[MATCHING] 按专辑筛选: {}, 剩余 {} 首", album, library.len());
+            }
+            library
+        }
+        (_, Some(album)) => {
+            info!("[MATCHING] 使用专辑音乐库: {}", album);
+            database::get_fingerprints_by_album(album)?
+        }
+        _ => {
+            info!("[MATCHING] 使用全部音乐库");
+            database::get_all_fingerprints()?
+        }
+    };
+    info!("[MATCHING] 音乐库加载完成: 共 {} 首音乐", library.len());
+    if library.is_empty() {
+        if let Some(album) = music_album {
+            return Err(AppError::NotFound(format!("没有可匹配的音乐（专辑: {}）", album)));
+        }
+        return Err(AppError::NotFound("音乐库为空，请先导入音乐".to_string()));
+    }
+
+    // 清除该项目的所有旧片段（音乐匹配 + 人物检测），每次任务输出全新结果
+    database::delete_segments_by_project(&project_id)?;
+    database::delete_detection_boxes_by_project(&project_id)?;
+
+    let temp_dir = create_operation_temp_dir(&format!("{}_match_", project_id))?;
+
+    // 生成匹配范围内的所有窗口时间点（含末尾锚定窗口）
+    let window_times = compute_window_times_in_range(range_start, range_end, window_size, hop_size);
+    if range_duration < total_duration {
+        info!("[MATCHING] 限定匹配范围: {:.1}s - {:.1}s", range_start, range_end);
+    }
+
+    let actual_windows = window_times.len();
+
+    // 进度计数器
+    let processed_count = Arc::new(AtomicUsize::new(0));
+    let window_for_progress = window.clone();
+    let project_id_for_progress = project_id.clone();
+
+    // 并行处理每个窗口（限制线程数，预留 CPU 给 tokio 和 UI 响应）
+    // 预先解码音乐库指纹，避免每个窗口重复解码
+    let library: Vec<(String, String, Vec<u32>)> = library
+        .into_iter()
+        .map(|(id, title, fp)| (id, title, fingerprint::decode_fingerprint(&fp)))
+        .collect();
     let library_arc = Arc::new(library);
     let temp_path = temp_dir.path().to_path_buf();
+    // 查询窗口与音乐库指纹使用相同的音频参数
+    let fingerprint_format = ffmpeg::AudioFormat::fingerprint();
     let accompaniment_path_arc = Arc::new(accompaniment_path.clone());
 
     let num_threads = num_cpus::get().saturating_sub(2).max(1);
@@ -486,13 +1375,14 @@ pub async fn match_video_segments(
         .build()
         .map_err(|e| AppError::Config(format!("创建线程池失败: {}", e)))?;
 
-    let window_results: Vec<Option<(usize, String, String, f64)>> = pool.install(|| {
+    // 每个窗口的匹配结果按置信度降序，首个为最佳匹配（多曲模式下其余为达到次级阈值的匹配）
+    let window_results: Vec<Vec<(usize, String, String, f64)>> = pool.install(|| {
         window_times
         .par_iter()
         .map(|(window_index, current_time)| {
             // 检查取消标志
             if cancel_flag.load(Ordering::SeqCst) {
-                return None;
+                return Vec::new();
             }
 
             // 提取窗口音频
@@ -502,25 +1392,42 @@ pub async fn match_video_segments(
                 window_path.to_str().unwrap(),
                 *current_time,
                 window_size,
+                fingerprint_format,
             ).is_err() {
-                return None;
+                return Vec::new();
             }
 
             // 提取指纹并匹配
-            let result = if let Ok((fp_data, _)) = fingerprint::extract_fingerprint_from_file(window_path.to_str().unwrap()) {
-                // 并行遍历音乐库，找到最佳匹配
-                let best_match = library_arc.par_iter()
-                    .map(|(music_id, music_title, music_fp)| {
-                        let confidence = fingerprint::compare_fingerprints(&fp_data, music_fp);
-                        (music_id, music_title, confidence)
-                    })
-                    .filter(|(_, _, conf)| *conf >= min_conf)
-                    .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
-                    .map(|(id, title, conf)| (id.clone(), title.clone(), conf));
-
-                best_match.map(|(id, title, conf)| (*window_index, id, title, conf))
+            let result = if let Ok((fp_data, _)) = fingerprint::extract_fingerprint_from_file(window_path.to_str().unwrap(), Some(window_size + WINDOW_FINGERPRINT_MARGIN)) {
+                let query = fingerprint::decode_fingerprint(&fp_data);
+                // 并行遍历音乐库，偏移对齐后计算相似度（重叠不足 min_overlap_ratio 的不计）
+                let scored = library_arc.par_iter()
+                    .filter_map(|(music_id, music_title, music_fp)| {
+                        fingerprint::compare_fingerprints_aligned(&query, music_fp, min_overlap_ratio)
+                            .map(|m| (music_id, music_title, m.similarity))
+                    });
+
+                let matches: Vec<(&String, &String, f64)> = if multi_match {
+                    let mut all: Vec<_> = scored.collect();
+                    all.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+                    all.into_iter()
+                        .enumerate()
+                        .filter(|(rank, m)| *rank == 0 || m.2 >= secondary_conf)
+                        .map(|(_, m)| m)
+                        .collect()
+                } else {
+                    scored
+                        .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+                        .into_iter()
+                        .collect()
+                };
+
+                matches
+                    .into_iter()
+                    .map(|(id, title, conf)| (*window_index, id.clone(), title.clone(), conf))
+                    .collect()
             } else {
-                None
+                Vec::new()
             };
 
             // 清理临时文件
@@ -547,98 +1454,72 @@ pub async fn match_video_segments(
         return Err(AppError::Cancelled);
     }
 
-    // 按窗口索引排序结果
+    // verbose 模式：输出每个窗口的最佳匹配（含低于阈值的），供前端绘制置信度热力图
+    if verbose.unwrap_or(false) {
+        let detail: Vec<serde_json::Value> = window_times
+            .iter()
+            .zip(window_results.iter())
+            .map(|((_, time), result)| match result.first() {
+                Some((_, music_id, _, conf)) => serde_json::json!({
+                    "time": time,
+                    "music_id": music_id,
+                    "confidence": conf
+                }),
+                None => serde_json::json!({
+                    "time": time,
+                    "music_id": null,
+                    "confidence": 0.0
+                }),
+            })
+            .collect();
+        let _ = window.emit("matching-detail", serde_json::json!({
+            "project_id": project_id,
+            "window_size": window_size,
+            "windows": detail
+        }));
+    }
+
+    // 按窗口索引排序结果（最佳匹配需达到置信度阈值，次级匹配需达到次级阈值）
+    // 审核模式下保留所有窗口的最佳候选，由合并后的置信度决定是否为待确认片段
+    let review_mode = review_mode.unwrap_or(false);
     let mut sorted_results: Vec<(usize, String, String, f64)> = window_results
         .into_iter()
-        .flatten()
+        .flat_map(|matches| filter_window_matches(matches, min_conf, secondary_conf, review_mode))
         .collect();
     sorted_results.sort_by_key(|(idx, _, _, _)| *idx);
 
-    // 顺序合并为片段
-    let mut segments: Vec<Segment> = Vec::new();
-    // (music_id, title, start_time, confidence, last_window_index)
-    let mut current_match: Option<(String, String, f64, f64, usize)> = None;
-
-    for (window_index, music_id, music_title, confidence) in sorted_results {
-        let current_time = window_index as f64 * hop_size;
-
-        match &current_match {
-            None => {
-                // 开始新的匹配片段
-                current_match = Some((music_id, music_title, current_time, confidence, window_index));
-            }
-            Some((curr_id, curr_title, start, conf, last_idx)) if curr_id == &music_id => {
-                // 检查时间连续性：计算与上一个匹配窗口的实际间隙
-                // 间隙 = 当前窗口开始时间 - 上一个窗口结束时间
-                let last_end_time = *last_idx as f64 * hop_size + window_size;
-                let gap = current_time - last_end_time;
-
-                if gap <= max_gap_duration {
-                    // 间隙在允许范围内（包括重叠的情况，gap <= 0），继续合并当前片段
-                    current_match = Some((curr_id.clone(), curr_title.clone(), *start, confidence.max(*conf), window_index));
-                } else {
-                    // 间隙过大，结束当前片段，开始新片段
-                    let end_time = *last_idx as f64 * hop_size + window_size;
-                    if end_time - start >= min_duration {
-                        segments.push(Segment {
-                            id: generate_id(),
-                            project_id: project_id.clone(),
-                            music_id: Some(curr_id.clone()),
-                            music_title: Some(curr_title.clone()),
-                            start_time: *start,
-                            end_time: end_time.min(total_duration),
-                            confidence: *conf,
-                            status: SegmentStatus::Detected,
-                            segment_type: SegmentType::Music,
-                        });
-                    }
-                    // 开始新的匹配片段
-                    current_match = Some((music_id, music_title, current_time, confidence, window_index));
-                }
-            }
-            Some((curr_id, curr_title, start, conf, last_idx)) => {
-                // 不同歌曲，结束当前匹配片段
-                let end_time = *last_idx as f64 * hop_size + window_size;
-                if end_time - start >= min_duration {
-                    segments.push(Segment {
-                        id: generate_id(),
-                        project_id: project_id.clone(),
-                        music_id: Some(curr_id.clone()),
-                        music_title: Some(curr_title.clone()),
-                        start_time: *start,
-                        end_time: end_time.min(total_duration),
-                        confidence: *conf,
-                        status: SegmentStatus::Detected,
-                        segment_type: SegmentType::Music,
-                    });
-                }
-                // 开始新的匹配
-                current_match = Some((music_id, music_title, current_time, confidence, window_index));
-            }
-        }
-    }
-
-    // 处理最后一个匹配片段
-    if let Some((music_id, music_title, start, conf, last_idx)) = current_match {
-        let end_time = last_idx as f64 * hop_size + window_size;
-        if end_time - start >= min_duration {
-            segments.push(Segment {
-                id: generate_id(),
-                project_id: project_id.clone(),
-                music_id: Some(music_id),
-                music_title: Some(music_title),
-                start_time: start,
-                end_time: end_time.min(total_duration),
-                confidence: conf,
-                status: SegmentStatus::Detected,
-                segment_type: SegmentType::Music,
-            });
-        }
+    // 顺序合并为片段（多曲模式按歌曲分组合并）
+    let merge = if multi_match { merge_window_matches_per_music } else { merge_window_matches };
+    let mut segments = merge(
+        sorted_results,
+        &window_times,
+        window_size,
+        max_gap_duration,
+        min_duration,
+        range_end,
+        &project_id,
+        config.matching.confidence_aggregation,
+    );
+    if review_mode {
+        mark_provisional_segments(&mut segments, min_conf);
     }
 
     // 保存片段到数据库（事务批量插入，只获取一次锁）
     database::batch_insert_segments(&segments)?;
 
+    // 记录本次实际使用的匹配参数，便于复现结果与预填重新匹配对话框
+    let effective_config = config::MatchConfig {
+        min_confidence: min_conf as f32,
+        window_size: window_size as f32,
+        hop_size: hop_size as f32,
+        min_segment_duration: min_duration as f32,
+        max_gap_duration: max_gap_duration as f32,
+        adaptive_window,
+        audio_source,
+        ..config.matching.clone()
+    };
+    database::set_project_match_config(&project_id, &effective_config)?;
+
     // 发送完成进度（确保前端收到 100%）
     let _ = window.emit("matching-progress", serde_json::json!({
         "progress": 1.0,
@@ -649,9 +1530,18 @@ pub async fn match_video_segments(
 
     let _ = window.emit("matching-complete", serde_json::json!({
         "segments": segments.len(),
+        "provisional": segments.iter().filter(|s| s.status == SegmentStatus::Provisional).count(),
+        "audio_source": audio_source,
         "project_id": project_id
     }));
 
+    OperationMetrics {
+        input_duration: Some(range_duration),
+        segment_count: Some(segments.len()),
+        ..OperationMetrics::new("match", Some(project_id.clone()), started, &accompaniment_path)
+    }
+    .emit(&window);
+
     Ok(segments)
 }
 
@@ -663,7 +1553,7 @@ pub async fn cut_video(
     window: Window,
     params: CutParams,
 ) -> AppResult<String> {
-    let _guard = CancelFlagGuard::new(params.project_id.clone());
+    let _guard = CancelFlagGuard::with_project_lock(params.project_id.clone(), &params.project_id);
     let cancel_flag = reset_cancel_flag(&params.project_id);
 
     let project = database::get_project_by_id(&params.project_id)?
@@ -687,22 +1577,24 @@ pub async fn cut_video(
 
     let window_clone = window.clone();
     let project_id_clone = params.project_id.clone();
-    ffmpeg::cut_video_segments(
-        &project.source_video_path,
-        &params.output_path,
-        &project.segments,
-        params.keep_matched,
-        Some(Box::new(move |progress| {
-            let _ = window_clone.emit("cut-progress", serde_json::json!({
-                "progress": progress,
-                "message": format!("剪辑中: {:.1}%", progress * 100.0),
-                "project_id": project_id_clone
-            }));
-        })),
-        cancel_flag,
-        &params.project_id,
-        prefer_lossless,
-    )?;
+    logging::operation_span(&params.project_id, "cut").in_scope(|| {
+        ffmpeg::cut_video_segments(
+            &project.source_video_path,
+            &params.output_path,
+            &project.segments,
+            params.keep_matched,
+            Some(Box::new(move |progress| {
+                let _ = window_clone.emit("cut-progress", serde_json::json!({
+                    "progress": progress,
+                    "message": format!("剪辑中: {:.1}%", progress * 100.0),
+                    "project_id": project_id_clone
+                }));
+            })),
+            cancel_flag,
+            &params.project_id,
+            prefer_lossless,
+        )
+    })?;
 
     info!("[CUT] 剪辑完成: {}", params.output_path);
 
@@ -714,34 +1606,176 @@ pub async fn cut_video(
     Ok(params.output_path)
 }
 
+/// 解析宽高比字符串（如 "9:16"）
+fn parse_aspect_ratio(aspect: &str) -> AppResult<(u32, u32)> {
+    let invalid = || AppError::InvalidArgument(format!("无效的宽高比: {}（格式应为 宽:高，如 9:16）", aspect));
+    let (w, h) = aspect.split_once(':').ok_or_else(invalid)?;
+    let w: u32 = w.trim().parse().map_err(|_| invalid())?;
+    let h: u32 = h.trim().parse().map_err(|_| invalid())?;
+    if w == 0 || h == 0 {
+        return Err(invalid());
+    }
+    Ok((w, h))
+}
+
+/// 校验音频码率（kbps）在允许范围内
+fn parse_audio_bitrate(audio_bitrate: Option<u32>) -> AppResult<Option<u32>> {
+    match audio_bitrate {
+        Some(kbps) if !ffmpeg::AUDIO_BITRATE_RANGE_KBPS.contains(&kbps) => Err(AppError::InvalidArgument(format!(
+            "音频码率必须在 {}-{} kbps 之间: {}",
+            ffmpeg::AUDIO_BITRATE_RANGE_KBPS.start(), ffmpeg::AUDIO_BITRATE_RANGE_KBPS.end(), kbps
+        ))),
+        other => Ok(other),
+    }
+}
+
+/// 校验软件编码预设（x264/x265 标准预设）
+fn parse_encode_preset(preset: Option<String>) -> AppResult<Option<String>> {
+    match preset {
+        Some(p) => {
+            let p = p.trim().to_lowercase();
+            if ffmpeg::ENCODE_PRESETS.contains(&p.as_str()) {
+                Ok(Some(p))
+            } else {
+                Err(AppError::InvalidArgument(format!(
+                    "无效的编码预设: {}（可选: {}）",
+                    p, ffmpeg::ENCODE_PRESETS.join(", ")
+                )))
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+/// 导出前校验输出容器与视频编码是否兼容
+///
+/// 重编码输出 H.264，无损模式沿用源视频编码（无损失败回退重编码时同样为 H.264）。
+fn validate_export_container(container: Option<OutputContainer>, video_info: &VideoInfo, reencode: bool) -> AppResult<()> {
+    let Some(container) = container else {
+        return Ok(());
+    };
+    let codec = if reencode { "h264" } else { video_info.video_codec.as_str() };
+    ffmpeg::check_container_codec(container, codec)
+}
+
+/// 显式指定容器时使输出路径扩展名与容器一致（如 a.mp4 + mkv → a.mkv），
+/// 扩展名已对应该容器（包括 .m4v 之于 mp4）或未指定容器时原样返回
+fn output_path_for_container(output_path: &str, container: Option<OutputContainer>) -> String {
+    match container {
+        Some(container) if OutputContainer::from_path(output_path) != Some(container) => {
+            Path::new(output_path).with_extension(container.extension()).to_string_lossy().to_string()
+        }
+        _ => output_path.to_string(),
+    }
+}
+
 /// 导出视频
 ///
 /// force_reencode: 为 true 时强制重编码（精确切割），默认 false 使用无损模式
+/// crop_aspect: 设置后（如 "9:16"）按该宽高比跟随人物裁剪画面，强制重编码；
+///              裁剪中心跟随人物检测保存的检测框，项目未做人物检测时使用静态居中裁剪
+/// preserve_mtime: 为 true 时将输出文件的修改时间设为源视频的修改时间，默认 false
+/// strip_metadata: 为 true 时清除 GPS/设备等元数据、章节与数据流，默认 false
+/// preset: 重编码时的软件编码预设（ultrafast ~ veryslow），默认 veryfast
+/// order: 片段拼接顺序（时间 / 置信度 / 自定义片段 ID 列表），默认按时间顺序并合并重叠片段
+/// keep: 导出匹配片段（默认）/ 被剪掉的部分 / 两者（反向结果写入 {文件名}_inverse.{扩展名}），返回 output_path
+///       两者时一个输出失败不影响另一个，完成事件的 outputs 逐个给出结果，部分失败时返回列出已完成文件的错误
+/// audio_bitrate: 重编码时的 AAC 音频码率（64-320 kbps），默认 192
+/// container: 输出容器（mp4 / mov / mkv），默认按输出文件扩展名推断；
+///            指定时输出扩展名改为与容器一致，返回实际写入的路径
+/// gap_fill: 片段间隙处理（remove 直接拼接 / black 黑屏 / freeze 定格），非 remove 时保持原始时间轴，
+///           强制重编码，且只支持按时间顺序拼接
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn export_video(
     window: Window,
     project_id: String,
     output_path: String,
     force_reencode: Option<bool>,
+    audio_source: Option<AudioSource>,
+    crop_aspect: Option<String>,
+    preserve_mtime: Option<bool>,
+    strip_metadata: Option<bool>,
+    preset: Option<String>,
+    order: Option<SegmentOrder>,
+    keep: Option<ExportSelection>,
+    audio_bitrate: Option<u32>,
+    container: Option<OutputContainer>,
+    gap_fill: Option<GapFill>,
+    state: State<'_, AppState>,
 ) -> AppResult<String> {
-    let _guard = CancelFlagGuard::new(project_id.clone());
+    let started = std::time::Instant::now();
+    let guard = CancelFlagGuard::with_project_lock(project_id.clone(), &project_id);
     let cancel_flag = reset_cancel_flag(&project_id);
 
     let project = database::get_project_by_id(&project_id)?
         .ok_or_else(|| AppError::NotFound("项目不存在".to_string()))?;
 
+    let requested_output_path = output_path;
+    let output_path = output_path_for_container(&requested_output_path, container);
+    if output_path != requested_output_path {
+        warn!("[EXPORT] 输出扩展名与容器 {:?} 不一致，改为: {}", container, output_path);
+    }
+
     // 检查源视频文件是否存在
-    if !Path::new(&project.source_video_path).exists() {
+    if !media_input_exists(&project.source_video_path) {
         error!("[EXPORT] 源视频文件不存在: {}", project.source_video_path);
         return Err(AppError::NotFound(format!("源视频文件不存在: {}", project.source_video_path)));
     }
 
+    let crop = match crop_aspect.as_deref() {
+        Some(aspect) => {
+            let (aspect_width, aspect_height) = parse_aspect_ratio(aspect)?;
+            // 人物检测保存的检测框作为裁剪中心，没有检测数据时居中裁剪
+            let boxes = database::get_detection_boxes(&project_id)?;
+            let crop = ffmpeg::FollowCrop::from_boxes(aspect_width, aspect_height, boxes.values().flatten());
+            if crop.keyframes.is_empty() {
+                info!("[EXPORT] 跟随人物裁剪: 宽高比 {}:{}（无人物位置数据，使用居中裁剪）", aspect_width, aspect_height);
+            } else {
+                info!("[EXPORT] 跟随人物裁剪: 宽高比 {}:{}，人物位置关键帧 {} 个", aspect_width, aspect_height, crop.keyframes.len());
+            }
+            Some(crop)
+        }
+        None => None,
+    };
+
+    let selection = keep.unwrap_or_default();
+    let order = order.unwrap_or_default();
+    let gap_fill = gap_fill.unwrap_or_default();
+    if gap_fill != GapFill::Remove && order != SegmentOrder::Chronological {
+        return Err(AppError::InvalidArgument("间隙填充只支持按时间顺序拼接".to_string()));
+    }
+    // 导出任务 (是否反向, 输出路径)
+    let jobs: Vec<(bool, String)> = match selection {
+        ExportSelection::Matched => vec![(false, output_path.clone())],
+        ExportSelection::Inverse => vec![(true, output_path.clone())],
+        ExportSelection::Both => vec![(false, output_path.clone()), (true, inverse_output_path(&output_path))],
+    };
+
+    let segment_cache_max_bytes = config::get_config().segment_cache_max_mb * 1024 * 1024;
+    let segment_cache_dir = state.app_dir.join("temp").join("segment_cache");
     let reencode = force_reencode.unwrap_or(false);
+    let encode_options = ffmpeg::EncodeOptions {
+        prefer_lossless: !reencode,
+        audio_stem: resolve_audio_stem(&state.app_dir, &project_id, &project.source_video_path, audio_source.unwrap_or_default())?,
+        crop,
+        segment_cache_dir: (segment_cache_max_bytes > 0).then(|| segment_cache_dir.clone()),
+        strip_metadata: strip_metadata.unwrap_or(false),
+        preset: parse_encode_preset(preset)?,
+        audio_bitrate: parse_audio_bitrate(audio_bitrate)?,
+        container,
+        gap_fill,
+        ..Default::default()
+    };
+
+    validate_export_container(container, &project.video_info, reencode || encode_options.requires_reencode())?;
     let mode_str = if reencode { "重编码" } else { "无损" };
     info!("[EXPORT] === 开始导出视频（{}模式）===", mode_str);
     info!("[EXPORT] 项目ID: {}", project_id);
     info!("[EXPORT] 源视频: {}", project.source_video_path);
     info!("[EXPORT] 输出路径: {}", output_path);
+    info!("[EXPORT] 导出内容: {:?}", selection);
+    info!("[EXPORT] 间隙处理: {:?}", gap_fill);
 
     // 详细记录从数据库读取的片段信息，用于排查导出内容与时间轴不一致的问题
     let total_count = project.segments.len();
@@ -769,60 +1803,296 @@ pub async fn export_video(
         "project_id": project_id
     }));
 
-    let window_clone = window.clone();
-    let project_id_clone = project_id.clone();
-    if let Err(e) = ffmpeg::export_video_with_mode(
-        &project.source_video_path,
-        &output_path,
-        &project.segments,
-        Some(Box::new(move |progress| {
+    let job_count = jobs.len();
+    let mut outputs: Vec<ExportOutputResult> = Vec::with_capacity(job_count);
+    for (job_index, (inverse, job_output)) in jobs.iter().enumerate() {
+        let work_dir = export_work_dir(&state.app_dir, &project_id, job_output);
+        let job_options = ffmpeg::EncodeOptions { work_dir: Some(work_dir.clone()), ..encode_options.clone() };
+
+        // 同时导出两者时按任务数平分进度
+        let window_clone = window.clone();
+        let project_id_clone = project_id.clone();
+        let progress_cb: ffmpeg::ProgressCallback = Box::new(move |progress| {
+            let overall = (job_index as f32 + progress) / job_count as f32;
             let _ = window_clone.emit("export-progress", serde_json::json!({
-                "progress": progress,
-                "message": format!("导出中: {:.1}%", progress * 100.0),
+                "progress": overall,
+                "message": format!("导出中: {:.1}%", overall * 100.0),
                 "project_id": project_id_clone
             }));
-        })),
-        cancel_flag,
-        &project_id,
-        !reencode, // prefer_lossless = !force_reencode
-    ) {
-        error!("[EXPORT] 导出失败: {}", e);
-        return Err(e);
+        });
+        let export_result = logging::operation_span(&project_id, "export").in_scope(|| {
+            if *inverse {
+                ffmpeg::export_inverse_video_with_mode(
+                    &project.source_video_path,
+                    job_output,
+                    &project.segments,
+                    Some(progress_cb),
+                    cancel_flag.clone(),
+                    &project_id,
+                    &job_options,
+                )
+            } else {
+                ffmpeg::export_video_with_mode(
+                    &project.source_video_path,
+                    job_output,
+                    &project.segments,
+                    Some(progress_cb),
+                    cancel_flag.clone(),
+                    &project_id,
+                    &job_options,
+                    &order,
+                )
+            }
+        });
+        if let Err(e) = export_result {
+            error!("[EXPORT] 导出失败: {}（已完成的片段保留在 {}，重试时复用）", e, work_dir.display());
+            // 单一输出或取消时直接返回；同时导出两者时继续另一个输出，结果逐个上报
+            if job_count == 1 || matches!(e, AppError::Cancelled) {
+                return Err(e);
+            }
+            outputs.push(ExportOutputResult { output_path: job_output.clone(), inverse: *inverse, error: Some(e.to_string()) });
+            continue;
+        }
+
+        // 导出成功，工作目录不再需要
+        if let Err(e) = std::fs::remove_dir_all(&work_dir) {
+            warn!("[EXPORT] 删除工作目录失败: {:?}, 错误: {}", work_dir, e);
+        }
+        if preserve_mtime.unwrap_or(false) {
+            copy_source_mtime(&project.source_video_path, job_output);
+        }
+        info!("[EXPORT] 导出完成: {}", job_output);
+        outputs.push(ExportOutputResult { output_path: job_output.clone(), inverse: *inverse, error: None });
+    }
+
+    let failed: Vec<&ExportOutputResult> = outputs.iter().filter(|o| o.error.is_some()).collect();
+    if failed.len() == outputs.len() {
+        let message = failed.iter().filter_map(|o| o.error.as_deref()).collect::<Vec<_>>().join("; ");
+        return Err(AppError::Video(format!("导出失败: {}", message)));
     }
 
-    info!("[EXPORT] 导出完成: {}", output_path);
+    // 先释放本次导出的取消标志，缓存清理才不会把自身视为进行中的操作
+    drop(guard);
+    let segment_cache = (segment_cache_max_bytes > 0).then(|| (segment_cache_dir, segment_cache_max_bytes));
+    clean_cache_after_export(state.app_dir.clone(), segment_cache).await;
 
+    let inverse_output = (selection == ExportSelection::Both).then(|| jobs[1].1.clone());
     let _ = window.emit("export-complete", serde_json::json!({
         "output_path": output_path,
+        "inverse_output_path": inverse_output,
+        "selection": selection,
+        "outputs": outputs,
         "project_id": project_id
     }));
 
+    // 导出使用软件编码（libx264），不使用硬件编码器
+    OperationMetrics {
+        input_size: Some(project.video_info.size),
+        input_duration: Some(project.video_info.duration),
+        segment_count: Some(detected_count),
+        ..OperationMetrics::new("export", Some(project_id.clone()), started, &project.source_video_path)
+    }
+    .emit(&window);
+
+    // 部分输出失败时返回错误，并列出已完成的文件，避免已完成的输出无人知晓
+    if !failed.is_empty() {
+        let completed: Vec<&str> = outputs.iter().filter(|o| o.error.is_none()).map(|o| o.output_path.as_str()).collect();
+        let errors: Vec<String> = failed.iter()
+            .map(|o| format!("{}: {}", o.output_path, o.error.as_deref().unwrap_or_default()))
+            .collect();
+        warn!("[EXPORT] 部分导出失败: {}；已完成: {}", errors.join("; "), completed.join(", "));
+        return Err(AppError::Video(format!("部分导出失败: {}（已完成: {}）", errors.join("; "), completed.join(", "))));
+    }
+
     Ok(output_path)
 }
 
+/// 合并导出中单个输出的结果（同时导出匹配与反向内容时逐个上报）
+#[derive(Debug, Clone, serde::Serialize)]
+struct ExportOutputResult {
+    output_path: String,
+    /// 是否为反向（被移除部分）输出
+    inverse: bool,
+    /// 失败原因，成功时为 None
+    error: Option<String>,
+}
+
+/// 导出结束后淘汰片段缓存（segment_cache 为 (目录, 上限字节)）并按 max_cache_bytes 清理 temp 缓存
+///
+/// 调用前需先释放导出自身的取消标志，否则共享缓存总被视为使用中；目录遍历与删除在阻塞线程中执行。
+/// 有其他进行中的操作时跳过片段缓存淘汰，避免删掉并发导出已查到、尚未拼接的缓存片段。
+async fn clean_cache_after_export(app_dir: std::path::PathBuf, segment_cache: Option<(std::path::PathBuf, u64)>) {
+    let result = tokio::task::spawn_blocking(move || {
+        if let Some((cache_dir, max_bytes)) = segment_cache {
+            if has_active_operations() {
+                info!("[EXPORT] 有其他进行中的操作，跳过片段缓存淘汰");
+            } else {
+                ffmpeg::evict_segment_cache(&cache_dir, max_bytes);
+            }
+        }
+        super::config::enforce_cache_limit(&app_dir)
+    })
+    .await;
+    if let Err(e) = result {
+        warn!("[EXPORT] 缓存清理任务失败: {}", e);
+    }
+}
+
+/// 同时导出匹配与反向内容时反向结果的输出路径：{文件名}_inverse.{扩展名}
+fn inverse_output_path(output_path: &str) -> String {
+    let path = Path::new(output_path);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}_inverse.{}", stem, ext.to_string_lossy()),
+        None => format!("{}_inverse", stem),
+    };
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+/// 将源文件的修改时间应用到导出文件（失败只记录警告，不影响导出结果）
+fn copy_source_mtime(source_path: &str, output_path: &str) {
+    if is_url(source_path) {
+        return;
+    }
+    let result = std::fs::metadata(source_path)
+        .and_then(|m| m.modified())
+        .and_then(|mtime| {
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(output_path)?
+                .set_modified(mtime)
+        });
+    if let Err(e) = result {
+        warn!("[EXPORT] 保留源文件修改时间失败: {} -> {}, 错误: {}", source_path, output_path, e);
+    }
+}
+
+/// 可恢复导出工作目录的过期时间，超过后启动时自动清理
+pub(crate) const EXPORT_WORK_DIR_MAX_AGE: std::time::Duration = std::time::Duration::from_secs(7 * 24 * 3600);
+
+/// 可恢复导出的持久工作目录: temp/{project_id}_export/{输出路径哈希}
+///
+/// 同一项目导出到同一路径时复用目录，中断后重试可跳过已完成的片段。
+pub(crate) fn export_work_dir(app_dir: &Path, project_id: &str, output_path: &str) -> std::path::PathBuf {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(output_path.as_bytes());
+    app_dir
+        .join("temp")
+        .join(format!("{}_export", project_id))
+        .join(hex::encode(&digest[..8]))
+}
+
+/// 清理可恢复导出的工作目录，返回删除的目录数
+///
+/// project_id 为 None 时处理所有项目；max_age 为 Some 时只删除超过该时长未修改的目录。
+pub(crate) fn purge_export_work_dirs(
+    app_dir: &Path,
+    project_id: Option<&str>,
+    max_age: Option<std::time::Duration>,
+) -> usize {
+    let temp_dir = app_dir.join("temp");
+    let project_dirs: Vec<std::path::PathBuf> = match project_id {
+        Some(id) => vec![temp_dir.join(format!("{}_export", id))],
+        None => std::fs::read_dir(&temp_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.is_dir() && p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.ends_with("_export")))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    let mut removed = 0;
+    for project_dir in project_dirs {
+        let Ok(entries) = std::fs::read_dir(&project_dir) else { continue };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if let Some(max_age) = max_age {
+                let expired = entry
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|t| t.elapsed().ok())
+                    .is_some_and(|age| age > max_age);
+                if !expired {
+                    continue;
+                }
+            }
+            match std::fs::remove_dir_all(&path) {
+                Ok(()) => removed += 1,
+                Err(e) => warn!("[EXPORT] 删除工作目录失败: {:?}, 错误: {}", path, e),
+            }
+        }
+        // 项目目录为空时一并删除（非空时 remove_dir 失败，忽略）
+        let _ = std::fs::remove_dir(&project_dir);
+    }
+
+    if removed > 0 {
+        info!("[EXPORT] 已清理 {} 个导出工作目录", removed);
+    }
+    removed
+}
+
+/// 清理可恢复导出的工作目录（放弃未完成导出的中间片段）
+///
+/// project_id 为 None 时清理所有项目，返回删除的目录数
+#[tauri::command]
+pub async fn clear_export_work_dirs(
+    project_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<usize> {
+    Ok(purge_export_work_dirs(&state.app_dir, project_id.as_deref(), None))
+}
+
 /// 分别导出视频片段（每个片段单独导出）
 ///
 /// force_reencode: 为 true 时强制重编码（精确切割），默认 false 使用无损模式
+/// preserve_mtime: 为 true 时每个输出文件都保留源视频的修改时间，默认 false
+/// strip_metadata: 为 true 时清除 GPS/设备等元数据、章节与数据流，默认 false
+/// preset: 重编码时的软件编码预设（ultrafast ~ veryslow），默认 veryfast
+/// audio_bitrate: 重编码时的 AAC 音频码率（64-320 kbps），默认 192
+/// container: 输出容器（mp4 / mov / mkv），默认沿用源视频扩展名
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn export_video_separately(
     window: Window,
     project_id: String,
     output_dir: String,
     force_reencode: Option<bool>,
+    audio_source: Option<AudioSource>,
+    preserve_mtime: Option<bool>,
+    strip_metadata: Option<bool>,
+    preset: Option<String>,
+    audio_bitrate: Option<u32>,
+    container: Option<OutputContainer>,
+    state: State<'_, AppState>,
 ) -> AppResult<serde_json::Value> {
-    let _guard = CancelFlagGuard::new(project_id.clone());
+    let _guard = CancelFlagGuard::with_project_lock(project_id.clone(), &project_id);
     let cancel_flag = reset_cancel_flag(&project_id);
 
     let project = database::get_project_by_id(&project_id)?
         .ok_or_else(|| AppError::NotFound("项目不存在".to_string()))?;
 
     // 检查源视频文件是否存在
-    if !Path::new(&project.source_video_path).exists() {
+    if !media_input_exists(&project.source_video_path) {
         error!("[EXPORT_SEP] 源视频文件不存在: {}", project.source_video_path);
         return Err(AppError::NotFound(format!("源视频文件不存在: {}", project.source_video_path)));
     }
 
     let reencode_sep = force_reencode.unwrap_or(false);
+    let encode_options = ffmpeg::EncodeOptions {
+        prefer_lossless: !reencode_sep,
+        audio_stem: resolve_audio_stem(&state.app_dir, &project_id, &project.source_video_path, audio_source.unwrap_or_default())?,
+        strip_metadata: strip_metadata.unwrap_or(false),
+        preset: parse_encode_preset(preset)?,
+        audio_bitrate: parse_audio_bitrate(audio_bitrate)?,
+        container,
+        ..Default::default()
+    };
+
+    validate_export_container(container, &project.video_info, reencode_sep || encode_options.requires_reencode())?;
     let mode_str_sep = if reencode_sep { "重编码" } else { "无损" };
     info!("[EXPORT_SEP] === 开始分别导出视频片段（{}模式）===", mode_str_sep);
     info!("[EXPORT_SEP] 项目ID: {}", project_id);
@@ -849,52 +2119,373 @@ pub async fn export_video_separately(
         .sum();
     info!("[EXPORT_SEP] 将导出的片段总时长（合并前）: {:.2}s", total_detected_duration);
 
-    let _ = window.emit("export-progress", serde_json::json!({
-        "progress": 0.0,
-        "message": "开始分别导出视频片段...",
-        "project_id": project_id
-    }));
+    let _ = window.emit("export-progress", serde_json::json!({
+        "progress": 0.0,
+        "message": "开始分别导出视频片段...",
+        "project_id": project_id
+    }));
+
+    let window_clone = window.clone();
+    let project_id_clone = project_id.clone();
+    let output_files = match ffmpeg::export_video_separately_with_mode(
+        &project.source_video_path,
+        &output_dir,
+        &project.segments,
+        Some(Box::new(move |progress| {
+            let _ = window_clone.emit("export-progress", serde_json::json!({
+                "progress": progress,
+                "message": format!("导出中: {:.1}%", progress * 100.0),
+                "project_id": project_id_clone
+            }));
+        })),
+        cancel_flag,
+        &project_id,
+        &encode_options,
+    ) {
+        Ok(files) => files,
+        Err(e) => {
+            error!("[EXPORT_SEP] 导出失败: {}", e);
+            return Err(e);
+        }
+    };
+
+    if preserve_mtime.unwrap_or(false) {
+        for output_file in &output_files {
+            copy_source_mtime(&project.source_video_path, output_file);
+        }
+    }
+
+    info!("[EXPORT_SEP] 导出完成，共 {} 个文件", output_files.len());
+
+    let _ = window.emit("export-complete", serde_json::json!({
+        "output_dir": output_dir,
+        "exported_count": output_files.len(),
+        "project_id": project_id
+    }));
+
+    Ok(serde_json::json!({
+        "exported_count": output_files.len(),
+        "output_files": output_files
+    }))
+}
+
+/// 导出预览（dry-run 结果）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ExportPreview {
+    /// 最终导出的时间段（已筛选、排序并合并重叠）
+    pub segments: Vec<(f64, f64)>,
+    /// 导出总时长（秒）
+    pub total_duration: f64,
+    /// 源视频时长（秒）
+    pub source_duration: f64,
+    /// 预估输出文件大小（字节）
+    pub estimated_size: u64,
+}
+
+/// 导出预览（不执行编码）
+///
+/// 使用与 export_video 相同的筛选/合并逻辑计算最终片段，并估算输出大小：
+/// - 无损模式（默认）：按源视频码率 × 导出时长
+/// - 重编码模式：按编码器与 CRF 的经验码率（默认 libx264 CRF 18，与导出参数一致），
+///   加上 audio_bitrate 指定的音频码率（默认 192 kbps）
+#[tauri::command]
+pub async fn preview_export(
+    project_id: String,
+    force_reencode: Option<bool>,
+    codec: Option<String>,
+    crf: Option<u32>,
+    audio_bitrate: Option<u32>,
+) -> AppResult<ExportPreview> {
+    let audio_bitrate = parse_audio_bitrate(audio_bitrate)?;
+    let project = database::get_project_by_id(&project_id)?
+        .ok_or_else(|| AppError::NotFound("项目不存在".to_string()))?;
+
+    let video_info = &project.video_info;
+    let segments = ffmpeg::plan_export_segments(&project.segments, video_info.duration);
+    let total_duration: f64 = segments.iter().map(|(s, e)| e - s).sum();
+
+    let estimated_size = if force_reencode.unwrap_or(false) {
+        let codec = codec.unwrap_or_else(|| "libx264".to_string());
+        ffmpeg::estimate_output_size(video_info, &segments, &codec, Some(crf.unwrap_or(18)), audio_bitrate)
+    } else {
+        ffmpeg::estimate_output_size(video_info, &segments, "copy", None, None)
+    };
+
+    info!(
+        "[EXPORT_PREVIEW] project_id={}, 片段 {} 个, 导出时长 {:.2}s / {:.2}s, 预估大小 {} 字节",
+        project_id, segments.len(), total_duration, video_info.duration, estimated_size
+    );
+
+    Ok(ExportPreview {
+        segments,
+        total_duration,
+        source_duration: video_info.duration,
+        estimated_size,
+    })
+}
+
+/// 批量导出选项
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct BatchExportOptions {
+    /// 强制重编码（默认无损）
+    pub force_reencode: bool,
+    /// 每个项目的片段分别导出到以项目命名的子目录
+    pub separately: bool,
+    /// 同时导出的项目数（默认 2）
+    pub max_concurrency: Option<usize>,
+    /// 输出音轨来源（默认原始音轨）
+    pub audio_source: AudioSource,
+    /// 清除 GPS/设备等元数据、章节与数据流
+    pub strip_metadata: bool,
+    /// 重编码时的软件编码预设（默认 veryfast）
+    pub preset: Option<String>,
+}
+
+/// 批量导出中单个项目的结果
+struct BatchExportItem {
+    project_id: String,
+    /// Ok(Some(输出路径列表)) 成功, Ok(None) 跳过, Err(消息) 失败
+    result: Result<Option<Vec<String>>, String>,
+}
+
+/// 批量导出的取消标识，cancel_processing("batch_export") 可取消整个批次
+const BATCH_EXPORT_FLAG_ID: &str = "batch_export";
+
+/// 批量导出多个项目
+///
+/// 按 max_concurrency 限制并行导出，每个项目使用自己的取消标志（可单独取消）。
+/// 源视频缺失或导出失败的项目会被跳过并记录错误，不中断整个批次。
+/// 事件: `batch-export-progress` / `batch-export-complete`
+#[tauri::command]
+pub async fn batch_export_projects(
+    window: Window,
+    project_ids: Vec<String>,
+    output_dir: String,
+    options: Option<BatchExportOptions>,
+    state: State<'_, AppState>,
+) -> AppResult<serde_json::Value> {
+    let mut options = options.unwrap_or_default();
+    options.preset = parse_encode_preset(options.preset.take())?;
+    let total = project_ids.len();
+    let concurrency = options.max_concurrency.unwrap_or(2).clamp(1, num_cpus::get().max(1));
+    let prefer_lossless = !options.force_reencode;
+
+    info!(
+        "[BATCH_EXPORT] === 开始批量导出 === 项目数={}, 输出目录={}, 并行数={}, 模式={}, 分别导出={}",
+        total, output_dir, concurrency, if prefer_lossless { "无损" } else { "重编码" }, options.separately
+    );
+
+    std::fs::create_dir_all(&output_dir)?;
+
+    let batch_guard = CancelFlagGuard::new(BATCH_EXPORT_FLAG_ID.to_string());
+    let batch_cancel = reset_cancel_flag(BATCH_EXPORT_FLAG_ID);
+
+    // 批次取消时，将取消信号转发给正在导出的各个项目
+    let batch_done = Arc::new(AtomicBool::new(false));
+    let watcher = {
+        let batch_cancel = batch_cancel.clone();
+        let batch_done = batch_done.clone();
+        let ids = project_ids.clone();
+        std::thread::spawn(move || {
+            while !batch_done.load(Ordering::SeqCst) {
+                if batch_cancel.load(Ordering::SeqCst) {
+                    for id in &ids {
+                        get_cancel_flag(id).store(true, Ordering::SeqCst);
+                        kill_child_processes(id);
+                    }
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+        })
+    };
+
+    let completed = Arc::new(AtomicUsize::new(0));
+    let used_names: Mutex<std::collections::HashSet<String>> = Mutex::new(std::collections::HashSet::new());
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(concurrency)
+        .build()
+        .map_err(|e| AppError::Video(format!("创建线程池失败: {}", e)))?;
+
+    let results: Vec<BatchExportItem> = pool.install(|| {
+        project_ids
+            .par_iter()
+            .map(|project_id| {
+                let result = (|| -> Result<Option<Vec<String>>, String> {
+                    if batch_cancel.load(Ordering::SeqCst) {
+                        return Err("批量导出已取消".to_string());
+                    }
+
+                    let project = match database::get_project_by_id(project_id) {
+                        Ok(Some(p)) => p,
+                        Ok(None) => return Err(format!("{}: 项目不存在", project_id)),
+                        Err(e) => return Err(format!("{}: 读取项目失败 - {}", project_id, e)),
+                    };
+
+                    if !media_input_exists(&project.source_video_path) {
+                        info!("[BATCH_EXPORT] 跳过源视频缺失的项目: {} ({})", project.name, project.source_video_path);
+                        return Ok(None);
+                    }
+
+                    let encode_options = ffmpeg::EncodeOptions {
+                        prefer_lossless,
+                        audio_stem: resolve_audio_stem(&state.app_dir, project_id, &project.source_video_path, options.audio_source)
+                            .map_err(|e| format!("{}: {}", project.name, e))?,
+                        strip_metadata: options.strip_metadata,
+                        preset: options.preset.clone(),
+                        ..Default::default()
+                    };
+
+                    // 生成安全且不重复的输出名称
+                    let safe_name: String = project.name
+                        .chars()
+                        .map(|c| match c {
+                            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                            _ => c,
+                        })
+                        .take(80)
+                        .collect();
+                    let unique_name = {
+                        let mut names = lock_or_recover(&used_names, "批量导出文件名");
+                        let mut candidate = safe_name.clone();
+                        let mut n = 1;
+                        while names.contains(&candidate) {
+                            candidate = format!("{}_{}", safe_name, n);
+                            n += 1;
+                        }
+                        names.insert(candidate.clone());
+                        candidate
+                    };
+
+                    let _guard = CancelFlagGuard::with_project_lock(project_id.clone(), project_id);
+                    let cancel_flag = reset_cancel_flag(project_id);
+
+                    let window_clone = window.clone();
+                    let pid = project_id.clone();
+                    let completed_for_cb = completed.clone();
+                    let progress_cb: ffmpeg::ProgressCallback = Box::new(move |progress| {
+                        let done = completed_for_cb.load(Ordering::SeqCst);
+                        let overall = (done as f32 + progress) / total as f32;
+                        let _ = window_clone.emit("batch-export-progress", serde_json::json!({
+                            "project_id": pid,
+                            "progress": progress,
+                            "overall_progress": overall.min(1.0),
+                            "current": done,
+                            "total": total,
+                            "message": format!("导出中: {:.1}%", progress * 100.0)
+                        }));
+                    });
+
+                    let output = if options.separately {
+                        let project_dir = Path::new(&output_dir).join(&unique_name);
+                        ffmpeg::export_video_separately_with_mode(
+                            &project.source_video_path,
+                            &project_dir.to_string_lossy(),
+                            &project.segments,
+                            Some(progress_cb),
+                            cancel_flag,
+                            project_id,
+                            &encode_options,
+                        )
+                    } else {
+                        let ext = Path::new(&project.source_video_path)
+                            .extension()
+                            .and_then(|s| s.to_str())
+                            .unwrap_or("mp4");
+                        let output_path = Path::new(&output_dir)
+                            .join(format!("{}.{}", unique_name, ext))
+                            .to_string_lossy()
+                            .to_string();
+                        ffmpeg::export_video_with_mode(
+                            &project.source_video_path,
+                            &output_path,
+                            &project.segments,
+                            Some(progress_cb),
+                            cancel_flag,
+                            project_id,
+                            &encode_options,
+                            &SegmentOrder::Chronological,
+                        )
+                        .map(|_| vec![output_path])
+                    };
+
+                    output
+                        .map(Some)
+                        .map_err(|e| format!("{}: {}", project.name, e))
+                })();
+
+                let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+                let _ = window.emit("batch-export-progress", serde_json::json!({
+                    "project_id": project_id,
+                    "progress": 1.0,
+                    "overall_progress": done as f32 / total.max(1) as f32,
+                    "current": done,
+                    "total": total,
+                    "message": match &result {
+                        Ok(Some(_)) => "导出完成".to_string(),
+                        Ok(None) => "跳过(源视频不存在)".to_string(),
+                        Err(e) => format!("导出失败: {}", e),
+                    }
+                }));
+
+                BatchExportItem { project_id: project_id.clone(), result }
+            })
+            .collect()
+    });
+
+    batch_done.store(true, Ordering::SeqCst);
+    let _ = watcher.join();
 
-    let window_clone = window.clone();
-    let project_id_clone = project_id.clone();
-    let output_files = match ffmpeg::export_video_separately_with_mode(
-        &project.source_video_path,
-        &output_dir,
-        &project.segments,
-        Some(Box::new(move |progress| {
-            let _ = window_clone.emit("export-progress", serde_json::json!({
-                "progress": progress,
-                "message": format!("导出中: {:.1}%", progress * 100.0),
-                "project_id": project_id_clone
-            }));
-        })),
-        cancel_flag,
-        &project_id,
-        !reencode_sep, // prefer_lossless = !force_reencode
-    ) {
-        Ok(files) => files,
-        Err(e) => {
-            error!("[EXPORT_SEP] 导出失败: {}", e);
-            return Err(e);
+    let mut output_files: Vec<String> = Vec::new();
+    let mut exported = 0;
+    let mut skipped = 0;
+    let mut errors: Vec<String> = Vec::new();
+    for BatchExportItem { project_id, result } in results {
+        match result {
+            Ok(Some(files)) => {
+                exported += 1;
+                output_files.extend(files);
+            }
+            Ok(None) => skipped += 1,
+            Err(e) => {
+                error!("[BATCH_EXPORT] 项目导出失败: project_id={}, {}", project_id, e);
+                errors.push(e);
+            }
         }
-    };
-
-    info!("[EXPORT_SEP] 导出完成，共 {} 个文件", output_files.len());
+    }
 
-    let _ = window.emit("export-complete", serde_json::json!({
-        "output_dir": output_dir,
-        "exported_count": output_files.len(),
-        "project_id": project_id
+    let _ = window.emit("batch-export-complete", serde_json::json!({
+        "exported": exported,
+        "skipped": skipped,
+        "errors": errors.len(),
+        "error_messages": errors,
+        "total": total
     }));
 
+    info!("[BATCH_EXPORT] 批量导出完成: 成功 {}, 跳过 {}, 失败 {}, 总计 {}", exported, skipped, errors.len(), total);
+    drop(batch_guard);
+    clean_cache_after_export(state.app_dir.clone(), None).await;
+
+    if batch_cancel.load(Ordering::SeqCst) {
+        return Err(AppError::Cancelled);
+    }
+
     Ok(serde_json::json!({
-        "exported_count": output_files.len(),
+        "exported_count": exported,
+        "skipped_count": skipped,
+        "error_messages": errors,
         "output_files": output_files
     }))
 }
 
 /// 导出自定义剪辑片段
+///
+/// preserve_mtime: 为 true 时输出文件保留源视频的修改时间，默认 false
+/// audio_bitrate: 重编码时的 AAC 音频码率（64-320 kbps），默认 192
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn export_custom_clip(
     window: Window,
     project_id: String,
@@ -902,26 +2493,25 @@ pub async fn export_custom_clip(
     end_time: f64,
     output_path: String,
     force_reencode: Option<bool>,
+    preserve_mtime: Option<bool>,
+    audio_bitrate: Option<u32>,
 ) -> AppResult<String> {
-    let _guard = CancelFlagGuard::new(project_id.clone());
+    let audio_bitrate = parse_audio_bitrate(audio_bitrate)?;
+    let _guard = CancelFlagGuard::with_project_lock(project_id.clone(), &project_id);
     let cancel_flag = reset_cancel_flag(&project_id);
 
     let project = database::get_project_by_id(&project_id)?
         .ok_or_else(|| AppError::NotFound("项目不存在".to_string()))?;
 
     // 检查源视频文件是否存在
-    if !Path::new(&project.source_video_path).exists() {
+    if !media_input_exists(&project.source_video_path) {
         error!("[EXPORT_CUSTOM] 源视频文件不存在: {}", project.source_video_path);
         return Err(AppError::NotFound(format!("源视频文件不存在: {}", project.source_video_path)));
     }
 
     // 验证时间范围
-    if start_time < 0.0 {
-        return Err(AppError::InvalidArgument("开始时间不能为负数".to_string()));
-    }
-    if end_time <= start_time {
-        return Err(AppError::InvalidArgument("结束时间必须大于开始时间".to_string()));
-    }
+    let min_clip_seconds = config::get_config().min_clip_seconds;
+    validate_clip_range("", start_time, end_time, project.video_info.duration, min_clip_seconds)?;
 
     let duration = end_time - start_time;
     info!("[EXPORT_CUSTOM] === 开始导出自定义剪辑片段 ===");
@@ -934,6 +2524,8 @@ pub async fn export_custom_clip(
     let prefer_lossless = !reencode;
     info!("[EXPORT_CUSTOM] 强制重编码: {}, prefer_lossless: {}", reencode, prefer_lossless);
 
+    check_clip_guardrails(&window, &project_id, &project.video_info, &[(start_time, end_time)], prefer_lossless, audio_bitrate)?;
+
     let _ = window.emit("export-progress", serde_json::json!({
         "progress": 0.0,
         "message": "开始导出自定义剪辑...",
@@ -959,11 +2551,16 @@ pub async fn export_custom_clip(
         cancel_flag,
         &project_id,
         prefer_lossless,
+        audio_bitrate,
     ) {
         error!("[EXPORT_CUSTOM] 导出失败: {}", e);
         return Err(e);
     }
 
+    if preserve_mtime.unwrap_or(false) {
+        copy_source_mtime(&project.source_video_path, &output_path);
+    }
+
     info!("[EXPORT_CUSTOM] 导出完成: {}", output_path);
 
     let _ = window.emit("export-complete", serde_json::json!({
@@ -974,6 +2571,100 @@ pub async fn export_custom_clip(
     Ok(output_path)
 }
 
+/// 源视频时长边界的容差（秒），容纳前端播放器时间与探测时长的微小差异
+const CLIP_BOUNDS_TOLERANCE: f64 = 0.05;
+
+/// 校验自定义剪辑时间范围
+///
+/// 要求开始时间非负、时长不小于 min_seconds，且结束时间不超出源视频时长
+/// （source_duration 未知即 <= 0 时跳过边界检查）。label 用于错误信息中标识片段。
+fn validate_clip_range(
+    label: &str,
+    start_time: f64,
+    end_time: f64,
+    source_duration: f64,
+    min_seconds: f64,
+) -> AppResult<()> {
+    if !start_time.is_finite() || !end_time.is_finite() {
+        return Err(AppError::InvalidArgument(format!("{}时间无效", label)));
+    }
+    if start_time < 0.0 {
+        return Err(AppError::InvalidArgument(format!("{}开始时间不能为负数", label)));
+    }
+    if end_time <= start_time {
+        return Err(AppError::InvalidArgument(format!("{}结束时间必须大于开始时间", label)));
+    }
+    if end_time - start_time < min_seconds {
+        return Err(AppError::InvalidArgument(format!(
+            "{}时长 {:.3}s 小于最短时长 {}s",
+            label, end_time - start_time, min_seconds
+        )));
+    }
+    if source_duration > 0.0 && end_time > source_duration + CLIP_BOUNDS_TOLERANCE {
+        return Err(AppError::InvalidArgument(format!(
+            "{}结束时间 {:.3}s 超出视频时长 {:.3}s",
+            label, end_time, source_duration
+        )));
+    }
+    Ok(())
+}
+
+/// 自定义剪辑导出护栏
+///
+/// 单段时长超过 max_clip_seconds 时返回错误；总时长或预估大小超过警告阈值时
+/// 发送 `export-warning` 事件（不阻止导出），避免误选超长范围导出数 GB 文件。
+fn check_clip_guardrails(
+    window: &Window,
+    project_id: &str,
+    video_info: &VideoInfo,
+    ranges: &[(f64, f64)],
+    prefer_lossless: bool,
+    audio_bitrate: Option<u32>,
+) -> AppResult<()> {
+    let config = config::get_config();
+
+    if config.max_clip_seconds > 0 {
+        let max_seconds = config.max_clip_seconds as f64;
+        if let Some((start, end)) = ranges.iter().find(|(s, e)| e - s > max_seconds) {
+            return Err(AppError::InvalidArgument(format!(
+                "剪辑时长 {:.1}s 超过上限 {}s（{:.1}s - {:.1}s）",
+                end - start, config.max_clip_seconds, start, end
+            )));
+        }
+    }
+
+    let total_duration: f64 = ranges.iter().map(|(s, e)| e - s).sum();
+    let estimated_size = if prefer_lossless {
+        ffmpeg::estimate_output_size(video_info, ranges, "copy", None, None)
+    } else {
+        ffmpeg::estimate_output_size(video_info, ranges, "libx264", Some(18), audio_bitrate)
+    };
+
+    let mut warnings = Vec::new();
+    if config.clip_warn_seconds > 0 && total_duration > config.clip_warn_seconds as f64 {
+        warnings.push(format!("导出总时长 {:.1}s 超过警告阈值 {}s", total_duration, config.clip_warn_seconds));
+    }
+    if config.clip_warn_size_mb > 0 && estimated_size > config.clip_warn_size_mb * 1024 * 1024 {
+        warnings.push(format!(
+            "预估输出大小 {:.1}MB 超过警告阈值 {}MB",
+            estimated_size as f64 / 1024.0 / 1024.0, config.clip_warn_size_mb
+        ));
+    }
+
+    if !warnings.is_empty() {
+        let message = warnings.join("；");
+        warn!("[EXPORT_CUSTOM] {}", message);
+        let _ = window.emit("export-warning", serde_json::json!({
+            "project_id": project_id,
+            "message": message,
+            "total_duration": total_duration,
+            "estimated_size": estimated_size
+        }));
+    }
+
+    Ok(())
+}
+
 /// 自定义剪辑时间范围（前端传入）
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct CustomClipRange {
@@ -982,6 +2673,8 @@ pub struct CustomClipRange {
 }
 
 /// 合并导出多个自定义剪辑片段
+///
+/// audio_bitrate: 重编码时的 AAC 音频码率（64-320 kbps），默认 192
 #[tauri::command]
 pub async fn export_custom_clips_merged(
     window: Window,
@@ -989,14 +2682,16 @@ pub async fn export_custom_clips_merged(
     segments: Vec<CustomClipRange>,
     output_path: String,
     force_reencode: Option<bool>,
+    audio_bitrate: Option<u32>,
 ) -> AppResult<String> {
-    let _guard = CancelFlagGuard::new(project_id.clone());
+    let audio_bitrate = parse_audio_bitrate(audio_bitrate)?;
+    let _guard = CancelFlagGuard::with_project_lock(project_id.clone(), &project_id);
     let cancel_flag = reset_cancel_flag(&project_id);
 
     let project = database::get_project_by_id(&project_id)?
         .ok_or_else(|| AppError::NotFound("项目不存在".to_string()))?;
 
-    if !Path::new(&project.source_video_path).exists() {
+    if !media_input_exists(&project.source_video_path) {
         error!("[EXPORT_CUSTOM_MERGED] 源视频文件不存在: {}", project.source_video_path);
         return Err(AppError::NotFound(format!("源视频文件不存在: {}", project.source_video_path)));
     }
@@ -1007,13 +2702,15 @@ pub async fn export_custom_clips_merged(
 
     // 验证并转换片段
     let mut time_ranges: Vec<(f64, f64)> = Vec::with_capacity(segments.len());
+    let min_clip_seconds = config::get_config().min_clip_seconds;
     for (i, seg) in segments.iter().enumerate() {
-        if seg.start_time < 0.0 {
-            return Err(AppError::InvalidArgument(format!("片段 {} 开始时间不能为负数", i + 1)));
-        }
-        if seg.end_time <= seg.start_time {
-            return Err(AppError::InvalidArgument(format!("片段 {} 结束时间必须大于开始时间", i + 1)));
-        }
+        validate_clip_range(
+            &format!("片段 {} ", i + 1),
+            seg.start_time,
+            seg.end_time,
+            project.video_info.duration,
+            min_clip_seconds,
+        )?;
         time_ranges.push((seg.start_time, seg.end_time));
     }
 
@@ -1033,6 +2730,8 @@ pub async fn export_custom_clips_merged(
     let prefer_lossless = !reencode;
     info!("[EXPORT_CUSTOM_MERGED] 强制重编码: {}, prefer_lossless: {}", reencode, prefer_lossless);
 
+    check_clip_guardrails(&window, &project_id, &project.video_info, &merged, prefer_lossless, audio_bitrate)?;
+
     let _ = window.emit("export-progress", serde_json::json!({
         "progress": 0.0,
         "message": "开始合并导出自定义剪辑...",
@@ -1060,7 +2759,7 @@ pub async fn export_custom_clips_merged(
         })),
         cancel_flag,
         &project_id,
-        prefer_lossless,
+        &ffmpeg::EncodeOptions { prefer_lossless, audio_bitrate, ..Default::default() },
     ) {
         error!("[EXPORT_CUSTOM_MERGED] 导出失败: {}", e);
         return Err(e);
@@ -1077,6 +2776,8 @@ pub async fn export_custom_clips_merged(
 }
 
 /// 分别导出多个自定义剪辑片段
+///
+/// audio_bitrate: 重编码时的 AAC 音频码率（64-320 kbps），默认 192
 #[tauri::command]
 pub async fn export_custom_clips_separately(
     window: Window,
@@ -1084,15 +2785,17 @@ pub async fn export_custom_clips_separately(
     segments: Vec<CustomClipRange>,
     output_dir: String,
     force_reencode: Option<bool>,
+    audio_bitrate: Option<u32>,
 ) -> AppResult<serde_json::Value> {
-    let _guard = CancelFlagGuard::new(project_id.clone());
+    let audio_bitrate = parse_audio_bitrate(audio_bitrate)?;
+    let _guard = CancelFlagGuard::with_project_lock(project_id.clone(), &project_id);
     let cancel_flag = reset_cancel_flag(&project_id);
     let internal_cancel = Arc::new(AtomicBool::new(false));
 
     let project = database::get_project_by_id(&project_id)?
         .ok_or_else(|| AppError::NotFound("项目不存在".to_string()))?;
 
-    if !Path::new(&project.source_video_path).exists() {
+    if !media_input_exists(&project.source_video_path) {
         error!("[EXPORT_CUSTOM_SEP] 源视频文件不存在: {}", project.source_video_path);
         return Err(AppError::NotFound(format!("源视频文件不存在: {}", project.source_video_path)));
     }
@@ -1102,13 +2805,15 @@ pub async fn export_custom_clips_separately(
     }
 
     // 验证片段
+    let min_clip_seconds = config::get_config().min_clip_seconds;
     for (i, seg) in segments.iter().enumerate() {
-        if seg.start_time < 0.0 {
-            return Err(AppError::InvalidArgument(format!("片段 {} 开始时间不能为负数", i + 1)));
-        }
-        if seg.end_time <= seg.start_time {
-            return Err(AppError::InvalidArgument(format!("片段 {} 结束时间必须大于开始时间", i + 1)));
-        }
+        validate_clip_range(
+            &format!("片段 {} ", i + 1),
+            seg.start_time,
+            seg.end_time,
+            project.video_info.duration,
+            min_clip_seconds,
+        )?;
     }
 
     let total_segments = segments.len();
@@ -1123,6 +2828,9 @@ pub async fn export_custom_clips_separately(
     let prefer_lossless = !reencode;
     info!("[EXPORT_CUSTOM_SEP] 强制重编码: {}, prefer_lossless: {}", reencode, prefer_lossless);
 
+    let time_ranges: Vec<(f64, f64)> = segments.iter().map(|s| (s.start_time, s.end_time)).collect();
+    check_clip_guardrails(&window, &project_id, &project.video_info, &time_ranges, prefer_lossless, audio_bitrate)?;
+
     let _ = window.emit("export-progress", serde_json::json!({
         "progress": 0.0,
         "message": "开始分别导出自定义剪辑片段...",
@@ -1214,7 +2922,7 @@ pub async fn export_custom_clips_separately(
                     *end_time,
                     &[&cancel_flag, &internal_cancel],
                     &project_id_clone,
-                    prefer_lossless,
+                    &ffmpeg::EncodeOptions { prefer_lossless, audio_bitrate, ..Default::default() },
                 ) {
                     Ok(()) => {
                         let completed = completed_count.fetch_add(1, Ordering::SeqCst) + 1;
@@ -1285,48 +2993,128 @@ pub async fn export_custom_clips_separately(
         "output_files": output_files
     }))
 }
+
+/// 检查已存在的输出文件能否直接复用
+///
+/// 非空文件直接复用（只读文件大小，不探测，列表渲染时的缩略图请求不会逐个启动 ffprobe）；
+/// 输出经临时文件重命名写入，中途崩溃不会留下不完整的目标文件。
+/// force 为 true 或文件为空时返回 false，由调用方写入临时文件后重命名覆盖。
+/// 需要覆盖的文件必须位于应用目录下，避免误覆盖用户文件。
+fn reuse_existing_output(path: &Path, force: bool, tag: &str, app_dir: &Path) -> AppResult<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    let non_empty = path.metadata().map(|m| m.len() > 0).unwrap_or(false);
+    if !force && non_empty {
+        return Ok(true);
+    }
+    let in_app_dir = match (path.canonicalize(), app_dir.canonicalize()) {
+        (Ok(path), Ok(app_dir)) => path.starts_with(app_dir),
+        _ => false,
+    };
+    if !in_app_dir {
+        return Err(AppError::InvalidArgument(format!(
+            "拒绝覆盖应用目录外的文件: {}",
+            path.display()
+        )));
+    }
+    info!("{} 重新生成已有文件: {} (force={}, empty={})", tag, path.display(), force, !non_empty);
+    Ok(false)
+}
+
+/// 输出文件的临时写入路径：同目录下 {stem}.tmp.{ext}，保留扩展名供 ffmpeg 识别格式
+fn temp_output_path(path: &Path) -> std::path::PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.tmp.{}", stem, ext.to_string_lossy()),
+        None => format!("{}.tmp", stem),
+    };
+    path.with_file_name(name)
+}
+
+/// 生成结果写入临时文件后重命名覆盖目标，失败时清理临时文件并保留原文件
+fn write_output_atomically(path: &Path, generate: impl FnOnce(&str) -> AppResult<()>) -> AppResult<()> {
+    let tmp_path = temp_output_path(path);
+    let result = generate(&tmp_path.to_string_lossy())
+        .and_then(|_| std::fs::rename(&tmp_path, path).map_err(AppError::from));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
 /// 获取视频缩略图
+///
+/// 传入 project_id 时将缩略图路径写入项目记录（兼容迁移前创建、未记录路径的项目）。
+/// force 为 true 时重新生成并覆盖已有缩略图；已有文件损坏时也会重新生成。
 #[tauri::command]
 pub async fn get_video_thumbnail(
     video_path: String,
     output_path: String,
     time: Option<f64>,
+    project_id: Option<String>,
+    force: Option<bool>,
+    state: State<'_, AppState>,
 ) -> AppResult<String> {
     info!(
         "[THUMBNAIL] 缩略图请求: video_path={}, output_path={}, time={:?}",
         video_path, output_path, time
     );
 
-    // 检查缩略图是否已存在，如果存在则直接返回
-    let output_file = Path::new(&output_path);
-    if output_file.exists() {
+    // 检查缩略图是否已存在且有效，如果存在则直接返回
+    if reuse_existing_output(Path::new(&output_path), force.unwrap_or(false), "[THUMBNAIL]", &state.app_dir)? {
         info!("[THUMBNAIL] 缩略图已存在，跳过生成: {}", output_path);
+        record_project_thumbnail(project_id.as_deref(), &output_path);
         return Ok(output_path);
     }
 
-    let timestamp = time.unwrap_or(0.0);
-    if !Path::new(&video_path).exists() {
+    if !media_input_exists(&video_path) {
         error!("[THUMBNAIL] 视频文件不存在: {}", video_path);
         return Err(AppError::NotFound(format!("视频文件不存在: {}", video_path)));
     }
+    // 未指定时间时按配置比例截取（需探测时长，失败则回退到首帧）
+    let timestamp = match time {
+        Some(t) => t,
+        None => ffmpeg::get_video_info(&video_path)
+            .map(|info| config::thumbnail_time(info.duration))
+            .unwrap_or(0.0),
+    };
 
-    if let Err(e) = ffmpeg::extract_thumbnail(&video_path, &output_path, timestamp) {
+    if let Err(e) = write_output_atomically(Path::new(&output_path), |tmp| {
+        ffmpeg::extract_thumbnail(&video_path, tmp, timestamp)
+    }) {
         error!("[THUMBNAIL] 生成失败: {}", e);
         return Err(e);
     }
     info!("[THUMBNAIL] 生成完成: {}", output_path);
+    record_project_thumbnail(project_id.as_deref(), &output_path);
     Ok(output_path)
 }
 
+/// 记录项目缩略图路径（失败只记录警告）
+fn record_project_thumbnail(project_id: Option<&str>, thumbnail_path: &str) {
+    if let Some(project_id) = project_id {
+        if let Err(e) = database::set_project_thumbnail_path(project_id, thumbnail_path) {
+            warn!("[THUMBNAIL] 保存缩略图路径失败: project_id={}, {}", project_id, e);
+        }
+    }
+}
+
 /// 检测视频是否需要转码预览
 #[tauri::command]
 pub async fn check_needs_preview(video_path: String) -> AppResult<bool> {
     info!("[PREVIEW] 检测视频是否需要预览转码: {}", video_path);
 
-    if !Path::new(&video_path).exists() {
+    if !media_input_exists(&video_path) {
         return Err(AppError::NotFound(format!("视频文件不存在: {}", video_path)));
     }
 
+    // 网络流无法保证 WebView 直接播放，统一转码到本地预览缓存
+    if is_url(&video_path) {
+        info!("[PREVIEW] 网络输入需要转码预览: {}", video_path);
+        return Ok(true);
+    }
+
     let video_info = ffmpeg::get_video_info(&video_path)?;
     let needs_preview = ffmpeg::needs_preview_transcode(&video_info);
 
@@ -1339,23 +3127,27 @@ pub async fn check_needs_preview(video_path: String) -> AppResult<bool> {
 }
 
 /// 生成预览视频
+///
+/// force 为 true 时重新生成并覆盖已有预览（修复损坏或截断的预览）；已有文件探测失败时也会重新生成。
 #[tauri::command]
 pub async fn generate_preview_video(
     window: Window,
     source_path: String,
     output_path: String,
     project_id: Option<String>,
+    force: Option<bool>,
 ) -> AppResult<String> {
     info!("[PREVIEW] === 开始生成预览视频 ===");
     info!("[PREVIEW] 源视频: {}", source_path);
     info!("[PREVIEW] 输出路径: {}", output_path);
 
-    if !Path::new(&source_path).exists() {
+    if !media_input_exists(&source_path) {
         return Err(AppError::NotFound(format!("源视频文件不存在: {}", source_path)));
     }
 
-    // 如果预览文件已存在，直接返回
-    if Path::new(&output_path).exists() {
+    // 如果预览文件已存在且有效，直接返回
+    let app_dir = window.state::<AppState>().app_dir.clone();
+    if reuse_existing_output(Path::new(&output_path), force.unwrap_or(false), "[PREVIEW]", &app_dir)? {
         info!("[PREVIEW] 预览视频已存在，跳过生成: {}", output_path);
         return Ok(output_path);
     }
@@ -1374,19 +3166,21 @@ pub async fn generate_preview_video(
     let window_clone = window.clone();
     let project_id_clone = project_id.clone();
 
-    ffmpeg::generate_preview_video(
-        &source_path,
-        &output_path,
-        Some(Box::new(move |progress| {
-            let _ = window_clone.emit("preview-progress", serde_json::json!({
-                "progress": progress,
-                "message": format!("生成预览: {:.1}%", progress * 100.0),
-                "project_id": project_id_clone
-            }));
-        })),
-        cancel_flag,
-        &cancel_flag_id,
-    )?;
+    write_output_atomically(Path::new(&output_path), |tmp| {
+        ffmpeg::generate_preview_video(
+            &source_path,
+            tmp,
+            Some(Box::new(move |progress| {
+                let _ = window_clone.emit("preview-progress", serde_json::json!({
+                    "progress": progress,
+                    "message": format!("生成预览: {:.1}%", progress * 100.0),
+                    "project_id": project_id_clone
+                }));
+            })),
+            cancel_flag,
+            &cancel_flag_id,
+        )
+    })?;
 
     let _ = window.emit("preview-complete", serde_json::json!({
         "output_path": output_path,
@@ -1397,6 +3191,232 @@ pub async fn generate_preview_video(
     Ok(output_path)
 }
 
+/// 预览代理与源视频允许的时长差（秒），至少 0.5 秒或源时长的 1%
+fn proxy_duration_tolerance(source_duration: f64) -> f64 {
+    (source_duration * 0.01).max(0.5)
+}
+
+/// 已验证的预览代理：(预览路径, 修改时间) -> 是否可用，预览重新生成后按新的修改时间重新探测
+static PROXY_VALIDATION: once_cell::sync::Lazy<Mutex<HashMap<(String, u128), bool>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 探测预览代理：能被 ffprobe 读取且时长与源视频一致时可用
+fn probe_preview_proxy(preview: &str, source_duration: f64) -> bool {
+    match ffmpeg::get_video_info(preview) {
+        Ok(info) if (info.duration - source_duration).abs() <= proxy_duration_tolerance(source_duration) => {
+            info!("[PREVIEW] 使用预览视频作为编辑代理: {}", preview);
+            true
+        }
+        Ok(info) => {
+            warn!(
+                "[PREVIEW] 预览视频时长 {:.2}s 与源视频 {:.2}s 不一致，改用源视频: {}",
+                info.duration, source_duration, preview
+            );
+            false
+        }
+        Err(e) => {
+            warn!("[PREVIEW] 探测预览视频失败，改用源视频: {}, {}", preview, e);
+            false
+        }
+    }
+}
+
+/// 编辑/预览操作使用的输入文件
+///
+/// 开启 use_preview_proxy 且预览视频存在时返回预览视频（低分辨率、与源视频时间轴一致，
+/// 时间无需换算），否则返回源视频。预览视频需能被 ffprobe 探测且时长与源视频一致，
+/// 截断或损坏的预览会回退到源视频；探测在阻塞线程中执行，结果按路径与修改时间缓存。
+/// 片段预览与波形缓存键包含所用文件的修改时间，预览重新生成后缓存自动失效。
+/// 导出不经过该函数，始终使用源视频。
+pub(crate) async fn editing_source_path(project: &crate::utils::Project) -> String {
+    if !config::get_config().use_preview_proxy {
+        return project.source_video_path.clone();
+    }
+    let Some(preview) = project.preview_video_path.as_deref() else {
+        return project.source_video_path.clone();
+    };
+    if !Path::new(preview).metadata().map(|m| m.len() > 0).unwrap_or(false) {
+        return project.source_video_path.clone();
+    }
+
+    let key = (preview.to_string(), source_mtime_nanos(preview));
+    let cached = lock_or_recover(&PROXY_VALIDATION, "预览代理").get(&key).copied();
+    let valid = match cached {
+        Some(valid) => valid,
+        None => {
+            let path = preview.to_string();
+            let source_duration = project.video_info.duration;
+            let valid = tokio::task::spawn_blocking(move || probe_preview_proxy(&path, source_duration))
+                .await
+                .unwrap_or(false);
+            let mut cache = lock_or_recover(&PROXY_VALIDATION, "预览代理");
+            cache.retain(|(path, _), _| path != preview);
+            cache.insert(key, valid);
+            valid
+        }
+    };
+    if valid {
+        preview.to_string()
+    } else {
+        project.source_video_path.clone()
+    }
+}
+
+/// 源文件修改时间（纳秒），用于缓存键；网络输入或无法读取时为 0
+fn source_mtime_nanos(path: &str) -> u128 {
+    Path::new(path).metadata().and_then(|m| m.modified()).ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+}
+
+/// 片段预览缓存键：源文件 + 修改时间 + 片段边界 + 音量增益，任一变化都会生成新的预览
+fn segment_preview_key(source_path: &str, segment: &Segment) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(source_path.as_bytes());
+    hasher.update(source_mtime_nanos(source_path).to_le_bytes());
+    hasher.update(segment.start_time.to_le_bytes());
+    hasher.update(segment.end_time.to_le_bytes());
+    hasher.update(segment.gain_db.unwrap_or(0.0).to_le_bytes());
+    hex::encode(&hasher.finalize()[..8])
+}
+
+/// 生成单个片段的预览视频
+///
+/// 只重编码该片段，输出到 previews/segments/{project_id}/{segment_id}_{key}.mp4。
+/// 缓存按片段边界寻址，编辑片段后自动重新生成，同一片段的旧预览会被清理。
+/// 可通过 cancel_preview_generation(project_id) 取消。
+#[tauri::command]
+pub async fn preview_segment(
+    project_id: String,
+    segment_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<String> {
+    let project = database::get_project_by_id(&project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", project_id)))?;
+    let segment = project.segments
+        .iter()
+        .find(|s| s.id == segment_id)
+        .ok_or_else(|| AppError::NotFound(format!("片段不存在: {}", segment_id)))?;
+    if !project.file_exists {
+        return Err(AppError::NotFound(format!("源视频文件不存在: {}", project.source_video_path)));
+    }
+
+    let source_path = editing_source_path(&project).await;
+    let preview_dir = state.app_dir.join("previews").join("segments").join(&project_id);
+    std::fs::create_dir_all(&preview_dir)?;
+    let file_prefix = format!("{}_", segment_id);
+    let output_path = preview_dir.join(format!(
+        "{}{}.mp4",
+        file_prefix,
+        segment_preview_key(&source_path, segment)
+    ));
+    if output_path.exists() {
+        info!("[PREVIEW] 片段预览已缓存: {}", output_path.display());
+        return Ok(output_path.to_string_lossy().to_string());
+    }
+
+    // 清理该片段编辑前的旧预览
+    if let Ok(entries) = std::fs::read_dir(&preview_dir) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&file_prefix) {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    info!(
+        "[PREVIEW] 生成片段预览: project_id={}, segment_id={}, {:.2}s - {:.2}s",
+        project_id, segment_id, segment.start_time, segment.end_time
+    );
+    let cancel_flag_id = format!("segment_preview_{}", project_id);
+    let _guard = CancelFlagGuard::new(cancel_flag_id.clone());
+    let cancel_flag = reset_cancel_flag(&cancel_flag_id);
+
+    let segment = segment.clone();
+    let output = output_path.to_string_lossy().to_string();
+    let output_clone = output.clone();
+    tokio::task::spawn_blocking(move || {
+        ffmpeg::encode_segment_preview(&source_path, &output_clone, &segment, &cancel_flag, &cancel_flag_id)
+    })
+    .await
+    .map_err(|e| AppError::Video(format!("片段预览任务失败: {}", e)))??;
+
+    info!("[PREVIEW] 片段预览生成完成: {}", output);
+    Ok(output)
+}
+
+/// 片段波形默认峰值数
+const DEFAULT_WAVEFORM_PEAKS: usize = 1000;
+/// 片段波形最大峰值数
+const MAX_WAVEFORM_PEAKS: usize = 20000;
+
+/// 获取时间范围内的音频波形（用于精细调整片段边界）
+///
+/// 只解码该时间范围的音频，返回 peaks 个归一化峰值（0-1），默认 1000。
+/// 结果按 (源文件, 修改时间, 时间范围, 峰值数) 缓存到 previews/waveforms/{project_id}/。
+#[tauri::command]
+pub async fn get_segment_audio_preview(
+    project_id: String,
+    start_time: f64,
+    end_time: f64,
+    peaks: Option<usize>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<f32>> {
+    if !start_time.is_finite() || !end_time.is_finite() || start_time < 0.0 || end_time <= start_time {
+        return Err(AppError::InvalidArgument(format!("无效的时间范围: {:.2}s - {:.2}s", start_time, end_time)));
+    }
+    let peaks = peaks.unwrap_or(DEFAULT_WAVEFORM_PEAKS);
+    if peaks == 0 || peaks > MAX_WAVEFORM_PEAKS {
+        return Err(AppError::InvalidArgument(format!("峰值数必须在 1-{} 之间: {}", MAX_WAVEFORM_PEAKS, peaks)));
+    }
+
+    let project = database::get_project_by_id(&project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("项目不存在: {}", project_id)))?;
+    if !project.file_exists {
+        return Err(AppError::NotFound(format!("源视频文件不存在: {}", project.source_video_path)));
+    }
+
+    let source_path = editing_source_path(&project).await;
+    let cache_key = {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(source_path.as_bytes());
+        hasher.update(source_mtime_nanos(&source_path).to_le_bytes());
+        hasher.update(start_time.to_le_bytes());
+        hasher.update(end_time.to_le_bytes());
+        hasher.update((peaks as u64).to_le_bytes());
+        hex::encode(&hasher.finalize()[..8])
+    };
+    let cache_dir = state.app_dir.join("previews").join("waveforms").join(&project_id);
+    let cache_file = cache_dir.join(format!("{}.json", cache_key));
+    if let Ok(content) = std::fs::read_to_string(&cache_file) {
+        match serde_json::from_str::<Vec<f32>>(&content) {
+            Ok(cached) => return Ok(cached),
+            Err(e) => warn!("[WAVEFORM] 波形缓存损坏，重新提取: {}", e),
+        }
+    }
+
+    info!("[WAVEFORM] 提取片段波形: project_id={}, {:.2}s - {:.2}s, peaks={}", project_id, start_time, end_time, peaks);
+    let data = tokio::task::spawn_blocking(move || {
+        ffmpeg::extract_waveform_peaks(&source_path, start_time, end_time, peaks)
+    })
+    .await
+    .map_err(|e| AppError::Video(format!("波形提取任务失败: {}", e)))??;
+
+    // 缓存写入失败不影响结果
+    let write_result = std::fs::create_dir_all(&cache_dir)
+        .map_err(AppError::from)
+        .and_then(|_| serde_json::to_string(&data).map_err(AppError::from))
+        .and_then(|content| std::fs::write(&cache_file, content).map_err(AppError::from));
+    if let Err(e) = write_result {
+        warn!("[WAVEFORM] 写入波形缓存失败: {}", e);
+    }
+
+    Ok(data)
+}
+
 /// 取消处理（指定项目）
 #[tauri::command]
 pub async fn cancel_processing(project_id: Option<String>) -> AppResult<()> {
@@ -1406,26 +3426,63 @@ pub async fn cancel_processing(project_id: Option<String>) -> AppResult<()> {
     let flag = get_cancel_flag(&flag_id);
     flag.store(true, Ordering::SeqCst);
 
-    // 2. 立即 kill 所有子进程，实现即时取消
+    // 2. 立即 kill 所有子进程，实现即时取消（挂起中的进程同样可以被 kill）
     kill_child_processes(&flag_id);
+    lock_or_recover(&PAUSED_OPERATIONS, "暂停状态").remove(&flag_id);
 
     info!("[CANCEL] 取消处理请求: project_id={}, 已终止所有子进程", flag_id);
     Ok(())
 }
 
+/// 暂停处理（指定项目）
+///
+/// 挂起正在运行的子进程而不终止，resume_processing 后从中断处继续；
+/// 暂停期间新启动的子进程会立即挂起，停滞超时不计入暂停时间。
+#[tauri::command]
+pub async fn pause_processing(project_id: Option<String>) -> AppResult<()> {
+    let flag_id = project_id.unwrap_or_else(|| "default".to_string());
+
+    if !lock_or_recover(&CANCEL_FLAGS, "取消标志").contains_key(&flag_id) {
+        return Err(AppError::NotFound(format!("没有进行中的任务: {}", flag_id)));
+    }
+
+    lock_or_recover(&PAUSED_OPERATIONS, "暂停状态").insert(flag_id.clone());
+    let suspended = set_child_processes_suspended(&flag_id, true);
+
+    info!("[PAUSE] 暂停处理请求: project_id={}, 已挂起 {} 个子进程", flag_id, suspended);
+    Ok(())
+}
+
+/// 恢复已暂停的处理（指定项目）
+#[tauri::command]
+pub async fn resume_processing(project_id: Option<String>) -> AppResult<()> {
+    let flag_id = project_id.unwrap_or_else(|| "default".to_string());
+
+    if !lock_or_recover(&PAUSED_OPERATIONS, "暂停状态").remove(&flag_id) {
+        return Err(AppError::InvalidArgument(format!("任务未暂停: {}", flag_id)));
+    }
+    let resumed = set_child_processes_suspended(&flag_id, false);
+
+    info!("[PAUSE] 恢复处理请求: project_id={}, 已恢复 {} 个子进程", flag_id, resumed);
+    Ok(())
+}
+
 /// 取消预览视频生成（仅取消预览任务，不影响其他处理任务）
 #[tauri::command]
 pub async fn cancel_preview_generation(project_id: Option<String>) -> AppResult<()> {
-    let flag_id = format!("preview_{}", project_id.unwrap_or_else(|| "default".to_string()));
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
 
-    // 1. 设置取消标志
-    let flag = get_cancel_flag(&flag_id);
-    flag.store(true, Ordering::SeqCst);
+    // 整体预览与片段预览一并取消
+    for flag_id in [format!("preview_{}", project_id), format!("segment_preview_{}", project_id)] {
+        // 1. 设置取消标志
+        let flag = get_cancel_flag(&flag_id);
+        flag.store(true, Ordering::SeqCst);
 
-    // 2. 立即 kill 预览生成的子进程
-    kill_child_processes(&flag_id);
+        // 2. 立即 kill 预览生成的子进程
+        kill_child_processes(&flag_id);
 
-    info!("[CANCEL] 取消预览生成请求: flag_id={}, 已终止预览生成进程", flag_id);
+        info!("[CANCEL] 取消预览生成请求: flag_id={}, 已终止预览生成进程", flag_id);
+    }
     Ok(())
 }
 
@@ -1454,11 +3511,17 @@ fn check_onnx_gpu() -> bool {
     let separator_path = separator::resolve_separator_path();
     info!("[GPU] 使用 audio-separator 检测 GPU: {}", separator_path);
 
-    // 传递 --model_file_dir 到系统临时目录，避免 audio-separator 在默认路径
-    // (/tmp/audio-separator-models/) 创建空文件夹
-    let temp_dir = std::env::temp_dir();
+    // 传递 --model_file_dir 到独立的临时目录，避免 audio-separator 在默认路径
+    // (/tmp/audio-separator-models/) 创建空文件夹，也避免与其他进程共享系统临时目录
+    let temp_dir = match tempfile::Builder::new().prefix("musiccut_gpu_check_").tempdir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            warn!("[GPU] 创建临时目录失败: {}", e);
+            return false;
+        }
+    };
     let output = hidden_command(&separator_path)
-        .args(["-e", "--model_file_dir", &temp_dir.to_string_lossy()])
+        .args(["-e", "--model_file_dir", &temp_dir.path().to_string_lossy()])
         .output();
 
     match output {
@@ -1503,3 +3566,211 @@ print('onnx_gpu_ok' if has_gpu else 'onnx_gpu_no')
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_path_for_container() {
+        assert_eq!(output_path_for_container("/out/a.mp4", None), "/out/a.mp4");
+        assert_eq!(output_path_for_container("/out/a.mp4", Some(OutputContainer::Mkv)), "/out/a.mkv");
+        assert_eq!(output_path_for_container("/out/a.m4v", Some(OutputContainer::Mp4)), "/out/a.m4v");
+        assert_eq!(output_path_for_container("/out/a.MOV", Some(OutputContainer::Mov)), "/out/a.MOV");
+        assert_eq!(output_path_for_container("/out/a", Some(OutputContainer::Mov)), "/out/a.mov");
+    }
+
+    #[test]
+    fn test_validate_clip_range() {
+        assert!(validate_clip_range("", 1.0, 2.0, 10.0, 0.1).is_ok());
+        // 结束时间在容差内超出视频时长
+        assert!(validate_clip_range("", 9.0, 10.02, 10.0, 0.1).is_ok());
+        // 时长未知时跳过边界检查
+        assert!(validate_clip_range("", 9.0, 20.0, 0.0, 0.1).is_ok());
+
+        assert!(validate_clip_range("", -1.0, 2.0, 10.0, 0.1).is_err());
+        assert!(validate_clip_range("", 2.0, 2.0, 10.0, 0.1).is_err());
+        assert!(validate_clip_range("", 2.0, 2.01, 10.0, 0.1).is_err());
+        assert!(validate_clip_range("", 9.0, 12.0, 10.0, 0.1).is_err());
+        assert!(validate_clip_range("", f64::NAN, 2.0, 10.0, 0.1).is_err());
+    }
+
+    #[test]
+    fn test_window_times_cover_video_end() {
+        // 65s 视频, 10s 窗口, 5s 步长：常规窗口最后结束于 65s，无需追加
+        let times = compute_window_times(65.0, 10.0, 5.0);
+        assert_eq!(times.last().map(|(_, t)| *t), Some(55.0));
+
+        // 67s 视频：常规窗口结束于 65s，剩余 2s 尾部，追加锚定在 57s 的窗口
+        let times = compute_window_times(67.0, 10.0, 5.0);
+        let (last_index, last_start) = *times.last().unwrap();
+        assert_eq!(last_index, times.len() - 1);
+        assert!((last_start + 10.0 - 67.0).abs() < 1e-9);
+
+        // 尾部很短（小于步长的比例阈值）时不追加
+        let times = compute_window_times(65.5, 10.0, 5.0);
+        assert_eq!(times.last().map(|(_, t)| *t), Some(55.0));
+    }
+
+    #[test]
+    fn test_window_times_short_video() {
+        assert!(compute_window_times(5.0, 10.0, 5.0).is_empty());
+        assert_eq!(compute_window_times(10.0, 10.0, 5.0), vec![(0, 0.0)]);
+    }
+
+    #[test]
+    fn test_window_times_in_range_offset() {
+        // 限定 100s-130s：窗口起点为绝对时间，末尾锚定窗口结束于范围终点
+        let times = compute_window_times_in_range(100.0, 132.0, 10.0, 5.0);
+        assert_eq!(times.first(), Some(&(0, 100.0)));
+        let (_, last_start) = *times.last().unwrap();
+        assert!((last_start + 10.0 - 132.0).abs() < 1e-9);
+        assert!(times.iter().all(|(_, t)| *t >= 100.0 && *t + 10.0 <= 132.0));
+    }
+
+    #[test]
+    fn test_multi_match_forms_parallel_segments() {
+        // 两首歌在 10s-35s 交叉：单曲合并会被来回切断，按歌曲分组后各自形成连续片段
+        let times = compute_window_times(60.0, 10.0, 5.0);
+        let song = |idx: usize, id: &str, conf: f64| (idx, id.to_string(), id.to_string(), conf);
+        let results = vec![
+            song(0, "a", 0.9), song(1, "a", 0.8), song(2, "a", 0.7), song(2, "b", 0.6),
+            song(3, "b", 0.8), song(3, "a", 0.65), song(4, "b", 0.9), song(5, "b", 0.9),
+        ];
+
+        let single = merge_window_matches(results.clone(), &times, 10.0, 2.0, 5.0, 60.0, "p1", ConfidenceAggregation::Max);
+        assert!(single.len() > 2);
+
+        let segments = merge_window_matches_per_music(results, &times, 10.0, 2.0, 5.0, 60.0, "p1", ConfidenceAggregation::Max);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].music_id.as_deref(), Some("a"));
+        assert!((segments[0].start_time - 0.0).abs() < 1e-9);
+        assert!((segments[0].end_time - 25.0).abs() < 1e-9);
+        assert_eq!(segments[1].music_id.as_deref(), Some("b"));
+        assert!((segments[1].start_time - 10.0).abs() < 1e-9);
+        assert!((segments[1].end_time - 35.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_end_of_video_match_detected() {
+        // 只有末尾锚定窗口匹配到歌曲，片段应一直延伸到视频结尾
+        let times = compute_window_times(67.0, 10.0, 5.0);
+        let last_index = times.len() - 1;
+        let results = vec![
+            (last_index - 1, "m1".to_string(), "Song".to_string(), 0.8),
+            (last_index, "m1".to_string(), "Song".to_string(), 0.9),
+        ];
+        let segments = merge_window_matches(results, &times, 10.0, 2.0, 5.0, 67.0, "p1", ConfidenceAggregation::Max);
+        assert_eq!(segments.len(), 1);
+        assert!((segments[0].end_time - 67.0).abs() < 1e-9);
+        assert!((segments[0].confidence - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_review_mode_marks_weak_segments_provisional() {
+        // 两段不同歌曲：m1 强匹配，m2 只有弱候选
+        let times = compute_window_times(60.0, 10.0, 5.0);
+        let results = vec![
+            (0, "m1".to_string(), "Strong".to_string(), 0.9),
+            (1, "m1".to_string(), "Strong".to_string(), 0.8),
+            (6, "m2".to_string(), "Weak".to_string(), 0.2),
+            (7, "m2".to_string(), "Weak".to_string(), 0.3),
+        ];
+        let mut segments = merge_window_matches(results, &times, 10.0, 2.0, 5.0, 60.0, "p1", ConfidenceAggregation::Max);
+        mark_provisional_segments(&mut segments, 0.6);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].status, SegmentStatus::Detected);
+        assert_eq!(segments[1].status, SegmentStatus::Provisional);
+    }
+
+    #[test]
+    fn test_temp_output_path_keeps_extension() {
+        assert_eq!(
+            temp_output_path(Path::new("/app/previews/p1.mp4")),
+            Path::new("/app/previews/p1.tmp.mp4")
+        );
+        assert_eq!(
+            temp_output_path(Path::new("/app/thumbnails/p1.jpg")),
+            Path::new("/app/thumbnails/p1.tmp.jpg")
+        );
+    }
+
+    #[test]
+    fn test_filter_window_matches_min_confidence() {
+        let window = |best: f64, second: f64| vec![
+            (3, "m1".to_string(), "A".to_string(), best),
+            (3, "m2".to_string(), "B".to_string(), second),
+        ];
+        // 最佳匹配低于阈值：整个窗口丢弃，次级匹配不会单独留下
+        assert!(filter_window_matches(window(0.4, 0.35), 0.5, 0.3, false).is_empty());
+        // 次级匹配按次级阈值筛选（默认次级阈值高于最低置信度）
+        let kept = filter_window_matches(window(0.9, 0.55), 0.5, 0.6, false);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].1, "m1");
+        assert_eq!(filter_window_matches(window(0.9, 0.6), 0.5, 0.6, false).len(), 2);
+        assert_eq!(filter_window_matches(window(0.9, 0.4), 0.5, 0.3, false).len(), 2);
+        // 审核模式只保留最佳候选
+        let kept = filter_window_matches(window(0.4, 0.35), 0.5, 0.6, true);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].1, "m1");
+        assert!(filter_window_matches(Vec::new(), 0.5, 0.6, true).is_empty());
+    }
+
+    #[test]
+    fn test_confidence_aggregation_modes() {
+        // 40s 片段只有首个窗口强匹配，其余窗口置信度较低，中间 20s-25s 无匹配窗口
+        let windows = vec![(0.0, 0.9), (5.0, 0.5), (10.0, 0.5), (25.0, 0.5), (30.0, 0.5)];
+        let max = aggregate_confidence(&windows, 10.0, 0.0, 40.0, ConfidenceAggregation::Max);
+        assert!((max - 0.9).abs() < 1e-9);
+        let mean = aggregate_confidence(&windows, 10.0, 0.0, 40.0, ConfidenceAggregation::Mean);
+        assert!((mean - 0.58).abs() < 1e-9);
+        // 覆盖: 0-10 (0.9), 10-15 (0.5), 15-20 (0.5), 25-35 (0.5), 35-40 (0.5)，间隙 20-25 计 0
+        let weighted = aggregate_confidence(&windows, 10.0, 0.0, 40.0, ConfidenceAggregation::CoverageWeighted);
+        assert!((weighted - (9.0 + 2.5 + 2.5 + 5.0 + 2.5) / 40.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_adaptive_window_at_duration_bounds() {
+        let matching = config::MatchConfig::default();
+        let min_window = matching.min_window_size as f64;
+        let max_window = matching.max_window_size as f64;
+
+        // 短于/等于下界：使用最小窗口
+        let (window, _) = adaptive_window_params(30.0, &matching);
+        assert!((window - min_window).abs() < 1e-9);
+        let (window, _) = adaptive_window_params(ADAPTIVE_SHORT_DURATION, &matching);
+        assert!((window - min_window).abs() < 1e-9);
+
+        // 长于/等于上界：使用最大窗口
+        let (window, _) = adaptive_window_params(ADAPTIVE_LONG_DURATION, &matching);
+        assert!((window - max_window).abs() < 1e-9);
+        let (window, _) = adaptive_window_params(ADAPTIVE_LONG_DURATION * 4.0, &matching);
+        assert!((window - max_window).abs() < 1e-9);
+
+        // 时长为 0 不产生 NaN
+        let (window, hop) = adaptive_window_params(0.0, &matching);
+        assert!((window - min_window).abs() < 1e-9);
+        assert!(hop > 0.0);
+    }
+
+    #[test]
+    fn test_adaptive_window_scaling() {
+        let matching = config::MatchConfig::default();
+        let min_window = matching.min_window_size as f64;
+        let max_window = matching.max_window_size as f64;
+
+        // 上下界的几何平均处取窗口范围的中点
+        let mid_duration = (ADAPTIVE_SHORT_DURATION * ADAPTIVE_LONG_DURATION).sqrt();
+        let (window, hop) = adaptive_window_params(mid_duration, &matching);
+        assert!((window - (min_window + max_window) / 2.0).abs() < 1e-9);
+
+        // 步长保持配置中的步长/窗口比例
+        let ratio = matching.hop_size as f64 / matching.window_size as f64;
+        assert!((hop / window - ratio).abs() < 1e-9);
+
+        // 时长越长窗口越大
+        let (short_window, _) = adaptive_window_params(600.0, &matching);
+        let (long_window, _) = adaptive_window_params(1800.0, &matching);
+        assert!(short_window < long_window);
+    }
+}
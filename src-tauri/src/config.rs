@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::fs;
 use crate::error::{AppError, AppResult};
+use crate::utils::MatchAudioSource;
 use once_cell::sync::OnceCell;
 use parking_lot::RwLock;
 use tracing::info;
@@ -85,6 +86,9 @@ pub struct SeparationConfig {
     pub selected_model_id: String,
     /// 输出格式
     pub output_format: String,
+    /// 所选模型未下载时，人声分离前自动下载（默认关闭）
+    #[serde(default)]
+    pub auto_download_model: bool,
 }
 
 fn default_model_id() -> String {
@@ -96,6 +100,7 @@ impl Default for SeparationConfig {
         Self {
             selected_model_id: "mdx-inst-hq3".to_string(),
             output_format: "wav".to_string(),
+            auto_download_model: false,
         }
     }
 }
@@ -132,25 +137,77 @@ impl Default for WindowState {
 pub struct DetectionConfig {
     /// 置信度阈值 (0.0 - 1.0)
     pub confidence_threshold: f32,
-    /// 抽帧间隔（每 N 帧检测一次）
+    /// 抽帧间隔（每 N 帧检测一次），detection_interval_seconds 未设置时生效
     pub frame_interval: u32,
+    /// 抽帧间隔（秒），设置后按视频帧率换算为帧间隔，优先于 frame_interval；为 null 时按帧数。
+    /// 新安装默认 0.2 秒；旧配置缺少该字段时为 None，继续使用用户保存的 frame_interval
+    #[serde(default)]
+    pub detection_interval_seconds: Option<f32>,
     /// 最小片段时长 (秒)
     pub min_segment_duration: f32,
     /// 最大合并间隔 (秒)
     pub max_gap_duration: f32,
 }
 
+/// 新安装的默认抽帧间隔（秒），只用于 Default，不用于反序列化旧配置
+fn default_detection_interval_seconds() -> Option<f32> {
+    Some(0.2)
+}
+
 impl Default for DetectionConfig {
     fn default() -> Self {
         Self {
             confidence_threshold: 0.5,
             frame_interval: 5,
+            detection_interval_seconds: default_detection_interval_seconds(),
             min_segment_duration: 1.0,
             max_gap_duration: 2.0,
         }
     }
 }
 
+/// 扫描/导入时识别的文件扩展名（小写，不含点）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileExtensionsConfig {
+    /// 视频扩展名（scan_video_files）
+    pub video: Vec<String>,
+    /// 音频扩展名（import_music_folder）
+    pub audio: Vec<String>,
+}
+
+impl Default for FileExtensionsConfig {
+    fn default() -> Self {
+        Self {
+            video: ["mp4", "mkv", "avi", "mov", "wmv", "flv", "webm", "ts", "mts", "m2ts"]
+                .iter().map(|s| s.to_string()).collect(),
+            audio: ["mp3", "flac", "wav", "m4a", "aac", "ogg", "wma"]
+                .iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl FileExtensionsConfig {
+    /// 文件扩展名是否在列表中（忽略大小写和前导点）
+    pub fn matches(extensions: &[String], path: &Path) -> bool {
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .is_some_and(|ext| extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&ext)))
+    }
+}
+
+/// 片段置信度聚合方式（合并多个匹配窗口时）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfidenceAggregation {
+    /// 取窗口置信度最大值
+    #[default]
+    Max,
+    /// 窗口置信度平均值
+    Mean,
+    /// 按窗口覆盖时长加权平均，片段内未被匹配窗口覆盖的间隙计为 0
+    CoverageWeighted,
+}
+
 /// 匹配配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchConfig {
@@ -165,12 +222,63 @@ pub struct MatchConfig {
     /// 最大允许间隙 (秒)，超过此间隙则分割为独立片段
     #[serde(default = "default_max_gap_duration")]
     pub max_gap_duration: f32,
+    /// 最小重叠比例 (0.0 - 1.0)：对齐后与歌曲重叠部分占窗口的比例低于此值时不视为匹配
+    #[serde(default = "default_min_overlap_ratio")]
+    pub min_overlap_ratio: f32,
+    /// 指纹提取时下混为单声道（音乐库指纹与查询窗口统一使用）
+    #[serde(default = "default_fingerprint_mono")]
+    pub fingerprint_mono: bool,
+    /// 指纹提取采样率 (Hz)，Chromaprint 内部按 11025Hz 处理，无需 44.1kHz
+    #[serde(default = "default_fingerprint_sample_rate")]
+    pub fingerprint_sample_rate: u32,
+    /// 自适应窗口：按视频时长在 [min_window_size, max_window_size] 内缩放窗口与步长
+    #[serde(default)]
+    pub adaptive_window: bool,
+    /// 自适应窗口下限 (秒)，短视频使用
+    #[serde(default = "default_min_window_size")]
+    pub min_window_size: f32,
+    /// 自适应窗口上限 (秒)，长视频使用
+    #[serde(default = "default_max_window_size")]
+    pub max_window_size: f32,
+    /// 多曲匹配模式下，同一窗口内除最佳匹配外其他歌曲的置信度阈值 (0.0 - 1.0)
+    #[serde(default = "default_secondary_confidence")]
+    pub secondary_confidence: f32,
+    /// 合并窗口为片段时的置信度聚合方式
+    #[serde(default)]
+    pub confidence_aggregation: ConfidenceAggregation,
+    /// 匹配使用的音频来源（调用时未指定则使用该值），匹配完成后记录实际使用的来源
+    #[serde(default)]
+    pub audio_source: MatchAudioSource,
 }
 
 fn default_max_gap_duration() -> f32 {
     10.0
 }
 
+fn default_min_overlap_ratio() -> f32 {
+    0.1
+}
+
+fn default_fingerprint_mono() -> bool {
+    true
+}
+
+fn default_fingerprint_sample_rate() -> u32 {
+    22050
+}
+
+fn default_min_window_size() -> f32 {
+    8.0
+}
+
+fn default_max_window_size() -> f32 {
+    30.0
+}
+
+fn default_secondary_confidence() -> f32 {
+    0.5
+}
+
 impl Default for MatchConfig {
     fn default() -> Self {
         Self {
@@ -179,6 +287,15 @@ impl Default for MatchConfig {
             window_size: 15.0,
             hop_size: 5.0,
             max_gap_duration: 10.0,
+            min_overlap_ratio: default_min_overlap_ratio(),
+            fingerprint_mono: default_fingerprint_mono(),
+            fingerprint_sample_rate: default_fingerprint_sample_rate(),
+            adaptive_window: false,
+            min_window_size: default_min_window_size(),
+            max_window_size: default_max_window_size(),
+            secondary_confidence: default_secondary_confidence(),
+            confidence_aggregation: ConfidenceAggregation::default(),
+            audio_source: MatchAudioSource::default(),
         }
     }
 }
@@ -201,6 +318,100 @@ pub struct AppConfig {
     /// 日志级别
     #[serde(default)]
     pub log_level: LogLevel,
+    /// 扫描/导入识别的文件扩展名
+    #[serde(default)]
+    pub file_extensions: FileExtensionsConfig,
+    /// 创建项目时自动为浏览器无法直接播放的格式生成预览视频
+    #[serde(default)]
+    pub auto_generate_preview: bool,
+    /// 编辑/预览操作（片段预览、波形）优先使用已生成的预览视频作为代理，导出始终使用源视频
+    #[serde(default)]
+    pub use_preview_proxy: bool,
+    /// 子进程无进展超时（秒），0 表示不限制
+    #[serde(default = "default_process_timeout_secs")]
+    pub process_timeout_secs: u64,
+    /// 重编码片段缓存上限（MB），0 表示不缓存
+    #[serde(default = "default_segment_cache_max_mb")]
+    pub segment_cache_max_mb: u64,
+    /// 人声分离/人物检测使用的 GPU 序号（CUDA_VISIBLE_DEVICES），None 表示由程序自行选择
+    #[serde(default)]
+    pub gpu_device_index: Option<u32>,
+    /// 是否为导出/分离/检测等任务额外写入 logs/{project_id}_{op}.log
+    #[serde(default)]
+    pub operation_logs: bool,
+    /// 自定义剪辑单段最大时长（秒），超过时拒绝导出，0 表示不限制
+    #[serde(default)]
+    pub max_clip_seconds: u64,
+    /// 自定义剪辑单段最短时长（秒），过短的片段会生成损坏的输出，导出时拒绝
+    #[serde(default = "default_min_clip_seconds")]
+    pub min_clip_seconds: f64,
+    /// 自定义剪辑总时长警告阈值（秒），超过时发送 export-warning 事件，0 表示不警告
+    #[serde(default = "default_clip_warn_seconds")]
+    pub clip_warn_seconds: u64,
+    /// 自定义剪辑预估大小警告阈值（MB），超过时发送 export-warning 事件，0 表示不警告
+    #[serde(default = "default_clip_warn_size_mb")]
+    pub clip_warn_size_mb: u64,
+    /// 导入音乐时检查与音乐库已有歌曲的指纹相似度（疑似重复不导入），每首需与全库比对
+    #[serde(default)]
+    pub near_duplicate_check: bool,
+    /// 疑似重复的指纹相似度阈值 (0.0 - 1.0)
+    #[serde(default = "default_near_duplicate_threshold")]
+    pub near_duplicate_threshold: f32,
+    /// 同时下载的模型数，超出的下载请求排队等待
+    #[serde(default = "default_max_concurrent_downloads")]
+    pub max_concurrent_downloads: u32,
+    /// 缩略图默认截取位置（占视频时长的比例 0.0 - 1.0），避开片头黑屏
+    #[serde(default = "default_thumbnail_time_ratio")]
+    pub thumbnail_time_ratio: f64,
+    /// temp 缓存上限（字节），超出时按修改时间自动清理最旧的中间文件，0 表示不限制
+    #[serde(default)]
+    pub max_cache_bytes: u64,
+    /// 日志文件保留天数（至少 1 天），启动时删除更早的日志
+    #[serde(default = "default_log_retention_days")]
+    pub log_retention_days: u32,
+    /// 匹配/拼接等操作的临时目录位置（绝对路径），实际使用其下的 musiccut_temp 子目录；
+    /// None 表示使用 {app_dir}/temp
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+}
+
+/// 默认日志保留天数
+pub const DEFAULT_LOG_RETENTION_DAYS: u32 = 7;
+
+fn default_log_retention_days() -> u32 {
+    DEFAULT_LOG_RETENTION_DAYS
+}
+
+fn default_segment_cache_max_mb() -> u64 {
+    2048
+}
+
+fn default_max_concurrent_downloads() -> u32 {
+    2
+}
+
+fn default_thumbnail_time_ratio() -> f64 {
+    0.1
+}
+
+fn default_near_duplicate_threshold() -> f32 {
+    0.9
+}
+
+fn default_min_clip_seconds() -> f64 {
+    0.1
+}
+
+fn default_clip_warn_seconds() -> u64 {
+    600
+}
+
+fn default_clip_warn_size_mb() -> u64 {
+    2048
+}
+
+fn default_process_timeout_secs() -> u64 {
+    600
 }
 
 impl Default for AppConfig {
@@ -212,10 +423,44 @@ impl Default for AppConfig {
             detection: DetectionConfig::default(),
             window_state: WindowState::default(),
             log_level: LogLevel::default(),
+            file_extensions: FileExtensionsConfig::default(),
+            auto_generate_preview: false,
+            use_preview_proxy: false,
+            process_timeout_secs: default_process_timeout_secs(),
+            segment_cache_max_mb: default_segment_cache_max_mb(),
+            gpu_device_index: None,
+            operation_logs: false,
+            max_clip_seconds: 0,
+            min_clip_seconds: default_min_clip_seconds(),
+            clip_warn_seconds: default_clip_warn_seconds(),
+            clip_warn_size_mb: default_clip_warn_size_mb(),
+            near_duplicate_check: false,
+            near_duplicate_threshold: default_near_duplicate_threshold(),
+            max_concurrent_downloads: default_max_concurrent_downloads(),
+            thumbnail_time_ratio: default_thumbnail_time_ratio(),
+            max_cache_bytes: 0,
+            log_retention_days: default_log_retention_days(),
+            cache_dir: None,
         }
     }
 }
 
+/// 子进程无进展超时时长，配置为 0 时返回 None（不限制）
+pub fn process_timeout() -> Option<std::time::Duration> {
+    match get_config().process_timeout_secs {
+        0 => None,
+        secs => Some(std::time::Duration::from_secs(secs)),
+    }
+}
+
+/// 按配置比例计算缩略图默认截取时间（秒），时长未知时返回 0
+pub fn thumbnail_time(duration: f64) -> f64 {
+    if !(duration.is_finite() && duration > 0.0) {
+        return 0.0;
+    }
+    duration * get_config().thumbnail_time_ratio.clamp(0.0, 1.0)
+}
+
 /// 初始化配置
 pub fn init_config(config_path: &Path) -> AppResult<()> {
     CONFIG_PATH.set(config_path.to_path_buf())
@@ -242,6 +487,22 @@ pub fn init_config(config_path: &Path) -> AppResult<()> {
     Ok(())
 }
 
+/// 配置的 cache_dir 下实际使用的子目录名，避免与该目录中用户自己的文件混在一起
+pub const CACHE_SUBDIR: &str = "musiccut_temp";
+
+/// 应用缓存目录：配置了 cache_dir 时为 {cache_dir}/musiccut_temp，否则为 {app_dir}/temp（与 config.json 同级）
+///
+/// 配置未初始化时回退到系统临时目录。
+pub fn app_temp_dir() -> std::path::PathBuf {
+    if let Some(dir) = get_config().cache_dir.filter(|d| !d.trim().is_empty()) {
+        return std::path::PathBuf::from(dir).join(CACHE_SUBDIR);
+    }
+    CONFIG_PATH.get()
+        .and_then(|path| path.parent())
+        .map(|dir| dir.join("temp"))
+        .unwrap_or_else(std::env::temp_dir)
+}
+
 /// 获取配置
 pub fn get_config() -> AppConfig {
     CONFIG.get()
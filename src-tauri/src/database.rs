@@ -3,15 +3,41 @@
 use rusqlite::{Connection, params};
 use std::path::Path;
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::collections::HashMap;
 use once_cell::sync::OnceCell;
 use tracing::{warn, error, info, debug};
 use rayon::prelude::*;
+use crate::config::MatchConfig;
+use crate::detection::detector::DetectionBox;
 use crate::error::{AppError, AppResult};
-use crate::utils::{MusicInfo, Project, Segment, VideoInfo, SegmentStatus, SegmentType};
+use crate::utils::{MusicInfo, MusicUsage, Project, Segment, VideoInfo, SegmentStatus, SegmentType, media_input_exists};
 
 static DB: OnceCell<Mutex<Connection>> = OnceCell::new();
 
+/// 正在进行的数据库密集操作数（如批量导入），维护操作需等待其结束
+static HEAVY_OPERATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// 数据库密集操作守卫，存活期间 optimize_database 会被拒绝
+pub struct HeavyOperationGuard;
+
+impl Drop for HeavyOperationGuard {
+    fn drop(&mut self) {
+        HEAVY_OPERATIONS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 标记开始一个数据库密集操作
+pub fn begin_heavy_operation() -> HeavyOperationGuard {
+    HEAVY_OPERATIONS.fetch_add(1, Ordering::SeqCst);
+    HeavyOperationGuard
+}
+
+/// 是否有数据库密集操作正在进行
+pub fn has_heavy_operation() -> bool {
+    HEAVY_OPERATIONS.load(Ordering::SeqCst) > 0
+}
+
 /// 初始化数据库
 pub fn init_database(db_path: &Path) -> AppResult<()> {
     let conn = Connection::open(db_path)?;
@@ -106,6 +132,94 @@ pub fn init_database(db_path: &Path) -> AppResult<()> {
         )?;
     }
 
+    // 迁移：添加 gain_db 列（片段音量调整）
+    let has_gain_db: bool = conn
+        .prepare("PRAGMA table_info(segments)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == "gain_db");
+
+    if !has_gain_db {
+        info!("[DB] 迁移: 添加 gain_db 列到 segments 表（片段音量调整）");
+        conn.execute("ALTER TABLE segments ADD COLUMN gain_db REAL", [])?;
+    }
+
+    // 迁移：添加 content_hash 列（批量创建时识别不同路径下的重复视频）
+    let has_content_hash: bool = conn
+        .prepare("PRAGMA table_info(projects)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == "content_hash");
+
+    if !has_content_hash {
+        info!("[DB] 迁移: 添加 content_hash 列到 projects 表（重复视频检测）");
+        conn.execute("ALTER TABLE projects ADD COLUMN content_hash TEXT", [])?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_projects_content_hash ON projects(content_hash)",
+            [],
+        )?;
+    }
+
+    // 迁移：添加 thumbnail_path 列（前端无需再按项目 ID 拼接缩略图路径）
+    let has_thumbnail_path: bool = conn
+        .prepare("PRAGMA table_info(projects)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == "thumbnail_path");
+
+    if !has_thumbnail_path {
+        info!("[DB] 迁移: 添加 thumbnail_path 列到 projects 表（缩略图路径）");
+        conn.execute("ALTER TABLE projects ADD COLUMN thumbnail_path TEXT", [])?;
+    }
+
+    // 迁移：添加 match_config 列（记录生成当前片段的匹配参数，JSON）
+    let has_match_config: bool = conn
+        .prepare("PRAGMA table_info(projects)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == "match_config");
+
+    if !has_match_config {
+        info!("[DB] 迁移: 添加 match_config 列到 projects 表（匹配参数快照）");
+        conn.execute("ALTER TABLE projects ADD COLUMN match_config TEXT", [])?;
+    }
+
+    // 创建项目时间索引（项目列表按最近修改/创建时间排序）
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_projects_updated_at ON projects(updated_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_projects_created_at ON projects(created_at)",
+        [],
+    )?;
+
+    // 创建检测框表（按片段 ID 存储人物检测框 JSON，独立于片段表，编辑片段不会丢失）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS detection_boxes (
+            segment_id TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL,
+            boxes TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_detection_boxes_project ON detection_boxes(project_id)",
+        [],
+    )?;
+
+    // 迁移：添加 fingerprint_version 列（指纹参数变化后识别需要重新提取的音乐）
+    let has_fingerprint_version: bool = conn
+        .prepare("PRAGMA table_info(music)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == "fingerprint_version");
+
+    if !has_fingerprint_version {
+        info!("[DB] 迁移: 添加 fingerprint_version 列到 music 表（指纹参数版本）");
+        conn.execute("ALTER TABLE music ADD COLUMN fingerprint_version TEXT", [])?;
+    }
+
     DB.set(Mutex::new(conn))
         .map_err(|_| AppError::Database(rusqlite::Error::InvalidQuery))?;
 
@@ -137,12 +251,13 @@ fn get_conn() -> AppResult<std::sync::MutexGuard<'static, Connection>> {
 
 /// 插入音乐
 /// 插入或更新音乐记录（含指纹数据）
-pub fn insert_music(music: &MusicInfo, fingerprint: &[u8]) -> AppResult<()> {
+/// fingerprint_version 为提取指纹时的参数标识（见 fingerprint::current_fingerprint_version）
+pub fn insert_music(music: &MusicInfo, fingerprint: &[u8], fingerprint_version: &str) -> AppResult<()> {
     debug!("[DB] 插入音乐: id={}, title={}", music.id, music.title);
     let conn = get_conn()?;
     conn.execute(
-        "INSERT OR REPLACE INTO music (id, title, album, duration, file_path, fingerprint, fingerprint_hash, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT OR REPLACE INTO music (id, title, album, duration, file_path, fingerprint, fingerprint_hash, created_at, fingerprint_version)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         params![
             music.id,
             music.title,
@@ -152,13 +267,57 @@ pub fn insert_music(music: &MusicInfo, fingerprint: &[u8]) -> AppResult<()> {
             fingerprint,
             music.fingerprint_hash,
             music.created_at,
+            fingerprint_version,
         ],
     )?;
     Ok(())
 }
 
+/// 获取指纹参数版本与当前不一致（含旧版本未记录）的音乐: (id, title, file_path)
+pub fn get_music_with_outdated_fingerprint(current_version: &str) -> AppResult<Vec<(String, String, String)>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, title, file_path FROM music
+         WHERE fingerprint_version IS NULL OR fingerprint_version != ?1
+         ORDER BY title COLLATE NOCASE ASC"
+    )?;
+    let rows = stmt
+        .query_map([current_version], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// 统计指纹参数版本与当前不一致的音乐数
+pub fn count_outdated_fingerprints(current_version: &str) -> AppResult<u64> {
+    let conn = get_conn()?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM music WHERE fingerprint_version IS NULL OR fingerprint_version != ?1",
+        [current_version],
+        |row| row.get(0),
+    )?;
+    Ok(count.max(0) as u64)
+}
+
+/// 更新音乐的指纹（重新提取后）
+pub fn update_music_fingerprint(
+    id: &str,
+    fingerprint: &[u8],
+    fingerprint_hash: &str,
+    duration: f64,
+    fingerprint_version: &str,
+) -> AppResult<()> {
+    debug!("[DB] 更新音乐指纹: id={}, version={}", id, fingerprint_version);
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE music SET fingerprint = ?1, fingerprint_hash = ?2, duration = ?3, fingerprint_version = ?4 WHERE id = ?5",
+        params![fingerprint, fingerprint_hash, duration, fingerprint_version, id],
+    )?;
+    Ok(())
+}
+
 /// 获取所有音乐
-pub fn get_all_music() -> AppResult<Vec<MusicInfo>> {
+/// check_files 为 false 时跳过文件存在性检查，file_exists 乐观地置为 true
+pub fn get_all_music(check_files: bool) -> AppResult<Vec<MusicInfo>> {
     let conn = get_conn()?;
     let mut stmt = conn.prepare(
         "SELECT id, title, album, duration, file_path, fingerprint_hash, created_at FROM music ORDER BY title COLLATE NOCASE ASC"
@@ -187,15 +346,84 @@ pub fn get_all_music() -> AppResult<Vec<MusicInfo>> {
     drop(conn);
 
     // 并行检查文件存在性（使用 rayon 提升大量文件时的性能）
-    music_list.par_iter_mut().for_each(|music| {
-        music.file_exists = Path::new(&music.file_path).exists();
-    });
+    check_music_files(&mut music_list, check_files);
 
     Ok(music_list)
 }
 
+/// 批量设置音乐文件存在性
+/// check_files 为 false 时不访问文件系统（网络盘/云同步目录较慢），由前端按需延迟校验
+fn check_music_files(music_list: &mut [MusicInfo], check_files: bool) {
+    if check_files {
+        music_list.par_iter_mut().for_each(|music| {
+            music.file_exists = Path::new(&music.file_path).exists();
+        });
+    } else {
+        music_list.iter_mut().for_each(|music| music.file_exists = true);
+    }
+}
+
+/// 音乐库排序方式对应的 ORDER BY 子句（白名单，避免 SQL 注入）
+fn music_order_clause(sort: &str) -> &'static str {
+    match sort {
+        "title_desc" => "title COLLATE NOCASE DESC",
+        "created_asc" => "created_at ASC",
+        "created_desc" => "created_at DESC",
+        "duration_asc" => "duration ASC",
+        "duration_desc" => "duration DESC",
+        _ => "title COLLATE NOCASE ASC",
+    }
+}
+
+/// 分页获取音乐
+/// 只对返回的当前页检查文件存在性，适用于超大音乐库的虚拟列表
+pub fn get_music_page(offset: u32, limit: u32, sort: &str, check_files: bool) -> AppResult<Vec<MusicInfo>> {
+    debug!("[DB] 分页获取音乐: offset={}, limit={}, sort={}", offset, limit, sort);
+    let conn = get_conn()?;
+    // id 作为次排序键，保证同值记录在翻页时顺序稳定
+    let sql = format!(
+        "SELECT id, title, album, duration, file_path, fingerprint_hash, created_at
+         FROM music ORDER BY {}, id LIMIT ?1 OFFSET ?2",
+        music_order_clause(sort)
+    );
+    let mut stmt = conn.prepare(&sql)?;
+
+    let music_iter = stmt.query_map(params![limit, offset], |row| {
+        Ok(MusicInfo {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            album: row.get(2)?,
+            duration: row.get(3)?,
+            file_path: row.get(4)?,
+            fingerprint_hash: row.get(5)?,
+            created_at: row.get(6)?,
+            file_exists: false, // 稍后批量检查
+        })
+    })?;
+
+    let mut music_list = Vec::new();
+    for music in music_iter {
+        music_list.push(music?);
+    }
+
+    // 释放数据库连接后检查当前页的文件存在性
+    drop(stmt);
+    drop(conn);
+
+    check_music_files(&mut music_list, check_files);
+
+    Ok(music_list)
+}
+
+/// 获取音乐总数
+pub fn count_music() -> AppResult<u64> {
+    let conn = get_conn()?;
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM music", [], |row| row.get(0))?;
+    Ok(count.max(0) as u64)
+}
+
 /// 搜索音乐
-pub fn search_music(query: &str) -> AppResult<Vec<MusicInfo>> {
+pub fn search_music(query: &str, check_files: bool) -> AppResult<Vec<MusicInfo>> {
     let conn = get_conn()?;
     let pattern = format!("%{}%", query);
     let mut stmt = conn.prepare(
@@ -228,9 +456,7 @@ pub fn search_music(query: &str) -> AppResult<Vec<MusicInfo>> {
     drop(conn);
 
     // 并行检查文件存在性（使用 rayon 提升大量文件时的性能）
-    music_list.par_iter_mut().for_each(|music| {
-        music.file_exists = Path::new(&music.file_path).exists();
-    });
+    check_music_files(&mut music_list, check_files);
 
     Ok(music_list)
 }
@@ -262,6 +488,76 @@ pub fn get_music_by_id(id: &str) -> AppResult<Option<MusicInfo>> {
     }
 }
 
+/// 获取音乐使用情况（引用片段数、项目数及最近使用的项目）
+pub fn get_music_usage(id: &str) -> AppResult<MusicUsage> {
+    let conn = get_conn()?;
+    let (segment_count, project_count): (i64, i64) = conn.query_row(
+        "SELECT COUNT(*), COUNT(DISTINCT project_id) FROM segments WHERE music_id = ?1",
+        [id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.name, p.updated_at
+         FROM segments s
+         JOIN projects p ON s.project_id = p.id
+         WHERE s.music_id = ?1
+         ORDER BY p.updated_at DESC
+         LIMIT 1"
+    )?;
+    let mut rows = stmt.query([id])?;
+    let (last_project_id, last_project_name, last_used_at) = if let Some(row) = rows.next()? {
+        (Some(row.get(0)?), Some(row.get(1)?), Some(row.get(2)?))
+    } else {
+        (None, None, None)
+    };
+
+    Ok(MusicUsage {
+        music_id: id.to_string(),
+        segment_count: segment_count.max(0) as u64,
+        project_count: project_count.max(0) as u64,
+        last_project_id,
+        last_project_name,
+        last_used_at,
+    })
+}
+
+/// 获取未被任何片段引用的音乐
+pub fn find_unused_music(check_files: bool) -> AppResult<Vec<MusicInfo>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, title, album, duration, file_path, fingerprint_hash, created_at
+         FROM music m
+         WHERE NOT EXISTS (SELECT 1 FROM segments s WHERE s.music_id = m.id)
+         ORDER BY title COLLATE NOCASE ASC"
+    )?;
+
+    let music_iter = stmt.query_map([], |row| {
+        Ok(MusicInfo {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            album: row.get(2)?,
+            duration: row.get(3)?,
+            file_path: row.get(4)?,
+            fingerprint_hash: row.get(5)?,
+            created_at: row.get(6)?,
+            file_exists: false, // 稍后批量检查
+        })
+    })?;
+
+    let mut music_list = Vec::new();
+    for music in music_iter {
+        music_list.push(music?);
+    }
+
+    drop(stmt);
+    drop(conn);
+
+    check_music_files(&mut music_list, check_files);
+
+    Ok(music_list)
+}
+
 /// 获取所有音乐指纹（用于全库匹配）
 pub fn get_all_fingerprints() -> AppResult<Vec<(String, String, Vec<u8>)>> {
     debug!("[DB] 获取所有音乐指纹");
@@ -284,6 +580,39 @@ pub fn get_all_fingerprints() -> AppResult<Vec<(String, String, Vec<u8>)>> {
     Ok(result)
 }
 
+/// 获取指定专辑下所有音乐的指纹（专辑名不区分大小写）
+pub fn get_fingerprints_by_album(album: &str) -> AppResult<Vec<(String, String, Vec<u8>)>> {
+    debug!("[DB] 按专辑获取音乐指纹: album={}", album);
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT id, title, fingerprint FROM music WHERE album = ?1 COLLATE NOCASE")?;
+
+    let iter = stmt.query_map([album], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Vec<u8>>(2)?,
+        ))
+    })?;
+
+    let mut result = Vec::new();
+    for item in iter {
+        result.push(item?);
+    }
+
+    Ok(result)
+}
+
+/// 获取单首音乐的指纹数据与时长
+pub fn get_fingerprint_by_id(id: &str) -> AppResult<Option<(Vec<u8>, f64)>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT fingerprint, duration FROM music WHERE id = ?1")?;
+    let mut rows = stmt.query([id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some((row.get(0)?, row.get(1)?))),
+        None => Ok(None),
+    }
+}
+
 /// 根据 ID 列表获取指定音乐的指纹
 pub fn get_fingerprints_by_ids(ids: &[String]) -> AppResult<Vec<(String, String, Vec<u8>)>> {
     debug!("[DB] get_fingerprints_by_ids: 请求 {} 个音乐 ID", ids.len());
@@ -351,6 +680,34 @@ pub fn delete_music(id: &str) -> AppResult<()> {
     Ok(())
 }
 
+/// 批量删除音乐（同一事务），返回实际删除的数量
+///
+/// 连接未开启 foreign_keys，片段表的 ON DELETE SET NULL 不会自动生效，
+/// 这里在同一事务内显式将引用这些音乐的片段 music_id 置空。
+pub fn delete_music_bulk(ids: &[String]) -> AppResult<usize> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    debug!("[DB] 批量删除音乐: count={}", ids.len());
+    let conn = get_conn()?;
+    conn.execute_batch("BEGIN")?;
+    let result = ids.iter().try_fold(0usize, |deleted, id| {
+        conn.execute("UPDATE segments SET music_id = NULL WHERE music_id = ?1", [id])?;
+        Ok::<_, rusqlite::Error>(deleted + conn.execute("DELETE FROM music WHERE id = ?1", [id])?)
+    });
+    match result {
+        Ok(deleted) => {
+            conn.execute_batch("COMMIT")?;
+            info!("[DB] 批量删除音乐: 请求 {} 首，实际删除 {} 首", ids.len(), deleted);
+            Ok(deleted)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(AppError::Database(e))
+        }
+    }
+}
+
 /// 检查音乐文件路径是否已存在于数据库中
 pub fn music_exists_by_path(path: &str) -> AppResult<bool> {
     let conn = get_conn()?;
@@ -375,23 +732,85 @@ pub fn project_exists_by_path(path: &str) -> AppResult<bool> {
     Ok(count > 0)
 }
 
+/// 记录项目源视频的内容哈希
+pub fn set_project_content_hash(project_id: &str, content_hash: &str) -> AppResult<()> {
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE projects SET content_hash = ?2 WHERE id = ?1",
+        params![project_id, content_hash],
+    )?;
+    Ok(())
+}
+
+/// 获取尚未记录内容哈希的项目（content_hash 列迁移前创建），返回 (项目ID, 源视频路径)
+pub fn get_projects_missing_content_hash() -> AppResult<Vec<(String, String)>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT id, source_video_path FROM projects WHERE content_hash IS NULL")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// 按内容哈希查找已有项目，返回 (项目ID, 项目名称)
+pub fn find_project_by_content_hash(content_hash: &str) -> AppResult<Option<(String, String)>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT id, name FROM projects WHERE content_hash = ?1 LIMIT 1")?;
+    let mut rows = stmt.query([content_hash])?;
+    match rows.next()? {
+        Some(row) => Ok(Some((row.get(0)?, row.get(1)?))),
+        None => Ok(None),
+    }
+}
+
+/// 设置项目缩略图路径
+pub fn set_project_thumbnail_path(project_id: &str, thumbnail_path: &str) -> AppResult<()> {
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE projects SET thumbnail_path = ?2 WHERE id = ?1",
+        params![project_id, thumbnail_path],
+    )?;
+    Ok(())
+}
+
+/// 保存项目最近一次音乐匹配使用的参数
+pub fn set_project_match_config(project_id: &str, match_config: &MatchConfig) -> AppResult<()> {
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE projects SET match_config = ?2 WHERE id = ?1",
+        params![project_id, serde_json::to_string(match_config)?],
+    )?;
+    Ok(())
+}
+
+/// 解析匹配参数快照，损坏时记录警告并视为无记录
+fn parse_match_config(json: Option<String>) -> Option<MatchConfig> {
+    let json = json?;
+    serde_json::from_str(&json)
+        .map_err(|e| warn!("项目匹配参数 JSON 解析失败: {}，忽略", e))
+        .ok()
+}
+
 /// 插入项目及其片段
 pub fn insert_project(project: &Project) -> AppResult<()> {
     debug!("[DB] 插入项目: id={}, name={}", project.id, project.name);
     let conn = get_conn()?;
     let video_info_json = serde_json::to_string(&project.video_info)?;
+    let match_config_json = project.match_config.as_ref().map(serde_json::to_string).transpose()?;
 
     conn.execute(
-        "INSERT INTO projects (id, name, source_video_path, preview_video_path, video_info, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        "INSERT INTO projects (id, name, source_video_path, preview_video_path, thumbnail_path, video_info, created_at, updated_at, match_config)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         params![
             project.id,
             project.name,
             project.source_video_path,
             project.preview_video_path,
+            project.thumbnail_path,
             video_info_json,
             project.created_at,
             project.updated_at,
+            match_config_json,
         ],
     )?;
 
@@ -423,14 +842,28 @@ pub fn update_project(project: &Project) -> AppResult<()> {
     Ok(())
 }
 
+/// 项目列表排序方式对应的 ORDER BY 子句（白名单，避免 SQL 注入）
+///
+/// recent: 最近修改（默认）；created: 最近创建；name: 名称
+fn project_order_clause(sort: &str) -> &'static str {
+    match sort {
+        "created" => "created_at DESC",
+        "name" => "name COLLATE NOCASE ASC",
+        _ => "updated_at DESC",
+    }
+}
+
 /// 获取所有项目
-pub fn get_all_projects() -> AppResult<Vec<Project>> {
+/// check_files 为 false 时跳过源视频存在性检查，file_exists 乐观地置为 true
+pub fn get_all_projects(check_files: bool, sort: &str) -> AppResult<Vec<Project>> {
     let conn = get_conn()?;
 
     // 查询 1: 获取所有项目基本信息（不在查询中检查文件存在性）
-    let mut stmt = conn.prepare(
-        "SELECT id, name, source_video_path, preview_video_path, video_info, created_at, updated_at FROM projects ORDER BY updated_at DESC"
-    )?;
+    let sql = format!(
+        "SELECT id, name, source_video_path, preview_video_path, video_info, created_at, updated_at, thumbnail_path, match_config FROM projects ORDER BY {}, id",
+        project_order_clause(sort)
+    );
+    let mut stmt = conn.prepare(&sql)?;
 
     let project_iter = stmt.query_map([], |row| {
         let video_info_json: String = row.get(4)?;
@@ -449,6 +882,7 @@ pub fn get_all_projects() -> AppResult<Vec<Project>> {
                 bitrate: 0,
                 size: 0,
                 format: String::new(),
+                audio_stream_count: 0,
             }
         });
 
@@ -457,11 +891,13 @@ pub fn get_all_projects() -> AppResult<Vec<Project>> {
             name: row.get(1)?,
             source_video_path: row.get(2)?,
             preview_video_path: row.get(3)?,
+            thumbnail_path: row.get(7)?,
             video_info,
             segments: Vec::new(),
             created_at: row.get(5)?,
             updated_at: row.get(6)?,
             file_exists: false, // 稍后批量检查
+            match_config: parse_match_config(row.get(8)?),
         })
     })?;
 
@@ -473,7 +909,7 @@ pub fn get_all_projects() -> AppResult<Vec<Project>> {
     // 查询 2: 一次性获取所有片段（解决 N+1 查询问题）
     let mut seg_stmt = conn.prepare(
         "SELECT s.id, s.project_id, s.music_id, m.title,
-                s.start_time, s.end_time, s.confidence, s.status, s.segment_type
+                s.start_time, s.end_time, s.confidence, s.status, s.segment_type, s.gain_db
          FROM segments s
          LEFT JOIN music m ON s.music_id = m.id
          ORDER BY s.start_time"
@@ -483,6 +919,7 @@ pub fn get_all_projects() -> AppResult<Vec<Project>> {
         let status_str: String = row.get(7)?;
         let status = match status_str.as_str() {
             "removed" => SegmentStatus::Removed,
+            "provisional" => SegmentStatus::Provisional,
             _ => SegmentStatus::Detected,
         };
 
@@ -496,6 +933,7 @@ pub fn get_all_projects() -> AppResult<Vec<Project>> {
             confidence: row.get(6)?,
             status,
             segment_type: SegmentType::from_str(&row.get::<_, String>(8).unwrap_or_else(|_| "music".to_string())),
+            gain_db: row.get(9)?,
         })
     })?;
 
@@ -514,9 +952,13 @@ pub fn get_all_projects() -> AppResult<Vec<Project>> {
     drop(conn);
 
     // 并行检查文件存在性并分配片段（使用 rayon 提升大量项目时的性能）
-    projects.par_iter_mut().for_each(|project| {
-        project.file_exists = Path::new(&project.source_video_path).exists();
-    });
+    if check_files {
+        projects.par_iter_mut().for_each(|project| {
+            project.file_exists = media_input_exists(&project.source_video_path);
+        });
+    } else {
+        projects.iter_mut().for_each(|project| project.file_exists = true);
+    }
     for project in &mut projects {
         project.segments = segments_map.remove(&project.id).unwrap_or_default();
     }
@@ -526,12 +968,12 @@ pub fn get_all_projects() -> AppResult<Vec<Project>> {
 
 /// 获取单个项目
 pub fn get_project_by_id(id: &str) -> AppResult<Option<Project>> {
-    let project_data: Option<(String, String, String, Option<String>, VideoInfo, String, String, bool)>;
+    let project_data: Option<(String, String, String, Option<String>, Option<String>, VideoInfo, String, String, bool, Option<MatchConfig>)>;
 
     {
         let conn = get_conn()?;
         let mut stmt = conn.prepare(
-            "SELECT id, name, source_video_path, preview_video_path, video_info, created_at, updated_at FROM projects WHERE id = ?1"
+            "SELECT id, name, source_video_path, preview_video_path, video_info, created_at, updated_at, thumbnail_path, match_config FROM projects WHERE id = ?1"
         )?;
 
         let mut rows = stmt.query([id])?;
@@ -539,17 +981,19 @@ pub fn get_project_by_id(id: &str) -> AppResult<Option<Project>> {
             let video_info_json: String = row.get(4)?;
             let video_info: VideoInfo = serde_json::from_str(&video_info_json)?;
             let source_video_path: String = row.get(2)?;
-            let file_exists = Path::new(&source_video_path).exists();
+            let file_exists = media_input_exists(&source_video_path);
 
             project_data = Some((
                 row.get(0)?,
                 row.get(1)?,
                 source_video_path,
                 row.get(3)?,
+                row.get(7)?,
                 video_info,
                 row.get(5)?,
                 row.get(6)?,
                 file_exists,
+                parse_match_config(row.get(8)?),
             ));
         } else {
             return Ok(None);
@@ -557,7 +1001,7 @@ pub fn get_project_by_id(id: &str) -> AppResult<Option<Project>> {
     }
 
     // 连接已释放，现在可以安全地获取片段
-    if let Some((proj_id, name, source_video_path, preview_video_path, video_info, created_at, updated_at, file_exists)) = project_data {
+    if let Some((proj_id, name, source_video_path, preview_video_path, thumbnail_path, video_info, created_at, updated_at, file_exists, match_config)) = project_data {
         let segments = get_segments_by_project(&proj_id)?;
 
         Ok(Some(Project {
@@ -565,11 +1009,13 @@ pub fn get_project_by_id(id: &str) -> AppResult<Option<Project>> {
             name,
             source_video_path,
             preview_video_path,
+            thumbnail_path,
             video_info,
             segments,
             created_at,
             updated_at,
             file_exists,
+            match_config,
         }))
     } else {
         Ok(None)
@@ -581,6 +1027,7 @@ pub fn delete_project(id: &str) -> AppResult<()> {
     debug!("[DB] 删除项目: id={}", id);
     let conn = get_conn()?;
     conn.execute("DELETE FROM segments WHERE project_id = ?1", [id])?;
+    conn.execute("DELETE FROM detection_boxes WHERE project_id = ?1", [id])?;
     conn.execute("DELETE FROM projects WHERE id = ?1", [id])?;
     Ok(())
 }
@@ -594,11 +1041,12 @@ pub fn insert_segment(segment: &Segment) -> AppResult<()> {
     let status = match segment.status {
         SegmentStatus::Detected => "detected",
         SegmentStatus::Removed => "removed",
+        SegmentStatus::Provisional => "provisional",
     };
 
     conn.execute(
-        "INSERT OR REPLACE INTO segments (id, project_id, music_id, start_time, end_time, confidence, status, segment_type)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT OR REPLACE INTO segments (id, project_id, music_id, start_time, end_time, confidence, status, segment_type, gain_db)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         params![
             segment.id,
             segment.project_id,
@@ -608,6 +1056,7 @@ pub fn insert_segment(segment: &Segment) -> AppResult<()> {
             segment.confidence,
             status,
             segment.segment_type.as_str(),
+            segment.gain_db,
         ],
     )?;
     Ok(())
@@ -619,7 +1068,7 @@ pub fn get_segments_by_project(project_id: &str) -> AppResult<Vec<Segment>> {
     // 使用 LEFT JOIN 从 music 表获取标题
     let mut stmt = conn.prepare(
         "SELECT s.id, s.project_id, s.music_id, m.title,
-                s.start_time, s.end_time, s.confidence, s.status, s.segment_type
+                s.start_time, s.end_time, s.confidence, s.status, s.segment_type, s.gain_db
          FROM segments s
          LEFT JOIN music m ON s.music_id = m.id
          WHERE s.project_id = ?1 ORDER BY s.start_time"
@@ -629,6 +1078,7 @@ pub fn get_segments_by_project(project_id: &str) -> AppResult<Vec<Segment>> {
         let status_str: String = row.get(7)?;
         let status = match status_str.as_str() {
             "removed" => SegmentStatus::Removed,
+            "provisional" => SegmentStatus::Provisional,
             _ => SegmentStatus::Detected,
         };
 
@@ -642,6 +1092,7 @@ pub fn get_segments_by_project(project_id: &str) -> AppResult<Vec<Segment>> {
             confidence: row.get(6)?,
             status,
             segment_type: SegmentType::from_str(&row.get::<_, String>(8).unwrap_or_else(|_| "music".to_string())),
+            gain_db: row.get(9)?,
         })
     })?;
 
@@ -660,6 +1111,28 @@ pub fn delete_segments_by_project(project_id: &str) -> AppResult<()> {
     Ok(())
 }
 
+/// 删除项目的所有检测框（重新检测/匹配生成全新片段时调用，编辑片段时保留）
+pub fn delete_detection_boxes_by_project(project_id: &str) -> AppResult<()> {
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM detection_boxes WHERE project_id = ?1", [project_id])?;
+    Ok(())
+}
+
+/// 删除片段已不存在的检测框（编辑删除/合并片段后调用，保留仍存在片段的检测框）
+pub fn delete_orphan_detection_boxes(project_id: &str) -> AppResult<()> {
+    let conn = get_conn()?;
+    delete_orphan_detection_boxes_row(&conn, project_id)?;
+    Ok(())
+}
+
+fn delete_orphan_detection_boxes_row(conn: &Connection, project_id: &str) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM detection_boxes WHERE project_id = ?1 \
+         AND segment_id NOT IN (SELECT id FROM segments WHERE project_id = ?1)",
+        [project_id],
+    )
+}
+
 /// 批量插入/更新片段（使用事务，只获取一次锁）
 pub fn batch_insert_segments(segments: &[Segment]) -> AppResult<()> {
     if segments.is_empty() {
@@ -670,24 +1143,7 @@ pub fn batch_insert_segments(segments: &[Segment]) -> AppResult<()> {
     let conn = get_conn()?;
     conn.execute_batch("BEGIN")?;
     for segment in segments {
-        let status = match segment.status {
-            SegmentStatus::Detected => "detected",
-            SegmentStatus::Removed => "removed",
-        };
-        if let Err(e) = conn.execute(
-            "INSERT OR REPLACE INTO segments (id, project_id, music_id, start_time, end_time, confidence, status, segment_type)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![
-                segment.id,
-                segment.project_id,
-                segment.music_id,
-                segment.start_time,
-                segment.end_time,
-                segment.confidence,
-                status,
-                segment.segment_type.as_str(),
-            ],
-        ) {
+        if let Err(e) = insert_segment_row(&conn, segment) {
             let _ = conn.execute_batch("ROLLBACK");
             return Err(AppError::Database(e));
         }
@@ -696,18 +1152,110 @@ pub fn batch_insert_segments(segments: &[Segment]) -> AppResult<()> {
     Ok(())
 }
 
+/// 写入单个片段行（INSERT OR REPLACE），由调用方管理事务
+fn insert_segment_row(conn: &Connection, segment: &Segment) -> rusqlite::Result<usize> {
+    let status = match segment.status {
+        SegmentStatus::Detected => "detected",
+        SegmentStatus::Removed => "removed",
+        SegmentStatus::Provisional => "provisional",
+    };
+    conn.execute(
+        "INSERT OR REPLACE INTO segments (id, project_id, music_id, start_time, end_time, confidence, status, segment_type, gain_db)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            segment.id,
+            segment.project_id,
+            segment.music_id,
+            segment.start_time,
+            segment.end_time,
+            segment.confidence,
+            status,
+            segment.segment_type.as_str(),
+            segment.gain_db,
+        ],
+    )
+}
+
+/// 替换项目的全部片段（删除 + 插入在同一事务中，只获取一次锁）
+///
+/// 手动保存与自动保存都经此写入，数据库锁保证两者不会交错成半新半旧的片段集合。
+pub fn replace_project_segments(project_id: &str, segments: &[Segment]) -> AppResult<()> {
+    debug!("[DB] 替换项目片段: project_id={}, count={}", project_id, segments.len());
+    let conn = get_conn()?;
+    conn.execute_batch("BEGIN")?;
+    let result = conn
+        .execute("DELETE FROM segments WHERE project_id = ?1", [project_id])
+        .and_then(|_| segments.iter().try_for_each(|segment| insert_segment_row(&conn, segment).map(|_| ())))
+        .and_then(|_| delete_orphan_detection_boxes_row(&conn, project_id).map(|_| ()));
+    if let Err(e) = result {
+        let _ = conn.execute_batch("ROLLBACK");
+        return Err(AppError::Database(e));
+    }
+    conn.execute_batch("COMMIT")?;
+    Ok(())
+}
+
 /// 批量更新片段（通过 INSERT OR REPLACE 实现）
 pub fn batch_update_segments(segments: &[Segment]) -> AppResult<()> {
     debug!("[DB] 批量更新片段: count={}", segments.len());
     batch_insert_segments(segments)
 }
 
+/// 获取库统计数据（聚合查询，不加载整表）
+/// 返回 (音乐数, 音乐总时长, 项目数, 片段数)
+pub fn get_aggregate_stats() -> AppResult<(u64, f64, u64, u64)> {
+    let conn = get_conn()?;
+    let (music_count, music_duration): (i64, f64) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(duration), 0.0) FROM music",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let project_count: i64 = conn.query_row("SELECT COUNT(*) FROM projects", [], |row| row.get(0))?;
+    let segment_count: i64 = conn.query_row("SELECT COUNT(*) FROM segments", [], |row| row.get(0))?;
+    Ok((
+        music_count.max(0) as u64,
+        music_duration,
+        project_count.max(0) as u64,
+        segment_count.max(0) as u64,
+    ))
+}
+
+/// 数据库文件总大小（主文件 + WAL 文件）
+fn database_file_size(db_path: &Path) -> u64 {
+    let wal_path = db_path.with_extension(
+        format!("{}-wal", db_path.extension().and_then(|e| e.to_str()).unwrap_or("db"))
+    );
+    [db_path, wal_path.as_path()]
+        .iter()
+        .map(|p| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0))
+        .sum()
+}
+
+/// 优化数据库：更新查询统计、合并 WAL 并 VACUUM 回收空间
+/// 返回 (优化前大小, 优化后大小)
+pub fn optimize_database(db_path: &Path) -> AppResult<(u64, u64)> {
+    let conn = get_conn()?;
+    let size_before = database_file_size(db_path);
+    info!("[DB] 开始优化数据库: {} ({} bytes)", db_path.display(), size_before);
+
+    conn.execute_batch("PRAGMA optimize")?;
+    // 先将 WAL 内容写回主文件并截断，VACUUM 才能真正缩小文件（非 WAL 模式下为空操作）
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    conn.execute_batch("VACUUM")?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+
+    let size_after = database_file_size(db_path);
+    info!("[DB] 数据库优化完成: {} -> {} bytes", size_before, size_after);
+    Ok((size_before, size_after))
+}
+
 /// 清空所有数据（项目、片段、音乐），在事务中执行以保证原子性
 pub fn clear_all_data() -> AppResult<()> {
     info!("[DB] 清空所有数据");
     let mut conn = get_conn()?;
     let tx = conn.transaction()?;
     tx.execute("DELETE FROM segments", [])?;
+    tx.execute("DELETE FROM detection_boxes", [])?;
     tx.execute("DELETE FROM projects", [])?;
     tx.execute("DELETE FROM music", [])?;
     tx.commit()?;
@@ -720,6 +1268,7 @@ pub fn clear_all_projects() -> AppResult<()> {
     let mut conn = get_conn()?;
     let tx = conn.transaction()?;
     tx.execute("DELETE FROM segments", [])?;
+    tx.execute("DELETE FROM detection_boxes", [])?;
     tx.execute("DELETE FROM projects", [])?;
     tx.commit()?;
     Ok(())
@@ -752,10 +1301,11 @@ pub fn batch_insert_detection_segments(segments: &[Segment], segment_type: &str)
         let status = match segment.status {
             SegmentStatus::Detected => "detected",
             SegmentStatus::Removed => "removed",
+            SegmentStatus::Provisional => "provisional",
         };
         if let Err(e) = conn.execute(
-            "INSERT OR REPLACE INTO segments (id, project_id, music_id, start_time, end_time, confidence, status, segment_type)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT OR REPLACE INTO segments (id, project_id, music_id, start_time, end_time, confidence, status, segment_type, gain_db)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
             params![
                 segment.id,
                 segment.project_id,
@@ -765,6 +1315,7 @@ pub fn batch_insert_detection_segments(segments: &[Segment], segment_type: &str)
                 segment.confidence,
                 status,
                 segment_type,
+                segment.gain_db,
             ],
         ) {
             let _ = conn.execute_batch("ROLLBACK");
@@ -776,16 +1327,63 @@ pub fn batch_insert_detection_segments(segments: &[Segment], segment_type: &str)
     Ok(())
 }
 
+/// 批量写入检测框: (segment_id, boxes)，没有检测框的片段不写入
+pub fn batch_insert_detection_boxes(project_id: &str, boxes: &[(String, Vec<DetectionBox>)]) -> AppResult<()> {
+    let boxes: Vec<_> = boxes.iter().filter(|(_, b)| !b.is_empty()).collect();
+    if boxes.is_empty() {
+        return Ok(());
+    }
+    debug!("[DB] 批量写入检测框: {} 个片段, project_id={}", boxes.len(), project_id);
+    let conn = get_conn()?;
+    conn.execute_batch("BEGIN")?;
+    for (segment_id, segment_boxes) in boxes {
+        let json = match serde_json::to_string(segment_boxes) {
+            Ok(json) => json,
+            Err(e) => {
+                let _ = conn.execute_batch("ROLLBACK");
+                return Err(e.into());
+            }
+        };
+        if let Err(e) = conn.execute(
+            "INSERT OR REPLACE INTO detection_boxes (segment_id, project_id, boxes) VALUES (?1, ?2, ?3)",
+            params![segment_id, project_id, json],
+        ) {
+            let _ = conn.execute_batch("ROLLBACK");
+            return Err(AppError::Database(e));
+        }
+    }
+    conn.execute_batch("COMMIT")?;
+    Ok(())
+}
+
+/// 获取项目的检测框: segment_id -> boxes（JSON 损坏的记录记录警告后跳过）
+pub fn get_detection_boxes(project_id: &str) -> AppResult<HashMap<String, Vec<DetectionBox>>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT segment_id, boxes FROM detection_boxes WHERE project_id = ?1")?;
+    let rows = stmt
+        .query_map([project_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(segment_id, json)| match serde_json::from_str(&json) {
+            Ok(boxes) => Some((segment_id, boxes)),
+            Err(e) => {
+                warn!("[DB] 检测框 JSON 解析失败: segment_id={}, {}", segment_id, e);
+                None
+            }
+        })
+        .collect())
+}
+
 /// 获取项目的指定类型片段
 ///
 /// 按 start_time 排序返回，LEFT JOIN music 表获取音乐标题（仅 music 类型片段有值）。
-#[allow(dead_code)]
 pub fn get_segments_by_type(project_id: &str, segment_type: &str) -> AppResult<Vec<Segment>> {
     debug!("[DB] 查询片段: project_id={}, segment_type={}", project_id, segment_type);
     let conn = get_conn()?;
     let mut stmt = conn.prepare(
         "SELECT s.id, s.project_id, s.music_id, m.title,
-                s.start_time, s.end_time, s.confidence, s.status, s.segment_type
+                s.start_time, s.end_time, s.confidence, s.status, s.segment_type, s.gain_db
          FROM segments s
          LEFT JOIN music m ON s.music_id = m.id
          WHERE s.project_id = ?1 AND s.segment_type = ?2
@@ -796,6 +1394,7 @@ pub fn get_segments_by_type(project_id: &str, segment_type: &str) -> AppResult<V
         let status_str: String = row.get(7)?;
         let status = match status_str.as_str() {
             "removed" => SegmentStatus::Removed,
+            "provisional" => SegmentStatus::Provisional,
             _ => SegmentStatus::Detected,
         };
 
@@ -809,6 +1408,7 @@ pub fn get_segments_by_type(project_id: &str, segment_type: &str) -> AppResult<V
             confidence: row.get(6)?,
             status,
             segment_type: SegmentType::from_str(&row.get::<_, String>(8).unwrap_or_else(|_| "music".to_string())),
+            gain_db: row.get(9)?,
         })
     })?;
 
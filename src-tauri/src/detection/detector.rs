@@ -9,17 +9,18 @@
 // 3. 开发模式: python python/person-detector/main.py
 // 4. 系统 PATH: person-detector
 
-use crate::config::{DetectionConfig, AccelerationMode};
+use crate::config::{self, DetectionConfig, AccelerationMode};
 use crate::error::{AppError, AppResult};
 use crate::models;
-use crate::utils::hidden_command;
+use crate::utils::{StallWatchdog, hidden_command, is_gpu_oom_output};
+use crate::video::ffmpeg;
 use crate::commands::video::register_child_process;
 use std::process::Stdio;
 use std::io::{BufRead, BufReader};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::path::{Path, PathBuf};
-use tracing::{info, error, debug};
+use tracing::{info, error, debug, warn};
 
 /// person-detector 输出的检测结果（对应 JSON 文件结构）
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -32,6 +33,9 @@ pub struct DetectionResult {
     pub processed_frames: u64,
     /// 检测到人物的帧数
     pub detection_frames: u64,
+    /// 是否为取消时读取的部分结果（只覆盖已处理的开头部分）
+    #[serde(default)]
+    pub partial: bool,
 }
 
 /// 单个人物时间片段（由连续检测帧合并而成）
@@ -41,6 +45,46 @@ pub struct PersonSegment {
     pub end_time: f64,
     /// 片段内所有检测帧的最大置信度
     pub confidence: f64,
+    /// 片段内抽样的检测框（旧版 person-detector 不输出时为空）
+    #[serde(default)]
+    pub boxes: Vec<DetectionBox>,
+}
+
+/// 单帧检测框（取该帧置信度最高的人物）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DetectionBox {
+    /// 帧时间（秒）
+    pub time: f64,
+    /// [x1, y1, x2, y2]，按画面宽高归一化到 0-1
+    #[serde(rename = "box")]
+    pub bbox: [f64; 4],
+    pub confidence: f64,
+}
+
+/// 计算实际抽帧间隔（帧数）
+///
+/// 配置了 detection_interval_seconds 时按视频帧率换算（至少 1 帧），
+/// 无法获取帧率时回退到 frame_interval。
+fn resolve_frame_interval(config: &DetectionConfig, video_path: &str) -> u32 {
+    let Some(seconds) = config.detection_interval_seconds.filter(|s| s.is_finite() && *s > 0.0) else {
+        return config.frame_interval.max(1);
+    };
+
+    match ffmpeg::get_video_info(video_path) {
+        Ok(info) if info.fps > 0.0 => {
+            let frames = (seconds as f64 * info.fps).round().max(1.0) as u32;
+            info!("[DETECTOR] 抽帧间隔 {}s × {:.2}fps = 每 {} 帧", seconds, info.fps, frames);
+            frames
+        }
+        Ok(_) => {
+            warn!("[DETECTOR] 视频帧率无效，使用帧间隔配置: {}", config.frame_interval);
+            config.frame_interval.max(1)
+        }
+        Err(e) => {
+            warn!("[DETECTOR] 获取视频帧率失败，使用帧间隔配置: {} ({})", config.frame_interval, e);
+            config.frame_interval.max(1)
+        }
+    }
 }
 
 /// 进度回调类型：(progress: 0.0-1.0, message)
@@ -49,7 +93,8 @@ pub type ProgressCallback = Box<dyn Fn(f32, &str) + Send + Sync>;
 /// 执行人物检测
 ///
 /// 启动 person-detector 子进程，通过 stderr 读取进度，等待完成后解析输出 JSON。
-/// 支持通过 cancel_flag 中途取消。
+/// 支持通过 cancel_flag 中途取消：person-detector 会定期写入中间结果，
+/// 取消时若已有中间结果则返回 partial=true 的部分结果，否则返回 Cancelled。
 pub fn detect_persons(
     video_path: &str,
     output_dir: &str,
@@ -99,6 +144,9 @@ pub fn detect_persons(
     // 构建输出 JSON 路径（每个项目独立文件，避免并发冲突）
     let output_json = Path::new(output_dir).join(format!("{}_detection.json", project_id));
     info!("[DETECTOR] 输出 JSON: {}", output_json.display());
+    // 中间结果文件，清除上次残留，避免取消时读到旧结果
+    let partial_json = Path::new(output_dir).join(format!("{}_detection.partial.json", project_id));
+    let _ = std::fs::remove_file(&partial_json);
 
     // 确定设备参数
     let device = match acceleration {
@@ -108,139 +156,153 @@ pub fn detect_persons(
     };
 
     info!("[DETECTOR] 设备: {}", device);
+    let frame_interval = resolve_frame_interval(config, video_path);
     let args = vec![
         "--video_path".to_string(), video_path.to_string(),
         "--model_path".to_string(), model_path.to_string_lossy().to_string(),
         "--output_json".to_string(), output_json.to_string_lossy().to_string(),
         "--confidence".to_string(), config.confidence_threshold.to_string(),
-        "--frame_interval".to_string(), config.frame_interval.to_string(),
+        "--frame_interval".to_string(), frame_interval.to_string(),
         "--device".to_string(), device.to_string(),
         "--max_gap_duration".to_string(), config.max_gap_duration.to_string(),
         "--min_segment_duration".to_string(), config.min_segment_duration.to_string(),
     ];
+    // 旧版 person-detector 不认识 --partial_json，遇到 argparse 报错时去掉该参数重试一次
+    let mut pass_partial_json = true;
 
     let (program, script_args) = resolve_detector_command();
     info!("[DETECTOR] 程序: {}", program);
     if !script_args.is_empty() {
         info!("[DETECTOR] 脚本参数: {:?}", script_args);
     }
-    info!("[DETECTOR] 检测参数: {}", args.join(" "));
-
-    if let Some(ref cb) = progress_callback {
-        cb(0.05, "启动 person-detector...");
-    }
+    let (status, error_output) = loop {
+        let mut run_args = args.clone();
+        if pass_partial_json {
+            run_args.push("--partial_json".to_string());
+            run_args.push(partial_json.to_string_lossy().to_string());
+        }
+        info!("[DETECTOR] 检测参数: {}", run_args.join(" "));
 
-    // 构建命令
-    let mut cmd = hidden_command(&program);
-    // 开发模式下先加脚本路径参数，再加检测参数
-    cmd.args(&script_args)
-        .args(&args)
-        .stdout(Stdio::null())
-        .stderr(Stdio::piped());
-
-    // CPU 模式禁用 GPU
-    if device == "cpu" {
-        cmd.env("CUDA_VISIBLE_DEVICES", "-1");
-    }
+        if let Some(ref cb) = progress_callback {
+            cb(0.05, "启动 person-detector...");
+        }
 
-    let child = cmd.spawn().map_err(|e| {
-        error!("[DETECTOR] 启动 person-detector 失败: {}", e);
-        AppError::Detection(format!("启动 person-detector 失败: {}", e))
-    })?;
+        // 构建命令
+        let mut cmd = hidden_command(&program);
+        // 开发模式下先加脚本路径参数，再加检测参数
+        cmd.args(&script_args)
+            .args(&run_args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+
+        // CPU 模式禁用 GPU，否则按配置指定 GPU 序号
+        if device == "cpu" {
+            cmd.env("CUDA_VISIBLE_DEVICES", "-1");
+        } else if let Some(index) = config::get_config().gpu_device_index {
+            cmd.env("CUDA_VISIBLE_DEVICES", index.to_string());
+            info!("[DETECTOR] 已设置 CUDA_VISIBLE_DEVICES=\"{}\" 指定 GPU", index);
+        }
 
-    // 使用 det_ 前缀注册子进程，与人声分离的子进程管理隔离
-    let det_key = format!("det_{}", project_id);
-    info!("[DETECTOR] 子进程已启动，注册键: {}", det_key);
-    let child_handle = register_child_process(&det_key, child);
-
-    // 取出 stderr
-    let stderr = {
-        let mut child_guard = child_handle.lock().unwrap();
-        let child = child_guard.as_mut()
-            .ok_or_else(|| AppError::Detection("子进程句柄已被释放".into()))?;
-        child.stderr.take()
-            .ok_or_else(|| AppError::Detection("无法获取 person-detector 错误输出流".into()))?
-    };
+        let child = cmd.spawn().map_err(|e| {
+            error!("[DETECTOR] 启动 person-detector 失败: {}", e);
+            AppError::Detection(format!("启动 person-detector 失败: {}", e))
+        })?;
+
+        // 使用 det_ 前缀注册子进程，与人声分离的子进程管理隔离
+        let det_key = format!("det_{}", project_id);
+        info!("[DETECTOR] 子进程已启动，注册键: {}", det_key);
+        let child_handle = register_child_process(&det_key, "detect_persons", child);
+
+        // 取出 stderr
+        let stderr = {
+            let mut child_guard = child_handle.lock().unwrap();
+            let child = child_guard.as_mut()
+                .ok_or_else(|| AppError::Detection("子进程句柄已被释放".into()))?;
+            child.stderr.take()
+                .ok_or_else(|| AppError::Detection("无法获取 person-detector 错误输出流".into()))?
+        };
 
-    // 设置 stderr 非阻塞模式，以便在轮询循环中同时检查取消标志和进程状态
-    #[cfg(unix)]
-    {
-        use std::os::unix::io::AsRawFd;
-        let fd = stderr.as_raw_fd();
-        unsafe {
-            let flags = libc::fcntl(fd, libc::F_GETFL);
-            libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+        // 设置 stderr 非阻塞模式，以便在轮询循环中同时检查取消标志和进程状态
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+            let fd = stderr.as_raw_fd();
+            unsafe {
+                let flags = libc::fcntl(fd, libc::F_GETFL);
+                libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+            }
         }
-    }
 
-    #[cfg(windows)]
-    {
-        use std::os::windows::io::AsRawHandle;
-        use windows_sys::Win32::Foundation::HANDLE;
-        use windows_sys::Win32::System::Pipes::SetNamedPipeHandleState;
-        use windows_sys::Win32::System::Pipes::PIPE_NOWAIT;
-        let handle = stderr.as_raw_handle() as HANDLE;
-        unsafe {
-            let mut mode = PIPE_NOWAIT;
-            SetNamedPipeHandleState(handle, &mut mode, std::ptr::null_mut(), std::ptr::null_mut());
+        #[cfg(windows)]
+        {
+            use std::os::windows::io::AsRawHandle;
+            use windows_sys::Win32::Foundation::HANDLE;
+            use windows_sys::Win32::System::Pipes::SetNamedPipeHandleState;
+            use windows_sys::Win32::System::Pipes::PIPE_NOWAIT;
+            let handle = stderr.as_raw_handle() as HANDLE;
+            unsafe {
+                let mut mode = PIPE_NOWAIT;
+                SetNamedPipeHandleState(handle, &mut mode, std::ptr::null_mut(), std::ptr::null_mut());
+            }
         }
-    }
 
-    let mut reader = BufReader::new(stderr);
-    let mut error_output = String::new();
-    let mut line_buffer = String::new();
+        let mut reader = BufReader::new(stderr);
+        let mut error_output = String::new();
+        let mut line_buffer = String::new();
+        // 有新输出即视为有进展，长时间无输出判定为卡死
+        let mut watchdog = StallWatchdog::new(config::process_timeout());
 
-    // 轮询循环：读取 stderr 进度、检查取消标志、等待进程结束
-    loop {
-        if cancel_flag.load(Ordering::SeqCst) {
-            info!("[DETECTOR] 人物检测被取消: project_id={}", project_id);
-            if let Ok(mut guard) = child_handle.lock() {
-                if let Some(ref mut child) = *guard {
-                    let _ = child.kill();
-                    let _ = child.wait();
+        // 轮询循环：读取 stderr 进度、检查取消标志、等待进程结束
+        loop {
+            if cancel_flag.load(Ordering::SeqCst) {
+                info!("[DETECTOR] 人物检测被取消: project_id={}", project_id);
+                if let Ok(mut guard) = child_handle.lock() {
+                    if let Some(ref mut child) = *guard {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                    }
                 }
+                return match read_partial_result(&partial_json) {
+                    Some(result) => {
+                        info!(
+                            "[DETECTOR] 返回取消前的部分结果: {} 个人物片段, 处理帧数={}",
+                            result.segments.len(), result.processed_frames
+                        );
+                        Ok(result)
+                    }
+                    None => Err(AppError::Cancelled),
+                };
             }
-            return Err(AppError::Cancelled);
-        }
 
-        let try_wait_result = {
-            let mut guard = child_handle.lock().unwrap();
-            if let Some(ref mut child) = *guard {
-                child.try_wait()
-            } else {
-                return Err(AppError::Cancelled);
+            // 暂停（进程被挂起）不算卡死
+            if crate::commands::video::is_paused(&det_key) {
+                watchdog.touch();
             }
-        };
-
-        match try_wait_result {
-            Ok(Some(_)) => {
-                // 进程已结束，读取剩余输出
-                while let Ok(n) = reader.read_line(&mut line_buffer) {
-                    if n == 0 { break; }
-                    let line = line_buffer.trim_end();
-                    if !line.is_empty() {
-                        debug!("[DETECTOR] stderr: {}", line);
-                        if !error_output.is_empty() { error_output.push('\n'); }
-                        error_output.push_str(line);
-                        if line.contains('%') {
-                            if let Some(progress) = parse_progress(line) {
-                                if let Some(ref cb) = progress_callback {
-                                    cb(progress, line);
-                                }
-                            }
-                        }
+            if watchdog.is_stalled() {
+                error!("[DETECTOR] person-detector 超过 {} 秒无进展，终止进程: project_id={}", watchdog.timeout_secs(), project_id);
+                if let Ok(mut guard) = child_handle.lock() {
+                    if let Some(ref mut child) = *guard {
+                        let _ = child.kill();
+                        let _ = child.wait();
                     }
-                    line_buffer.clear();
                 }
-                break;
+                return Err(AppError::Timeout(format!("person-detector 超过 {} 秒无进展", watchdog.timeout_secs())));
             }
-            Ok(None) => {
-                // 进程仍在运行，尝试读取一行 stderr
-                match reader.read_line(&mut line_buffer) {
-                    Ok(0) => {
-                        std::thread::sleep(std::time::Duration::from_millis(100));
-                    }
-                    Ok(_) => {
+
+            let try_wait_result = {
+                let mut guard = child_handle.lock().unwrap();
+                if let Some(ref mut child) = *guard {
+                    child.try_wait()
+                } else {
+                    return Err(AppError::Cancelled);
+                }
+            };
+
+            match try_wait_result {
+                Ok(Some(_)) => {
+                    // 进程已结束，读取剩余输出
+                    while let Ok(n) = reader.read_line(&mut line_buffer) {
+                        if n == 0 { break; }
                         let line = line_buffer.trim_end();
                         if !line.is_empty() {
                             debug!("[DETECTOR] stderr: {}", line);
@@ -248,7 +310,6 @@ pub fn detect_persons(
                             error_output.push_str(line);
                             if line.contains('%') {
                                 if let Some(progress) = parse_progress(line) {
-                                    debug!("[DETECTOR] 检测进度: {:.1}%", progress * 100.0);
                                     if let Some(ref cb) = progress_callback {
                                         cb(progress, line);
                                     }
@@ -257,40 +318,78 @@ pub fn detect_persons(
                         }
                         line_buffer.clear();
                     }
-                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                        std::thread::sleep(std::time::Duration::from_millis(100));
-                    }
-                    Err(_) => {
-                        std::thread::sleep(std::time::Duration::from_millis(100));
+                    break;
+                }
+                Ok(None) => {
+                    // 进程仍在运行，尝试读取一行 stderr
+                    match reader.read_line(&mut line_buffer) {
+                        Ok(0) => {
+                            std::thread::sleep(std::time::Duration::from_millis(100));
+                        }
+                        Ok(_) => {
+                            let line = line_buffer.trim_end();
+                            if !line.is_empty() {
+                                watchdog.touch();
+                                debug!("[DETECTOR] stderr: {}", line);
+                                if !error_output.is_empty() { error_output.push('\n'); }
+                                error_output.push_str(line);
+                                if line.contains('%') {
+                                    if let Some(progress) = parse_progress(line) {
+                                        debug!("[DETECTOR] 检测进度: {:.1}%", progress * 100.0);
+                                        if let Some(ref cb) = progress_callback {
+                                            cb(progress, line);
+                                        }
+                                    }
+                                }
+                            }
+                            line_buffer.clear();
+                        }
+                        Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                            std::thread::sleep(std::time::Duration::from_millis(100));
+                        }
+                        Err(_) => {
+                            std::thread::sleep(std::time::Duration::from_millis(100));
+                        }
                     }
                 }
-            }
-            Err(e) => {
-                error!("[DETECTOR] 检查进程状态失败: {}", e);
-                if let Ok(mut guard) = child_handle.lock() {
-                    if let Some(ref mut child) = *guard {
-                        let _ = child.kill();
+                Err(e) => {
+                    error!("[DETECTOR] 检查进程状态失败: {}", e);
+                    if let Ok(mut guard) = child_handle.lock() {
+                        if let Some(ref mut child) = *guard {
+                            let _ = child.kill();
+                        }
                     }
+                    return Err(AppError::Detection(format!("检查进程状态失败: {}", e)));
                 }
-                return Err(AppError::Detection(format!("检查进程状态失败: {}", e)));
             }
         }
-    }
 
-    // 等待进程结束并获取退出码
-    info!("[DETECTOR] 等待 person-detector 进程结束...");
-    let status = {
-        let mut guard = child_handle.lock().unwrap();
-        if let Some(ref mut child) = *guard {
-            child.wait()?
-        } else {
-            return Err(AppError::Cancelled);
+        // 等待进程结束并获取退出码
+        info!("[DETECTOR] 等待 person-detector 进程结束...");
+        let status = {
+            let mut guard = child_handle.lock().unwrap();
+            if let Some(ref mut child) = *guard {
+                child.wait()?
+            } else {
+                return Err(AppError::Cancelled);
+            }
+        };
+        info!("[DETECTOR] person-detector 退出码: {:?}", status.code());
+
+        if !status.success() && pass_partial_json && is_unrecognized_partial_json(&error_output) {
+            warn!("[DETECTOR] person-detector 不支持 --partial_json，去掉该参数后重试");
+            pass_partial_json = false;
+            continue;
         }
+        break (status, error_output);
     };
-    info!("[DETECTOR] person-detector 退出码: {:?}", status.code());
 
     if !status.success() {
         error!("[DETECTOR] person-detector 处理失败，退出码: {:?}", status.code());
+        if device != "cpu" && is_gpu_oom_output(&error_output) {
+            let tail: String = error_output.chars().rev().take(500).collect::<Vec<_>>().into_iter().rev().collect();
+            return Err(AppError::GpuOutOfMemory(format!("person-detector: {}", tail)));
+        }
         let error_msg = if error_output.is_empty() {
             "person-detector 处理失败（无详细错误信息）".to_string()
         } else {
@@ -320,6 +419,27 @@ pub fn detect_persons(
     Ok(result)
 }
 
+/// 判断 stderr 是否为旧版 person-detector 的 argparse 未知参数报错（--partial_json）
+fn is_unrecognized_partial_json(output: &str) -> bool {
+    output.lines().any(|l| l.contains("unrecognized arguments") && l.contains("--partial_json"))
+}
+
+/// 读取取消时残留的中间结果，读取后删除文件；不存在或无法解析时返回 None
+fn read_partial_result(path: &Path) -> Option<DetectionResult> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let _ = std::fs::remove_file(path);
+    match serde_json::from_str::<DetectionResult>(&content) {
+        Ok(mut result) => {
+            result.partial = true;
+            Some(result)
+        }
+        Err(e) => {
+            warn!("[DETECTOR] 解析中间结果失败，忽略: {}", e);
+            None
+        }
+    }
+}
+
 /// 解析进度输出
 fn parse_progress(line: &str) -> Option<f32> {
     if let Some(pos) = line.find('%') {
@@ -373,3 +493,30 @@ pub fn resolve_detector_command() -> (String, Vec<String>) {
 fn find_project_root(exe_dir: &Path) -> Option<PathBuf> {
     exe_dir.ancestors().find(|p| p.join("src-tauri").exists()).map(|p| p.to_path_buf())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_unrecognized_partial_json() {
+        let old = "usage: main.py [-h] --video_path VIDEO_PATH\nmain.py: error: unrecognized arguments: --partial_json /tmp/p.json";
+        assert!(is_unrecognized_partial_json(old));
+        assert!(!is_unrecognized_partial_json("CUDA out of memory"));
+        assert!(!is_unrecognized_partial_json("main.py: error: unrecognized arguments: --foo 1"));
+    }
+
+    #[test]
+    fn test_detection_result_without_boxes() {
+        // 旧版 person-detector 输出不含 boxes / partial 字段
+        let json = r#"{"segments":[{"start_time":1.0,"end_time":3.5,"confidence":0.8}],"total_frames":100,"processed_frames":20,"detection_frames":8}"#;
+        let result: DetectionResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.segments.len(), 1);
+        assert!(result.segments[0].boxes.is_empty());
+        assert!(!result.partial);
+
+        let json = r#"{"segments":[{"start_time":1.0,"end_time":3.5,"confidence":0.8,"boxes":[{"time":1.2,"box":[0.1,0.2,0.5,0.9],"confidence":0.8}]}],"total_frames":100,"processed_frames":20,"detection_frames":8}"#;
+        let result: DetectionResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.segments[0].boxes[0].bbox, [0.1, 0.2, 0.5, 0.9]);
+    }
+}
@@ -38,6 +38,12 @@ pub enum AppError {
     #[error("任务已取消")]
     Cancelled,
 
+    #[error("进程超时: {0}")]
+    Timeout(String),
+
+    #[error("GPU 显存不足: {0}")]
+    GpuOutOfMemory(String),
+
     #[error("未找到: {0}")]
     NotFound(String),
 
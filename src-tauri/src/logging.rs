@@ -1,33 +1,47 @@
 // MusicCut - 日志管理模块
 // 提供异步非阻塞的日志持久化功能
+//
+// 除共享的 musiccut.log 外，可选将单个任务（按项目 ID）的日志同时写入
+// logs/{project_id}_{op}.log，便于问题反馈时只附带相关日志。
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::io::Write;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Span, Subscriber};
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{
     fmt,
-    layer::SubscriberExt,
+    layer::{Context, Layer, SubscriberExt},
+    registry::LookupSpan,
     util::SubscriberInitExt,
     EnvFilter,
 };
-use crate::config::{AppConfig, LogLevel};
+use crate::config::{self, AppConfig, LogLevel};
 
-/// 日志保留天数
-const LOG_RETENTION_DAYS: u64 = 7;
+/// 从配置文件读取原始配置（日志初始化早于配置加载，直接解析 config.json）
+fn read_raw_config(app_data_dir: &Path) -> Option<AppConfig> {
+    let content = fs::read_to_string(app_data_dir.join("config.json")).ok()?;
+    serde_json::from_str::<AppConfig>(&content).ok()
+}
 
 /// 从配置文件读取日志级别
 fn read_log_level_from_config(app_data_dir: &Path) -> LogLevel {
-    let config_path = app_data_dir.join("config.json");
-
-    if let Ok(content) = fs::read_to_string(&config_path) {
-        if let Ok(config) = serde_json::from_str::<AppConfig>(&content) {
-            return config.log_level;
-        }
-    }
-
     // 默认 info 级别
-    LogLevel::default()
+    read_raw_config(app_data_dir)
+        .map(|config| config.log_level)
+        .unwrap_or_default()
+}
+
+/// 从配置文件读取日志保留天数（无效值回退到默认 7 天）
+fn read_log_retention_days_from_config(app_data_dir: &Path) -> u64 {
+    read_raw_config(app_data_dir)
+        .map(|config| config.log_retention_days)
+        .filter(|days| *days >= 1)
+        .unwrap_or(config::DEFAULT_LOG_RETENTION_DAYS) as u64
 }
 
 /// 初始化日志系统
@@ -42,7 +56,7 @@ pub fn init_logging(app_data_dir: &Path) -> WorkerGuard {
     }
 
     // 清理旧日志文件
-    cleanup_old_logs(&log_dir);
+    cleanup_old_logs(&log_dir, read_log_retention_days_from_config(app_data_dir));
 
     // 按天轮转日志文件
     let file_appender = RollingFileAppender::new(
@@ -77,6 +91,9 @@ pub fn init_logging(app_data_dir: &Path) -> WorkerGuard {
         .with_target(true);      // 记录模块路径
 
     // 控制台日志层 - 仅在 debug 模式下启用
+    // 单任务日志层 - 将 operation span 内的日志同时写入独立文件
+    let operation_layer = OperationLogLayer { log_dir: log_dir.clone() };
+
     #[cfg(debug_assertions)]
     {
         let console_layer = fmt::layer()
@@ -90,6 +107,7 @@ pub fn init_logging(app_data_dir: &Path) -> WorkerGuard {
         tracing_subscriber::registry()
             .with(env_filter)
             .with(file_layer)
+            .with(operation_layer)
             .with(console_layer)
             .init();
     }
@@ -99,6 +117,7 @@ pub fn init_logging(app_data_dir: &Path) -> WorkerGuard {
         tracing_subscriber::registry()
             .with(env_filter)
             .with(file_layer)
+            .with(operation_layer)
             .init();
     }
 
@@ -106,9 +125,9 @@ pub fn init_logging(app_data_dir: &Path) -> WorkerGuard {
 }
 
 /// 清理超过保留期限的旧日志文件
-fn cleanup_old_logs(log_dir: &Path) {
+fn cleanup_old_logs(log_dir: &Path, retention_days: u64) {
     let now = std::time::SystemTime::now();
-    let retention_duration = std::time::Duration::from_secs(LOG_RETENTION_DAYS * 24 * 60 * 60);
+    let retention_duration = std::time::Duration::from_secs(retention_days * 24 * 60 * 60);
 
     let entries = match fs::read_dir(log_dir) {
         Ok(entries) => entries,
@@ -128,8 +147,8 @@ fn cleanup_old_logs(log_dir: &Path) {
             None => continue,
         };
 
-        // 只清理 musiccut.log 相关文件
-        if !file_name.starts_with("musiccut.log") {
+        // 只清理 musiccut.log 相关文件和单任务日志（*.log）
+        if !file_name.starts_with("musiccut.log") && !file_name.ends_with(".log") {
             continue;
         }
 
@@ -154,3 +173,122 @@ fn cleanup_old_logs(log_dir: &Path) {
         }
     }
 }
+
+/// 单任务日志 span 名称
+const OPERATION_SPAN_NAME: &str = "operation";
+
+/// 创建单任务日志 span
+///
+/// 配置开启 operation_logs 时，span 内（同一线程）产生的日志会同时写入
+/// logs/{project_id}_{op}.log；未开启时返回禁用的 span，无额外开销。
+/// 用法: `logging::operation_span(&project_id, "export").in_scope(|| ...)`
+pub fn operation_span(project_id: &str, op: &str) -> Span {
+    if !config::get_config().operation_logs {
+        return Span::none();
+    }
+    tracing::info_span!("operation", project_id = %project_id, op = %op)
+}
+
+/// 挂在 operation span 上的日志文件句柄
+struct OperationLogFile(Mutex<fs::File>);
+
+/// 单任务日志层
+struct OperationLogLayer {
+    log_dir: PathBuf,
+}
+
+/// 读取 operation span 的 project_id / op 字段
+#[derive(Default)]
+struct OperationFields {
+    project_id: Option<String>,
+    op: Option<String>,
+}
+
+impl Visit for OperationFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "project_id" => self.project_id = Some(value.to_string()),
+            "op" => self.op = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        // `%value` 形式的字段以 Display 包装后经 record_debug 传入
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+/// 将事件字段格式化为单行文本（message 在前，其余字段以 key=value 追加）
+#[derive(Default)]
+struct EventText(String);
+
+impl Visit for EventText {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.0.insert_str(0, &format!("{:?}", value));
+        } else {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
+
+/// 文件名中的非法字符替换为下划线
+fn sanitize_log_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+impl<S> Layer<S> for OperationLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != OPERATION_SPAN_NAME {
+            return;
+        }
+
+        let mut fields = OperationFields::default();
+        attrs.record(&mut fields);
+        let (Some(project_id), Some(op)) = (fields.project_id, fields.op) else { return };
+
+        let path = self.log_dir.join(format!(
+            "{}_{}.log",
+            sanitize_log_name(&project_id),
+            sanitize_log_name(&op)
+        ));
+        match fs::OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => {
+                if let Some(span) = ctx.span(id) {
+                    span.extensions_mut().insert(OperationLogFile(Mutex::new(file)));
+                }
+            }
+            Err(e) => eprintln!("创建单任务日志文件失败 {:?}: {}", path, e),
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(scope) = ctx.event_scope(event) else { return };
+
+        for span in scope {
+            let extensions = span.extensions();
+            let Some(log_file) = extensions.get::<OperationLogFile>() else { continue };
+
+            let mut text = EventText::default();
+            event.record(&mut text);
+            let metadata = event.metadata();
+            let line = format!(
+                "{} {:>5} {}: {}\n",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                metadata.level(),
+                metadata.target(),
+                text.0
+            );
+            if let Ok(mut file) = log_file.0.lock() {
+                let _ = file.write_all(line.as_bytes());
+            }
+            break;
+        }
+    }
+}
@@ -127,6 +127,12 @@ fn main() {
                 warn!("创建临时目录失败: {}", e);
             }
 
+            // 清理过期的可恢复导出工作目录
+            commands::video::purge_export_work_dirs(&app_dir, None, Some(commands::video::EXPORT_WORK_DIR_MAX_AGE));
+
+            // 缓存超过 max_cache_bytes 时清理最旧的中间文件
+            commands::config::enforce_cache_limit(&app_dir);
+
             // 创建缩略图目录
             let thumbnails_dir = app_dir.join("thumbnails");
             if let Err(e) = std::fs::create_dir_all(&thumbnails_dir) {
@@ -186,27 +192,44 @@ fn main() {
             commands::system::get_system_info,
             commands::system::get_gpu_info,
             commands::system::check_dependencies,
+            commands::system::get_ffmpeg_capabilities,
+            commands::system::run_self_test,
+            commands::system::export_diagnostics,
+            commands::system::open_in_file_manager,
 
             // 配置命令
             commands::config::get_config,
             commands::config::update_config,
             commands::config::get_acceleration_options,
             commands::config::get_storage_info,
+            commands::config::get_stats,
             commands::config::clear_cache,
+            commands::config::auto_clean_cache,
             commands::config::reset_database,
+            commands::config::optimize_database,
             commands::config::reset_config,
 
             // 音乐库命令
             commands::library::import_music_folder,
             commands::library::import_music_files,
             commands::library::get_music_library,
+            commands::library::get_music_library_page,
+            commands::library::count_music,
             commands::library::delete_music,
             commands::library::delete_all_music,
+            commands::library::delete_music_bulk,
+            commands::library::delete_music_where,
+            commands::library::get_fingerprint_status,
+            commands::library::reextract_fingerprints,
             commands::library::search_music,
             commands::library::get_music_info,
+            commands::library::get_music_usage,
+            commands::library::find_unused_music,
 
             // 指纹命令
             commands::fingerprint::extract_fingerprint,
+            commands::fingerprint::extract_fingerprint_raw,
+            commands::fingerprint::get_fingerprint,
             commands::fingerprint::match_fingerprint,
             commands::fingerprint::batch_extract_fingerprints,
 
@@ -219,37 +242,69 @@ fn main() {
             commands::video::cut_video,
             commands::video::export_video,
             commands::video::export_video_separately,
+            commands::video::clear_export_work_dirs,
+            commands::video::batch_export_projects,
+            commands::video::preview_export,
             commands::video::export_custom_clip,
             commands::video::export_custom_clips_merged,
             commands::video::export_custom_clips_separately,
             commands::video::get_video_thumbnail,
             commands::video::cancel_processing,
+            commands::video::pause_processing,
+            commands::video::resume_processing,
+            commands::video::get_active_jobs,
+            commands::video::list_child_processes,
+            commands::video::kill_child_process,
             commands::video::cancel_preview_generation,
             commands::video::check_needs_preview,
             commands::video::generate_preview_video,
+            commands::video::preview_segment,
+            commands::video::get_segment_audio_preview,
 
             // 项目命令
             commands::project::create_project,
             commands::project::save_project,
             commands::project::load_project,
+            commands::project::export_project_file,
+            commands::project::import_project_file,
             commands::project::get_projects,
             commands::project::delete_project,
             commands::project::delete_all_projects,
             commands::project::update_segments,
+            commands::project::set_autosave,
+            commands::project::autosave_segments,
+            commands::project::get_segments_by_type,
             commands::project::update_project_preview,
+            commands::project::set_project_thumbnail,
+            commands::project::snap_segments_to_keyframes,
+            commands::project::merge_adjacent_segments,
+            commands::project::merge_project_segments,
+            commands::project::split_segment_at_silence,
             commands::project::scan_video_files,
             commands::project::batch_create_projects,
 
             // 模型命令
             commands::models::get_available_models,
             commands::models::get_models_status,
+            commands::models::get_model_storage,
             commands::models::check_model_downloaded,
             commands::models::get_model_info,
             commands::models::download_model,
+            commands::models::cancel_model_download,
 
             // 人物检测命令
             commands::detection::detect_persons,
+            commands::detection::batch_detect_persons,
             commands::detection::cancel_detection,
+            commands::detection::get_detection_boxes,
+
+            // 媒体分析命令
+            commands::analysis::detect_silence,
+            commands::analysis::detect_scene_changes,
+            commands::analysis::detect_black_frames,
+            commands::analysis::get_keyframes,
+            commands::analysis::generate_spectrogram,
+            commands::analysis::detect_beats,
         ])
         .run(tauri::generate_context!())
         .unwrap_or_else(|e| {
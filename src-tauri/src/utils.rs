@@ -1,9 +1,12 @@
 // 工具模块
 
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
 use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
+use crate::config::MatchConfig;
+use crate::error::{AppError, AppResult};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
@@ -26,6 +29,135 @@ pub fn hidden_command(program: &str) -> Command {
     Command::new(program)
 }
 
+/// 挂起子进程（暂停任务而不丢失进度）
+///
+/// Unix 发送 SIGSTOP；Windows 逐个挂起进程内的线程。
+pub fn suspend_process(child: &Child) -> std::io::Result<()> {
+    set_process_suspended(child.id(), true)
+}
+
+/// 恢复被挂起的子进程
+pub fn resume_process(child: &Child) -> std::io::Result<()> {
+    set_process_suspended(child.id(), false)
+}
+
+#[cfg(unix)]
+fn set_process_suspended(pid: u32, suspend: bool) -> std::io::Result<()> {
+    let signal = if suspend { libc::SIGSTOP } else { libc::SIGCONT };
+    // SAFETY: 调用 POSIX kill 向子进程发送 SIGSTOP/SIGCONT
+    // - 仅传入整数参数，不涉及指针或内存访问
+    // - pid 失效时返回错误并由 last_os_error 上报，不会导致未定义行为
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+fn set_process_suspended(pid: u32, suspend: bool) -> std::io::Result<()> {
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::Threading::{OpenThread, ResumeThread, SuspendThread, THREAD_SUSPEND_RESUME};
+
+    // SAFETY: 调用 Windows ToolHelp/线程 API 挂起或恢复目标进程的所有线程
+    // - THREADENTRY32 是纯数据结构，全零初始化合法，调用前已按要求设置 dwSize
+    // - entry 在整个遍历期间有效，仅以可变引用传给 Thread32First/Thread32Next
+    // - 快照与线程句柄均在使用后 CloseHandle，句柄无效时跳过，不会重复释放
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut entry: THREADENTRY32 = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+        let mut has_entry = Thread32First(snapshot, &mut entry) != 0;
+        while has_entry {
+            if entry.th32OwnerProcessID == pid {
+                let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+                if thread != 0 {
+                    if suspend {
+                        SuspendThread(thread);
+                    } else {
+                        ResumeThread(thread);
+                    }
+                    CloseHandle(thread);
+                }
+            }
+            has_entry = Thread32Next(snapshot, &mut entry) != 0;
+        }
+        CloseHandle(snapshot);
+    }
+    Ok(())
+}
+
+/// 操作临时目录的类型（目录名为 {id}_{类型}_{随机后缀}）
+const OPERATION_TEMP_KINDS: &[&str] = &["match", "concat", "fingerprint"];
+
+/// 名称是否为 create_operation_temp_dir 创建的操作临时目录（{uuid}_{类型}_*）
+///
+/// 配置的 cache_dir 中只清理此类目录，不会误删其他文件。
+pub fn is_operation_temp_name(name: &str) -> bool {
+    let Some((owner, rest)) = name.split_once('_') else {
+        return false;
+    };
+    uuid::Uuid::parse_str(owner).is_ok()
+        && OPERATION_TEMP_KINDS.iter().any(|kind| {
+            rest.strip_prefix(kind).is_some_and(|tail| tail.starts_with('_'))
+        })
+}
+
+/// 在应用缓存目录（可通过 cache_dir 配置）下创建操作临时目录，离开作用域时自动删除
+///
+/// 不使用系统临时目录：受限系统上系统 temp 可能不可写或空间不足。
+/// prefix 为 {project_id}_{类型}_（类型见 OPERATION_TEMP_KINDS），缓存清理会在项目任务进行中跳过该目录。
+/// 创建失败时返回包含目录路径与剩余空间的错误信息。
+pub fn create_operation_temp_dir(prefix: &str) -> AppResult<tempfile::TempDir> {
+    create_operation_temp_dir_in(&crate::config::app_temp_dir(), prefix)
+}
+
+fn create_operation_temp_dir_in(root: &Path, prefix: &str) -> AppResult<tempfile::TempDir> {
+    std::fs::create_dir_all(root)
+        .and_then(|_| tempfile::Builder::new().prefix(prefix).tempdir_in(root))
+        .map_err(|e| {
+            let free = available_space(root)
+                .map(|bytes| format!("{:.1} MB", bytes as f64 / 1024.0 / 1024.0))
+                .unwrap_or_else(|| "未知".to_string());
+            AppError::Video(format!("无法创建临时目录 {}: {}（可用空间 {}）", root.display(), e, free))
+        })
+}
+
+/// 查询路径所在磁盘的可用空间（字节），查询失败返回 None
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // statvfs 字段类型因平台而异
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: libc::statvfs 是纯数据结构，全零初始化合法，随后由 statvfs 填充
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path 是以 NUL 结尾的有效 C 字符串，stat 是有效的可写结构体，调用期间均保持存活
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// 查询路径所在磁盘的可用空间（字节），查询失败返回 None
+#[cfg(windows)]
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut free: u64 = 0;
+    // SAFETY: wide 是以 0 结尾的 UTF-16 路径，free 是有效的可写 u64，其余输出参数允许为空指针
+    if unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut free, std::ptr::null_mut(), std::ptr::null_mut()) } == 0 {
+        return None;
+    }
+    Some(free)
+}
+
 /// 应用状态
 pub struct AppState {
     pub db_path: PathBuf,
@@ -48,6 +180,20 @@ pub struct MusicInfo {
     pub file_exists: bool,
 }
 
+/// 音乐使用情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MusicUsage {
+    pub music_id: String,
+    /// 引用该音乐的片段数
+    pub segment_count: u64,
+    /// 引用该音乐的项目数
+    pub project_count: u64,
+    /// 最近使用该音乐的项目（按项目更新时间）
+    pub last_project_id: Option<String>,
+    pub last_project_name: Option<String>,
+    pub last_used_at: Option<String>,
+}
+
 fn default_file_exists() -> bool {
     true
 }
@@ -66,6 +212,9 @@ pub struct VideoInfo {
     pub bitrate: u64,
     pub size: u64,
     pub format: String,
+    /// 音频流数量（多音轨视频可按序号选择）
+    #[serde(default)]
+    pub audio_stream_count: u32,
 }
 
 /// 片段类型
@@ -115,14 +264,19 @@ pub struct Segment {
     pub status: SegmentStatus,
     #[serde(default)]
     pub segment_type: SegmentType,
+    /// 导出时的音量增益（dB），None 表示不调整
+    #[serde(default)]
+    pub gain_db: Option<f64>,
 }
 
 /// 片段状态
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum SegmentStatus {
     Detected,
     Removed,
+    /// 低于置信度阈值的候选片段，等待用户确认（确认后改为 Detected，拒绝改为 Removed）
+    Provisional,
 }
 
 /// 项目信息
@@ -134,6 +288,9 @@ pub struct Project {
     /// 预览视频路径（用于播放不支持的格式，如 FLV）
     #[serde(default)]
     pub preview_video_path: Option<String>,
+    /// 缩略图路径（创建项目时生成）
+    #[serde(default)]
+    pub thumbnail_path: Option<String>,
     pub video_info: VideoInfo,
     pub segments: Vec<Segment>,
     pub created_at: String,
@@ -141,6 +298,9 @@ pub struct Project {
     /// 源视频文件是否存在
     #[serde(default = "default_file_exists")]
     pub file_exists: bool,
+    /// 最近一次音乐匹配实际使用的参数（含调用时覆盖与自适应窗口结果），未匹配过为 None
+    #[serde(default)]
+    pub match_config: Option<MatchConfig>,
 }
 
 /// 匹配结果
@@ -170,6 +330,122 @@ pub struct CutParams {
     pub force_reencode: Option<bool>,
 }
 
+/// 导出音轨来源
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioSource {
+    /// 原始音轨
+    #[default]
+    Original,
+    /// 人声分离结果中的人声
+    Vocals,
+    /// 人声分离结果中的伴奏
+    Instrumental,
+}
+
+/// 音乐匹配使用的音频来源
+///
+/// 伴奏去除了人声干扰，匹配准确率最高，但需要先执行耗时的人声分离；
+/// 混合音轨可跳过分离，适合音乐占主导的视频，人声或解说较多时置信度会明显下降、漏检增多。
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchAudioSource {
+    /// 人声分离后的伴奏
+    #[default]
+    Accompaniment,
+    /// 直接提取的原始混合音轨
+    Mixed,
+}
+
+/// 合并导出时的片段拼接顺序
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentOrder {
+    /// 按时间顺序（合并重叠片段）
+    #[default]
+    Chronological,
+    /// 按置信度从高到低
+    ByConfidenceDesc,
+    /// 按指定片段 ID 顺序，未列出的片段不导出
+    Custom(Vec<String>),
+}
+
+/// 合并导出保留的内容
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportSelection {
+    /// 保留匹配的片段
+    #[default]
+    Matched,
+    /// 保留被剪掉的部分（未匹配片段之外的时间段）
+    Inverse,
+    /// 同时导出两者，反向结果写入 {文件名}_inverse.{扩展名}
+    Both,
+}
+
+/// 合并导出时片段之间间隙的处理方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GapFill {
+    /// 删除间隙，片段直接拼接
+    #[default]
+    Remove,
+    /// 以等长黑屏（静音）填充，保持原始时间轴
+    Black,
+    /// 以间隙起点的定格画面（静音）填充，保持原始时间轴
+    Freeze,
+}
+
+/// 导出输出容器（与视频编码独立选择）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputContainer {
+    Mp4,
+    Mov,
+    /// Matroska：支持章节与多音轨，不需要 faststart
+    Mkv,
+}
+
+impl OutputContainer {
+    /// 按输出文件扩展名推断容器，无法识别时返回 None
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "mp4" | "m4v" => Some(Self::Mp4),
+            "mov" => Some(Self::Mov),
+            "mkv" => Some(Self::Mkv),
+            _ => None,
+        }
+    }
+
+    /// 文件扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Mov => "mov",
+            Self::Mkv => "mkv",
+        }
+    }
+
+    /// ffmpeg 输出格式名（-f）
+    pub fn muxer(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Mov => "mov",
+            Self::Mkv => "matroska",
+        }
+    }
+
+    /// 容器能否封装该视频编码（ffprobe codec_name）
+    pub fn supports_video_codec(self, codec: &str) -> bool {
+        match self {
+            Self::Mp4 => matches!(codec, "h264" | "hevc" | "h265" | "av1" | "vp9" | "mpeg4"),
+            Self::Mov => matches!(codec, "h264" | "hevc" | "h265" | "prores" | "mpeg4" | "mjpeg"),
+            Self::Mkv => true,
+        }
+    }
+}
+
 /// GPU 信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpuInfo {
@@ -202,6 +478,8 @@ pub struct DependencyCheck {
     pub version: Option<String>,
     pub path: Option<String>,
     pub message: String,
+    /// 已安装但版本过旧等问题的警告
+    pub warning: Option<String>,
 }
 
 /// 加速选项
@@ -231,6 +509,74 @@ pub fn lock_or_recover<'a, T>(mutex: &'a Mutex<T>, label: &str) -> MutexGuard<'a
     })
 }
 
+/// 子进程停滞看门狗
+///
+/// 轮询子进程时，每当进度推进调用 `touch()` 重置计时；
+/// 超过 timeout 仍无进展则 `is_stalled()` 返回 true，由调用方终止进程。
+/// timeout 为 None 时永不超时。
+pub struct StallWatchdog {
+    timeout: Option<Duration>,
+    last_progress: Instant,
+}
+
+impl StallWatchdog {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self { timeout, last_progress: Instant::now() }
+    }
+
+    /// 记录一次进展，重置计时
+    pub fn touch(&mut self) {
+        self.last_progress = Instant::now();
+    }
+
+    /// 是否已超过 timeout 无进展
+    pub fn is_stalled(&self) -> bool {
+        self.timeout.is_some_and(|t| self.last_progress.elapsed() > t)
+    }
+
+    /// 超时时长（秒），用于日志和错误信息
+    pub fn timeout_secs(&self) -> u64 {
+        self.timeout.map(|t| t.as_secs()).unwrap_or(0)
+    }
+}
+
+/// GPU 显存不足时子进程 stderr 中的特征字符串（小写，CUDA / ONNX Runtime / PyTorch）
+const GPU_OOM_SIGNATURES: &[&str] = &[
+    "out of memory",
+    "cuda_error_out_of_memory",
+    "cudaerrormemoryallocation",
+    "cublas_status_alloc_failed",
+    // ONNX Runtime: BFCArena::AllocateRawInternal Failed to allocate memory for requested buffer of size ...
+    "failed to allocate memory for requested buffer",
+    // ONNX Runtime: Available memory of N is smaller than requested bytes of M
+    "is smaller than requested bytes",
+];
+
+/// 判断子进程错误输出是否为 GPU 显存不足
+pub fn is_gpu_oom_output(output: &str) -> bool {
+    let lower = output.to_lowercase();
+    GPU_OOM_SIGNATURES.iter().any(|sig| lower.contains(sig))
+}
+
+/// ffmpeg/ffprobe 可直接读取的网络输入协议
+const STREAM_URL_SCHEMES: &[&str] = &[
+    "http://", "https://",
+    "rtmp://", "rtmps://",
+    "rtsp://", "srt://", "udp://",
+];
+
+/// 判断输入是否为网络 URL（而非本地文件路径）
+pub fn is_url(input: &str) -> bool {
+    let lower = input.trim().to_lowercase();
+    STREAM_URL_SCHEMES.iter().any(|scheme| lower.starts_with(scheme))
+}
+
+/// 检查媒体输入是否可用
+/// URL 直接交给 ffmpeg 处理（不做存在性检查），本地路径检查文件是否存在
+pub fn media_input_exists(input: &str) -> bool {
+    is_url(input) || std::path::Path::new(input).exists()
+}
+
 /// 获取可执行文件所在目录
 pub fn get_exe_dir() -> Option<PathBuf> {
     std::env::current_exe().ok()?.parent().map(|p| p.to_path_buf())
@@ -253,3 +599,49 @@ pub fn resolve_tool_path(tool_name: &str) -> String {
     // 回退到系统 PATH
     tool_name.to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_gpu_oom_output() {
+        assert!(is_gpu_oom_output("RuntimeError: CUDA out of memory. Tried to allocate 2.00 GiB"));
+        assert!(is_gpu_oom_output(
+            "[E:onnxruntime:, sequential_executor.cc:514] BFCArena::AllocateRawInternal Failed to allocate memory for requested buffer of size 1073741824"
+        ));
+        assert!(is_gpu_oom_output("Available memory of 0 is smaller than requested bytes of 268435456"));
+        // BFCArena 的普通日志（如扩展内存池）不是显存不足
+        assert!(!is_gpu_oom_output("[I:onnxruntime:, bfc_arena.cc:29 BFCArena] Creating BFCArena for Cuda with initial_chunk_size_bytes: 1048576"));
+        assert!(!is_gpu_oom_output("[I:onnxruntime:, bfc_arena.cc:347 AllocateRawInternal] Extending BFCArena for Cuda. bin_num:0"));
+    }
+
+    #[test]
+    fn test_create_operation_temp_dir_error_message() {
+        // 以普通文件作为缓存根目录，创建必然失败
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let root = file.path().join("cache");
+        let err = create_operation_temp_dir_in(&root, "p1_match_").unwrap_err();
+        let AppError::Video(msg) = &err else { panic!("应返回 Video 错误: {:?}", err) };
+        assert!(msg.contains("无法创建临时目录"), "{}", msg);
+        assert!(msg.contains(&root.display().to_string()), "{}", msg);
+        assert!(msg.contains("可用空间"), "{}", msg);
+
+        let dir = tempfile::tempdir().unwrap();
+        let op_dir = create_operation_temp_dir_in(dir.path(), "p1_match_").unwrap();
+        assert!(op_dir.path().starts_with(dir.path()));
+    }
+
+    #[test]
+    fn test_is_operation_temp_name() {
+        let id = "0f8fad5b-d9cb-469f-a165-70867728950e";
+        assert!(is_operation_temp_name(&format!("{}_match_a1B2c3", id)));
+        assert!(is_operation_temp_name(&format!("{}_concat_xyz", id)));
+        assert!(is_operation_temp_name(&format!("{}_fingerprint_Qw3", id)));
+        // 项目缓存与用户文件不属于操作临时目录
+        assert!(!is_operation_temp_name(&format!("{}_audio.wav", id)));
+        assert!(!is_operation_temp_name(&format!("{}_matches", id)));
+        assert!(!is_operation_temp_name("holiday_match_2024.mp4"));
+        assert!(!is_operation_temp_name("Videos"));
+    }
+}
@@ -7,29 +7,50 @@
 // - cut_video_segments / export_video: 按片段剪辑/导出视频
 // - extract_thumbnail: 提取视频缩略图
 // - generate_preview_video: 生成浏览器兼容的预览视频
+// - detect_silence: 静音区间检测（silencedetect）
+// - detect_scene_changes: 场景切换检测（select + showinfo）
+// - detect_black_frames: 黑屏/静止画面检测（blackdetect + freezedetect）
+// - get_capabilities: 探测 ffmpeg 支持的格式与解码器（按会话缓存）
 //
 // 所有日志统一使用 [FFMPEG] 前缀。
 
+use crate::config;
+use crate::detection::detector::DetectionBox;
 use crate::error::{AppError, AppResult};
-use crate::utils::{VideoInfo, Segment, SegmentStatus, SegmentType, resolve_tool_path, hidden_command};
-use tracing::{error, info};
+use crate::utils::{VideoInfo, Segment, SegmentOrder, SegmentStatus, SegmentType, GapFill, OutputContainer, StallWatchdog, create_operation_temp_dir, resolve_tool_path, hidden_command, is_url};
+use tracing::{error, info, warn};
 use std::process::Stdio;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::Mutex;
+use std::sync::{Mutex, OnceLock};
 use std::path::Path;
 use std::fs;
 use regex::Regex;
 use rayon::prelude::*;
+use sha2::{Digest, Sha256};
 
 // 静态正则表达式，避免重复编译
 lazy_static::lazy_static! {
     static ref TIME_REGEX: Regex = Regex::new(r"out_time_ms=(\d+)").unwrap();
+    // silencedetect 输出解析
+    static ref SILENCE_START_REGEX: Regex = Regex::new(r"silence_start:\s*(-?[\d.]+)").unwrap();
+    static ref SILENCE_END_REGEX: Regex = Regex::new(r"silence_end:\s*(-?[\d.]+)").unwrap();
+    // showinfo 输出解析
+    static ref SHOWINFO_PTS_REGEX: Regex = Regex::new(r"pts_time:\s*(-?[\d.]+)").unwrap();
+    // blackdetect / freezedetect 输出解析
+    static ref BLACK_REGEX: Regex = Regex::new(r"black_start:\s*(-?[\d.]+)\s+black_end:\s*(-?[\d.]+)").unwrap();
+    static ref FREEZE_START_REGEX: Regex = Regex::new(r"freeze_start:\s*(-?[\d.]+)").unwrap();
+    static ref FREEZE_END_REGEX: Regex = Regex::new(r"freeze_end:\s*(-?[\d.]+)").unwrap();
+    // `ffmpeg -formats` 表格行：D/E 标志列（新版多一列设备标志 d）后跟名称
+    static ref FORMAT_ROW_REGEX: Regex = Regex::new(r"^ ([D ])([E ])([d ]?) +(\S+)").unwrap();
     // 缓存检测到的硬件编码器
     static ref HW_ENCODER_CACHE: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
 }
 
+/// 缓存 ffmpeg 支持的格式与解码器（成功探测后整个会话复用）
+static CAPABILITIES_CACHE: OnceLock<FfmpegCapabilities> = OnceLock::new();
+
 /// 检测可用的 FFmpeg 硬件编码器
 /// 优先级: NVENC (NVIDIA) > AMF (AMD) > QSV (Intel) > 软件编码
 fn detect_hw_encoder() -> Option<String> {
@@ -75,37 +96,433 @@ fn detect_hw_encoder() -> Option<String> {
 /// 进度回调类型
 pub type ProgressCallback = Box<dyn Fn(f32) + Send + Sync>;
 
-/// 浏览器原生支持的视频编解码器
-const BROWSER_SUPPORTED_VIDEO_CODECS: &[&str] = &[
-    "h264", "avc1", "avc",  // H.264/AVC
-    "vp8",                   // VP8
-    "vp9",                   // VP9
-    "av1",                   // AV1
-    "theora",                // Theora (OGG)
+/// 重编码导出选项（作用于 encode_segment）
+#[derive(Debug, Clone, Default)]
+pub struct EncodeOptions {
+    /// 优先无损剪辑（快速，失败回退重编码），false 强制重编码（精确）；
+    /// requires_reencode 为 true 的选项组合始终重编码
+    pub prefer_lossless: bool,
+    /// 替换输出音轨的分离音频文件（人声或伴奏），None 表示保留原始音轨
+    /// 设置后无法无损剪辑，始终走重编码
+    pub audio_stem: Option<String>,
+    /// 片段音量增益 (start, end, gain_db)，源视频时间轴，由 with_segment_gains 从片段列表生成
+    pub segment_gains: Vec<(f64, f64, f64)>,
+    /// 跟随人物裁剪，None 表示不裁剪
+    pub crop: Option<FollowCrop>,
+    /// 分段合并的持久工作目录（可恢复导出），None 表示使用自动清理的临时目录
+    /// 设置后已完成的重编码片段保留在目录中，重试时直接复用
+    pub work_dir: Option<std::path::PathBuf>,
+    /// 重编码片段缓存目录，None 表示不缓存
+    /// 按 (源文件, 修改时间, 时间范围, 编码参数) 寻址，跨导出复用未变化的片段
+    pub segment_cache_dir: Option<std::path::PathBuf>,
+    /// 清除元数据（GPS 位置、设备信息、章节、数据流），保护隐私
+    pub strip_metadata: bool,
+    /// 软件编码预设（x264/x265），None 使用 DEFAULT_ENCODE_PRESET
+    pub preset: Option<String>,
+    /// 重编码的 AAC 音频码率（kbps），None 使用 DEFAULT_AUDIO_BITRATE_KBPS
+    pub audio_bitrate: Option<u32>,
+    /// 最终输出的容器，None 表示按输出文件扩展名推断（不影响中间 .ts 片段）
+    pub container: Option<OutputContainer>,
+    /// 合并导出时片段间隙的处理方式，非 Remove 时强制重编码并插入等长填充片段
+    pub gap_fill: GapFill,
+    /// 当前编码的是间隙填充片段（由 smart_concat_segments 设置），画面替换为黑屏/定格并静音
+    pub gap_clip: Option<GapFill>,
+}
+
+/// 默认软件编码预设：速度与画质的折中
+pub const DEFAULT_ENCODE_PRESET: &str = "veryfast";
+
+/// 默认重编码音频码率（kbps）
+pub const DEFAULT_AUDIO_BITRATE_KBPS: u32 = 192;
+
+/// 允许的音频码率范围（kbps），低于下限音质明显劣化，高于上限 AAC 无实际收益
+pub const AUDIO_BITRATE_RANGE_KBPS: std::ops::RangeInclusive<u32> = 64..=320;
+
+/// x264/x265 支持的标准编码预设（由快到慢，越慢文件越小）
+pub const ENCODE_PRESETS: &[&str] = &[
+    "ultrafast", "superfast", "veryfast", "faster", "fast",
+    "medium", "slow", "slower", "veryslow",
 ];
 
-/// 浏览器原生支持的容器格式
-const BROWSER_SUPPORTED_FORMATS: &[&str] = &[
-    "mp4", "mov", "m4v",     // MP4 容器
-    "webm",                   // WebM 容器
-    "ogg", "ogv",            // OGG 容器
+/// 清除元数据的输出参数：全局/流元数据、章节、数据流
+///
+/// 需要写入的元数据（如章节、标题）应追加在这些参数之后，避免被一并清除。
+const STRIP_METADATA_ARGS: &[&str] = &["-map_metadata", "-1", "-map_chapters", "-1", "-dn"];
+
+/// 片段重编码的拼接兼容参数：恒定帧率与固定编码时间基
+///
+/// 拼接素材中各段的 SAR/时间基可能不同，独立重编码后以 `-c copy` 拼接会失败或卡顿；
+/// 配合 `segment_video_filter` 统一 SAR 后，所有中间片段都可安全拼接。
+///
+/// 使用 `-vsync` 而非 `-fps_mode`：后者需要 ffmpeg 5.1+，低于 MIN_FFMPEG_VERSION 支持的 4.3。
+const SEGMENT_NORMALIZE_ARGS: &[&str] = &["-vsync", "cfr", "-enc_time_base:v", "1/90000"];
+
+/// 片段重编码的视频滤镜链：可选裁剪、间隙填充（黑屏/定格 duration 秒）后统一 SAR 为 1:1
+///
+/// 非方形像素的片段先按 SAR 缩放宽度（取偶数）再设置 SAR=1，保持显示比例不变。
+fn segment_video_filter(crop_filter: Option<String>, gap_clip: Option<(GapFill, f64)>) -> String {
+    let mut filters: Vec<String> = crop_filter.into_iter().collect();
+    match gap_clip {
+        Some((GapFill::Black, _)) => filters.push("drawbox=c=black:t=fill".to_string()),
+        Some((GapFill::Freeze, duration)) => {
+            filters.push(format!("trim=end_frame=1,tpad=stop_mode=clone:stop_duration={:.3}", duration));
+        }
+        Some((GapFill::Remove, _)) | None => {}
+    }
+    filters.push("scale=trunc(iw*sar/2)*2:ih,setsar=1".to_string());
+    filters.join(",")
+}
+
+/// 间隙判定阈值（秒），小于该值的间隙视为连续，不插入填充片段
+const GAP_FILL_MIN_SECONDS: f64 = 0.01;
+
+/// 按间隙填充方式展开片段列表：在片段之间（含开头）插入 (start, end, Some(mode)) 填充项
+///
+/// segments 需已按时间排序并合并重叠；Remove 时原样返回。
+fn fill_gaps(segments: &[(f64, f64)], mode: GapFill) -> Vec<(f64, f64, Option<GapFill>)> {
+    if mode == GapFill::Remove {
+        return segments.iter().map(|&(s, e)| (s, e, None)).collect();
+    }
+    let mut pieces = Vec::with_capacity(segments.len() * 2);
+    let mut cursor = 0.0;
+    for &(start, end) in segments {
+        if start - cursor > GAP_FILL_MIN_SECONDS {
+            pieces.push((cursor, start, Some(mode)));
+        }
+        pieces.push((start, end, None));
+        cursor = f64::max(cursor, end);
+    }
+    pieces
+}
+
+/// 输出容器相关参数
+///
+/// 显式指定容器时追加 `-f`（不依赖扩展名），MP4/MOV 添加 faststart 使 moov atom
+/// 位于文件开头；MKV 及无法识别的扩展名（如中间 .ts 片段）不添加额外参数。
+fn container_output_args(output_path: &str, container: Option<OutputContainer>) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(explicit) = container {
+        args.extend(["-f".to_string(), explicit.muxer().to_string()]);
+    }
+    if matches!(
+        container.or_else(|| OutputContainer::from_path(output_path)),
+        Some(OutputContainer::Mp4 | OutputContainer::Mov)
+    ) {
+        args.extend(["-movflags".to_string(), "+faststart".to_string()]);
+    }
+    args
+}
+
+/// 校验输出容器能否封装视频编码（如 MOV 不支持 VP9），不兼容时返回 InvalidArgument
+pub fn check_container_codec(container: OutputContainer, video_codec: &str) -> AppResult<()> {
+    let codec = video_codec.to_lowercase();
+    if container.supports_video_codec(&codec) {
+        Ok(())
+    } else {
+        Err(AppError::InvalidArgument(format!(
+            "{} 容器不支持 {} 编码，请更换容器或强制重编码",
+            container.extension().to_uppercase(), video_codec
+        )))
+    }
+}
+
+/// 跟随人物裁剪参数
+///
+/// 按目标宽高比从源画面中裁出最大区域，裁剪中心随人物位置移动。
+/// 没有人物位置数据时退化为静态居中裁剪。
+#[derive(Debug, Clone)]
+pub struct FollowCrop {
+    /// 目标宽高比（宽）
+    pub aspect_width: u32,
+    /// 目标宽高比（高）
+    pub aspect_height: u32,
+    /// 人物中心位置 (time, center_x, center_y)，源视频时间轴，坐标归一化到 0-1
+    pub keyframes: Vec<(f64, f64, f64)>,
+}
+
+/// 裁剪中心平滑系数（指数移动平均，越小越平滑）
+const CROP_SMOOTHING_ALPHA: f64 = 0.3;
+/// 裁剪中心关键帧最小间隔（秒），避免滤镜表达式过长
+const CROP_KEYFRAME_MIN_INTERVAL: f64 = 0.5;
+
+impl FollowCrop {
+    /// 按人物检测框生成裁剪关键帧（取检测框中心），按时间排序
+    ///
+    /// 检测框为空时没有关键帧，即静态居中裁剪。
+    pub fn from_boxes<'a>(
+        aspect_width: u32,
+        aspect_height: u32,
+        boxes: impl IntoIterator<Item = &'a DetectionBox>,
+    ) -> Self {
+        let mut keyframes: Vec<(f64, f64, f64)> = boxes
+            .into_iter()
+            .filter(|b| b.time.is_finite())
+            .map(|b| {
+                let [x1, y1, x2, y2] = b.bbox;
+                (b.time, (x1 + x2) / 2.0, (y1 + y2) / 2.0)
+            })
+            .collect();
+        keyframes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        Self { aspect_width, aspect_height, keyframes }
+    }
+
+    /// 生成 [start, end) 区间的 crop 滤镜
+    ///
+    /// 宽高只计算一次（取满足宽高比的最大偶数尺寸），x/y 按输出时间逐帧计算。
+    fn filter(&self, start: f64, end: f64) -> String {
+        let aspect = self.aspect_width as f64 / self.aspect_height as f64;
+        let w = format!("trunc(min(iw,ih*{:.6})/2)*2", aspect);
+        let h = format!("trunc(min(ih,iw/{:.6})/2)*2", aspect);
+
+        let points = self.smoothed_points(start, end);
+        let cx = Self::interpolate_expr(points.iter().map(|&(t, x, _)| (t, x)).collect());
+        let cy = Self::interpolate_expr(points.iter().map(|&(t, _, y)| (t, y)).collect());
+
+        format!(
+            "crop=w='{}':h='{}':x='clip(({})*iw-ow/2,0,iw-ow)':y='clip(({})*ih-oh/2,0,ih-oh)'",
+            w, h, cx, cy
+        )
+    }
+
+    /// 截取区间内的关键帧，按最小间隔抽稀并做指数平滑，时间转换为相对区间起点
+    fn smoothed_points(&self, start: f64, end: f64) -> Vec<(f64, f64, f64)> {
+        let mut points: Vec<(f64, f64, f64)> = Vec::new();
+        let mut smoothed: Option<(f64, f64)> = None;
+        for &(t, x, y) in self.keyframes.iter().filter(|(t, _, _)| *t >= start - CROP_KEYFRAME_MIN_INTERVAL && *t <= end) {
+            let (x, y) = (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0));
+            let (sx, sy) = match smoothed {
+                Some((px, py)) => (
+                    px + CROP_SMOOTHING_ALPHA * (x - px),
+                    py + CROP_SMOOTHING_ALPHA * (y - py),
+                ),
+                None => (x, y),
+            };
+            smoothed = Some((sx, sy));
+
+            let rel_t = (t - start).max(0.0);
+            let far_enough = match points.last() {
+                Some(&(last_t, _, _)) => rel_t - last_t >= CROP_KEYFRAME_MIN_INTERVAL,
+                None => true,
+            };
+            if far_enough {
+                points.push((rel_t, sx, sy));
+            }
+        }
+        points
+    }
+
+    /// 分段线性插值表达式，无数据时返回居中位置 0.5
+    fn interpolate_expr(points: Vec<(f64, f64)>) -> String {
+        match points.as_slice() {
+            [] => "0.5".to_string(),
+            [(_, v)] => format!("{:.4}", v),
+            _ => {
+                let (_, last_v) = points[points.len() - 1];
+                points.windows(2).rev().fold(format!("{:.4}", last_v), |acc, pair| {
+                    let (t0, v0) = pair[0];
+                    let (t1, v1) = pair[1];
+                    format!(
+                        "if(lt(t,{:.3}),{:.4}+({:.4})*(t-{:.3})/{:.3},{})",
+                        t1, v0, v1 - v0, t0, (t1 - t0).max(0.001), acc
+                    )
+                })
+            }
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// 是否需要重编码（无法使用 -c copy）
+    pub(crate) fn requires_reencode(&self) -> bool {
+        self.audio_stem.is_some() || !self.segment_gains.is_empty() || self.crop.is_some()
+            || self.gap_fill != GapFill::Remove
+    }
+
+    /// 收集未移除片段上设置的音量增益（忽略 0dB）
+    pub fn with_segment_gains(&self, segments: &[Segment]) -> Self {
+        let segment_gains: Vec<(f64, f64, f64)> = segments
+            .iter()
+            .filter(|s| s.status == SegmentStatus::Detected)
+            .filter_map(|s| match s.gain_db {
+                Some(gain) if gain.is_finite() && gain != 0.0 => Some((s.start_time, s.end_time, gain)),
+                _ => None,
+            })
+            .collect();
+        if !segment_gains.is_empty() {
+            info!("[FFMPEG] {} 个片段设置了音量增益，将使用重编码导出", segment_gains.len());
+        }
+        Self { segment_gains, ..self.clone() }
+    }
+
+    /// 生成 [start, end) 区间的音量滤镜
+    ///
+    /// 单个增益覆盖整个区间时使用 `volume={gain}dB`；
+    /// 合并后的区间包含多个增益时，按输出时间逐帧计算（未覆盖部分保持原音量）。
+    fn volume_filter(&self, start: f64, end: f64) -> Option<String> {
+        let ranges: Vec<(f64, f64, f64)> = self.segment_gains
+            .iter()
+            .filter_map(|&(s, e, gain)| {
+                let rel_start = s.max(start) - start;
+                let rel_end = e.min(end) - start;
+                (rel_end > rel_start).then_some((rel_start, rel_end, gain))
+            })
+            .collect();
+
+        match ranges.as_slice() {
+            [] => None,
+            [(rel_start, rel_end, gain)] if *rel_start <= 0.001 && *rel_end >= end - start - 0.001 => {
+                Some(format!("volume={}dB", gain))
+            }
+            _ => {
+                let expr = ranges.iter().rev().fold("1".to_string(), |acc, (rel_start, rel_end, gain)| {
+                    format!("if(between(t,{:.3},{:.3}),{:.6},{})", rel_start, rel_end, 10f64.powf(gain / 20.0), acc)
+                });
+                Some(format!("volume='{}':eval=frame", expr))
+            }
+        }
+    }
+}
+
+/// 浏览器原生支持的容器，以及容器内可直接播放的视频编码（ffprobe codec_name，精确匹配）
+///
+/// MP4 中的 HEVC/H.265、ProRes、MPEG-4 Part 2 等多数浏览器无法解码，需要转码预览。
+const BROWSER_SUPPORTED_CONTAINERS: &[(&str, &[&str])] = &[
+    ("mp4", &["h264", "av1", "vp9"]),   // MP4/MOV/M4V 容器
+    ("webm", &["vp8", "vp9", "av1"]),   // WebM 容器
+    ("ogg", &["theora", "vp8"]),        // OGG 容器
 ];
 
+/// 按 ffprobe format_name 判断浏览器可播放的容器类型
+///
+/// format_name 为逗号分隔的列表（MP4 为 "mov,mp4,m4a,3gp,3g2,mj2"），逐项精确匹配。
+/// Matroska 与 WebM 共用 "matroska,webm"，只有 .webm 扩展名的文件才视为 WebM。
+fn browser_container(format: &str, path: &str) -> Option<&'static str> {
+    let format = format.to_lowercase();
+    let names: Vec<&str> = format.split(',').map(|n| n.trim()).collect();
+    let has = |name: &str| names.contains(&name);
+
+    if has("mp4") || has("mov") || has("m4v") {
+        return Some("mp4");
+    }
+    if has("webm") {
+        let is_webm_file = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("webm"));
+        return is_webm_file.then_some("webm");
+    }
+    if has("ogg") {
+        return Some("ogg");
+    }
+    None
+}
+
+/// 当前安装的 ffmpeg 支持的容器格式与解码器
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct FfmpegCapabilities {
+    /// 可读取的容器格式（`-formats` 中带 D 标志）
+    pub demuxers: Vec<String>,
+    /// 可写入的容器格式（`-formats` 中带 E 标志）
+    pub muxers: Vec<String>,
+    /// 视频解码器
+    pub video_decoders: Vec<String>,
+    /// 音频解码器
+    pub audio_decoders: Vec<String>,
+}
+
+/// 获取 ffmpeg 的格式与解码器能力（按会话缓存）
+///
+/// 与 BROWSER_SUPPORTED_CONTAINERS 不同，这里反映的是 ffmpeg 实际能处理的内容，
+/// 前端据此判断源视频是否根本无法解码（连转码预览都不可能）。
+pub fn get_capabilities() -> AppResult<FfmpegCapabilities> {
+    if let Some(caps) = CAPABILITIES_CACHE.get() {
+        return Ok(caps.clone());
+    }
+
+    let ffmpeg_path = resolve_tool_path("ffmpeg");
+    let run = |arg: &str| -> AppResult<String> {
+        let output = hidden_command(&ffmpeg_path)
+            .args(["-hide_banner", arg])
+            .output()
+            .map_err(|e| AppError::DependencyMissing(format!("ffmpeg 执行失败: {}", e)))?;
+        if !output.status.success() {
+            return Err(AppError::FFmpeg(format!("ffmpeg {} 执行失败", arg)));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    };
+
+    let (demuxers, muxers) = parse_formats_output(&run("-formats")?);
+    let (video_decoders, audio_decoders) = parse_decoders_output(&run("-decoders")?);
+    let caps = FfmpegCapabilities { demuxers, muxers, video_decoders, audio_decoders };
+    info!(
+        "[FFMPEG] 能力探测完成: {} 个解封装格式, {} 个封装格式, {} 个视频解码器, {} 个音频解码器",
+        caps.demuxers.len(), caps.muxers.len(), caps.video_decoders.len(), caps.audio_decoders.len()
+    );
+
+    Ok(CAPABILITIES_CACHE.get_or_init(|| caps).clone())
+}
+
+/// 解析 `ffmpeg -formats` 输出，返回 (解封装格式, 封装格式)
+///
+/// 按标志列格式识别表格行，不依赖分隔行（旧版为 ` --`、新版为 ` ---` 且多一列设备标志），
+/// 每行为 `标志 名称[,别名] 描述`，如 ` DE matroska,webm  Matroska / WebM`、` D d lavfi  Libavfilter virtual input device`。
+/// 图例行（` D. = Demuxing supported`）的标志含 `.`，不会被识别为格式。
+fn parse_formats_output(output: &str) -> (Vec<String>, Vec<String>) {
+    let mut demuxers = Vec::new();
+    let mut muxers = Vec::new();
+    for caps in output.lines().filter_map(|line| FORMAT_ROW_REGEX.captures(line)) {
+        let demux = &caps[1] == "D";
+        let mux = &caps[2] == "E";
+        if !demux && !mux {
+            continue;
+        }
+        for name in caps[4].split(',') {
+            if demux {
+                demuxers.push(name.to_string());
+            }
+            if mux {
+                muxers.push(name.to_string());
+            }
+        }
+    }
+    (demuxers, muxers)
+}
+
+/// 解析 `ffmpeg -decoders` 输出，返回 (视频解码器, 音频解码器)
+///
+/// 表格在 ` ------` 分隔行之后，标志首字符 V/A/S 表示类型，如 ` V....D hevc  HEVC`。
+fn parse_decoders_output(output: &str) -> (Vec<String>, Vec<String>) {
+    let mut video = Vec::new();
+    let mut audio = Vec::new();
+    for line in output.lines().skip_while(|l| l.trim() != "------").skip(1) {
+        let mut parts = line.split_whitespace();
+        let (Some(flags), Some(name)) = (parts.next(), parts.next()) else { continue };
+        match flags.chars().next() {
+            Some('V') => video.push(name.to_string()),
+            Some('A') => audio.push(name.to_string()),
+            _ => {}
+        }
+    }
+    (video, audio)
+}
+
 /// 检测视频是否需要转码预览
 /// 返回 true 表示需要生成预览文件，false 表示可以直接播放
 pub fn needs_preview_transcode(video_info: &VideoInfo) -> bool {
     let format = video_info.format.to_lowercase();
     let video_codec = video_info.video_codec.to_lowercase();
 
-    // 检查容器格式是否支持
-    let format_supported = BROWSER_SUPPORTED_FORMATS.iter()
-        .any(|f| format.contains(f));
+    // 容器和编码都必须浏览器可播放，且编码需在该容器的支持列表内
+    let container = browser_container(&format, &video_info.path);
+    let format_supported = container.is_some();
+    let codec_supported = container
+        .and_then(|c| BROWSER_SUPPORTED_CONTAINERS.iter().find(|(name, _)| *name == c))
+        .is_some_and(|(_, codecs)| codecs.contains(&video_codec.as_str()));
 
-    // 检查视频编解码器是否支持
-    let codec_supported = BROWSER_SUPPORTED_VIDEO_CODECS.iter()
-        .any(|c| video_codec.contains(c));
+    if container == Some("mp4") && matches!(video_codec.as_str(), "hevc" | "h265") {
+        info!("[FFMPEG] MP4 容器内为 HEVC 编码，多数浏览器无法解码，需要转码预览");
+    }
 
-    // 如果格式或编解码器不支持，则需要转码
     let needs_transcode = !format_supported || !codec_supported;
 
     if needs_transcode {
@@ -209,7 +626,7 @@ pub fn generate_preview_video(
         .map_err(|e| AppError::FFmpeg(format!("ffmpeg 执行失败: {}", e)))?;
 
     // 注册子进程句柄，支持即时取消
-    let child_handle = crate::commands::video::register_child_process(project_id, child);
+    let child_handle = crate::commands::video::register_child_process(project_id, "preview", child);
 
     // 取出 stdout 后释放锁
     let stdout = {
@@ -333,6 +750,9 @@ pub fn generate_preview_video(
 /// 无损剪辑单个片段（LosslessCut 风格）
 /// 使用 -c copy 直接复制流，速度极快但只能在关键帧处精确切割
 /// 返回 Ok(true) 表示成功，Ok(false) 表示需要回退到重编码
+/// strip_metadata: 清除元数据并丢弃数据流
+/// container: 输出容器，None 表示按扩展名推断
+#[allow(clippy::too_many_arguments)]
 fn lossless_cut_segment(
     input_path: &str,
     output_path: &str,
@@ -340,6 +760,8 @@ fn lossless_cut_segment(
     end: f64,
     cancel_flags: &[&AtomicBool],
     project_id: &str,
+    strip_metadata: bool,
+    container: Option<OutputContainer>,
 ) -> AppResult<bool> {
     info!(
         "[FFMPEG] 尝试无损剪辑片段 {:.2}s - {:.2}s",
@@ -355,11 +777,6 @@ fn lossless_cut_segment(
 
     let ffmpeg_path = resolve_tool_path("ffmpeg");
 
-    // 判断输出格式
-    let is_mp4 = output_path.to_lowercase().ends_with(".mp4")
-        || output_path.to_lowercase().ends_with(".m4v")
-        || output_path.to_lowercase().ends_with(".mov");
-
     // LosslessCut 风格的无损剪辑参数
     // -ss 在 -i 之前：快速定位（不精确但快）
     // -c copy：直接复制流，不重新编码
@@ -374,12 +791,15 @@ fn lossless_cut_segment(
         "-map".to_string(), "0".to_string(),  // 复制所有流
     ];
 
-    // MP4 格式添加 faststart
-    if is_mp4 {
-        args.push("-movflags".to_string());
-        args.push("+faststart".to_string());
+    if strip_metadata {
+        // 显式 -map 0 会带上数据流（如 GPS 轨道），需用负向映射排除
+        args.extend(["-map".to_string(), "-0:d".to_string()]);
+        args.extend(STRIP_METADATA_ARGS.iter().map(|a| a.to_string()));
     }
 
+    // 容器参数（MP4/MOV 添加 faststart）
+    args.extend(container_output_args(output_path, container));
+
     args.push("-y".to_string());
     args.push(output_path.to_string());
 
@@ -392,7 +812,7 @@ fn lossless_cut_segment(
         .map_err(|e| AppError::FFmpeg(format!("ffmpeg 执行失败: {}", e)))?;
 
     // 注册子进程句柄
-    let child_handle = crate::commands::video::register_child_process(project_id, child);
+    let child_handle = crate::commands::video::register_child_process(project_id, "lossless_cut", child);
 
     // 轮询检查进程状态和取消标志
     loop {
@@ -460,11 +880,11 @@ pub(crate) fn smart_cut_segment(
     end: f64,
     cancel_flags: &[&AtomicBool],
     project_id: &str,
-    prefer_lossless: bool,
+    options: &EncodeOptions,
 ) -> AppResult<()> {
-    if prefer_lossless {
+    if options.prefer_lossless && !options.requires_reencode() {
         // 先尝试无损剪辑
-        match lossless_cut_segment(input_path, output_path, start, end, cancel_flags, project_id)? {
+        match lossless_cut_segment(input_path, output_path, start, end, cancel_flags, project_id, options.strip_metadata, options.container)? {
             true => return Ok(()),  // 无损剪辑成功
             false => {
                 info!("[FFMPEG] 无损剪辑失败，回退到重编码模式");
@@ -474,7 +894,7 @@ pub(crate) fn smart_cut_segment(
     }
 
     // 重编码模式
-    encode_segment(input_path, output_path, start, end, cancel_flags, project_id)
+    encode_segment(input_path, output_path, start, end, options, cancel_flags, project_id)
 }
 
 /// 重编码导出单个片段
@@ -485,6 +905,7 @@ fn encode_segment(
     output_path: &str,
     start: f64,
     end: f64,
+    options: &EncodeOptions,
     cancel_flags: &[&AtomicBool],
     project_id: &str,
 ) -> AppResult<()> {
@@ -502,31 +923,64 @@ fn encode_segment(
 
     let ffmpeg_path = resolve_tool_path("ffmpeg");
 
-    // 判断输出格式，决定是否添加 movflags
-    let is_mp4 = output_path.to_lowercase().ends_with(".mp4")
-        || output_path.to_lowercase().ends_with(".m4v")
-        || output_path.to_lowercase().ends_with(".mov");
-
     let mut args = vec![
         "-v".to_string(), "warning".to_string(),  // 只输出警告和错误，减少 stderr 输出量，避免管道缓冲区阻塞
         "-ss".to_string(), start.to_string(),
         "-i".to_string(), input_path.to_string(),
+    ];
+
+    // 使用分离音频替换原始音轨（分离音频与源视频时间轴一致，同样按 start 定位）
+    if let Some(ref stem) = options.audio_stem {
+        args.extend([
+            "-ss".to_string(), start.to_string(),
+            "-i".to_string(), stem.clone(),
+            "-map".to_string(), "0:v:0".to_string(),
+            "-map".to_string(), "1:a:0".to_string(),
+        ]);
+    }
+
+    args.extend([
         "-t".to_string(), (end - start).to_string(),
         "-c:v".to_string(), "libx264".to_string(),
-        "-preset".to_string(), "veryfast".to_string(),  // 速度优化：veryfast 比 fast 快 2 倍，画质相同
+        "-preset".to_string(), options.preset.as_deref().unwrap_or(DEFAULT_ENCODE_PRESET).to_string(),  // 默认 veryfast：比 fast 快 2 倍，画质相同
         "-crf".to_string(), "18".to_string(),
         "-threads".to_string(), "0".to_string(),  // 自动使用所有 CPU 核心
         "-force_key_frames".to_string(), "expr:eq(n,0)".to_string(),  // 强制第一帧为关键帧
         "-c:a".to_string(), "aac".to_string(),
-        "-b:a".to_string(), "192k".to_string(),
+        "-b:a".to_string(), format!("{}k", options.audio_bitrate.unwrap_or(DEFAULT_AUDIO_BITRATE_KBPS)),
         "-avoid_negative_ts".to_string(), "make_zero".to_string(),
-    ];
+    ]);
+
+    // 跟随人物裁剪 + 统一 SAR，保证片段可直接拼接
+    args.push("-vf".to_string());
+    args.push(segment_video_filter(
+        options.crop.as_ref().map(|c| c.filter(start, end)),
+        options.gap_clip.map(|mode| (mode, end - start)),
+    ));
+    args.extend(SEGMENT_NORMALIZE_ARGS.iter().map(|a| a.to_string()));
+
+    // 片段音量调整（间隙填充片段静音）
+    let audio_filter = match options.gap_clip {
+        Some(_) => Some("volume=0".to_string()),
+        None => options.volume_filter(start, end),
+    };
+    if let Some(filter) = audio_filter {
+        info!("[FFMPEG] 应用音量滤镜: {}", filter);
+        args.push("-af".to_string());
+        args.push(filter);
+    }
+
+    if options.strip_metadata {
+        args.extend(STRIP_METADATA_ARGS.iter().map(|a| a.to_string()));
+    }
 
-    // MP4 格式添加 faststart，确保 moov atom 在文件开头，支持快速播放
-    if is_mp4 {
-        args.push("-movflags".to_string());
-        args.push("+faststart".to_string());
+    // 容器参数（MP4/MOV 添加 faststart，确保 moov atom 在文件开头，支持快速播放）；
+    // MP4/MOV 并固定轨道时间刻度（.ts 输出固定为 90kHz，无需设置）
+    let container_args = container_output_args(output_path, options.container);
+    if container_args.iter().any(|a| a == "+faststart") {
+        args.extend(["-video_track_timescale".to_string(), "90000".to_string()]);
     }
+    args.extend(container_args);
 
     args.push("-y".to_string());
     args.push(output_path.to_string());
@@ -540,7 +994,11 @@ fn encode_segment(
         .map_err(|e| AppError::FFmpeg(format!("ffmpeg 执行失败: {}", e)))?;
 
     // 注册子进程句柄，支持即时取消
-    let child_handle = crate::commands::video::register_child_process(project_id, child);
+    let child_handle = crate::commands::video::register_child_process(project_id, "encode_segment", child);
+
+    // 输出文件持续增长即视为有进展，长时间不变判定为卡死
+    let mut watchdog = StallWatchdog::new(config::process_timeout());
+    let mut last_output_size = 0u64;
 
     // 轮询检查进程状态和取消标志
     loop {
@@ -558,6 +1016,33 @@ fn encode_segment(
             return Err(AppError::Cancelled);
         }
 
+        let output_size = fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+        if output_size != last_output_size {
+            last_output_size = output_size;
+            watchdog.touch();
+        }
+        // 暂停（进程被挂起）不算卡死
+        if crate::commands::video::is_paused(project_id) {
+            watchdog.touch();
+        }
+        if watchdog.is_stalled() {
+            error!(
+                "[FFMPEG] 片段重编码超过 {} 秒无进展，终止进程: {:.2}s - {:.2}s, project_id={}",
+                watchdog.timeout_secs(), start, end, project_id
+            );
+            if let Ok(mut guard) = child_handle.lock() {
+                if let Some(ref mut child) = *guard {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+            }
+            let _ = fs::remove_file(output_path);
+            return Err(AppError::Timeout(format!(
+                "ffmpeg 重编码片段 {:.2}s - {:.2}s 超过 {} 秒无进展",
+                start, end, watchdog.timeout_secs()
+            )));
+        }
+
         // 检查进程是否完成（短暂获取锁）
         let try_wait_result = {
             let mut guard = child_handle.lock().unwrap();
@@ -608,6 +1093,117 @@ fn encode_segment(
     }
 }
 
+/// 波形提取的采样率（只用于绘制，8kHz 足够且解码量小）
+const WAVEFORM_SAMPLE_RATE: u32 = 8000;
+
+/// 提取时间范围内的音频波形峰值
+///
+/// 解码为单声道 16 位 PCM 后按 num_peaks 等分，每段取绝对值最大值并归一化到 0-1。
+pub fn extract_waveform_peaks(
+    input_path: &str,
+    start_time: f64,
+    end_time: f64,
+    num_peaks: usize,
+) -> AppResult<Vec<f32>> {
+    let ffmpeg_path = resolve_tool_path("ffmpeg");
+    let output = hidden_command(&ffmpeg_path)
+        .args([
+            "-ss", &start_time.to_string(),
+            "-t", &(end_time - start_time).to_string(),
+            "-i", input_path,
+            "-vn",
+            "-ac", "1",
+            "-ar", &WAVEFORM_SAMPLE_RATE.to_string(),
+            "-f", "s16le",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| AppError::FFmpeg(format!("ffmpeg 执行失败: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::FFmpeg(format!("提取波形失败: {}", stderr)));
+    }
+
+    let samples: Vec<i16> = output.stdout
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    Ok(compute_waveform_peaks(&samples, num_peaks))
+}
+
+/// 将整个文件的音频解码为单声道 16 位 PCM 采样（用于节拍分析等需要原始采样的场景）
+pub fn decode_mono_pcm(input_path: &str, sample_rate: u32) -> AppResult<Vec<i16>> {
+    let ffmpeg_path = resolve_tool_path("ffmpeg");
+    let output = hidden_command(&ffmpeg_path)
+        .args([
+            "-i", input_path,
+            "-vn",
+            "-ac", "1",
+            "-ar", &sample_rate.to_string(),
+            "-f", "s16le",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| AppError::FFmpeg(format!("ffmpeg 执行失败: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::FFmpeg(format!("解码音频失败: {}", stderr)));
+    }
+
+    Ok(output.stdout
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect())
+}
+
+/// 将 PCM 采样按 num_peaks 等分计算峰值（采样数不足时每个采样一个峰值）
+fn compute_waveform_peaks(samples: &[i16], num_peaks: usize) -> Vec<f32> {
+    if samples.is_empty() || num_peaks == 0 {
+        return Vec::new();
+    }
+    let bucket_size = samples.len().div_ceil(num_peaks);
+    samples
+        .chunks(bucket_size)
+        .map(|chunk| {
+            let peak = chunk.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0);
+            peak as f32 / i16::MAX as f32
+        })
+        .map(|p| p.min(1.0))
+        .collect()
+}
+
+/// 片段预览的编码预设：只求尽快出片供检查
+const SEGMENT_PREVIEW_PRESET: &str = "ultrafast";
+
+/// 重编码单个片段为预览 MP4（应用片段音量增益）
+///
+/// 先写入临时文件再重命名，取消或失败不会留下不完整的缓存文件。
+pub fn encode_segment_preview(
+    input_path: &str,
+    output_path: &str,
+    segment: &Segment,
+    cancel_flag: &AtomicBool,
+    project_id: &str,
+) -> AppResult<()> {
+    let options = EncodeOptions {
+        preset: Some(SEGMENT_PREVIEW_PRESET.to_string()),
+        ..Default::default()
+    }
+    .with_segment_gains(std::slice::from_ref(segment));
+
+    let temp_output = format!("{}.part.mp4", output_path);
+    if let Err(e) = encode_segment(input_path, &temp_output, segment.start_time, segment.end_time, &options, &[cancel_flag], project_id) {
+        let _ = fs::remove_file(&temp_output);
+        return Err(e);
+    }
+    fs::rename(&temp_output, output_path)?;
+    Ok(())
+}
+
 /// 获取视频信息
 pub fn get_video_info(video_path: &str) -> AppResult<VideoInfo> {
     let ffprobe_path = resolve_tool_path("ffprobe");
@@ -640,6 +1236,7 @@ pub fn get_video_info(video_path: &str) -> AppResult<VideoInfo> {
     let mut fps = 0.0f64;
     let mut video_codec = String::new();
     let mut audio_codec = String::new();
+    let mut audio_stream_count = 0u32;
 
     for stream in streams {
         let codec_type = stream["codec_type"].as_str().unwrap_or("");
@@ -649,12 +1246,22 @@ pub fn get_video_info(video_path: &str) -> AppResult<VideoInfo> {
             height = stream["height"].as_u64().unwrap_or(0) as u32;
             video_codec = stream["codec_name"].as_str().unwrap_or("").to_string();
 
-            // 解析帧率
-            if let Some(fps_str) = stream["r_frame_rate"].as_str() {
-                fps = parse_frame_rate(fps_str);
+            // 解析帧率：r_frame_rate 无效（如 0/0）时回退到 avg_frame_rate
+            fps = stream["r_frame_rate"].as_str()
+                .and_then(parse_frame_rate)
+                .or_else(|| stream["avg_frame_rate"].as_str().and_then(parse_frame_rate))
+                .unwrap_or_else(|| {
+                    warn!(
+                        "[FFMPEG] 无法获取帧率 (r_frame_rate={}, avg_frame_rate={})，使用默认值 {}",
+                        stream["r_frame_rate"], stream["avg_frame_rate"], DEFAULT_FPS
+                    );
+                    DEFAULT_FPS
+                });
+        } else if codec_type == "audio" {
+            if audio_codec.is_empty() {
+                audio_codec = stream["codec_name"].as_str().unwrap_or("").to_string();
             }
-        } else if codec_type == "audio" && audio_codec.is_empty() {
-            audio_codec = stream["codec_name"].as_str().unwrap_or("").to_string();
+            audio_stream_count += 1;
         }
     }
 
@@ -680,10 +1287,20 @@ pub fn get_video_info(video_path: &str) -> AppResult<VideoInfo> {
         .unwrap_or("")
         .to_string();
 
-    let filename = Path::new(video_path)
-        .file_name()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_default();
+    let filename = if is_url(video_path) {
+        // URL 取路径最后一段并去掉查询参数
+        video_path
+            .split(['?', '#'])
+            .next()
+            .and_then(|p| p.trim_end_matches('/').rsplit('/').next())
+            .unwrap_or(video_path)
+            .to_string()
+    } else {
+        Path::new(video_path)
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
 
     Ok(VideoInfo {
         path: video_path.to_string(),
@@ -697,30 +1314,109 @@ pub fn get_video_info(video_path: &str) -> AppResult<VideoInfo> {
         bitrate,
         size,
         format: format_name,
+        audio_stream_count,
     })
 }
 
-/// 解析帧率字符串
-fn parse_frame_rate(fps_str: &str) -> f64 {
-    let parts: Vec<&str> = fps_str.split('/').collect();
-    if parts.len() == 2 {
-        let num: f64 = parts[0].parse().unwrap_or(0.0);
-        let den: f64 = parts[1].parse().unwrap_or(1.0);
-        if den > 0.0 {
-            return num / den;
+/// 无法获取帧率时使用的默认值
+const DEFAULT_FPS: f64 = 30.0;
+
+/// 解析帧率字符串（"30000/1001"、"25" 等）
+/// 0/0、N/A 等无效值返回 None
+fn parse_frame_rate(fps_str: &str) -> Option<f64> {
+    let fps = match fps_str.trim().split_once('/') {
+        Some((num, den)) => {
+            let num: f64 = num.trim().parse().ok()?;
+            let den: f64 = den.trim().parse().ok()?;
+            if den <= 0.0 {
+                return None;
+            }
+            num / den
         }
+        None => fps_str.trim().parse().ok()?,
+    };
+    (fps.is_finite() && fps > 0.0).then_some(fps)
+}
+
+/// 获取视频关键帧时间戳（秒，升序）
+/// 使用 -skip_frame nokey 只解码关键帧，长视频仍可能耗时较长
+pub fn get_keyframes(video_path: &str) -> AppResult<Vec<f64>> {
+    info!("[FFMPEG] 开始探测关键帧: {}", video_path);
+    let ffprobe_path = resolve_tool_path("ffprobe");
+    let output = hidden_command(&ffprobe_path)
+        .args([
+            "-v", "error",
+            "-select_streams", "v:0",
+            "-skip_frame", "nokey",
+            "-show_entries", "frame=pts_time,pkt_dts_time",
+            "-of", "csv=p=0",
+            video_path,
+        ])
+        .output()
+        .map_err(|e| AppError::FFmpeg(format!("ffprobe 执行失败: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::FFmpeg(format!("ffprobe 探测关键帧失败: {}", stderr)));
+    }
+
+    // 每行格式: pts_time,pkt_dts_time，任一字段可能为 N/A，取第一个可解析的值
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut keyframes: Vec<f64> = stdout
+        .lines()
+        .filter_map(|line| {
+            line.split(',')
+                .filter_map(|field| field.trim().parse::<f64>().ok())
+                .next()
+        })
+        .filter(|t| t.is_finite() && *t >= 0.0)
+        .collect();
+
+    keyframes.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    keyframes.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+
+    info!("[FFMPEG] 关键帧探测完成: {} 个关键帧", keyframes.len());
+    Ok(keyframes)
+}
+
+/// 将时间吸附到最近的关键帧（keyframes 需已升序）
+/// 最近关键帧距离超过 tolerance 时返回原时间
+pub(crate) fn snap_to_keyframe(time: f64, keyframes: &[f64], tolerance: f64) -> f64 {
+    if keyframes.is_empty() {
+        return time;
+    }
+
+    // 二分查找第一个 >= time 的关键帧，与其前一个比较距离
+    let idx = keyframes.partition_point(|&k| k < time);
+    let candidates = [idx.checked_sub(1), Some(idx)];
+    let nearest = candidates
+        .iter()
+        .flatten()
+        .filter_map(|&i| keyframes.get(i))
+        .min_by(|a, b| {
+            (*a - time).abs()
+                .partial_cmp(&(*b - time).abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+    match nearest {
+        Some(&k) if (k - time).abs() <= tolerance => k,
+        _ => time,
     }
-    fps_str.parse().unwrap_or(0.0)
 }
 
 /// 获取音频时长
+///
+/// 优先使用容器级 format.duration；部分裸流/直播录制文件没有该字段时，
+/// 依次回退到流级 duration、duration_ts * time_base、nb_frames / r_frame_rate，
+/// 仍无法获取时完整解码一遍（-f null）读取最终时间戳。
 pub fn get_audio_duration(audio_path: &str) -> AppResult<f64> {
     let ffprobe_path = resolve_tool_path("ffprobe");
     let output = hidden_command(&ffprobe_path)
         .args([
             "-v", "quiet",
-            "-show_entries", "format=duration",
-            "-of", "default=noprint_wrappers=1:nokey=1",
+            "-print_format", "json",
+            "-show_entries", "format=duration:stream=codec_type,duration,duration_ts,time_base,nb_frames,r_frame_rate",
             audio_path,
         ])
         .output()
@@ -731,35 +1427,153 @@ pub fn get_audio_duration(audio_path: &str) -> AppResult<f64> {
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    stdout
-        .trim()
-        .parse::<f64>()
-        .map_err(|_| AppError::FFmpeg("解析音频时长失败".to_string()))
+    if let Some(duration) = parse_probe_duration(&stdout) {
+        return Ok(duration);
+    }
+
+    info!("[FFMPEG] ffprobe 未返回时长，解码获取: {}", audio_path);
+    decode_duration(audio_path)
+}
+
+/// 从 ffprobe JSON 输出中解析时长（容器级优先，其次流级）
+fn parse_probe_duration(probe_json: &str) -> Option<f64> {
+    let json: serde_json::Value = serde_json::from_str(probe_json).ok()?;
+    let parse_f64 = |v: &serde_json::Value| -> Option<f64> {
+        v.as_str()
+            .and_then(|s| s.parse::<f64>().ok())
+            .or_else(|| v.as_f64())
+            .filter(|d| d.is_finite() && *d > 0.0)
+    };
+
+    if let Some(duration) = parse_f64(&json["format"]["duration"]) {
+        return Some(duration);
+    }
+
+    let streams = json["streams"].as_array()?;
+    let stream_duration = |stream: &serde_json::Value| -> Option<f64> {
+        if let Some(duration) = parse_f64(&stream["duration"]) {
+            return Some(duration);
+        }
+        // duration_ts * time_base
+        let duration_ts = stream["duration_ts"].as_u64().or_else(|| stream["duration_ts"].as_str()?.parse().ok());
+        if let (Some(ts), Some(time_base)) = (duration_ts, stream["time_base"].as_str()) {
+            if let Some(tb) = parse_frame_rate(time_base).filter(|_| ts > 0) {
+                return Some(ts as f64 * tb);
+            }
+        }
+        // nb_frames / r_frame_rate（音频流的 r_frame_rate 通常为 0/0，只对视频流有效）
+        let nb_frames = stream["nb_frames"].as_str().and_then(|s| s.parse::<u64>().ok());
+        let fps = stream["r_frame_rate"].as_str().and_then(parse_frame_rate);
+        match (nb_frames, fps) {
+            (Some(frames), Some(fps)) if frames > 0 => Some(frames as f64 / fps),
+            _ => None,
+        }
+    };
+
+    // 优先音频流，其次取所有流中最长的时长
+    streams
+        .iter()
+        .filter(|s| s["codec_type"].as_str() == Some("audio"))
+        .filter_map(stream_duration)
+        .reduce(f64::max)
+        .or_else(|| streams.iter().filter_map(stream_duration).reduce(f64::max))
+}
+
+/// 完整解码媒体文件获取时长（最后手段，长文件耗时较长）
+fn decode_duration(input_path: &str) -> AppResult<f64> {
+    let ffmpeg_path = resolve_tool_path("ffmpeg");
+    let output = hidden_command(&ffmpeg_path)
+        .args(["-nostdin", "-i", input_path, "-vn", "-f", "null", "-"])
+        .output()
+        .map_err(|e| AppError::FFmpeg(format!("ffmpeg 执行失败: {}", e)))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    parse_decoded_duration(&stderr)
+        .ok_or_else(|| AppError::FFmpeg("解析音频时长失败".to_string()))
+}
+
+/// 解析 ffmpeg 解码统计输出中最后一个 `time=HH:MM:SS.xx`
+fn parse_decoded_duration(stderr: &str) -> Option<f64> {
+    stderr
+        .split("time=")
+        .skip(1)
+        .filter_map(|rest| {
+            let token = rest.split_whitespace().next()?;
+            let parts: Vec<&str> = token.split(':').collect();
+            if parts.len() != 3 {
+                return None;
+            }
+            let h: f64 = parts[0].parse().ok()?;
+            let m: f64 = parts[1].parse().ok()?;
+            let s: f64 = parts[2].parse().ok()?;
+            Some(h * 3600.0 + m * 60.0 + s)
+        })
+        .last()
+        .filter(|d| *d > 0.0)
+}
+
+/// PCM 音频输出参数（采样率 / 声道数）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: u32,
+}
+
+impl AudioFormat {
+    /// 用户音频导出：44.1kHz 立体声
+    pub const EXPORT: AudioFormat = AudioFormat { sample_rate: 44100, channels: 2 };
+
+    /// 指纹匹配：按配置下混/降采样，音乐库指纹与查询窗口必须使用同一参数
+    pub fn fingerprint() -> Self {
+        let matching = config::get_config().matching;
+        AudioFormat {
+            sample_rate: matching.fingerprint_sample_rate,
+            channels: if matching.fingerprint_mono { 1 } else { 2 },
+        }
+    }
 }
 
 /// 提取音频轨道
+///
+/// `audio_track` 为音频流序号（从 0 开始，映射为 `-map 0:a:{index}`），
+/// None 时由 ffmpeg 选择默认音轨。
 pub fn extract_audio_track(
     video_path: &str,
     output_path: &str,
+    audio_track: Option<usize>,
+    format: AudioFormat,
     progress_callback: Option<ProgressCallback>,
 ) -> AppResult<()> {
     // 获取视频时长用于计算进度
     let video_info = get_video_info(video_path)?;
     let total_duration = video_info.duration;
 
-    let args = vec![
+    let mut args = vec![
         "-i".to_string(),
         video_path.to_string(),
+    ];
+    if let Some(index) = audio_track {
+        let stream_count = video_info.audio_stream_count as usize;
+        if index >= stream_count {
+            return Err(AppError::InvalidArgument(format!(
+                "音轨序号 {} 超出范围（视频共有 {} 条音轨）",
+                index, stream_count
+            )));
+        }
+        args.push("-map".to_string());
+        args.push(format!("0:a:{}", index));
+    }
+    args.extend([
         "-vn".to_string(),
         "-acodec".to_string(),
         "pcm_s16le".to_string(),
         "-ar".to_string(),
-        "44100".to_string(),
+        format.sample_rate.to_string(),
         "-ac".to_string(),
-        "2".to_string(),
+        format.channels.to_string(),
         "-y".to_string(),
         output_path.to_string(),
-    ];
+    ]);
 
     run_ffmpeg_with_progress(&args, total_duration, progress_callback)
 }
@@ -770,6 +1584,7 @@ pub fn extract_audio_segment(
     output_path: &str,
     start_time: f64,
     duration: f64,
+    format: AudioFormat,
 ) -> AppResult<()> {
     let ffmpeg_path = resolve_tool_path("ffmpeg");
     let output = hidden_command(&ffmpeg_path)
@@ -778,8 +1593,8 @@ pub fn extract_audio_segment(
             "-ss", &start_time.to_string(),
             "-t", &duration.to_string(),
             "-acodec", "pcm_s16le",
-            "-ar", "44100",
-            "-ac", "2",
+            "-ar", &format.sample_rate.to_string(),
+            "-ac", &format.channels.to_string(),
             "-y",
             output_path,
         ])
@@ -794,23 +1609,20 @@ pub fn extract_audio_segment(
     Ok(())
 }
 
-/// 提取视频缩略图
-pub fn extract_thumbnail(
-    video_path: &str,
+/// 将整个音频文件转码为指定参数的 PCM WAV
+pub fn transcode_audio(
+    input_path: &str,
     output_path: &str,
-    timestamp: f64,
+    format: AudioFormat,
 ) -> AppResult<()> {
-    info!(
-        "ffmpeg thumbnail start: video_path={}, output_path={}, timestamp={}",
-        video_path, output_path, timestamp
-    );
     let ffmpeg_path = resolve_tool_path("ffmpeg");
     let output = hidden_command(&ffmpeg_path)
         .args([
-            "-ss", &timestamp.to_string(),
-            "-i", video_path,
-            "-vframes", "1",
-            "-q:v", "2",
+            "-i", input_path,
+            "-vn",
+            "-acodec", "pcm_s16le",
+            "-ar", &format.sample_rate.to_string(),
+            "-ac", &format.channels.to_string(),
             "-y",
             output_path,
         ])
@@ -819,7 +1631,38 @@ pub fn extract_thumbnail(
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        error!("ffmpeg thumbnail failed: {}", stderr);
+        return Err(AppError::FFmpeg(format!("音频转码失败: {}", stderr)));
+    }
+
+    Ok(())
+}
+
+/// 提取视频缩略图
+pub fn extract_thumbnail(
+    video_path: &str,
+    output_path: &str,
+    timestamp: f64,
+) -> AppResult<()> {
+    info!(
+        "ffmpeg thumbnail start: video_path={}, output_path={}, timestamp={}",
+        video_path, output_path, timestamp
+    );
+    let ffmpeg_path = resolve_tool_path("ffmpeg");
+    let output = hidden_command(&ffmpeg_path)
+        .args([
+            "-ss", &timestamp.to_string(),
+            "-i", video_path,
+            "-vframes", "1",
+            "-q:v", "2",
+            "-y",
+            output_path,
+        ])
+        .output()
+        .map_err(|e| AppError::FFmpeg(format!("ffmpeg 执行失败: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("ffmpeg thumbnail failed: {}", stderr);
         return Err(AppError::FFmpeg(format!("提取缩略图失败: {}", stderr)));
     }
 
@@ -843,12 +1686,13 @@ pub fn cut_video_segments(
     let total_duration = video_info.duration;
 
     info!("[CUT] === 剪辑片段统计 === keep_matched={}", keep_matched);
-    let detected_count = segments.iter().filter(|s| s.status != SegmentStatus::Removed).count();
+    let detected_count = segments.iter().filter(|s| s.status == SegmentStatus::Detected).count();
+    let provisional_count = segments.iter().filter(|s| s.status == SegmentStatus::Provisional).count();
     let removed_count = segments.iter().filter(|s| s.status == SegmentStatus::Removed).count();
     let music_count = segments.iter().filter(|s| s.segment_type == SegmentType::Music).count();
     let person_count = segments.iter().filter(|s| s.segment_type == SegmentType::Person).count();
-    info!("[CUT] 传入片段: 总计={}, detected={}, removed={}, music={}, person={}, 视频总时长={:.2}s",
-        segments.len(), detected_count, removed_count, music_count, person_count, total_duration);
+    info!("[CUT] 传入片段: 总计={}, detected={}, provisional={}, removed={}, music={}, person={}, 视频总时长={:.2}s",
+        segments.len(), detected_count, provisional_count, removed_count, music_count, person_count, total_duration);
 
     // 计算需要保留的时间段
     let keep_segments = if keep_matched {
@@ -876,14 +1720,18 @@ pub fn cut_video_segments(
     }
 
     // 使用智能分段合并（根据 prefer_lossless 决定模式）
-    smart_concat_segments(input_path, output_path, &keep_segments, progress_callback, cancel_flag, project_id, prefer_lossless)
+    // 仅保留匹配片段时才应用片段音量增益（反向剪辑保留的是未匹配部分）
+    let options = EncodeOptions { prefer_lossless, ..Default::default() };
+    let options = if keep_matched { options.with_segment_gains(segments) } else { options };
+
+    smart_concat_segments(input_path, output_path, &keep_segments, progress_callback, cancel_flag, project_id, &options)
 }
 
 /// 计算反向片段（移除匹配片段后的剩余部分）
 fn calculate_inverse_segments(segments: &[Segment], total_duration: f64) -> Vec<(f64, f64)> {
     let mut matched: Vec<(f64, f64)> = segments
         .iter()
-        .filter(|s| s.status != SegmentStatus::Removed)
+        .filter(|s| s.status == SegmentStatus::Detected)
         .map(|s| (s.start_time, s.end_time))
         .collect();
 
@@ -961,8 +1809,8 @@ fn filter_valid_segments_with_ref<'a>(
 
     // 记录被过滤掉的片段及原因
     for (i, s) in segments.iter().enumerate() {
-        if s.status == SegmentStatus::Removed {
-            info!("[FILTER]   ✗ 片段[{}] 被过滤: status=Removed, id={}", i, s.id);
+        if s.status != SegmentStatus::Detected {
+            info!("[FILTER]   ✗ 片段[{}] 被过滤: status={:?}, id={}", i, s.status, s.id);
         } else {
             let start = s.start_time.max(0.0);
             let end = s.end_time.min(total_duration);
@@ -974,7 +1822,7 @@ fn filter_valid_segments_with_ref<'a>(
 
     let mut valid_segments: Vec<(f64, f64, &'a Segment)> = segments
         .iter()
-        .filter(|s| s.status != SegmentStatus::Removed)
+        .filter(|s| s.status == SegmentStatus::Detected)
         .map(|s| {
             let start = s.start_time.max(0.0);
             let end = s.end_time.min(total_duration);
@@ -999,15 +1847,199 @@ fn filter_valid_segments_with_ref<'a>(
     valid_segments
 }
 
+/// 计算合并导出的最终时间段（与 export_video_with_mode 相同的筛选与合并逻辑）
+pub(crate) fn plan_export_segments(segments: &[Segment], total_duration: f64) -> Vec<(f64, f64)> {
+    let keep_segments = filter_valid_segments(segments, total_duration);
+    merge_overlapping_segments(&keep_segments)
+}
+
+/// 按拼接顺序计算合并导出的时间段
+///
+/// Chronological 与 plan_export_segments 相同（合并重叠片段）；
+/// 其余顺序不合并重叠片段，重叠部分会重复出现在输出中。
+pub(crate) fn plan_ordered_export_segments(
+    segments: &[Segment],
+    total_duration: f64,
+    order: &SegmentOrder,
+) -> AppResult<Vec<(f64, f64)>> {
+    match order {
+        SegmentOrder::Chronological => Ok(plan_export_segments(segments, total_duration)),
+        SegmentOrder::ByConfidenceDesc => {
+            let mut valid = filter_valid_segments_with_ref(segments, total_duration);
+            // 稳定排序：置信度相同时保持时间顺序
+            valid.sort_by(|a, b| b.2.confidence.partial_cmp(&a.2.confidence).unwrap_or(std::cmp::Ordering::Equal));
+            Ok(valid.into_iter().map(|(start, end, _)| (start, end)).collect())
+        }
+        SegmentOrder::Custom(ids) => {
+            let valid = filter_valid_segments_with_ref(segments, total_duration);
+            let mut ordered = Vec::with_capacity(ids.len());
+            for id in ids {
+                if !segments.iter().any(|s| &s.id == id) {
+                    return Err(AppError::InvalidArgument(format!("片段不存在: {}", id)));
+                }
+                // 已移除或超出视频范围的片段跳过
+                if let Some((start, end, _)) = valid.iter().find(|(_, _, s)| &s.id == id) {
+                    ordered.push((*start, *end));
+                } else {
+                    info!("[FFMPEG] 自定义顺序中的片段无效，跳过: {}", id);
+                }
+            }
+            Ok(ordered)
+        }
+    }
+}
+
+/// 估算导出文件大小（字节）
+/// codec 为 "copy" 时按源视频平均码率计算；否则按编码器与 CRF 的经验码率估算：
+/// 以 CRF 23 时的每像素比特数为基准，CRF 每增加 6 码率约减半，
+/// 有音频流时再加上 AAC 音频码率（audio_bitrate kbps，None 使用 DEFAULT_AUDIO_BITRATE_KBPS）。
+/// 结果为近似值（误差约 30% 以内），仅用于导出前提示。
+pub fn estimate_output_size(
+    video_info: &VideoInfo,
+    segments: &[(f64, f64)],
+    codec: &str,
+    crf: Option<u32>,
+    audio_bitrate: Option<u32>,
+) -> u64 {
+    let duration: f64 = segments.iter().map(|(s, e)| (e - s).max(0.0)).sum();
+    if duration <= 0.0 {
+        return 0;
+    }
+
+    let bits_per_second = if codec == "copy" {
+        if video_info.bitrate > 0 {
+            video_info.bitrate as f64
+        } else if video_info.duration > 0.0 {
+            video_info.size as f64 * 8.0 / video_info.duration
+        } else {
+            0.0
+        }
+    } else {
+        let codec = codec.to_lowercase();
+        // CRF 23 时的每像素每帧比特数（经验值）
+        let (base_bpp, default_crf) = if codec.contains("265") || codec.contains("hevc") {
+            (0.06, 28)
+        } else if codec.contains("av1") {
+            (0.045, 30)
+        } else if codec.contains("vp9") {
+            (0.06, 31)
+        } else {
+            (0.10, 23)
+        };
+        let crf = crf.unwrap_or(default_crf) as f64;
+        let bpp = base_bpp * 2f64.powf((default_crf as f64 - crf) / 6.0);
+        let fps = if video_info.fps > 0.0 { video_info.fps } else { 30.0 };
+        let video_bps = bpp * video_info.width as f64 * video_info.height as f64 * fps;
+        let audio_bps = if video_info.audio_codec.is_empty() {
+            0.0
+        } else {
+            audio_bitrate.unwrap_or(DEFAULT_AUDIO_BITRATE_KBPS) as f64 * 1000.0
+        };
+        video_bps + audio_bps
+    };
+
+    // 容器封装开销约 2%
+    (bits_per_second / 8.0 * duration * 1.02).round() as u64
+}
+
+/// 源文件版本标识（大小与修改时间），源文件被替换或重新编码后随之变化
+///
+/// 网络输入或无法读取元数据时返回 None。
+fn source_version(input_path: &str) -> Option<String> {
+    if is_url(input_path) {
+        return None;
+    }
+    let meta = fs::metadata(input_path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(format!("{}|{}.{}", meta.len(), mtime.as_secs(), mtime.subsec_nanos()))
+}
+
+/// 片段键摘要：输入、源文件版本、时间范围与编码参数
+fn segment_key_digest(input_path: &str, source: &str, start: f64, end: f64, options: &EncodeOptions) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(format!(
+        "{}|{}|{:.3}|{:.3}|{:?}|{:?}|{:?}|{}|{:?}|{:?}|{}|{:?}",
+        input_path,
+        source,
+        start,
+        end,
+        options.audio_stem,
+        options.volume_filter(start, end),
+        options.crop.as_ref().map(|c| c.filter(start, end)),
+        options.strip_metadata,
+        options.preset,
+        options.audio_bitrate,
+        SEGMENT_NORMALIZE_ARGS.join(" "),
+        options.gap_clip,
+    ));
+    hasher.finalize().to_vec()
+}
+
+/// 可恢复导出的片段文件名键
+///
+/// 输入（含源文件大小与修改时间）、时间范围或编码参数任一变化都会得到不同的键，
+/// 避免源文件在两次导出之间被替换后复用旧片段。
+fn resumable_segment_key(input_path: &str, start: f64, end: f64, options: &EncodeOptions) -> String {
+    let source = source_version(input_path).unwrap_or_default();
+    hex::encode(&segment_key_digest(input_path, &source, start, end, options)[..8])
+}
+
+/// 片段缓存键，与可恢复键使用相同参数但摘要更长
+///
+/// 网络输入或无法读取源文件元数据时返回 None（不缓存）。
+fn segment_cache_key(input_path: &str, start: f64, end: f64, options: &EncodeOptions) -> Option<String> {
+    let source = source_version(input_path)?;
+    Some(hex::encode(&segment_key_digest(input_path, &source, start, end, options)[..16]))
+}
+
+/// 片段缓存中正在写入的临时文件后缀，淘汰时跳过
+const SEGMENT_CACHE_PARTIAL_SUFFIX: &str = ".partial.ts";
+
+/// 按 LRU（修改时间）淘汰片段缓存，直到总大小不超过 max_bytes
+///
+/// 正在写入的临时文件不参与淘汰。返回释放的字节数。
+pub fn evict_segment_cache(cache_dir: &Path, max_bytes: u64) -> u64 {
+    let Ok(entries) = fs::read_dir(cache_dir) else { return 0 };
+
+    let mut files: Vec<(std::path::PathBuf, u64, std::time::SystemTime)> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.file_name().to_string_lossy().ends_with(SEGMENT_CACHE_PARTIAL_SUFFIX))
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            meta.is_file().then(|| (e.path(), meta.len(), meta.modified().unwrap_or(std::time::UNIX_EPOCH)))
+        })
+        .collect();
+    // 最久未使用的排在前面
+    files.sort_by_key(|(_, _, mtime)| *mtime);
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    let mut freed = 0u64;
+    for (path, size, _) in files {
+        if total <= max_bytes {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= size;
+            freed += size;
+        }
+    }
+
+    if freed > 0 {
+        info!("[FFMPEG] 片段缓存淘汰: 释放 {} 字节，剩余 {} 字节", freed, total);
+    }
+    freed
+}
+
 /// 记录片段筛选日志（公共函数，避免代码重复）
 fn log_segment_filter_stats(segments: &[Segment], valid_count: usize) {
-    let not_removed = segments.iter().filter(|s| s.status != SegmentStatus::Removed).count();
+    let detected = segments.iter().filter(|s| s.status == SegmentStatus::Detected).count();
+    let provisional = segments.iter().filter(|s| s.status == SegmentStatus::Provisional).count();
     let removed = segments.iter().filter(|s| s.status == SegmentStatus::Removed).count();
-    let music = segments.iter().filter(|s| s.segment_type == SegmentType::Music && s.status != SegmentStatus::Removed).count();
-    let person = segments.iter().filter(|s| s.segment_type == SegmentType::Person && s.status != SegmentStatus::Removed).count();
+    let music = segments.iter().filter(|s| s.segment_type == SegmentType::Music && s.status == SegmentStatus::Detected).count();
+    let person = segments.iter().filter(|s| s.segment_type == SegmentType::Person && s.status == SegmentStatus::Detected).count();
     info!(
-        "[FFMPEG] 片段筛选: 总计 {} 个, 未移除 {} 个 (music={}, person={}), 已移除 {} 个, 最终有效 {} 个",
-        segments.len(), not_removed, music, person, removed, valid_count
+        "[FFMPEG] 片段筛选: 总计 {} 个, 已确认 {} 个 (music={}, person={}), 待确认 {} 个, 已移除 {} 个, 最终有效 {} 个",
+        segments.len(), detected, music, person, provisional, removed, valid_count
     );
 }
 
@@ -1023,16 +2055,29 @@ pub(crate) fn smart_concat_segments(
     progress_callback: Option<ProgressCallback>,
     cancel_flag: Arc<AtomicBool>,
     project_id: &str,
-    prefer_lossless: bool,
+    options: &EncodeOptions,
 ) -> AppResult<()> {
+    let prefer_lossless = options.prefer_lossless && !options.requires_reencode();
     let mode_str = if prefer_lossless { "智能（优先无损）" } else { "重编码" };
     info!("[FFMPEG] 开始{}分段合并，共 {} 个片段", mode_str, segments.len());
 
-    // 创建临时目录
-    let temp_dir = tempfile::tempdir()?;
-    let temp_path = temp_dir.path();
+    // 创建临时目录（指定持久工作目录时使用该目录，中断后可恢复）
+    let temp_dir;
+    let temp_path: &Path = match options.work_dir {
+        Some(ref dir) => {
+            fs::create_dir_all(dir)?;
+            info!("[FFMPEG] 使用可恢复工作目录: {}", dir.display());
+            dir
+        }
+        None => {
+            temp_dir = create_operation_temp_dir(&format!("{}_concat_", project_id))?;
+            temp_dir.path()
+        }
+    };
 
     let total_segments = segments.len();
+    // 中间片段统一输出 .ts，容器参数只作用于最终合并输出
+    let segment_options = EncodeOptions { container: None, ..options.clone() };
 
     // 获取源文件扩展名
     let source_ext = Path::new(input_path)
@@ -1061,7 +2106,7 @@ pub(crate) fn smart_concat_segments(
             info!("[FFMPEG] 无损剪辑片段 {}/{}: {:.2}s - {:.2}s", i + 1, total_segments, start, end);
 
             // 尝试无损剪辑
-            match lossless_cut_segment(input_path, &segment_path, *start, *end, &[&cancel_flag], project_id)? {
+            match lossless_cut_segment(input_path, &segment_path, *start, *end, &[&cancel_flag], project_id, options.strip_metadata, None)? {
                 true => {
                     segment_files.push(segment_path);
                     // 更新进度（无损成功时：片段导出占 95%，合并占 5%）
@@ -1093,20 +2138,82 @@ pub(crate) fn smart_concat_segments(
     if !use_lossless {
         info!("[FFMPEG] 使用重编码模式导出所有片段...");
 
-        for (i, (start, end)) in segments.iter().enumerate() {
+        let pieces = fill_gaps(segments, options.gap_fill);
+        let total_segments = pieces.len();
+        if total_segments > segments.len() {
+            info!("[FFMPEG] 间隙填充（{:?}）: 插入 {} 个填充片段", options.gap_fill, total_segments - segments.len());
+        }
+
+        for (i, (start, end, gap_clip)) in pieces.iter().enumerate() {
+            let gap_options;
+            let piece_options = match gap_clip {
+                Some(mode) => {
+                    gap_options = EncodeOptions { gap_clip: Some(*mode), ..segment_options.clone() };
+                    &gap_options
+                }
+                None => &segment_options,
+            };
+
             if cancel_flag.load(Ordering::SeqCst) {
                 info!("[FFMPEG] 分段合并被取消（重编码阶段）");
                 return Err(AppError::Cancelled);
             }
 
+            // 优先使用片段缓存（跨导出复用，源文件或参数变化时自动失效）
+            let cache_key = options.segment_cache_dir.as_ref()
+                .and_then(|_| segment_cache_key(input_path, *start, *end, piece_options));
+            if let (Some(cache_dir), Some(key)) = (options.segment_cache_dir.as_ref(), cache_key) {
+                let cached_file = cache_dir.join(format!("{}.ts", key));
+                if fs::metadata(&cached_file).map(|m| m.len() > 0).unwrap_or(false) {
+                    info!("[FFMPEG] 片段缓存命中 {}/{}: {:.2}s - {:.2}s", i + 1, total_segments, start, end);
+                    // 刷新修改时间，供 LRU 淘汰使用
+                    if let Ok(file) = fs::File::options().write(true).open(&cached_file) {
+                        let _ = file.set_modified(std::time::SystemTime::now());
+                    }
+                } else {
+                    info!("[FFMPEG] 重编码片段 {}/{}（写入缓存）: {:.2}s - {:.2}s", i + 1, total_segments, start, end);
+                    fs::create_dir_all(cache_dir)?;
+                    // 先写入唯一命名的临时文件，完成后重命名为缓存文件：
+                    // 失败或取消时临时文件随 drop 删除，并发导出同一片段也不会互相覆盖
+                    let partial_file = tempfile::Builder::new()
+                        .prefix(&format!("{}.", key))
+                        .suffix(SEGMENT_CACHE_PARTIAL_SUFFIX)
+                        .tempfile_in(cache_dir)?;
+                    encode_segment(input_path, &partial_file.path().to_string_lossy(), *start, *end, piece_options, &[&cancel_flag], project_id)?;
+                    partial_file.persist(&cached_file).map_err(|e| AppError::Io(e.error))?;
+                }
+                segment_files.push(cached_file.to_string_lossy().to_string());
+                if let Some(ref cb) = progress_callback {
+                    cb(((i + 1) as f32 / total_segments as f32) * 0.95);
+                }
+                continue;
+            }
+
             // 重编码模式使用 .ts 格式（更适合拼接）
-            let segment_file = temp_path.join(format!("segment_{:04}.ts", i));
+            let segment_file = if options.work_dir.is_some() {
+                temp_path.join(format!("segment_{:04}_{}.ts", i, resumable_segment_key(input_path, *start, *end, piece_options)))
+            } else {
+                temp_path.join(format!("segment_{:04}.ts", i))
+            };
             let segment_path = segment_file.to_string_lossy().to_string();
 
-            info!("[FFMPEG] 重编码片段 {}/{}: {:.2}s - {:.2}s", i + 1, total_segments, start, end);
+            if options.work_dir.is_some() {
+                // 片段先写入 .partial.ts，完成后重命名，因此已存在的非空片段必定完整
+                let complete = fs::metadata(&segment_file).map(|m| m.len() > 0).unwrap_or(false);
+                if complete {
+                    info!("[FFMPEG] 复用已完成片段 {}/{}: {:.2}s - {:.2}s", i + 1, total_segments, start, end);
+                } else {
+                    info!("[FFMPEG] 重编码片段 {}/{}: {:.2}s - {:.2}s", i + 1, total_segments, start, end);
+                    let partial_file = segment_file.with_extension("partial.ts");
+                    encode_segment(input_path, &partial_file.to_string_lossy(), *start, *end, piece_options, &[&cancel_flag], project_id)?;
+                    fs::rename(&partial_file, &segment_file)?;
+                }
+            } else {
+                info!("[FFMPEG] 重编码片段 {}/{}: {:.2}s - {:.2}s", i + 1, total_segments, start, end);
 
-            // 重编码导出
-            encode_segment(input_path, &segment_path, *start, *end, &[&cancel_flag], project_id)?;
+                // 重编码导出
+                encode_segment(input_path, &segment_path, *start, *end, piece_options, &[&cancel_flag], project_id)?;
+            }
 
             segment_files.push(segment_path);
 
@@ -1140,15 +2247,16 @@ pub(crate) fn smart_concat_segments(
     let concat_list_str = concat_list_path.to_string_lossy().to_string();
     let merge_start_time = std::time::Instant::now();
 
+    let container_args = container_output_args(output_path, options.container);
+    let mut merge_args = vec!["-f", "concat", "-safe", "0", "-i", concat_list_str.as_str(), "-c", "copy"];
+    if options.strip_metadata {
+        merge_args.extend_from_slice(STRIP_METADATA_ARGS);
+    }
+    merge_args.extend(container_args.iter().map(String::as_str));
+    merge_args.extend(["-y", output_path]);
+
     let mut child = hidden_command(&ffmpeg_path)
-        .args([
-            "-f", "concat",
-            "-safe", "0",
-            "-i", &concat_list_str,
-            "-c", "copy",
-            "-y",
-            output_path,
-        ])
+        .args(&merge_args)
         .stdout(Stdio::null())
         .stderr(Stdio::piped())
         .spawn()
@@ -1214,7 +2322,8 @@ pub(crate) fn smart_concat_segments(
 }
 
 /// 导出视频（可选模式）
-/// prefer_lossless: true 优先无损剪辑（快速），false 强制重编码（精确）
+/// options.prefer_lossless: true 优先无损剪辑（快速），false 强制重编码（精确）
+/// order: 片段拼接顺序，非时间顺序时不合并重叠片段
 pub fn export_video_with_mode(
     input_path: &str,
     output_path: &str,
@@ -1222,7 +2331,8 @@ pub fn export_video_with_mode(
     progress_callback: Option<ProgressCallback>,
     cancel_flag: Arc<AtomicBool>,
     project_id: &str,
-    prefer_lossless: bool,
+    options: &EncodeOptions,
+    order: &SegmentOrder,
 ) -> AppResult<()> {
     // 检查取消标志
     if cancel_flag.load(Ordering::SeqCst) {
@@ -1230,7 +2340,7 @@ pub fn export_video_with_mode(
         return Err(AppError::Cancelled);
     }
 
-    let mode_str = if prefer_lossless { "智能（优先无损）" } else { "重编码" };
+    let mode_str = if options.prefer_lossless { "智能（优先无损）" } else { "重编码" };
     info!("[FFMPEG] 开始导出视频（{}模式）", mode_str);
     info!("[FFMPEG] 输入: {}", input_path);
     info!("[FFMPEG] 输出: {}", output_path);
@@ -1257,8 +2367,14 @@ pub fn export_video_with_mode(
         return Err(AppError::Video("没有可导出的片段".to_string()));
     }
 
-    // 合并重叠片段，避免重复内容
-    let merged_segments = merge_overlapping_segments(&keep_segments);
+    // 按时间顺序时合并重叠片段，避免重复内容；其他顺序按指定顺序拼接，不合并
+    let merged_segments = plan_ordered_export_segments(segments, total_duration, order)?;
+    if merged_segments.is_empty() {
+        return Err(AppError::Video("没有可导出的片段".to_string()));
+    }
+    if *order != SegmentOrder::Chronological {
+        info!("[FFMPEG] 拼接顺序: {:?}（跳过重叠合并）", order);
+    }
 
     // 记录最终导出的片段时间范围
     for (i, (start, end)) in merged_segments.iter().enumerate() {
@@ -1266,7 +2382,7 @@ pub fn export_video_with_mode(
     }
     let total_export_duration: f64 = merged_segments.iter().map(|(s, e)| e - s).sum();
     info!("[FFMPEG] 导出总时长: {:.2}s / 视频总时长: {:.2}s ({:.1}%)", total_export_duration, total_duration, total_export_duration / total_duration * 100.0);
-    if merged_segments.len() < keep_segments.len() {
+    if *order == SegmentOrder::Chronological && merged_segments.len() < keep_segments.len() {
         info!(
             "[FFMPEG] 合并重叠片段: {} -> {} 个",
             keep_segments.len(),
@@ -1280,7 +2396,8 @@ pub fn export_video_with_mode(
     }
 
     // 使用智能分段合并（优先无损，失败回退重编码）
-    let result = smart_concat_segments(input_path, output_path, &merged_segments, progress_callback, cancel_flag, project_id, prefer_lossless);
+    let options = options.with_segment_gains(segments);
+    let result = smart_concat_segments(input_path, output_path, &merged_segments, progress_callback, cancel_flag, project_id, &options);
 
     if result.is_ok() {
         info!("[FFMPEG] 导出视频完成: {}", output_path);
@@ -1289,8 +2406,49 @@ pub fn export_video_with_mode(
     result
 }
 
+/// 导出反向片段（移除匹配片段后的剩余部分）
+/// options.prefer_lossless: true 优先无损剪辑（快速），false 强制重编码（精确）
+/// 片段音量增益只作用于匹配片段，反向导出不应用
+pub fn export_inverse_video_with_mode(
+    input_path: &str,
+    output_path: &str,
+    segments: &[Segment],
+    progress_callback: Option<ProgressCallback>,
+    cancel_flag: Arc<AtomicBool>,
+    project_id: &str,
+    options: &EncodeOptions,
+) -> AppResult<()> {
+    if cancel_flag.load(Ordering::SeqCst) {
+        info!("[FFMPEG] 反向导出被取消（启动前）");
+        return Err(AppError::Cancelled);
+    }
+
+    let total_duration = get_video_info(input_path)?.duration;
+    let inverse_segments = calculate_inverse_segments(segments, total_duration);
+
+    let inverse_total_duration: f64 = inverse_segments.iter().map(|(s, e)| e - s).sum();
+    info!(
+        "[FFMPEG] 反向导出 {} 个片段, 总时长 {:.2}s / 视频总时长 {:.2}s",
+        inverse_segments.len(), inverse_total_duration, total_duration
+    );
+    for (i, (start, end)) in inverse_segments.iter().enumerate() {
+        info!("[FFMPEG]   反向片段[{}]: {:.2}s - {:.2}s (时长 {:.2}s)", i, start, end, end - start);
+    }
+
+    if inverse_segments.is_empty() {
+        return Err(AppError::Video("匹配片段覆盖了整个视频，没有可导出的剩余部分".to_string()));
+    }
+
+    if let Some(parent) = Path::new(output_path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let options = EncodeOptions { segment_gains: Vec::new(), ..options.clone() };
+    smart_concat_segments(input_path, output_path, &inverse_segments, progress_callback, cancel_flag, project_id, &options)
+}
+
 /// 分别导出视频片段（可选模式）
-/// prefer_lossless: true 优先无损剪辑（快速），false 强制重编码（精确）
+/// options.prefer_lossless: true 优先无损剪辑（快速），false 强制重编码（精确）
 pub fn export_video_separately_with_mode(
     input_path: &str,
     output_dir: &str,
@@ -1298,8 +2456,9 @@ pub fn export_video_separately_with_mode(
     progress_callback: Option<ProgressCallback>,
     cancel_flag: Arc<AtomicBool>,
     project_id: &str,
-    prefer_lossless: bool,
+    options: &EncodeOptions,
 ) -> AppResult<Vec<String>> {
+    let prefer_lossless = options.prefer_lossless;
     // 检查取消标志
     if cancel_flag.load(Ordering::SeqCst) {
         info!("[FFMPEG] 分别导出被取消（启动前）");
@@ -1373,10 +2532,14 @@ pub fn export_video_separately_with_mode(
     // 限制源文件名长度
     let safe_source_name: String = safe_source_name.chars().take(30).collect();
 
-    let source_ext = Path::new(input_path)
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("mp4");
+    // 指定容器时使用容器扩展名，否则沿用源文件扩展名
+    let source_ext = match options.container {
+        Some(container) => container.extension(),
+        None => Path::new(input_path)
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("mp4"),
+    };
 
     // 进度计数器
     let completed_count = Arc::new(AtomicUsize::new(0));
@@ -1393,7 +2556,8 @@ pub fn export_video_separately_with_mode(
 
     // 预先生成所有输出路径（保持顺序），使用已修正的时间范围
     // 添加序号前缀防止文件名冲突
-    let tasks: Vec<(usize, f64, f64, String)> = export_segments
+    // 每个任务携带该片段自身的音量增益
+    let tasks: Vec<(usize, f64, f64, String, EncodeOptions)> = export_segments
         .iter()
         .enumerate()
         .map(|(i, (start_time, end_time, segment))| {
@@ -1427,7 +2591,8 @@ pub fn export_video_separately_with_mode(
                 source_ext
             );
             let output_path = Path::new(output_dir).join(&output_filename);
-            (i, *start_time, *end_time, output_path.to_string_lossy().to_string())
+            let segment_options = options.with_segment_gains(std::slice::from_ref(*segment));
+            (i, *start_time, *end_time, output_path.to_string_lossy().to_string(), segment_options)
         })
         .collect();
 
@@ -1442,7 +2607,7 @@ pub fn export_video_separately_with_mode(
     let results: Vec<Result<String, AppError>> = pool.install(|| {
         tasks
             .par_iter()
-            .map(|(i, start_time, end_time, output_path_str)| {
+            .map(|(i, start_time, end_time, output_path_str, segment_options)| {
                 // 检查取消标志（包括用户取消和其他任务失败导致的内部取消）
                 if cancel_flag.load(Ordering::SeqCst) {
                     // 用户取消，同步设置内部取消标志
@@ -1462,7 +2627,7 @@ pub fn export_video_separately_with_mode(
                 );
 
                 // 智能剪辑导出单个片段（优先无损，失败回退重编码）
-                match smart_cut_segment(input_path, output_path_str, *start_time, *end_time, &[&cancel_flag, &internal_cancel], project_id, prefer_lossless) {
+                match smart_cut_segment(input_path, output_path_str, *start_time, *end_time, &[&cancel_flag, &internal_cancel], project_id, segment_options) {
                     Ok(()) => {
                         // 更新进度（确保单调递增）
                         let completed = completed_count.fetch_add(1, Ordering::SeqCst) + 1;
@@ -1540,6 +2705,8 @@ pub fn export_video_separately_with_mode(
 
 /// 导出自定义剪辑片段（可选模式）
 /// prefer_lossless: true 优先无损剪辑（快速），false 强制重编码（精确）
+/// audio_bitrate: 重编码时的音频码率（kbps），None 使用默认值
+#[allow(clippy::too_many_arguments)]
 pub fn export_custom_segment_with_mode(
     input_path: &str,
     output_path: &str,
@@ -1549,6 +2716,7 @@ pub fn export_custom_segment_with_mode(
     cancel_flag: Arc<AtomicBool>,
     project_id: &str,
     prefer_lossless: bool,
+    audio_bitrate: Option<u32>,
 ) -> AppResult<()> {
     // 检查取消标志
     if cancel_flag.load(Ordering::SeqCst) {
@@ -1574,7 +2742,7 @@ pub fn export_custom_segment_with_mode(
             cb(0.1);  // 开始无损尝试
         }
 
-        match lossless_cut_segment(input_path, output_path, start_time, end_time, &[&cancel_flag], project_id)? {
+        match lossless_cut_segment(input_path, output_path, start_time, end_time, &[&cancel_flag], project_id, false, None)? {
             true => {
                 // 无损剪辑成功
                 info!("[FFMPEG] 自定义剪辑导出完成（无损模式）: {}", output_path);
@@ -1594,10 +2762,11 @@ pub fn export_custom_segment_with_mode(
     }
 
     // 重编码模式（带进度报告）
-    export_custom_segment_reencode(input_path, output_path, start_time, end_time, duration, progress_callback, cancel_flag, project_id)
+    export_custom_segment_reencode(input_path, output_path, start_time, end_time, duration, progress_callback, cancel_flag, project_id, audio_bitrate)
 }
 
 /// 重编码导出自定义剪辑片段（内部函数，带进度报告）
+#[allow(clippy::too_many_arguments)]
 fn export_custom_segment_reencode(
     input_path: &str,
     output_path: &str,
@@ -1607,14 +2776,10 @@ fn export_custom_segment_reencode(
     progress_callback: Option<ProgressCallback>,
     cancel_flag: Arc<AtomicBool>,
     project_id: &str,
+    audio_bitrate: Option<u32>,
 ) -> AppResult<()> {
     let ffmpeg_path = resolve_tool_path("ffmpeg");
 
-    // 判断输出格式，决定是否添加 movflags
-    let is_mp4 = output_path.to_lowercase().ends_with(".mp4")
-        || output_path.to_lowercase().ends_with(".m4v")
-        || output_path.to_lowercase().ends_with(".mov");
-
     let mut args = vec![
         "-progress".to_string(), "pipe:1".to_string(),
         "-ss".to_string(), start_time.to_string(),
@@ -1626,15 +2791,12 @@ fn export_custom_segment_reencode(
         "-threads".to_string(), "0".to_string(),
         "-force_key_frames".to_string(), "expr:eq(n,0)".to_string(),
         "-c:a".to_string(), "aac".to_string(),
-        "-b:a".to_string(), "192k".to_string(),
+        "-b:a".to_string(), format!("{}k", audio_bitrate.unwrap_or(DEFAULT_AUDIO_BITRATE_KBPS)),
         "-avoid_negative_ts".to_string(), "make_zero".to_string(),
     ];
 
-    // MP4 格式添加 faststart
-    if is_mp4 {
-        args.push("-movflags".to_string());
-        args.push("+faststart".to_string());
-    }
+    // 容器参数（MP4/MOV 添加 faststart）
+    args.extend(container_output_args(output_path, None));
 
     args.push("-y".to_string());
     args.push(output_path.to_string());
@@ -1648,7 +2810,7 @@ fn export_custom_segment_reencode(
         .map_err(|e| AppError::FFmpeg(format!("ffmpeg 执行失败: {}", e)))?;
 
     // 注册子进程句柄，支持即时取消
-    let child_handle = crate::commands::video::register_child_process(project_id, child);
+    let child_handle = crate::commands::video::register_child_process(project_id, "custom_reencode", child);
 
     // 取出 stdout 后释放锁
     let stdout = {
@@ -1886,3 +3048,743 @@ fn run_ffmpeg_with_progress_and_cancel(
 
     Ok(())
 }
+
+/// 运行 FFmpeg 分析类滤镜，返回完整的 stderr 输出
+/// 分析结果（silencedetect / showinfo 等）写入 stderr，进度通过 -progress pipe:1 从 stdout 读取。
+/// stderr 在独立线程中持续读取，避免输出量大时管道缓冲区写满导致 ffmpeg 阻塞。
+fn run_ffmpeg_analysis(
+    args: &[String],
+    total_duration: f64,
+    progress_callback: Option<ProgressCallback>,
+    cancel_flag: Arc<AtomicBool>,
+    project_id: &str,
+) -> AppResult<String> {
+    let mut cmd_args = vec![
+        "-nostats".to_string(),
+        "-progress".to_string(), "pipe:1".to_string(),
+    ];
+    cmd_args.extend(args.iter().cloned());
+
+    let ffmpeg_path = resolve_tool_path("ffmpeg");
+    let child = hidden_command(&ffmpeg_path)
+        .args(&cmd_args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::FFmpeg(format!("ffmpeg 启动失败: {}", e)))?;
+
+    // 注册子进程句柄，支持即时取消
+    let child_handle = crate::commands::video::register_child_process(project_id, "analysis", child);
+
+    // 取出 stdout/stderr 后释放锁
+    let (stdout, stderr) = {
+        let mut guard = child_handle.lock().unwrap();
+        let child = guard.as_mut()
+            .ok_or_else(|| AppError::FFmpeg("子进程句柄已被释放".into()))?;
+        let stdout = child.stdout.take()
+            .ok_or_else(|| AppError::FFmpeg("无法获取 FFmpeg 输出流".into()))?;
+        let stderr = child.stderr.take()
+            .ok_or_else(|| AppError::FFmpeg("无法获取 FFmpeg 错误输出流".into()))?;
+        (stdout, stderr)
+    };
+
+    // stderr 在独立线程中阻塞读取，进程退出后管道关闭，线程自然结束
+    let stderr_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = BufReader::new(stderr).read_to_end(&mut buf);
+        String::from_utf8_lossy(&buf).to_string()
+    });
+
+    // 设置非阻塞模式
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::Foundation::HANDLE;
+        use windows_sys::Win32::System::Pipes::SetNamedPipeHandleState;
+        use windows_sys::Win32::System::Pipes::PIPE_NOWAIT;
+        let handle = stdout.as_raw_handle() as HANDLE;
+        unsafe {
+            let mut mode = PIPE_NOWAIT;
+            SetNamedPipeHandleState(handle, &mut mode, std::ptr::null_mut(), std::ptr::null_mut());
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        let fd = stdout.as_raw_fd();
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFL);
+            libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+    }
+
+    let mut reader = BufReader::new(stdout);
+    let mut line_buffer = String::new();
+
+    loop {
+        // 检查取消标志
+        if cancel_flag.load(Ordering::SeqCst) {
+            if let Ok(mut guard) = child_handle.lock() {
+                if let Some(ref mut child) = *guard {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+            }
+            info!("[FFMPEG] 分析任务被取消: project_id={}", project_id);
+            return Err(AppError::Cancelled);
+        }
+
+        // 检查进程是否结束（短暂获取锁）
+        let try_wait_result = {
+            let mut guard = child_handle.lock().unwrap();
+            if let Some(ref mut child) = *guard {
+                child.try_wait()
+            } else {
+                info!("[FFMPEG] 分析任务被取消（进程已终止）: project_id={}", project_id);
+                return Err(AppError::Cancelled);
+            }
+        };
+
+        match try_wait_result {
+            Ok(Some(status)) => {
+                let stderr_output = stderr_reader.join().unwrap_or_default();
+                if !status.success() {
+                    // 只保留末尾几行错误信息，避免错误消息过长
+                    let tail: Vec<&str> = stderr_output.lines().rev().take(5).collect();
+                    let tail: Vec<&str> = tail.into_iter().rev().collect();
+                    return Err(AppError::FFmpeg(format!("FFmpeg 分析失败: {}", tail.join("\n"))));
+                }
+                if let Some(ref cb) = progress_callback {
+                    cb(1.0);
+                }
+                return Ok(stderr_output);
+            }
+            Ok(None) => {
+                // 进程仍在运行，尝试读取进度
+                match reader.read_line(&mut line_buffer) {
+                    Ok(0) => {
+                        std::thread::sleep(std::time::Duration::from_millis(100));
+                    }
+                    Ok(_) => {
+                        if let Some(caps) = TIME_REGEX.captures(&line_buffer) {
+                            if let Some(time_ms) = caps.get(1) {
+                                if let Ok(ms) = time_ms.as_str().parse::<f64>() {
+                                    let current_time = ms / 1_000_000.0;
+                                    if total_duration > 0.0 {
+                                        let progress = (current_time / total_duration).min(1.0);
+                                        if let Some(ref cb) = progress_callback {
+                                            cb(progress as f32);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        line_buffer.clear();
+                    }
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(std::time::Duration::from_millis(100));
+                    }
+                    Err(_) => {
+                        std::thread::sleep(std::time::Duration::from_millis(100));
+                    }
+                }
+            }
+            Err(e) => {
+                if let Ok(mut guard) = child_handle.lock() {
+                    if let Some(ref mut child) = *guard {
+                        let _ = child.kill();
+                    }
+                }
+                return Err(AppError::FFmpeg(format!("检查进程状态失败: {}", e)));
+            }
+        }
+    }
+}
+
+/// 检测静音区间
+/// threshold_db: 静音阈值（dB，如 -30.0），低于该音量视为静音
+/// min_duration: 最短静音时长（秒），短于该时长的静音会被忽略
+/// 返回按时间排序的 (start, end) 静音区间列表
+pub fn detect_silence(
+    input_path: &str,
+    threshold_db: f64,
+    min_duration: f64,
+    progress_callback: Option<ProgressCallback>,
+    cancel_flag: Arc<AtomicBool>,
+    project_id: &str,
+) -> AppResult<Vec<(f64, f64)>> {
+    detect_silence_in_range(input_path, None, threshold_db, min_duration, progress_callback, cancel_flag, project_id)
+}
+
+/// 检测指定时间范围内的静音区间
+/// range: (start, end)，None 表示整个文件；返回的区间仍使用源文件时间轴
+pub fn detect_silence_in_range(
+    input_path: &str,
+    range: Option<(f64, f64)>,
+    threshold_db: f64,
+    min_duration: f64,
+    progress_callback: Option<ProgressCallback>,
+    cancel_flag: Arc<AtomicBool>,
+    project_id: &str,
+) -> AppResult<Vec<(f64, f64)>> {
+    info!(
+        "[FFMPEG] 开始静音检测: {}, range={:?}, threshold={}dB, min_duration={}s",
+        input_path, range, threshold_db, min_duration
+    );
+
+    let mut args = Vec::new();
+    let (offset, total_duration) = match range {
+        Some((start, end)) => {
+            args.extend([
+                "-ss".to_string(), start.to_string(),
+                "-t".to_string(), (end - start).to_string(),
+            ]);
+            (start, end - start)
+        }
+        None => (0.0, get_audio_duration(input_path)?),
+    };
+    args.extend([
+        "-i".to_string(), input_path.to_string(),
+        "-vn".to_string(),
+        "-af".to_string(), format!("silencedetect=noise={}dB:d={}", threshold_db, min_duration),
+        "-f".to_string(), "null".to_string(),
+        "-".to_string(),
+    ]);
+
+    let stderr_output = run_ffmpeg_analysis(&args, total_duration, progress_callback, cancel_flag, project_id)?;
+    let intervals: Vec<(f64, f64)> = parse_silence_output(&stderr_output, total_duration)
+        .into_iter()
+        .map(|(s, e)| (s + offset, e + offset))
+        .collect();
+
+    let silence_total: f64 = intervals.iter().map(|(s, e)| e - s).sum();
+    info!(
+        "[FFMPEG] 静音检测完成: {} 个区间, 静音总时长 {:.2}s / {:.2}s",
+        intervals.len(), silence_total, total_duration
+    );
+
+    Ok(intervals)
+}
+
+/// 解析 silencedetect 输出
+/// 输出格式：
+///   [silencedetect @ 0x...] silence_start: 12.345
+///   [silencedetect @ 0x...] silence_end: 15.678 | silence_duration: 3.333
+/// 文件末尾仍处于静音时只有 silence_start，此时以总时长作为结束时间
+fn parse_silence_output(output: &str, total_duration: f64) -> Vec<(f64, f64)> {
+    let mut intervals = Vec::new();
+    let mut pending_start: Option<f64> = None;
+
+    for line in output.lines() {
+        if let Some(caps) = SILENCE_START_REGEX.captures(line) {
+            if let Ok(start) = caps[1].parse::<f64>() {
+                // 音频开头的静音可能输出微小的负值
+                pending_start = Some(start.max(0.0));
+            }
+        } else if let Some(caps) = SILENCE_END_REGEX.captures(line) {
+            if let (Some(start), Ok(end)) = (pending_start.take(), caps[1].parse::<f64>()) {
+                if end > start {
+                    intervals.push((start, end));
+                }
+            }
+        }
+    }
+
+    if let Some(start) = pending_start {
+        if total_duration > start {
+            intervals.push((start, total_duration));
+        }
+    }
+
+    intervals
+}
+
+/// 生成音频频谱图（PNG）
+///
+/// 使用 showspectrumpic 滤镜绘制整个文件的频谱，先写入临时文件再重命名，
+/// 取消或失败不会留下不完整的图片。showspectrumpic 在读完输入后才输出画面，
+/// 进度只在完成时更新。
+pub fn generate_spectrogram(
+    input_path: &str,
+    output_path: &str,
+    width: u32,
+    height: u32,
+    progress_callback: Option<ProgressCallback>,
+    cancel_flag: Arc<AtomicBool>,
+    project_id: &str,
+) -> AppResult<()> {
+    info!("[FFMPEG] 开始生成频谱图: {} -> {} ({}x{})", input_path, output_path, width, height);
+
+    if let Some(parent) = Path::new(output_path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let total_duration = get_audio_duration(input_path)?;
+    let temp_output = format!("{}.part", output_path);
+    let args = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vn".to_string(),
+        "-lavfi".to_string(), format!("showspectrumpic=s={}x{}:legend=1", width, height),
+        "-frames:v".to_string(), "1".to_string(),
+        "-f".to_string(), "image2".to_string(),
+        "-c:v".to_string(), "png".to_string(),
+        "-y".to_string(), temp_output.clone(),
+    ];
+
+    if let Err(e) = run_ffmpeg_analysis(&args, total_duration, progress_callback, cancel_flag, project_id) {
+        let _ = fs::remove_file(&temp_output);
+        return Err(e);
+    }
+    fs::rename(&temp_output, output_path)?;
+
+    info!("[FFMPEG] 频谱图生成完成: {}", output_path);
+    Ok(())
+}
+
+/// 检测场景切换点
+/// threshold: 场景变化阈值（0.0 - 1.0），越小越敏感
+/// 返回按时间排序的切换时间点（秒）
+pub fn detect_scene_changes(
+    input_path: &str,
+    threshold: f64,
+    progress_callback: Option<ProgressCallback>,
+    cancel_flag: Arc<AtomicBool>,
+    project_id: &str,
+) -> AppResult<Vec<f64>> {
+    info!("[FFMPEG] 开始场景切换检测: {}, threshold={}", input_path, threshold);
+
+    let video_info = get_video_info(input_path)?;
+    let total_duration = video_info.duration;
+
+    let args = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-an".to_string(),
+        "-vf".to_string(), format!("select='gt(scene,{})',showinfo", threshold),
+        "-f".to_string(), "null".to_string(),
+        "-".to_string(),
+    ];
+
+    let stderr_output = run_ffmpeg_analysis(&args, total_duration, progress_callback, cancel_flag, project_id)?;
+    let cut_points = parse_showinfo_timestamps(&stderr_output);
+
+    info!("[FFMPEG] 场景切换检测完成: {} 个切换点", cut_points.len());
+    Ok(cut_points)
+}
+
+/// 解析 showinfo 输出中的帧时间戳
+/// 输出格式：[Parsed_showinfo_1 @ 0x...] n:   0 pts:  12345 pts_time:4.115 ...
+fn parse_showinfo_timestamps(output: &str) -> Vec<f64> {
+    let mut timestamps: Vec<f64> = output
+        .lines()
+        .filter(|line| line.contains("showinfo"))
+        .filter_map(|line| SHOWINFO_PTS_REGEX.captures(line))
+        .filter_map(|caps| caps[1].parse::<f64>().ok())
+        .filter(|t| t.is_finite() && *t >= 0.0)
+        .collect();
+
+    timestamps.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    timestamps.dedup_by(|a, b| (*a - *b).abs() < 1e-3);
+    timestamps
+}
+
+/// 黑屏检测：最短持续时长（秒）与像素亮度阈值
+const BLACK_MIN_DURATION: f64 = 0.1;
+const BLACK_PIXEL_THRESHOLD: f64 = 0.10;
+/// 静止画面检测：噪声容差与最短持续时长（秒）
+const FREEZE_NOISE_DB: f64 = -60.0;
+const FREEZE_MIN_DURATION: f64 = 0.5;
+
+/// 检测黑屏与静止画面区间
+/// 同一次解码中串联 blackdetect 与 freezedetect，两类区间合并重叠部分后返回
+/// 返回按时间排序的 (start, end) 区间列表
+pub fn detect_black_frames(
+    input_path: &str,
+    progress_callback: Option<ProgressCallback>,
+    cancel_flag: Arc<AtomicBool>,
+    project_id: &str,
+) -> AppResult<Vec<(f64, f64)>> {
+    info!("[FFMPEG] 开始黑屏/静止画面检测: {}", input_path);
+
+    let video_info = get_video_info(input_path)?;
+    let total_duration = video_info.duration;
+
+    let args = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-an".to_string(),
+        "-vf".to_string(), format!(
+            "blackdetect=d={}:pix_th={},freezedetect=n={}dB:d={}",
+            BLACK_MIN_DURATION, BLACK_PIXEL_THRESHOLD, FREEZE_NOISE_DB, FREEZE_MIN_DURATION
+        ),
+        "-f".to_string(), "null".to_string(),
+        "-".to_string(),
+    ];
+
+    let stderr_output = run_ffmpeg_analysis(&args, total_duration, progress_callback, cancel_flag, project_id)?;
+    let intervals = parse_black_freeze_output(&stderr_output, total_duration);
+
+    let total: f64 = intervals.iter().map(|(s, e)| e - s).sum();
+    info!(
+        "[FFMPEG] 黑屏/静止画面检测完成: {} 个区间, 总时长 {:.2}s / {:.2}s",
+        intervals.len(), total, total_duration
+    );
+    Ok(intervals)
+}
+
+/// 解析 blackdetect / freezedetect 输出
+/// 输出格式：
+///   [blackdetect @ 0x...] black_start:0 black_end:2.5 black_duration:2.5
+///   [freezedetect @ 0x...] lavfi.freezedetect.freeze_start: 10.01
+///   [freezedetect @ 0x...] lavfi.freezedetect.freeze_duration: 3.2
+///   [freezedetect @ 0x...] lavfi.freezedetect.freeze_end: 13.21
+/// 文件末尾仍处于静止时只有 freeze_start，此时以总时长作为结束时间；
+/// 区间裁剪到 [0, total_duration]，排序后合并重叠部分
+fn parse_black_freeze_output(output: &str, total_duration: f64) -> Vec<(f64, f64)> {
+    let clamp = |t: f64| if total_duration > 0.0 { t.clamp(0.0, total_duration) } else { t.max(0.0) };
+    let mut intervals = Vec::new();
+    let mut pending_freeze: Option<f64> = None;
+
+    for line in output.lines() {
+        if let Some(caps) = BLACK_REGEX.captures(line) {
+            if let (Ok(start), Ok(end)) = (caps[1].parse::<f64>(), caps[2].parse::<f64>()) {
+                intervals.push((clamp(start), clamp(end)));
+            }
+        } else if let Some(caps) = FREEZE_START_REGEX.captures(line) {
+            if let Ok(start) = caps[1].parse::<f64>() {
+                pending_freeze = Some(clamp(start));
+            }
+        } else if let Some(caps) = FREEZE_END_REGEX.captures(line) {
+            if let (Some(start), Ok(end)) = (pending_freeze.take(), caps[1].parse::<f64>()) {
+                intervals.push((start, clamp(end)));
+            }
+        }
+    }
+
+    if let Some(start) = pending_freeze {
+        if total_duration > start {
+            intervals.push((start, total_duration));
+        }
+    }
+
+    intervals.retain(|(s, e)| s.is_finite() && e.is_finite() && e > s);
+    intervals.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    merge_overlapping_segments(&intervals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_black_freeze_output() {
+        let stderr = "[blackdetect @ 0x1] black_start:0 black_end:2.5 black_duration:2.5\n\
+                      [freezedetect @ 0x2] lavfi.freezedetect.freeze_start: 2\n\
+                      [freezedetect @ 0x2] lavfi.freezedetect.freeze_duration: 1.5\n\
+                      [freezedetect @ 0x2] lavfi.freezedetect.freeze_end: 3.5\n\
+                      frame= 100 fps=50 time=00:00:04.00\n\
+                      [freezedetect @ 0x2] lavfi.freezedetect.freeze_start: 55.5\n";
+        // 黑屏与静止区间重叠时合并；结尾未结束的静止区间延伸到总时长
+        let intervals = parse_black_freeze_output(stderr, 60.0);
+        assert_eq!(intervals, vec![(0.0, 3.5), (55.5, 60.0)]);
+        assert!(parse_black_freeze_output("", 60.0).is_empty());
+    }
+
+    #[test]
+    fn test_fill_gaps() {
+        let segments = [(2.0, 4.0), (4.005, 6.0), (8.0, 9.0)];
+        let pieces = fill_gaps(&segments, GapFill::Remove);
+        assert_eq!(pieces.len(), 3);
+        assert!(pieces.iter().all(|p| p.2.is_none()));
+
+        // 开头与片段之间的间隙插入填充片段，极小间隙视为连续
+        let pieces = fill_gaps(&segments, GapFill::Black);
+        assert_eq!(pieces, vec![
+            (0.0, 2.0, Some(GapFill::Black)),
+            (2.0, 4.0, None),
+            (4.005, 6.0, None),
+            (6.0, 8.0, Some(GapFill::Black)),
+            (8.0, 9.0, None),
+        ]);
+
+        assert_eq!(
+            segment_video_filter(None, Some((GapFill::Freeze, 2.0))),
+            "trim=end_frame=1,tpad=stop_mode=clone:stop_duration=2.000,scale=trunc(iw*sar/2)*2:ih,setsar=1"
+        );
+        assert_eq!(
+            segment_video_filter(Some("crop=640:360:0:0".to_string()), Some((GapFill::Black, 2.0))),
+            "crop=640:360:0:0,drawbox=c=black:t=fill,scale=trunc(iw*sar/2)*2:ih,setsar=1"
+        );
+    }
+
+    #[test]
+    fn test_follow_crop_from_boxes() {
+        let boxes = [
+            DetectionBox { time: 4.0, bbox: [0.6, 0.2, 1.0, 0.8], confidence: 0.9 },
+            DetectionBox { time: 1.0, bbox: [0.0, 0.0, 0.4, 1.0], confidence: 0.8 },
+        ];
+        let crop = FollowCrop::from_boxes(9, 16, &boxes);
+        assert_eq!(crop.keyframes.len(), 2);
+        assert_eq!(crop.keyframes[0], (1.0, 0.2, 0.5));
+        assert!((crop.keyframes[1].1 - 0.8).abs() < 1e-9);
+        // 有检测框时裁剪位置随时间变化，不再是居中常量
+        assert!(!crop.filter(0.0, 5.0).contains("x='clip((0.5)*iw"));
+        assert!(FollowCrop::from_boxes(9, 16, &[]).keyframes.is_empty());
+    }
+
+    #[test]
+    fn test_container_output_args() {
+        // 按扩展名推断：MP4/MOV 添加 faststart，MKV 与中间 .ts 片段不添加
+        assert_eq!(container_output_args("/out/a.MP4", None), vec!["-movflags", "+faststart"]);
+        assert_eq!(container_output_args("/out/a.mov", None), vec!["-movflags", "+faststart"]);
+        assert!(container_output_args("/out/a.mkv", None).is_empty());
+        assert!(container_output_args("/tmp/segment_0001.ts", None).is_empty());
+
+        // 显式容器优先于扩展名
+        assert_eq!(
+            container_output_args("/out/a.mp4", Some(OutputContainer::Mkv)),
+            vec!["-f", "matroska"]
+        );
+        assert_eq!(
+            container_output_args("/out/a.bin", Some(OutputContainer::Mov)),
+            vec!["-f", "mov", "-movflags", "+faststart"]
+        );
+    }
+
+    #[test]
+    fn test_check_container_codec() {
+        assert!(check_container_codec(OutputContainer::Mkv, "h264").is_ok());
+        assert!(check_container_codec(OutputContainer::Mkv, "vp9").is_ok());
+        assert!(check_container_codec(OutputContainer::Mp4, "H264").is_ok());
+        assert!(check_container_codec(OutputContainer::Mov, "vp9").is_err());
+        assert!(check_container_codec(OutputContainer::Mp4, "vp8").is_err());
+    }
+
+    /// 回归：拼接素材中 SAR 不同（如 1:1 与 4:3 的 1440x1080 片段）的两段，
+    /// 独立重编码后必须得到相同的 SAR 与时间基，否则 `-c copy` 拼接失败或卡顿
+    #[test]
+    fn test_segment_normalization_for_mixed_sar() {
+        // 裁剪在前，SAR 统一在后（裁剪不会再改变 SAR）
+        let filter = segment_video_filter(Some("crop=640:360:0:0".to_string()), None);
+        assert!(filter.starts_with("crop=640:360:0:0,"));
+
+        // 非方形像素按 SAR 缩放宽度（取偶数）后统一为 1:1
+        assert_eq!(segment_video_filter(None, None), "scale=trunc(iw*sar/2)*2:ih,setsar=1");
+
+        let args = SEGMENT_NORMALIZE_ARGS.join(" ");
+        assert!(args.contains("-vsync cfr"));
+        assert!(args.contains("-enc_time_base:v 1/90000"));
+    }
+
+    #[test]
+    fn test_parse_formats_output() {
+        let output = "File formats:\n D. = Demuxing supported\n .E = Muxing supported\n --\n D  aac             raw ADTS AAC\n  E adts            ADTS AAC\n DE matroska,webm   Matroska / WebM\n";
+        let (demuxers, muxers) = parse_formats_output(output);
+        assert_eq!(demuxers, vec!["aac", "matroska", "webm"]);
+        assert_eq!(muxers, vec!["adts", "matroska", "webm"]);
+
+        // ffmpeg 6.1+：分隔行为 ---，多一列设备标志
+        let output = "File formats:\n D.. = Demuxing supported\n .E. = Muxing supported\n ..d = Is a device\n ---\n D   aac             raw ADTS AAC (Advanced Audio Coding)\n  E  adts            ADTS AAC (Advanced Audio Coding)\n D d lavfi           Libavfilter virtual input device\n DE  matroska,webm   Matroska / WebM\n";
+        let (demuxers, muxers) = parse_formats_output(output);
+        assert_eq!(demuxers, vec!["aac", "lavfi", "matroska", "webm"]);
+        assert_eq!(muxers, vec!["adts", "matroska", "webm"]);
+    }
+
+    #[test]
+    fn test_parse_decoders_output() {
+        let output = "Decoders:\n V..... = Video\n A..... = Audio\n ------\n V....D hevc                 HEVC (High Efficiency Video Coding)\n A....D aac                  AAC (Advanced Audio Coding)\n S..... srt                  SubRip subtitle\n";
+        let (video, audio) = parse_decoders_output(output);
+        assert_eq!(video, vec!["hevc"]);
+        assert_eq!(audio, vec!["aac"]);
+    }
+
+    #[test]
+    fn test_probe_duration_from_format() {
+        let probe = r#"{"streams":[{"codec_type":"audio","duration":"12.000000"}],"format":{"duration":"12.500000"}}"#;
+        assert_eq!(parse_probe_duration(probe), Some(12.5));
+    }
+
+    #[test]
+    fn test_probe_duration_stream_only() {
+        // 裸 AAC/直播录制文件：format 段没有 duration，只有流级时长
+        let probe = r#"{"programs":[],"streams":[{"codec_type":"audio","duration":"183.466667"}],"format":{}}"#;
+        let duration = parse_probe_duration(probe).unwrap();
+        assert!((duration - 183.466667).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_probe_duration_from_duration_ts() {
+        let probe = r#"{"streams":[{"codec_type":"audio","duration_ts":441000,"time_base":"1/44100"}],"format":{"duration":"N/A"}}"#;
+        let duration = parse_probe_duration(probe).unwrap();
+        assert!((duration - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_probe_duration_from_frame_count() {
+        let probe = r#"{"streams":[{"codec_type":"video","nb_frames":"750","r_frame_rate":"25/1"},{"codec_type":"audio","r_frame_rate":"0/0"}],"format":{}}"#;
+        let duration = parse_probe_duration(probe).unwrap();
+        assert!((duration - 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_probe_duration_missing() {
+        let probe = r#"{"streams":[{"codec_type":"audio"}],"format":{}}"#;
+        assert_eq!(parse_probe_duration(probe), None);
+        assert_eq!(parse_probe_duration("not json"), None);
+    }
+
+    #[test]
+    fn test_parse_frame_rate() {
+        assert_eq!(parse_frame_rate("0/0"), None);
+        assert_eq!(parse_frame_rate("N/A"), None);
+        assert_eq!(parse_frame_rate(""), None);
+        assert_eq!(parse_frame_rate("25"), Some(25.0));
+        assert_eq!(parse_frame_rate("25/1"), Some(25.0));
+        let ntsc = parse_frame_rate("30000/1001").unwrap();
+        assert!((ntsc - 29.97).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_parse_decoded_duration() {
+        let stderr = "size=N/A time=00:00:10.00 bitrate=N/A speed=20x\r\
+                      size=N/A time=00:01:02.50 bitrate=N/A speed=21x\n";
+        let duration = parse_decoded_duration(stderr).unwrap();
+        assert!((duration - 62.5).abs() < 1e-9);
+        assert_eq!(parse_decoded_duration("no timing here"), None);
+    }
+
+    fn preview_test_info(path: &str, format: &str, video_codec: &str) -> VideoInfo {
+        VideoInfo {
+            path: path.to_string(),
+            filename: Path::new(path).file_name().unwrap().to_string_lossy().to_string(),
+            duration: 10.0,
+            width: 1920,
+            height: 1080,
+            fps: 30.0,
+            video_codec: video_codec.to_string(),
+            audio_codec: "aac".to_string(),
+            bitrate: 0,
+            size: 0,
+            format: format.to_string(),
+            audio_stream_count: 1,
+        }
+    }
+
+    #[test]
+    fn test_estimate_output_size_audio_bitrate() {
+        let info = preview_test_info("/videos/a.mp4", "mov,mp4,m4a,3gp,3g2,mj2", "h264");
+        let segments = [(0.0, 10.0)];
+        let default_size = estimate_output_size(&info, &segments, "libx264", Some(18), None);
+        let high_size = estimate_output_size(&info, &segments, "libx264", Some(18), Some(320));
+        // 320k 比默认 192k 每秒多 128 kbit，10 秒约多 160 KB（含 2% 封装开销）
+        let expected = (128_000.0 / 8.0 * 10.0 * 1.02) as i64;
+        assert!(((high_size as i64 - default_size as i64) - expected).abs() <= 1);
+
+        // 无音频流时不计音频码率
+        let silent = VideoInfo { audio_codec: String::new(), ..info };
+        assert_eq!(
+            estimate_output_size(&silent, &segments, "libx264", Some(18), Some(64)),
+            estimate_output_size(&silent, &segments, "libx264", Some(18), Some(320)),
+        );
+    }
+
+    #[test]
+    fn test_needs_preview_mp4_hevc() {
+        let info = preview_test_info("/videos/a.mp4", "mov,mp4,m4a,3gp,3g2,mj2", "hevc");
+        assert!(needs_preview_transcode(&info));
+    }
+
+    #[test]
+    fn test_needs_preview_mp4_h264() {
+        let info = preview_test_info("/videos/a.mp4", "mov,mp4,m4a,3gp,3g2,mj2", "h264");
+        assert!(!needs_preview_transcode(&info));
+    }
+
+    #[test]
+    fn test_needs_preview_mkv_h264() {
+        // Matroska 与 WebM 共用 format_name，.mkv 文件不能视为 WebM
+        let info = preview_test_info("/videos/a.mkv", "matroska,webm", "h264");
+        assert!(needs_preview_transcode(&info));
+        let webm = preview_test_info("/videos/a.webm", "matroska,webm", "vp9");
+        assert!(!needs_preview_transcode(&webm));
+    }
+
+    #[test]
+    fn test_needs_preview_flv_h264() {
+        let info = preview_test_info("/videos/a.flv", "flv", "h264");
+        assert!(needs_preview_transcode(&info));
+    }
+
+    fn order_test_segment(id: &str, start: f64, end: f64, confidence: f64) -> Segment {
+        Segment {
+            id: id.to_string(),
+            project_id: "p".to_string(),
+            music_id: None,
+            music_title: None,
+            start_time: start,
+            end_time: end,
+            confidence,
+            status: SegmentStatus::Detected,
+            segment_type: SegmentType::Music,
+            gain_db: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_ordered_export_segments() {
+        let segments = vec![
+            order_test_segment("a", 0.0, 10.0, 0.5),
+            order_test_segment("b", 5.0, 15.0, 0.9),
+            order_test_segment("c", 20.0, 30.0, 0.7),
+        ];
+
+        // 时间顺序：合并重叠片段
+        let chrono = plan_ordered_export_segments(&segments, 60.0, &SegmentOrder::Chronological).unwrap();
+        assert_eq!(chrono, vec![(0.0, 15.0), (20.0, 30.0)]);
+
+        // 按置信度：不合并
+        let by_conf = plan_ordered_export_segments(&segments, 60.0, &SegmentOrder::ByConfidenceDesc).unwrap();
+        assert_eq!(by_conf, vec![(5.0, 15.0), (20.0, 30.0), (0.0, 10.0)]);
+
+        // 自定义：只导出列出的片段
+        let custom = SegmentOrder::Custom(vec!["c".to_string(), "a".to_string()]);
+        let ordered = plan_ordered_export_segments(&segments, 60.0, &custom).unwrap();
+        assert_eq!(ordered, vec![(20.0, 30.0), (0.0, 10.0)]);
+
+        let unknown = SegmentOrder::Custom(vec!["x".to_string()]);
+        assert!(plan_ordered_export_segments(&segments, 60.0, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_compute_waveform_peaks() {
+        let samples = [0i16, 100, -32768, 50, 16384, -10, 0, 0];
+        let peaks = compute_waveform_peaks(&samples, 4);
+        assert_eq!(peaks.len(), 4);
+        assert!((peaks[0] - 100.0 / 32767.0).abs() < 1e-6);
+        // i16::MIN 的绝对值截断到 1.0
+        assert_eq!(peaks[1], 1.0);
+        assert!((peaks[2] - 0.5).abs() < 1e-3);
+        assert_eq!(peaks[3], 0.0);
+
+        assert!(compute_waveform_peaks(&[], 10).is_empty());
+        assert_eq!(compute_waveform_peaks(&samples, 100).len(), samples.len());
+    }
+
+    #[test]
+    fn test_resumable_segment_key_tracks_source_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("a.mp4");
+        let input = input.to_string_lossy().to_string();
+        let options = EncodeOptions::default();
+
+        fs::write(&input, b"original").unwrap();
+        let before = resumable_segment_key(&input, 0.0, 10.0, &options);
+        assert_eq!(before, resumable_segment_key(&input, 0.0, 10.0, &options));
+
+        // 同路径的源文件被替换后，旧片段不应再被复用
+        fs::write(&input, b"replaced with a longer file").unwrap();
+        assert_ne!(before, resumable_segment_key(&input, 0.0, 10.0, &options));
+        assert!(segment_cache_key(&input, 0.0, 10.0, &options).is_some());
+
+        // 网络输入不进入片段缓存
+        assert!(segment_cache_key("https://example.com/a.mp4", 0.0, 10.0, &options).is_none());
+    }
+}
@@ -421,12 +421,12 @@ const Editor: React.FC = () => {
         console.info('[thumbnail] generate', {
           videoPath: currentProject.source_video_path,
           thumbPath,
-          time: 0,
         });
         await api.getVideoThumbnail(
           currentProject.source_video_path,
           thumbPath,
-          0
+          undefined,
+          currentProject.id
         );
         console.info('[thumbnail] done', { thumbPath });
       } catch (e) {
@@ -131,7 +131,7 @@ const Projects: React.FC = () => {
       // 批量收集所有缩略图路径
       for (const project of projects) {
         try {
-          const thumbPath = await join(thumbDir, `${project.id}.jpg`);
+          const thumbPath = project.thumbnail_path ?? await join(thumbDir, `${project.id}.jpg`);
           newThumbnails[project.id] = convertFileSrc(thumbPath);
         } catch (e) {
           console.error(`Failed to load thumbnail for project ${project.id}:`, e);
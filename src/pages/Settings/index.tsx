@@ -669,6 +669,25 @@ const Settings: React.FC = () => {
                                 />
                                 <p className="text-xs text-[hsl(var(--text-muted))] mt-1">{t('settings.detection.confidenceThresholdDesc')}</p>
                               </div>
+                              <div>
+                                <label className="block text-sm font-medium text-[hsl(var(--text-secondary))] mb-2">
+                                  {t('settings.detection.intervalSeconds')}
+                                </label>
+                                <Input
+                                  type="number"
+                                  min={0.01}
+                                  max={5}
+                                  step={0.05}
+                                  value={localConfig.detection.detection_interval_seconds ?? ''}
+                                  onChange={(e) =>
+                                    updateLocalConfig(
+                                      'detection.detection_interval_seconds',
+                                      parseFloat(e.target.value) || null
+                                    )
+                                  }
+                                />
+                                <p className="text-xs text-[hsl(var(--text-muted))] mt-1">{t('settings.detection.intervalSecondsDesc')}</p>
+                              </div>
                               <div>
                                 <label className="block text-sm font-medium text-[hsl(var(--text-secondary))] mb-2">
                                   {t('settings.detection.frameInterval')}
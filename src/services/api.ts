@@ -7,9 +7,11 @@ import { exists } from '@tauri-apps/api/fs';
 import i18n from '@/i18n';
 import type {
   MusicInfo,
+  MusicUsage,
   VideoInfo,
   Project,
   Segment,
+  SegmentType,
   SystemInfo,
   GpuInfo,
   MatchResult,
@@ -22,9 +24,36 @@ import type {
   ProgressInfo,
   ImportProgress,
   StorageInfo,
+  StatsInfo,
+  DatabaseOptimizeResult,
   ModelInfo,
   ModelStatus,
   ModelDownloadProgress,
+  MusicSort,
+  BatchExportOptions,
+  ExportPreview,
+  AudioSource,
+  MatchAudioSource,
+  ActiveJobs,
+  BatchCreateResult,
+  EncodePreset,
+  NearDuplicate,
+  RawFingerprint,
+  FfmpegCapabilities,
+  ModelStorage,
+  SelfTestReport,
+  CachePurgeReport,
+  MusicDeleteFilter,
+  FingerprintStatus,
+  ProjectSort,
+  SegmentOrder,
+  OperationMetrics,
+  ExportSelection,
+  ExportCompleteResult,
+  OutputContainer,
+  GapFill,
+  ChildProcessInfo,
+  DetectionBox,
 } from '@/types';
 
 // ==================== 系统 API ====================
@@ -44,6 +73,26 @@ export async function checkDependencies(): Promise<DependencyCheck[]> {
   return invoke('check_dependencies');
 }
 
+/** 获取当前 ffmpeg 支持的容器格式与解码器 */
+export async function getFfmpegCapabilities(): Promise<FfmpegCapabilities> {
+  return invoke('get_ffmpeg_capabilities');
+}
+
+/** 端到端自检：生成测试视频并依次验证音频提取、指纹、人声分离 */
+export async function runSelfTest(): Promise<SelfTestReport> {
+  return invoke('run_self_test');
+}
+
+/** 导出诊断信息压缩包（日志、配置、系统信息），用于问题反馈，返回压缩包路径 */
+export async function exportDiagnostics(dest: string): Promise<string> {
+  return invoke('export_diagnostics', { dest });
+}
+
+/** 在系统文件管理器中显示文件（或打开目录） */
+export async function openInFileManager(path: string): Promise<void> {
+  return invoke('open_in_file_manager', { path });
+}
+
 // ==================== 配置 API ====================
 
 /** 获取应用配置 */
@@ -66,16 +115,31 @@ export async function getStorageInfo(): Promise<StorageInfo> {
   return invoke('get_storage_info');
 }
 
+/** 获取音乐库与项目统计信息 */
+export async function getStats(): Promise<StatsInfo> {
+  return invoke('get_stats');
+}
+
 /** 清理缓存文件，返回释放的字节数 */
 export async function clearCache(): Promise<number> {
   return invoke('clear_cache');
 }
 
+/** 缓存超过 max_cache_bytes 时清理最旧的中间文件（跳过进行中的项目） */
+export async function autoCleanCache(): Promise<CachePurgeReport> {
+  return invoke('auto_clean_cache');
+}
+
 /** 重置数据库（清空所有数据） */
 export async function resetDatabase(): Promise<void> {
   return invoke('reset_database');
 }
 
+/** 优化数据库（VACUUM），返回优化前后的文件大小 */
+export async function optimizeDatabase(): Promise<DatabaseOptimizeResult> {
+  return invoke('optimize_database');
+}
+
 /** 重置配置为默认值 */
 export async function resetConfig(): Promise<void> {
   return invoke('reset_config');
@@ -94,8 +158,23 @@ export async function importMusicFiles(paths: string[]): Promise<MusicInfo[]> {
 }
 
 /** 获取音乐库中所有音乐 */
-export async function getMusicLibrary(): Promise<MusicInfo[]> {
-  return invoke('get_music_library');
+export async function getMusicLibrary(checkFiles?: boolean): Promise<MusicInfo[]> {
+  return invoke('get_music_library', { checkFiles });
+}
+
+/** 分页获取音乐库（用于虚拟列表） */
+export async function getMusicLibraryPage(
+  offset: number,
+  limit: number,
+  sort?: MusicSort,
+  checkFiles?: boolean
+): Promise<MusicInfo[]> {
+  return invoke('get_music_library_page', { offset, limit, sort, checkFiles });
+}
+
+/** 获取音乐库总数 */
+export async function countMusic(): Promise<number> {
+  return invoke('count_music');
 }
 
 /** 删除指定音乐 */
@@ -108,9 +187,29 @@ export async function deleteAllMusic(): Promise<void> {
   return invoke('delete_all_music');
 }
 
+/** 获取音乐库指纹参数状态（outdated_count > 0 时需重新提取） */
+export async function getFingerprintStatus(): Promise<FingerprintStatus> {
+  return invoke('get_fingerprint_status');
+}
+
+/** 按当前指纹参数重新提取过时的音乐库指纹，返回成功更新数量 */
+export async function reextractFingerprints(): Promise<number> {
+  return invoke('reextract_fingerprints');
+}
+
+/** 批量删除音乐（单个事务），返回删除数量 */
+export async function deleteMusicBulk(ids: string[]): Promise<number> {
+  return invoke('delete_music_bulk', { ids });
+}
+
+/** 按条件批量删除音乐（源文件缺失 / 专辑），返回删除数量 */
+export async function deleteMusicWhere(filter: MusicDeleteFilter): Promise<number> {
+  return invoke('delete_music_where', { filter });
+}
+
 /** 按关键词搜索音乐 */
-export async function searchMusic(query: string): Promise<MusicInfo[]> {
-  return invoke('search_music', { query });
+export async function searchMusic(query: string, checkFiles?: boolean): Promise<MusicInfo[]> {
+  return invoke('search_music', { query, checkFiles });
 }
 
 /** 获取指定音乐详情 */
@@ -118,6 +217,16 @@ export async function getMusicInfo(id: string): Promise<MusicInfo | null> {
   return invoke('get_music_info', { id });
 }
 
+/** 获取音乐使用情况（引用片段数、项目数、最近使用的项目） */
+export async function getMusicUsage(id: string): Promise<MusicUsage> {
+  return invoke('get_music_usage', { id });
+}
+
+/** 获取未被任何项目片段引用的音乐 */
+export async function findUnusedMusic(checkFiles?: boolean): Promise<MusicInfo[]> {
+  return invoke('find_unused_music', { checkFiles });
+}
+
 // ==================== 指纹 API ====================
 
 /** 提取单个音频文件的指纹 */
@@ -125,6 +234,16 @@ export async function extractFingerprint(audioPath: string): Promise<string> {
   return invoke('extract_fingerprint', { audioPath });
 }
 
+/** 提取原始指纹（base64，不写入音乐库） */
+export async function extractFingerprintRaw(path: string): Promise<RawFingerprint> {
+  return invoke('extract_fingerprint_raw', { path });
+}
+
+/** 获取音乐库中已存储的原始指纹（base64） */
+export async function getFingerprint(musicId: string): Promise<RawFingerprint> {
+  return invoke('get_fingerprint', { musicId });
+}
+
 /** 匹配音频指纹，返回匹配结果列表 */
 export async function matchFingerprint(
   audioPath: string,
@@ -140,9 +259,9 @@ export async function batchExtractFingerprints(paths: string[]): Promise<string[
 
 // ==================== 视频 API ====================
 
-/** 分析视频文件，获取视频元信息 */
-export async function analyzeVideo(path: string): Promise<VideoInfo> {
-  return invoke('analyze_video', { path });
+/** 分析视频文件，获取视频元信息（探测期间发送 analyze-progress 心跳事件） */
+export async function analyzeVideo(path: string, projectId?: string): Promise<VideoInfo> {
+  return invoke('analyze_video', { path, projectId });
 }
 
 /** 检查项目的缓存状态（音频提取、人声分离是否已完成） */
@@ -154,13 +273,14 @@ export async function checkCacheStatus(
   return invoke('check_cache_status', { projectId, videoPath, modelId });
 }
 
-/** 从视频中提取音频轨道 */
+/** 从视频中提取音频轨道（audioTrack 为音轨序号，从 0 开始） */
 export async function extractAudio(
   videoPath: string,
   outputPath: string,
-  projectId?: string
+  projectId?: string,
+  audioTrack?: number
 ): Promise<string> {
-  return invoke('extract_audio', { videoPath, outputPath, projectId });
+  return invoke('extract_audio', { videoPath, outputPath, projectId, audioTrack });
 }
 
 /** 人声/伴奏分离（GPU 信号量排队） */
@@ -178,13 +298,37 @@ export async function matchVideoSegments(
   accompanimentPath: string,
   projectId: string,
   minConfidence?: number,
-  musicIds?: string[]
+  musicIds?: string[],
+  verbose?: boolean,
+  adaptiveWindow?: boolean,
+  startTime?: number,
+  endTime?: number,
+  multiMatch?: boolean,
+  musicAlbum?: string,
+  reviewMode?: boolean,
+  audioSource?: MatchAudioSource,
+  windowSize?: number,
+  hopSize?: number,
+  maxGapDuration?: number,
+  minSegmentDuration?: number
 ): Promise<Segment[]> {
   return invoke('match_video_segments', {
     accompanimentPath,
     projectId,
     minConfidence,
     musicIds,
+    verbose,
+    adaptiveWindow,
+    startTime,
+    endTime,
+    multiMatch,
+    musicAlbum,
+    reviewMode,
+    audioSource,
+    windowSize,
+    hopSize,
+    maxGapDuration,
+    minSegmentDuration,
   });
 }
 
@@ -193,9 +337,28 @@ export async function cutVideo(params: CutParams): Promise<string> {
   return invoke('cut_video', { params: { ...params, force_reencode: params.force_reencode ?? false } });
 }
 
-/** 合并导出视频（所有检测片段合并为一个文件） */
-export async function exportVideo(projectId: string, outputPath: string, forceReencode?: boolean): Promise<string> {
-  return invoke('export_video', { projectId, outputPath, forceReencode: forceReencode ?? false });
+/** 合并导出视频（所有检测片段合并为一个文件），cropAspect 如 "9:16" 时按该比例跟随人物裁剪 */
+export async function exportVideo(
+  projectId: string,
+  outputPath: string,
+  forceReencode?: boolean,
+  audioSource?: AudioSource,
+  cropAspect?: string,
+  preserveMtime?: boolean,
+  stripMetadata?: boolean,
+  preset?: EncodePreset,
+  order?: SegmentOrder,
+  keep?: ExportSelection,
+  audioBitrate?: number,
+  container?: OutputContainer,
+  gapFill?: GapFill,
+): Promise<string> {
+  return invoke('export_video', { projectId, outputPath, forceReencode: forceReencode ?? false, audioSource, cropAspect, preserveMtime, stripMetadata, preset, order, keep, audioBitrate, container, gapFill });
+}
+
+/** 清理可恢复导出的工作目录（不传 projectId 时清理全部），返回删除的目录数 */
+export async function clearExportWorkDirs(projectId?: string): Promise<number> {
+  return invoke('clear_export_work_dirs', { projectId });
 }
 
 /** 分别导出视频片段到指定目录 */
@@ -203,8 +366,34 @@ export async function exportVideoSeparately(
   projectId: string,
   outputDir: string,
   forceReencode?: boolean,
+  audioSource?: AudioSource,
+  preserveMtime?: boolean,
+  stripMetadata?: boolean,
+  preset?: EncodePreset,
+  audioBitrate?: number,
+  container?: OutputContainer,
 ): Promise<{ exported_count: number; output_files: string[] }> {
-  return invoke('export_video_separately', { projectId, outputDir, forceReencode: forceReencode ?? false });
+  return invoke('export_video_separately', { projectId, outputDir, forceReencode: forceReencode ?? false, audioSource, preserveMtime, stripMetadata, preset, audioBitrate, container });
+}
+
+/** 导出预览：返回最终片段、导出时长与预估文件大小（不执行编码） */
+export async function previewExport(
+  projectId: string,
+  forceReencode?: boolean,
+  codec?: string,
+  crf?: number,
+  audioBitrate?: number,
+): Promise<ExportPreview> {
+  return invoke('preview_export', { projectId, forceReencode, codec, crf, audioBitrate });
+}
+
+/** 批量导出多个项目（有限并行，失败项目不中断批次） */
+export async function batchExportProjects(
+  projectIds: string[],
+  outputDir: string,
+  options?: BatchExportOptions
+): Promise<{ exported_count: number; skipped_count: number; error_messages: string[]; output_files: string[] }> {
+  return invoke('batch_export_projects', { projectIds, outputDir, options });
 }
 
 /** 导出自定义时间范围的视频片段 */
@@ -213,9 +402,11 @@ export async function exportCustomClip(
   startTime: number,
   endTime: number,
   outputPath: string,
-  forceReencode?: boolean
+  forceReencode?: boolean,
+  preserveMtime?: boolean,
+  audioBitrate?: number
 ): Promise<string> {
-  return invoke('export_custom_clip', { projectId, startTime, endTime, outputPath, forceReencode: forceReencode ?? false });
+  return invoke('export_custom_clip', { projectId, startTime, endTime, outputPath, forceReencode: forceReencode ?? false, preserveMtime, audioBitrate });
 }
 
 /** 合并导出多个自定义片段为一个文件 */
@@ -223,9 +414,10 @@ export async function exportCustomClipsMerged(
   projectId: string,
   segments: Array<{ start_time: number; end_time: number }>,
   outputPath: string,
-  forceReencode?: boolean
+  forceReencode?: boolean,
+  audioBitrate?: number
 ): Promise<string> {
-  return invoke('export_custom_clips_merged', { projectId, segments, outputPath, forceReencode: forceReencode ?? false });
+  return invoke('export_custom_clips_merged', { projectId, segments, outputPath, forceReencode: forceReencode ?? false, audioBitrate });
 }
 
 /** 分别导出多个自定义片段到目录 */
@@ -233,18 +425,21 @@ export async function exportCustomClipsSeparately(
   projectId: string,
   segments: Array<{ start_time: number; end_time: number }>,
   outputDir: string,
-  forceReencode?: boolean
+  forceReencode?: boolean,
+  audioBitrate?: number
 ): Promise<{ exported_count: number; output_files: string[] }> {
-  return invoke('export_custom_clips_separately', { projectId, segments, outputDir, forceReencode: forceReencode ?? false });
+  return invoke('export_custom_clips_separately', { projectId, segments, outputDir, forceReencode: forceReencode ?? false, audioBitrate });
 }
 
 /** 获取视频缩略图（自动缓存） */
 export async function getVideoThumbnail(
   videoPath: string,
   outputPath: string,
-  time?: number
+  time?: number,
+  projectId?: string,
+  force?: boolean
 ): Promise<string> {
-  return invoke('get_video_thumbnail', { videoPath, outputPath, time });
+  return invoke('get_video_thumbnail', { videoPath, outputPath, time, projectId, force });
 }
 
 /** 取消正在进行的处理任务 */
@@ -252,6 +447,31 @@ export async function cancelProcessing(projectId?: string): Promise<void> {
   return invoke('cancel_processing', { projectId });
 }
 
+/** 暂停处理（挂起子进程，不丢失进度） */
+export async function pauseProcessing(projectId?: string): Promise<void> {
+  return invoke('pause_processing', { projectId });
+}
+
+/** 恢复已暂停的处理 */
+export async function resumeProcessing(projectId?: string): Promise<void> {
+  return invoke('resume_processing', { projectId });
+}
+
+/** 获取进行中的任务（"正在处理"面板） */
+export async function getActiveJobs(): Promise<ActiveJobs> {
+  return invoke('get_active_jobs');
+}
+
+/** 列出项目下仍在运行的子进程（排查卡住的任务） */
+export async function listChildProcesses(projectId: string): Promise<ChildProcessInfo[]> {
+  return invoke('list_child_processes', { projectId });
+}
+
+/** 终止项目下的单个子进程，不取消整个任务 */
+export async function killChildProcess(projectId: string, pid: number): Promise<void> {
+  return invoke('kill_child_process', { projectId, pid });
+}
+
 /** 取消预览视频生成 */
 export async function cancelPreviewGeneration(projectId?: string): Promise<void> {
   return invoke('cancel_preview_generation', { projectId });
@@ -266,9 +486,25 @@ export async function checkNeedsPreview(videoPath: string): Promise<boolean> {
 export async function generatePreviewVideo(
   sourcePath: string,
   outputPath: string,
-  projectId?: string
+  projectId?: string,
+  force?: boolean
 ): Promise<string> {
-  return invoke('generate_preview_video', { sourcePath, outputPath, projectId });
+  return invoke('generate_preview_video', { sourcePath, outputPath, projectId, force });
+}
+
+/** 生成单个片段的预览视频（按片段边界缓存），返回预览文件路径 */
+export async function previewSegment(projectId: string, segmentId: string): Promise<string> {
+  return invoke('preview_segment', { projectId, segmentId });
+}
+
+/** 获取时间范围内的音频波形峰值（0-1，按范围缓存），用于精细调整片段边界 */
+export async function getSegmentAudioPreview(
+  projectId: string,
+  startTime: number,
+  endTime: number,
+  peaks?: number,
+): Promise<number[]> {
+  return invoke('get_segment_audio_preview', { projectId, startTime, endTime, peaks });
 }
 
 /** 监听预览生成进度 */
@@ -308,9 +544,19 @@ export async function loadProject(id: string): Promise<Project> {
   return invoke('load_project', { id });
 }
 
-/** 获取所有项目列表 */
-export async function getProjects(): Promise<Project[]> {
-  return invoke('get_projects');
+/** 导出项目（项目信息与片段）为带格式版本的 JSON 文件，返回文件路径 */
+export async function exportProjectFile(projectId: string, dest: string): Promise<string> {
+  return invoke('export_project_file', { projectId, dest });
+}
+
+/** 导入 exportProjectFile 导出的项目文件（格式版本不兼容时报错） */
+export async function importProjectFile(path: string): Promise<Project> {
+  return invoke('import_project_file', { path });
+}
+
+/** 获取所有项目列表（默认按最近修改排序） */
+export async function getProjects(checkFiles?: boolean, sort?: ProjectSort): Promise<Project[]> {
+  return invoke('get_projects', { checkFiles, sort });
 }
 
 /** 删除指定项目及其片段 */
@@ -326,9 +572,28 @@ export async function deleteAllProjects(): Promise<void> {
 /** 更新项目的片段列表 */
 export async function updateSegments(
   projectId: string,
-  segments: Segment[]
+  segments: Segment[],
+  mergeOverlaps?: boolean
 ): Promise<void> {
-  return invoke('update_segments', { projectId, segments });
+  return invoke('update_segments', { projectId, segments, mergeOverlaps });
+}
+
+/** 设置定时自动保存（秒），不传或传 0 关闭并写入剩余快照 */
+export async function setAutosave(projectId: string, intervalSecs?: number): Promise<void> {
+  return invoke('set_autosave', { projectId, intervalSecs });
+}
+
+/** 推送片段快照用于自动保存（未开启定时保存时立即写入） */
+export async function autosaveSegments(projectId: string, segments: Segment[]): Promise<void> {
+  return invoke('autosave_segments', { projectId, segments });
+}
+
+/** 获取项目的指定类型片段（音乐匹配或人物检测） */
+export async function getSegmentsByType(
+  projectId: string,
+  segmentType: SegmentType
+): Promise<Segment[]> {
+  return invoke('get_segments_by_type', { projectId, segmentType });
 }
 
 /** 更新项目预览视频路径 */
@@ -339,14 +604,50 @@ export async function updateProjectPreview(
   return invoke('update_project_preview', { projectId, previewPath });
 }
 
+/** 在指定时间（秒）重新生成项目缩略图，返回缩略图路径 */
+export async function setProjectThumbnail(projectId: string, time: number): Promise<string> {
+  return invoke('set_project_thumbnail', { projectId, time });
+}
+
+/** 将项目片段边界吸附到最近的关键帧，返回调整后的片段 */
+export async function snapSegmentsToKeyframes(
+  projectId: string,
+  tolerance?: number
+): Promise<Segment[]> {
+  return invoke('snap_segments_to_keyframes', { projectId, tolerance });
+}
+
+/** 合并相邻的同一歌曲片段（间隙不超过 maxGap 秒），返回合并后的片段 */
+export async function mergeAdjacentSegments(
+  projectId: string,
+  maxGap?: number
+): Promise<Segment[]> {
+  return invoke('merge_adjacent_segments', { projectId, maxGap });
+}
+
+/** 将同一源视频的另一个项目的片段合并到目标项目（跳过完全重叠的片段），返回目标项目的全部片段 */
+export async function mergeProjectSegments(targetId: string, sourceId: string): Promise<Segment[]> {
+  return invoke('merge_project_segments', { targetId, sourceId });
+}
+
+/** 在静音处拆分片段（静音部分被剔除），返回项目的全部片段 */
+export async function splitSegmentAtSilence(
+  projectId: string,
+  segmentId: string,
+  thresholdDb?: number,
+  minSilence?: number
+): Promise<Segment[]> {
+  return invoke('split_segment_at_silence', { projectId, segmentId, thresholdDb, minSilence });
+}
+
 /** 扫描文件夹中的视频文件 */
 export async function scanVideoFiles(folderPath: string): Promise<string[]> {
   return invoke('scan_video_files', { folderPath });
 }
 
-/** 批量创建项目 */
-export async function batchCreateProjects(videoPaths: string[]): Promise<Project[]> {
-  return invoke('batch_create_projects', { videoPaths });
+/** 批量创建项目（skipDuplicates 为 true 时跳过与已有项目内容相同的视频） */
+export async function batchCreateProjects(videoPaths: string[], skipDuplicates?: boolean): Promise<Project[]> {
+  return invoke('batch_create_projects', { videoPaths, skipDuplicates });
 }
 
 /** 监听批量创建进度 */
@@ -360,10 +661,10 @@ export function onBatchCreateProgress(
 
 /** 监听批量创建完成 */
 export function onBatchCreateComplete(
-  callback: (result: { created: number; skipped: number; errors: number; error_messages: string[]; total: number }) => void
+  callback: (result: BatchCreateResult) => void
 ): Promise<UnlistenFn> {
   return listen('batch-create-complete', (event) => {
-    callback(event.payload as { created: number; skipped: number; errors: number; error_messages: string[]; total: number });
+    callback(event.payload as BatchCreateResult);
   });
 }
 
@@ -379,6 +680,11 @@ export async function getModelsStatus(): Promise<ModelStatus[]> {
   return invoke('get_models_status');
 }
 
+/** 获取各模型目录的磁盘占用（含孤立目录） */
+export async function getModelStorage(): Promise<ModelStorage> {
+  return invoke('get_model_storage');
+}
+
 /** 检查指定模型是否已下载 */
 export async function checkModelDownloaded(modelId: string): Promise<boolean> {
   return invoke('check_model_downloaded', { modelId });
@@ -394,6 +700,11 @@ export async function downloadModel(modelId: string): Promise<void> {
   return invoke('download_model', { modelId });
 }
 
+/** 取消模型下载（排队中或下载中） */
+export async function cancelModelDownload(modelId: string): Promise<void> {
+  return invoke('cancel_model_download', { modelId });
+}
+
 /** 监听模型下载进度 */
 export function onModelDownloadProgress(
   callback: (progress: ModelDownloadProgress) => void
@@ -472,10 +783,10 @@ export function onImportProgress(
 
 /** 监听音乐导入完成 */
 export function onImportComplete(
-  callback: (result: { imported: number; skipped: number; errors: number }) => void
+  callback: (result: { imported: number; skipped: number; errors: number; duplicates: NearDuplicate[] }) => void
 ): Promise<UnlistenFn> {
   return listen('import-complete', (event) => {
-    callback(event.payload as { imported: number; skipped: number; errors: number });
+    callback(event.payload as { imported: number; skipped: number; errors: number; duplicates: NearDuplicate[] });
   });
 }
 
@@ -515,6 +826,33 @@ export function onSeparationComplete(
   });
 }
 
+/** 监听 GPU 显存不足自动回退 CPU 的通知（人声分离 / 人物检测） */
+export function onGpuFallback(
+  callback: (data: { project_id: string; operation: 'separation' | 'detection'; message: string }) => void
+): Promise<UnlistenFn> {
+  return listen('gpu-fallback', (event) => {
+    callback(event.payload as { project_id: string; operation: 'separation' | 'detection'; message: string });
+  });
+}
+
+/** 监听匹配/分离/导出/检测完成后的性能指标 */
+export function onOperationMetrics(
+  callback: (metrics: OperationMetrics) => void
+): Promise<UnlistenFn> {
+  return listen('operation-metrics', (event) => {
+    callback(event.payload as OperationMetrics);
+  });
+}
+
+/** 监听自定义剪辑导出超过时长/大小警告阈值的通知 */
+export function onExportWarning(
+  callback: (data: { project_id: string; message: string; total_duration: number; estimated_size: number }) => void
+): Promise<UnlistenFn> {
+  return listen('export-warning', (event) => {
+    callback(event.payload as { project_id: string; message: string; total_duration: number; estimated_size: number });
+  });
+}
+
 /** 监听指纹匹配进度 */
 export function onMatchingProgress(
   callback: (progress: ProgressInfo & { segments_found?: number }) => void
@@ -526,10 +864,10 @@ export function onMatchingProgress(
 
 /** 监听指纹匹配完成 */
 export function onMatchingComplete(
-  callback: (result: { segments: number }) => void
+  callback: (result: { segments: number; provisional: number }) => void
 ): Promise<UnlistenFn> {
   return listen('matching-complete', (event) => {
-    callback(event.payload as { segments: number });
+    callback(event.payload as { segments: number; provisional: number });
   });
 }
 
@@ -560,12 +898,30 @@ export function onExportProgress(
   });
 }
 
-/** 监听视频导出完成 */
+/** 监听视频导出完成（outputs 为每个输出的结果，同时导出匹配与反向内容时可能部分失败） */
 export function onExportComplete(
-  callback: (result: { output_path: string }) => void
+  callback: (result: ExportCompleteResult) => void
 ): Promise<UnlistenFn> {
   return listen('export-complete', (event) => {
-    callback(event.payload as { output_path: string });
+    callback(event.payload as ExportCompleteResult);
+  });
+}
+
+/** 监听批量导出进度 */
+export function onBatchExportProgress(
+  callback: (progress: ProgressInfo & { overall_progress: number; current: number; total: number }) => void
+): Promise<UnlistenFn> {
+  return listen('batch-export-progress', (event) => {
+    callback(event.payload as ProgressInfo & { overall_progress: number; current: number; total: number });
+  });
+}
+
+/** 监听批量导出完成 */
+export function onBatchExportComplete(
+  callback: (result: { exported: number; skipped: number; errors: number; error_messages: string[]; total: number }) => void
+): Promise<UnlistenFn> {
+  return listen('batch-export-complete', (event) => {
+    callback(event.payload as { exported: number; skipped: number; errors: number; error_messages: string[]; total: number });
   });
 }
 
@@ -581,6 +937,38 @@ export async function detectPersons(
   return invoke('detect_persons', { projectId, videoPath, outputDir, acceleration });
 }
 
+/** 批量人物检测（逐个项目串行，失败的项目记录错误后继续） */
+export async function batchDetectPersons(
+  projectIds: string[],
+  outputDir: string,
+  acceleration?: string
+): Promise<{ detected_count: number; skipped_count: number; segments_count: number; error_messages: string[] }> {
+  return invoke('batch_detect_persons', { projectIds, outputDir, acceleration });
+}
+
+/** 监听批量人物检测进度 */
+export function onBatchDetectionProgress(
+  callback: (progress: ProgressInfo & { overall_progress: number; current: number; total: number }) => void
+): Promise<UnlistenFn> {
+  return listen('batch-detection-progress', (event) => {
+    callback(event.payload as ProgressInfo & { overall_progress: number; current: number; total: number });
+  });
+}
+
+/** 监听批量人物检测完成 */
+export function onBatchDetectionComplete(
+  callback: (result: { detected: number; skipped: number; errors: number; error_messages: string[]; total: number }) => void
+): Promise<UnlistenFn> {
+  return listen('batch-detection-complete', (event) => {
+    callback(event.payload as { detected: number; skipped: number; errors: number; error_messages: string[]; total: number });
+  });
+}
+
+/** 获取项目人物片段的检测框（segment_id -> 检测框列表） */
+export async function getDetectionBoxes(projectId: string): Promise<Record<string, DetectionBox[]>> {
+  return invoke('get_detection_boxes', { projectId });
+}
+
 /** 取消人物检测任务 */
 export async function cancelDetection(projectId: string): Promise<void> {
   return invoke('cancel_detection', { projectId });
@@ -606,9 +994,100 @@ export function onDetectionProgress(
 
 /** 监听人物检测完成 */
 export function onDetectionComplete(
-  callback: (result: { project_id: string; segments_count: number; total_frames: number; processed_frames: number; detection_frames: number }) => void
+  callback: (result: { project_id: string; segments_count: number; total_frames: number; processed_frames: number; detection_frames: number; partial: boolean }) => void
 ): Promise<UnlistenFn> {
   return listen('detection-complete', (event) => {
-    callback(event.payload as { project_id: string; segments_count: number; total_frames: number; processed_frames: number; detection_frames: number });
+    callback(event.payload as { project_id: string; segments_count: number; total_frames: number; processed_frames: number; detection_frames: number; partial: boolean });
   });
 }
+
+// ==================== 媒体分析 API ====================
+
+/** 检测静音区间，返回 [start, end] 列表（秒） */
+export async function detectSilence(
+  path: string,
+  thresholdDb?: number,
+  minDuration?: number,
+  projectId?: string
+): Promise<[number, number][]> {
+  return invoke('detect_silence', { path, thresholdDb, minDuration, projectId });
+}
+
+/** 监听静音检测进度 */
+export function onSilenceProgress(
+  callback: (progress: ProgressInfo) => void
+): Promise<UnlistenFn> {
+  return listen('silence-progress', (event) => {
+    callback(event.payload as ProgressInfo);
+  });
+}
+
+/** 检测场景切换点，返回候选剪辑时间列表（秒） */
+export async function detectSceneChanges(
+  path: string,
+  threshold?: number,
+  projectId?: string
+): Promise<number[]> {
+  return invoke('detect_scene_changes', { path, threshold, projectId });
+}
+
+/** 监听场景切换检测进度 */
+export function onSceneProgress(
+  callback: (progress: ProgressInfo) => void
+): Promise<UnlistenFn> {
+  return listen('scene-progress', (event) => {
+    callback(event.payload as ProgressInfo);
+  });
+}
+
+/** 检测黑屏/静止画面区间（秒），可用于建议裁剪片头片尾或排除出匹配范围 */
+export async function detectBlackFrames(
+  videoPath: string,
+  projectId?: string
+): Promise<[number, number][]> {
+  return invoke('detect_black_frames', { videoPath, projectId });
+}
+
+/** 监听黑屏/静止画面检测进度 */
+export function onBlackFramesProgress(
+  callback: (progress: ProgressInfo) => void
+): Promise<UnlistenFn> {
+  return listen('black-frames-progress', (event) => {
+    callback(event.payload as ProgressInfo);
+  });
+}
+
+/** 获取视频关键帧时间戳（传入 projectId 时按项目缓存） */
+export async function getKeyframes(videoPath: string, projectId?: string): Promise<number[]> {
+  return invoke('get_keyframes', { videoPath, projectId });
+}
+
+/** 生成频谱图 PNG（不传 outputPng 时按项目缓存），返回图片路径 */
+export async function generateSpectrogram(
+  path: string,
+  outputPng?: string,
+  width?: number,
+  height?: number,
+  projectId?: string
+): Promise<string> {
+  return invoke('generate_spectrogram', { path, outputPng, width, height, projectId });
+}
+
+/** 取消频谱图生成（不影响同一项目的其他任务） */
+export async function cancelSpectrogram(projectId?: string): Promise<void> {
+  return invoke('cancel_processing', { projectId: `spectrogram_${projectId ?? 'default'}` });
+}
+
+/** 监听频谱图生成进度 */
+export function onSpectrogramProgress(
+  callback: (progress: ProgressInfo) => void
+): Promise<UnlistenFn> {
+  return listen('spectrogram-progress', (event) => {
+    callback(event.payload as ProgressInfo);
+  });
+}
+
+/** 检测音频节拍，返回节拍时间戳（秒，升序），用于片段边界吸附 */
+export async function detectBeats(audioPath: string): Promise<number[]> {
+  return invoke('detect_beats', { audioPath });
+}
@@ -13,7 +13,7 @@ export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
 export type TaskStatus = 'pending' | 'running' | 'completed' | 'failed' | 'cancelled';
 
 // 片段状态
-export type SegmentStatus = 'detected' | 'removed';
+export type SegmentStatus = 'detected' | 'removed' | 'provisional';
 
 // 音乐信息
 export interface MusicInfo {
@@ -28,6 +28,47 @@ export interface MusicInfo {
   file_exists: boolean;
 }
 
+// 音乐库指纹参数状态
+export interface FingerprintStatus {
+  /** 当前配置对应的指纹参数标识 */
+  current_version: string;
+  /** 指纹参数已过时、需要重新提取的音乐数（> 0 时匹配结果可能不可靠） */
+  outdated_count: number;
+}
+
+// 按条件批量删除音乐的筛选条件（多个条件同时满足才删除）
+export interface MusicDeleteFilter {
+  /** 只删除源文件已不存在的音乐 */
+  missing_file?: boolean;
+  /** 只删除指定专辑的音乐（不区分大小写） */
+  album?: string;
+}
+
+// 音乐使用情况
+export interface MusicUsage {
+  music_id: string;
+  /** 引用该音乐的片段数 */
+  segment_count: number;
+  /** 引用该音乐的项目数 */
+  project_count: number;
+  /** 最近使用该音乐的项目（按项目更新时间） */
+  last_project_id?: string | null;
+  last_project_name?: string | null;
+  last_used_at?: string | null;
+}
+
+// 音乐库排序方式
+export type MusicSort =
+  | 'title_asc'
+  | 'title_desc'
+  | 'created_asc'
+  | 'created_desc'
+  | 'duration_asc'
+  | 'duration_desc';
+
+// 项目列表排序方式：最近修改 / 最近创建 / 名称
+export type ProjectSort = 'recent' | 'created' | 'name';
+
 // 视频信息
 export interface VideoInfo {
   path: string;
@@ -41,6 +82,7 @@ export interface VideoInfo {
   bitrate: number;
   size: number;
   format: string;
+  audio_stream_count: number;
 }
 
 // 片段类型
@@ -58,6 +100,8 @@ export interface Segment {
   status: SegmentStatus;
   /** 片段类型：音乐匹配片段或人物检测片段，后端始终返回，默认 'music' */
   segment_type: SegmentType;
+  /** 导出时的音量增益（dB），为空表示不调整 */
+  gain_db?: number | null;
 }
 
 // 自定义剪辑片段（临时，不持久化到数据库）
@@ -74,12 +118,39 @@ export interface Project {
   source_video_path: string;
   /** 预览视频路径（用于播放不支持的格式，如 FLV） */
   preview_video_path?: string;
+  /** 缩略图路径（创建项目时生成） */
+  thumbnail_path?: string;
   video_info: VideoInfo;
   segments: Segment[];
   created_at: string;
   updated_at: string;
   /** 源视频文件是否存在 */
   file_exists: boolean;
+  /** 最近一次音乐匹配实际使用的参数，未匹配过为 null */
+  match_config?: MatchConfig | null;
+}
+
+// 批量创建项目时跳过的重复视频
+export interface DuplicateVideo {
+  video_path: string;
+  project_id: string;
+  project_name: string;
+}
+
+// 批量创建项目完成结果
+export interface BatchCreateResult {
+  created: number;
+  skipped: number;
+  errors: number;
+  error_messages: string[];
+  duplicates: DuplicateVideo[];
+  total: number;
+}
+
+// 原始指纹（Chromaprint 小端 i32 数组的 base64 编码）
+export interface RawFingerprint {
+  fingerprint: string;
+  duration: number;
 }
 
 // 匹配结果
@@ -91,6 +162,48 @@ export interface MatchResult {
   end_time: number;
 }
 
+// 匹配明细（matching-detail 事件，verbose 模式）
+export interface MatchingWindowDetail {
+  time: number;
+  music_id: string | null;
+  confidence: number;
+}
+
+export interface MatchingDetailEvent {
+  project_id: string;
+  window_size: number;
+  windows: MatchingWindowDetail[];
+}
+
+// 进行中的任务
+export type JobPipeline = 'processing' | 'detection' | 'preview' | 'batch_export' | 'batch_detection' | 'model_download';
+
+export interface ActiveJob {
+  /** 项目 ID，批量任务为 null */
+  project_id: string | null;
+  pipeline: JobPipeline;
+  /** 原始取消标志 ID */
+  flag_id: string;
+  /** 已请求取消但尚未退出 */
+  cancelling: boolean;
+  /** 已暂停（子进程被挂起） */
+  paused: boolean;
+  child_processes: number;
+}
+
+export interface ActiveJobs {
+  jobs: ActiveJob[];
+  separation_gpu_busy: boolean;
+  detection_gpu_busy: boolean;
+}
+
+// 子进程信息
+export interface ChildProcessInfo {
+  pid: number;
+  op: string;
+  elapsed_secs: number;
+}
+
 // 分离结果
 export interface SeparationResult {
   vocals_path: string;
@@ -144,6 +257,32 @@ export interface DependencyCheck {
   version?: string;
   path?: string;
   message: string;
+  /** 已安装但版本过旧等问题的警告 */
+  warning?: string;
+}
+
+// ffmpeg 能力（实际支持的格式与解码器）
+export interface FfmpegCapabilities {
+  demuxers: string[];
+  muxers: string[];
+  video_decoders: string[];
+  audio_decoders: string[];
+}
+
+// 自检阶段结果
+export interface SelfTestStage {
+  name: 'generate' | 'extract_audio' | 'fingerprint' | 'separation';
+  passed: boolean;
+  /** 前置阶段失败或模型未下载时跳过 */
+  skipped: boolean;
+  duration_ms: number;
+  message: string;
+}
+
+// 自检报告
+export interface SelfTestReport {
+  stages: SelfTestStage[];
+  passed: boolean;
 }
 
 // 加速选项
@@ -161,6 +300,8 @@ export interface AccelerationOptions {
 export interface SeparationConfig {
   selected_model_id: string;
   output_format: string;
+  /** 所选模型未下载时，人声分离前自动下载 */
+  auto_download_model: boolean;
 }
 
 // 模型架构类型
@@ -186,6 +327,23 @@ export interface ModelStatus {
   local_path?: string;
 }
 
+// 单个模型目录的磁盘占用
+export interface ModelStorageEntry {
+  model_id: string;
+  name?: string;
+  kind: 'separation' | 'detection';
+  path: string;
+  size: number;
+  /** 目录不对应任何已知模型 */
+  orphaned: boolean;
+}
+
+// 模型磁盘占用汇总
+export interface ModelStorage {
+  models: ModelStorageEntry[];
+  total_size: number;
+}
+
 // 模型下载进度
 export interface ModelDownloadProgress {
   model_id: string;
@@ -193,8 +351,13 @@ export interface ModelDownloadProgress {
   message: string;
   completed: boolean;
   error?: string;
+  /** 是否因用户取消而结束 */
+  cancelled: boolean;
 }
 
+// 片段置信度聚合方式：最大值 / 平均值 / 按覆盖时长加权
+export type ConfidenceAggregation = 'max' | 'mean' | 'coverage_weighted';
+
 // 匹配配置
 export interface MatchConfig {
   min_confidence: number;
@@ -202,6 +365,17 @@ export interface MatchConfig {
   window_size: number;
   hop_size: number;
   max_gap_duration: number;
+  min_overlap_ratio: number;
+  fingerprint_mono: boolean;
+  fingerprint_sample_rate: number;
+  adaptive_window: boolean;
+  min_window_size: number;
+  max_window_size: number;
+  secondary_confidence: number;
+  /** 合并窗口为片段时的置信度聚合方式 */
+  confidence_aggregation?: ConfidenceAggregation;
+  /** 匹配使用的音频来源，调用时未指定则使用该值 */
+  audio_source?: MatchAudioSource;
 }
 
 // 窗口状态
@@ -217,10 +391,27 @@ export interface WindowState {
 export interface DetectionConfig {
   confidence_threshold: number;
   frame_interval: number;
+  /** 抽帧间隔（秒），设置后优先于 frame_interval，null 表示按帧数 */
+  detection_interval_seconds?: number | null;
   min_segment_duration: number;
   max_gap_duration: number;
 }
 
+// 人物检测框（取该帧置信度最高的人物）
+export interface DetectionBox {
+  /** 帧时间（秒） */
+  time: number;
+  /** [x1, y1, x2, y2]，按画面宽高归一化到 0-1 */
+  box: [number, number, number, number];
+  confidence: number;
+}
+
+// 扫描/导入识别的文件扩展名（小写，不含点）
+export interface FileExtensionsConfig {
+  video: string[];
+  audio: string[];
+}
+
 // 应用配置
 export interface AppConfig {
   detected_gpu: GpuType;
@@ -232,6 +423,66 @@ export interface AppConfig {
   window_state: WindowState;
   /** 日志级别，后端始终返回（带默认值） */
   log_level: LogLevel;
+  /** 扫描/导入识别的文件扩展名，后端始终返回（带默认值） */
+  file_extensions: FileExtensionsConfig;
+  /** 创建项目时自动为需要转码的格式生成预览视频 */
+  auto_generate_preview?: boolean;
+  /** 片段预览、波形等编辑操作优先使用预览视频作为代理（导出始终使用源视频） */
+  use_preview_proxy?: boolean;
+  /** 子进程无进展超时（秒），0 表示不限制 */
+  process_timeout_secs?: number;
+  /** 重编码片段缓存上限（MB），0 表示不缓存 */
+  segment_cache_max_mb?: number;
+  /** 人声分离/人物检测使用的 GPU 序号，null 表示自动 */
+  gpu_device_index?: number | null;
+  /** 是否为导出/分离/检测等任务额外写入 logs/{project_id}_{op}.log */
+  operation_logs?: boolean;
+  /** 自定义剪辑单段最大时长（秒），0 表示不限制 */
+  max_clip_seconds?: number;
+  /** 自定义剪辑单段最短时长（秒），过短的片段拒绝导出 */
+  min_clip_seconds?: number;
+  /** 自定义剪辑总时长警告阈值（秒），0 表示不警告 */
+  clip_warn_seconds?: number;
+  /** 自定义剪辑预估大小警告阈值（MB），0 表示不警告 */
+  clip_warn_size_mb?: number;
+  /** 导入音乐时检查与已有歌曲的指纹相似度（疑似重复不导入） */
+  near_duplicate_check?: boolean;
+  /** 疑似重复的指纹相似度阈值 (0-1) */
+  near_duplicate_threshold?: number;
+  /** 同时下载的模型数，超出的排队等待 */
+  max_concurrent_downloads?: number;
+  /** 缩略图默认截取位置（占视频时长的比例 0-1） */
+  thumbnail_time_ratio?: number;
+  /** temp 缓存上限（字节），超出时自动清理最旧的中间文件，0 表示不限制 */
+  max_cache_bytes?: number;
+  /** 日志文件保留天数（至少 1 天），下次启动时生效 */
+  log_retention_days?: number;
+  /** 匹配/拼接等操作的临时目录（绝对路径），为空时使用应用目录下的 temp */
+  cache_dir?: string | null;
+}
+
+// 缓存自动清理结果
+export interface CachePurgeReport {
+  /** 被删除的缓存项（temp 下的文件/目录名） */
+  purged: string[];
+  freed_bytes: number;
+  remaining_bytes: number;
+}
+
+// 操作完成后的性能指标（operation-metrics 事件）
+export interface OperationMetrics {
+  project_id: string | null;
+  operation: 'match' | 'separation' | 'export' | 'detection';
+  /** 总耗时（毫秒，含排队等待） */
+  duration_ms: number;
+  /** 输入文件大小（字节） */
+  input_size: number | null;
+  /** 输入媒体时长（秒） */
+  input_duration: number | null;
+  /** 产生/导出的片段数 */
+  segment_count: number | null;
+  /** 是否使用了 GPU 或硬件编码 */
+  gpu: boolean;
 }
 
 // 进度信息
@@ -249,6 +500,14 @@ export interface ImportProgress {
   message: string;
 }
 
+// 导入时识别出的疑似重复音乐（未导入）
+export interface NearDuplicate {
+  file_path: string;
+  music_id: string;
+  music_title: string;
+  similarity: number;
+}
+
 // 存储信息
 export interface StorageInfo {
   app_dir: string;
@@ -260,6 +519,98 @@ export interface StorageInfo {
   total_size: number;
 }
 
+// 数据库优化结果
+export interface DatabaseOptimizeResult {
+  size_before: number;
+  size_after: number;
+  reclaimed: number;
+}
+
+// 库与项目统计信息
+export interface StatsInfo {
+  music_count: number;
+  /** 音乐库总时长（秒） */
+  music_total_duration: number;
+  project_count: number;
+  segment_count: number;
+  temp_size: number;
+  previews_size: number;
+  thumbnails_size: number;
+  cache_total_size: number;
+}
+
+// 导出预览（dry-run）
+export interface ExportPreview {
+  /** 最终导出的时间段 [start, end]（秒） */
+  segments: [number, number][];
+  total_duration: number;
+  source_duration: number;
+  /** 预估输出大小（字节） */
+  estimated_size: number;
+}
+
+// 批量导出选项
+export interface BatchExportOptions {
+  /** 强制重编码（默认无损） */
+  force_reencode?: boolean;
+  /** 每个项目的片段分别导出到子目录 */
+  separately?: boolean;
+  /** 同时导出的项目数（默认 2） */
+  max_concurrency?: number;
+  /** 输出音轨来源（默认原始音轨） */
+  audio_source?: AudioSource;
+  /** 清除 GPS/设备等元数据、章节与数据流 */
+  strip_metadata?: boolean;
+  /** 重编码时的软件编码预设（默认 veryfast） */
+  preset?: EncodePreset;
+}
+
+// 软件编码预设（x264/x265，由快到慢，越慢文件越小）
+export type EncodePreset =
+  | 'ultrafast'
+  | 'superfast'
+  | 'veryfast'
+  | 'faster'
+  | 'fast'
+  | 'medium'
+  | 'slow'
+  | 'slower'
+  | 'veryslow';
+
+// 导出音轨来源：原始音轨 / 分离出的人声 / 分离出的伴奏
+export type AudioSource = 'original' | 'vocals' | 'instrumental';
+
+// 音乐匹配的音频来源：人声分离后的伴奏（准确率高）/ 原始混合音轨（跳过分离）
+export type MatchAudioSource = 'accompaniment' | 'mixed';
+
+// 合并导出的片段拼接顺序：时间顺序 / 置信度从高到低 / 自定义片段 ID 顺序
+export type SegmentOrder = 'chronological' | 'by_confidence_desc' | { custom: string[] };
+
+// 合并导出保留的内容：匹配片段 / 被剪掉的部分 / 两者（反向结果写入 {文件名}_inverse.{扩展名}）
+export type ExportSelection = 'matched' | 'inverse' | 'both';
+
+// 合并导出中单个输出的结果
+export interface ExportOutputResult {
+  output_path: string;
+  inverse: boolean;
+  error: string | null;
+}
+
+// 合并导出完成事件
+export interface ExportCompleteResult {
+  output_path: string;
+  inverse_output_path: string | null;
+  selection: ExportSelection;
+  outputs: ExportOutputResult[];
+  project_id: string;
+}
+
+// 合并导出时片段间隙的处理：直接拼接 / 黑屏填充 / 定格填充（后两者保持原始时间轴）
+export type GapFill = 'remove' | 'black' | 'freeze';
+
+// 导出输出容器（与视频编码独立选择）
+export type OutputContainer = 'mp4' | 'mov' | 'mkv';
+
 // Toast 类型
 export type ToastType = 'success' | 'error' | 'warning' | 'info';
 